@@ -0,0 +1,106 @@
+//! Onboarding issue text for `project announce`. The body is regenerated from
+//! the project and its tracked repositories on every run, so edits made on
+//! GitHub are overwritten.
+
+use crate::github::{PREFERENCES_FILE_PATH, PROFILE_REPO_NAME, WALLET_FILE_PATH};
+use crate::types::{Project, Repository};
+
+pub fn announcement_title(project: &Project) -> String {
+    format!("Get rewarded for contributing to {}", project.name)
+}
+
+pub fn announcement_body(project: &Project, repos: &[Repository]) -> String {
+    let mut body = format!("<!-- gitcircles:announcement {} -->\n", project.id);
+    body.push_str(&format!(
+        "## {} rewards contributors with GitCircles\n\n",
+        project.name
+    ));
+    if let Some(desc) = &project.description {
+        body.push_str(&format!("{}\n\n", desc));
+    }
+    body.push_str(
+        "GitCircles tracks merged pull requests and pays their authors in ERG \
+         on the Ergo blockchain.\n\n",
+    );
+
+    body.push_str("### Reward policy\n\n");
+    if repos.is_empty() {
+        body.push_str(
+            "- Pull requests merged into this repository's default branch count \
+             towards rewards.\n",
+        );
+    } else {
+        body.push_str(
+            "- Pull requests merged into these branches count towards rewards:\n",
+        );
+        for repo in repos {
+            body.push_str(&format!(
+                "  - `{}/{}` (`{}`)\n",
+                repo.owner, repo.name, repo.current_base_branch
+            ));
+        }
+    }
+    body.push_str(&format!(
+        "- Rewards are paid to the address in your `{}` repository. Without \
+         one, rewards cannot be paid out.\n",
+        PROFILE_REPO_NAME
+    ));
+    body.push_str(
+        "- To stop receiving rewards, comment a line reading `opt out` on any \
+         pull request, or set `opt_out = true` in your preferences.\n\n",
+    );
+
+    body.push_str("### Set up your wallet\n\n");
+    body.push_str(&format!(
+        "1. Create a **public** repository named `{}` under your account.\n",
+        PROFILE_REPO_NAME
+    ));
+    body.push_str(&format!(
+        "2. Add a file `{}` on its default branch containing only your Ergo \
+         mainnet P2PK address (it starts with `9`).\n",
+        WALLET_FILE_PATH
+    ));
+    body.push_str(&format!(
+        "3. Optionally add `{}` next to it:\n\n",
+        PREFERENCES_FILE_PATH
+    ));
+    body.push_str(
+        "```toml\n\
+         display_name = \"Your Name\"\n\
+         payout_schedule = \"monthly\" # immediate, weekly or monthly\n\
+         min_payout_erg = 1.0\n\
+         ```\n",
+    );
+    body
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    #[test]
+    fn body_lists_tracked_branches_and_setup_steps() {
+        let project = Project {
+            description: Some("Composable widgets.".into()),
+            ..Project::for_test("widgets-1a2b", "Widgets")
+        };
+        let repo = Repository {
+            owner: "acme".into(),
+            name: "widgets".into(),
+            current_base_branch: "develop".into(),
+            last_sync: None,
+            total_prs: 0,
+            first_sync: Utc::now(),
+            metadata: None,
+        };
+
+        let body = announcement_body(&project, &[repo]);
+        assert!(body.starts_with("<!-- gitcircles:announcement widgets-1a2b -->"));
+        assert!(body.contains("Composable widgets."));
+        assert!(body.contains("`acme/widgets` (`develop`)"));
+        assert!(body.contains("named `gitcircles-profile`"));
+        assert!(body.contains("`P2PK.pub`"));
+        assert!(body.contains("`gitcircles.toml`"));
+    }
+}
@@ -0,0 +1,231 @@
+//! Pre-payout heuristics for sybil accounts and reward farming. These only
+//! flag patterns for a human to review; nothing is blocked automatically.
+
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::types::{MergedPullRequest, WalletAddress, WalletLoginLink};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Anomaly {
+    /// Several logins pay into the same wallet
+    WalletFunnel {
+        wallet: WalletAddress,
+        logins: Vec<String>,
+    },
+    /// One author merged many PRs into a repository in a short window
+    PrBurst {
+        repository: String,
+        author: String,
+        count: usize,
+        first_merged_at: DateTime<Utc>,
+        last_merged_at: DateTime<Utc>,
+    },
+    /// The author's account was created shortly before their first PR
+    NewAccount {
+        login: String,
+        account_created_at: DateTime<Utc>,
+        first_merged_at: DateTime<Utc>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct AnomalyThresholds {
+    pub funnel_min_logins: usize,
+    pub burst_min_prs: usize,
+    pub burst_window: Duration,
+    pub new_account_max_age: Duration,
+}
+
+impl Default for AnomalyThresholds {
+    fn default() -> Self {
+        Self {
+            funnel_min_logins: 3,
+            burst_min_prs: 5,
+            burst_window: Duration::hours(24),
+            new_account_max_age: Duration::days(7),
+        }
+    }
+}
+
+pub fn detect_wallet_funnels(
+    links: &[WalletLoginLink],
+    thresholds: &AnomalyThresholds,
+) -> Vec<Anomaly> {
+    let mut by_wallet: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for link in links {
+        let logins = by_wallet.entry(link.wallet.as_str()).or_default();
+        if !logins.contains(&link.login) {
+            logins.push(link.login.clone());
+        }
+    }
+
+    links
+        .iter()
+        .filter_map(|link| {
+            let logins = by_wallet.remove(link.wallet.as_str())?;
+            (logins.len() >= thresholds.funnel_min_logins).then(|| {
+                Anomaly::WalletFunnel {
+                    wallet: link.wallet.clone(),
+                    logins,
+                }
+            })
+        })
+        .collect()
+}
+
+/// Flags the densest window per (repository, author) holding at least
+/// `burst_min_prs` merges.
+pub fn detect_pr_bursts(
+    prs: &[MergedPullRequest],
+    thresholds: &AnomalyThresholds,
+) -> Vec<Anomaly> {
+    let mut groups: BTreeMap<(&str, &str), Vec<DateTime<Utc>>> = BTreeMap::new();
+    for pr in prs {
+        groups
+            .entry((pr.repository.as_str(), pr.author.as_str()))
+            .or_default()
+            .push(pr.merged_at);
+    }
+
+    let mut anomalies = Vec::new();
+    for ((repository, author), mut times) in groups {
+        times.sort();
+        let mut best: Option<(usize, usize)> = None;
+        let mut start = 0;
+        for end in 0..times.len() {
+            while times[end] - times[start] > thresholds.burst_window {
+                start += 1;
+            }
+            if best.is_none_or(|(s, e)| end - start > e - s) {
+                best = Some((start, end));
+            }
+        }
+
+        if let Some((s, e)) = best
+            && e - s + 1 >= thresholds.burst_min_prs
+        {
+            anomalies.push(Anomaly::PrBurst {
+                repository: repository.to_string(),
+                author: author.to_string(),
+                count: e - s + 1,
+                first_merged_at: times[s],
+                last_merged_at: times[e],
+            });
+        }
+    }
+    anomalies
+}
+
+/// `account_created` maps logins to their GitHub account creation time;
+/// authors missing from it are skipped.
+pub fn detect_new_accounts(
+    prs: &[MergedPullRequest],
+    account_created: &HashMap<String, DateTime<Utc>>,
+    thresholds: &AnomalyThresholds,
+) -> Vec<Anomaly> {
+    let mut first_pr: BTreeMap<&str, DateTime<Utc>> = BTreeMap::new();
+    for pr in prs {
+        let first = first_pr.entry(pr.author.as_str()).or_insert(pr.merged_at);
+        *first = (*first).min(pr.merged_at);
+    }
+
+    first_pr
+        .into_iter()
+        .filter_map(|(login, first_merged_at)| {
+            let created = *account_created.get(login)?;
+            (first_merged_at - created <= thresholds.new_account_max_age).then(
+                || Anomaly::NewAccount {
+                    login: login.to_string(),
+                    account_created_at: created,
+                    first_merged_at,
+                },
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(hours: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(1_700_000_000, 0).unwrap() + Duration::hours(hours)
+    }
+
+    fn pr(number: u64, author: &str, hours: i64) -> MergedPullRequest {
+        MergedPullRequest {
+            title: format!("Typo fix {}", number),
+            merged_at: at(hours),
+            from_fork: true,
+            ..MergedPullRequest::for_test(number, author)
+        }
+    }
+
+    #[test]
+    fn wallets_with_many_logins_are_funnels() {
+        let wallet = WalletAddress::try_from(
+            "9hUzb5RvSgDqJdtyCN9Ke496Yy63mpcUJKbRq4swzQ5EQKgygKT",
+        )
+        .unwrap();
+        let link = |login: &str| WalletLoginLink {
+            wallet: wallet.clone(),
+            platform: "github".into(),
+            login: login.into(),
+            linked_at: at(0),
+        };
+        let links = [link("a"), link("b"), link("b")];
+        let thresholds = AnomalyThresholds::default();
+        assert!(detect_wallet_funnels(&links, &thresholds).is_empty());
+
+        let links = [link("a"), link("b"), link("c")];
+        assert_eq!(
+            detect_wallet_funnels(&links, &thresholds),
+            [Anomaly::WalletFunnel {
+                wallet,
+                logins: vec!["a".into(), "b".into(), "c".into()],
+            }]
+        );
+    }
+
+    #[test]
+    fn bursts_need_enough_prs_inside_the_window() {
+        let mut prs: Vec<_> =
+            (0..5).map(|n| pr(n, "farmer", n as i64 * 5)).collect();
+        prs.push(pr(10, "steady", 0));
+        prs.push(pr(11, "steady", 48));
+
+        let anomalies = detect_pr_bursts(&prs, &AnomalyThresholds::default());
+        assert_eq!(anomalies.len(), 1);
+        assert!(matches!(
+            &anomalies[0],
+            Anomaly::PrBurst { author, count: 5, .. } if author == "farmer"
+        ));
+
+        // Spread past 24h, the densest window only holds four PRs
+        prs[4].merged_at = at(30);
+        assert!(detect_pr_bursts(&prs, &AnomalyThresholds::default()).is_empty());
+    }
+
+    #[test]
+    fn accounts_created_just_before_their_first_pr_are_flagged() {
+        let prs = [
+            pr(1, "fresh", 100),
+            pr(2, "fresh", 10),
+            pr(3, "veteran", 10),
+        ];
+        let created = HashMap::from([
+            ("fresh".to_string(), at(0)),
+            ("veteran".to_string(), at(-24 * 365)),
+        ]);
+        assert_eq!(
+            detect_new_accounts(&prs, &created, &AnomalyThresholds::default()),
+            [Anomaly::NewAccount {
+                login: "fresh".into(),
+                account_created_at: at(0),
+                first_merged_at: at(10),
+            }]
+        );
+    }
+}
@@ -1,11 +1,30 @@
-use clap::{Parser, Subcommand};
-use comfy_table::presets::UTF8_FULL;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use clap::{Parser, Subcommand, ValueEnum};
+use comfy_table::presets::{ASCII_FULL, UTF8_FULL};
 use comfy_table::{ContentArrangement, Table};
+use serde::Serialize;
+use std::io::IsTerminal;
 
+use crate::anomalies::Anomaly;
+use crate::config::{Config, EffectiveSetting, SettingSource};
+use crate::digest::ProjectDigest;
+use crate::doctor::{CheckStatus, DoctorCheck};
+use crate::ergo::{DEFAULT_NODE_URL, NodeHealth};
+use crate::gitcircles::{
+    ContributorProfile, ImportOutcome, PayoutStanding, ProjectStatus,
+};
+use crate::output::{Icon, info, local_time, output, status};
+use crate::treasury::TreasuryHistory;
 use crate::types::{
-    MergedPullRequest, Project, ProjectOwner, Repository, UserWallet,
-    WalletHistoryEntry,
+    AddressBookEntry, ContributorOptOut, ContributorPreferences, ForgetReport,
+    GitCirclesError, MergedPullRequest, OptOutSource, ProfileRef, Project,
+    ProjectCounters, ProjectLink, ProjectOwner, ProjectTreasury, PullRequestChange,
+    Repository, Result, SyncLogEntry, UserWallet, WalletHistoryEntry, WalletLookup,
+    WalletSource, WalletTransition, WalletWarning, WalletWarningKind,
+    get_database_path,
 };
+use crate::wallet::DEFAULT_CONFIRMATION_WINDOW_HOURS;
 
 #[derive(Parser)]
 #[command(name = "gitcircles-github")]
@@ -13,6 +32,37 @@ use crate::types::{
 pub struct Cli {
     #[command(subcommand)]
     pub command: Commands,
+
+    /// GitHub login to act as; project changes then require a matching role
+    #[arg(long = "as", global = true, value_name = "LOGIN")]
+    pub actor: Option<String>,
+
+    /// Disable colors and terminal hyperlinks (also set by NO_COLOR)
+    #[arg(long, global = true)]
+    pub no_color: bool,
+
+    /// Only print results, warnings and errors
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+
+    /// Use ASCII table borders and text markers instead of emoji
+    #[arg(long, global = true)]
+    pub ascii: bool,
+
+    /// Treat unreadable database records as missing instead of failing
+    #[arg(long, global = true)]
+    pub lenient: bool,
+
+    /// Read from GitHub but store nothing and post no comments; `collect`
+    /// and `wallet sync` print what would change. Commands that change
+    /// data without a dry-run mode refuse it
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+
+    /// Show timestamps in this IANA zone (e.g. "Europe/Berlin") instead of
+    /// `display.timezone` from the config file
+    #[arg(long, global = true, value_name = "ZONE", value_parser = parse_timezone)]
+    pub timezone: Option<Tz>,
 }
 
 #[derive(Subcommand)]
@@ -35,16 +85,66 @@ pub enum Commands {
         #[arg(short, long)]
         days: Option<u64>,
 
+        /// Backfill PRs merged on or after this date (YYYY-MM-DD) via the
+        /// Search API
+        #[arg(long, value_name = "DATE", conflicts_with_all = ["days", "detect_external_merges"])]
+        from: Option<NaiveDate>,
+
+        /// Last merge date to backfill, inclusive [default: today]
+        #[arg(long, value_name = "DATE", requires = "from")]
+        to: Option<NaiveDate>,
+
         /// Project ID to associate this repository with (optional)
         #[arg(short, long)]
         project_id: Option<String>,
+
+        /// Skip fetching PR commits to resolve co-authors (saves API calls)
+        #[arg(long)]
+        skip_commit_authors: bool,
+
+        /// Truncate stored PR descriptions to this many characters
+        #[arg(long, default_value_t = 4000)]
+        max_body_chars: usize,
+
+        /// PRs requested per listing page (1-100)
+        #[arg(long, default_value_t = 100, value_parser = clap::value_parser!(u8).range(1..=100))]
+        per_page: u8,
+
+        /// Stop listing after this many pages
+        #[arg(long)]
+        max_pages: Option<u32>,
+
+        /// Store at most this many new PRs; later runs pick up the rest
+        #[arg(long)]
+        max_prs: Option<usize>,
+
+        /// Also update stored PRs whose title, labels or author association
+        /// changed after merge
+        #[arg(long)]
+        refresh: bool,
+
+        /// Also collect closed PRs whose head commit is on the base branch
+        /// although GitHub shows no merge (one extra API call per such PR)
+        #[arg(long)]
+        detect_external_merges: bool,
+
+        /// Limit the project to PRs touching these paths, e.g.
+        /// 'packages/foo/**' (repeatable; replaces the stored claim)
+        #[arg(long = "path", value_name = "GLOB")]
+        paths: Vec<String>,
     },
 
     /// Show status of tracked repositories
     Status {
-        /// Show status for a specific project only
-        #[arg(short, long)]
+        /// Show only this project's repositories, PR counts and payout
+        /// readiness
+        #[arg(short, long, visible_alias = "project")]
         project_id: Option<String>,
+
+        /// Also show each repository's stars, forks, language, license and
+        /// description
+        #[arg(long)]
+        detailed: bool,
     },
 
     /// Initialize local database
@@ -57,6 +157,26 @@ pub enum Commands {
         token: Option<String>,
     },
 
+    /// Check the setup end to end: config, database, GitHub token and its
+    /// scopes, Ergo node
+    Doctor {
+        /// GitHub personal access token
+        #[arg(short, long)]
+        token: Option<String>,
+
+        /// Node REST API URL (or use ERGO_NODE_URL env var)
+        #[arg(long)]
+        node_url: Option<String>,
+
+        /// Node API key (or use ERGO_NODE_API_KEY env var)
+        #[arg(long)]
+        node_api_key: Option<String>,
+    },
+
+    /// Check and inspect ~/.gitcircles/config.toml
+    #[command(subcommand)]
+    Config(ConfigCommands),
+
     /// Manage projects
     #[command(subcommand)]
     Project(ProjectCommands),
@@ -64,6 +184,434 @@ pub enum Commands {
     /// Wallet management commands
     #[command(subcommand)]
     Wallet(WalletCommands),
+
+    /// Name known addresses, such as treasuries and exchanges, for reports
+    #[command(subcommand)]
+    AddressBook(AddressBookCommands),
+
+    /// Inspect stored pull requests
+    #[command(subcommand)]
+    Pr(PrCommands),
+
+    /// Manage contributor settings such as reward opt-outs
+    #[command(subcommand)]
+    Contributor(ContributorCommands),
+
+    /// Ergo node checks
+    #[command(subcommand)]
+    Node(NodeCommands),
+
+    /// Show the ERG/USD price from the feed configured under [price]
+    Price {
+        /// Fetch a new price even if the cached one is recent
+        #[arg(short, long)]
+        refresh: bool,
+    },
+
+    /// Treasury transactions from the explorer configured under [treasury]
+    #[command(subcommand)]
+    Treasury(TreasuryCommands),
+
+    /// Reports over collected data
+    #[command(subcommand)]
+    Report(ReportCommands),
+
+    /// Inspect tracked repositories
+    #[command(subcommand)]
+    Repo(RepoCommands),
+
+    /// Backfill PR history from outside the GitHub API
+    #[command(subcommand)]
+    Import(ImportCommands),
+
+    /// Handle personal data requests
+    #[command(subcommand)]
+    Privacy(PrivacyCommands),
+}
+
+#[derive(Subcommand)]
+pub enum PrivacyCommands {
+    /// Delete a login's wallets, history and contributor records, keeping
+    /// its PRs under a pseudonym so counts don't change
+    Forget {
+        /// GitHub login to forget
+        #[arg(short, long)]
+        login: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ImportCommands {
+    /// Store a local clone's merge commits as imported PRs, e.g. for
+    /// history that predates GitHub
+    GitLog {
+        /// Path to the local clone
+        #[arg(short, long)]
+        repo: std::path::PathBuf,
+
+        /// Repository to store the PRs under (owner/repo) [default: the
+        /// clone's origin remote]
+        #[arg(short, long)]
+        name: Option<String>,
+
+        /// Branch whose merges to import [default: the checked-out branch]
+        #[arg(short, long)]
+        base_branch: Option<String>,
+    },
+
+    /// Store merged PRs from a GitHub export without using the API: an
+    /// extracted migration archive or a decompressed GH Archive file
+    Archive {
+        /// Directory of an extracted migration archive, or a GH Archive
+        /// JSON file (one event per line)
+        #[arg(short, long)]
+        path: std::path::PathBuf,
+
+        /// Repository whose PRs to import (owner/repo)
+        #[arg(short, long)]
+        repo: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommands {
+    /// Report syntax errors, unknown keys and values of the wrong type
+    Validate {
+        /// Config file to check [default: ~/.gitcircles/config.toml]
+        #[arg(long)]
+        file: Option<std::path::PathBuf>,
+    },
+
+    /// Print the settings loaded from the config file
+    Show {
+        /// Also resolve environment variables and global flags, showing
+        /// where each setting came from
+        #[arg(long)]
+        effective: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum RepoCommands {
+    /// Show recent collect runs for a repository, newest first
+    SyncLog {
+        /// Repository in format "owner/repo"
+        #[arg(short, long)]
+        repo: String,
+
+        /// Maximum number of runs to show
+        #[arg(short, long, default_value_t = 20)]
+        limit: usize,
+    },
+
+    /// Recompute every repository's PR counter from the stored PRs
+    Recount,
+}
+
+#[derive(Subcommand)]
+pub enum ReportCommands {
+    /// Flag possible sybil accounts and reward farming before payouts
+    Anomalies {
+        /// Only look at one project
+        #[arg(short, long)]
+        project_id: Option<String>,
+
+        /// Also flag accounts created shortly before their first PR
+        /// (one GitHub API call per author)
+        #[arg(long)]
+        check_accounts: bool,
+
+        /// GitHub personal access token (for --check-accounts)
+        #[arg(short, long)]
+        token: Option<String>,
+    },
+
+    /// Summarise recent activity per project: new PRs, new contributors and
+    /// contributors still missing a wallet
+    Digest {
+        /// Only summarise one project
+        #[arg(short, long)]
+        project_id: Option<String>,
+
+        /// Length of the period in days
+        #[arg(short, long, default_value_t = 7)]
+        days: i64,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum NodeCommands {
+    /// Check that the Ergo node is reachable, synced and ready for payouts
+    Status {
+        /// Node REST API URL (or use ERGO_NODE_URL env var)
+        #[arg(short, long)]
+        url: Option<String>,
+
+        /// Node API key, needed for the wallet check (or use ERGO_NODE_API_KEY)
+        #[arg(short = 'k', long)]
+        api_key: Option<String>,
+
+        /// Also require an initialized, unlocked node wallet
+        #[arg(short, long)]
+        wallet: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ContributorCommands {
+    /// Record that a contributor declines rewards
+    OptOut {
+        /// GitHub username
+        login: String,
+    },
+
+    /// Withdraw a contributor's opt-out
+    OptIn {
+        /// GitHub username
+        login: String,
+    },
+
+    /// List contributors who opted out
+    OptOuts,
+
+    /// Show a contributor's PR totals, repositories and wallet status
+    Show {
+        /// GitHub username
+        login: String,
+    },
+
+    /// Record opt-outs from "OPT OUT" comments on a pull request
+    ScanComments {
+        /// Repository in format "owner/repo"
+        repo: String,
+
+        /// Pull request number
+        number: u64,
+
+        /// GitHub personal access token
+        #[arg(short, long)]
+        token: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum PrCommands {
+    /// Show a stored pull request with its provenance
+    Show {
+        /// Repository in format "owner/repo"
+        repo: String,
+
+        /// Pull request number
+        number: u64,
+
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+
+        /// Replace logins and addresses with stable ids derived from
+        /// privacy.pseudonym_key, e.g. for publishing the export
+        #[arg(long)]
+        pseudonymize: bool,
+    },
+
+    /// Search stored pull requests by title, description and author
+    Search {
+        /// Words to match (all must match, prefixes allowed)
+        query: String,
+
+        /// Limit results to one repository ("owner/repo")
+        #[arg(short, long)]
+        repo: Option<String>,
+
+        /// Rebuild the search index from stored PRs first
+        #[arg(long)]
+        reindex: bool,
+
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+
+        /// Replace logins and addresses with stable ids derived from
+        /// privacy.pseudonym_key, e.g. for publishing the export
+        #[arg(long)]
+        pseudonymize: bool,
+    },
+
+    /// Label PRs on GitHub with their pipeline state (scheduled, stopped)
+    ///
+    /// Needs labels.enabled = true in config.toml; the label names are
+    /// configurable there too.
+    Labels {
+        /// Only label PRs of one repository ("owner/repo")
+        #[arg(short, long)]
+        repo: Option<String>,
+
+        /// GitHub personal access token
+        #[arg(short, long)]
+        token: Option<String>,
+    },
+
+    /// Publish each PR's pipeline state as a commit status on its merge
+    /// commit
+    ///
+    /// Needs statuses.enabled = true in config.toml, where the status
+    /// context and details link are set too.
+    Statuses {
+        /// Only publish for PRs of one repository ("owner/repo")
+        #[arg(short, long)]
+        repo: Option<String>,
+
+        /// GitHub personal access token
+        #[arg(short, long)]
+        token: Option<String>,
+    },
+}
+
+impl Cli {
+    /// Settings from `config` (with the raw `file_text` marking what the
+    /// file set) followed by those taken from the environment and global
+    /// flags. Secrets are only reported as set or unset.
+    pub fn effective_settings(
+        &self,
+        config: &Config,
+        file_text: Option<&str>,
+    ) -> Result<Vec<EffectiveSetting>> {
+        let env = |name: &'static str| {
+            std::env::var(name)
+                .ok()
+                .filter(|value| !value.is_empty())
+                .map(|value| (value, SettingSource::Env(name)))
+        };
+        let secret = |name: &'static str| {
+            env(name)
+                .map_or(("unset".to_string(), SettingSource::Default), |(_, s)| {
+                    ("set".to_string(), s)
+                })
+        };
+        let flag = |key: &str, set: bool, name: &'static str| EffectiveSetting {
+            key: key.to_string(),
+            value: set.to_string(),
+            source: if set {
+                SettingSource::Flag(name)
+            } else {
+                SettingSource::Default
+            },
+        };
+        let setting = |key: &str, (value, source): (String, SettingSource)| {
+            EffectiveSetting {
+                key: key.to_string(),
+                value,
+                source,
+            }
+        };
+
+        let mut settings = config.settings(file_text);
+        if let Some(timezone) = self.timezone
+            && let Some(shown) =
+                settings.iter_mut().find(|s| s.key == "display.timezone")
+        {
+            shown.value = timezone.to_string();
+            shown.source = SettingSource::Flag("--timezone");
+        }
+        settings.push(setting(
+            "database.path",
+            (get_database_path()?, SettingSource::Default),
+        ));
+        settings.push(setting("github.token", secret("GITHUB_TOKEN")));
+        settings.push(setting(
+            "ergo.node_url",
+            env("ERGO_NODE_URL")
+                .unwrap_or((DEFAULT_NODE_URL.to_string(), SettingSource::Default)),
+        ));
+        settings.push(setting("ergo.node_api_key", secret("ERGO_NODE_API_KEY")));
+        settings.push(setting(
+            "output.no_color",
+            if self.no_color {
+                ("true".to_string(), SettingSource::Flag("--no-color"))
+            } else {
+                env("NO_COLOR").map_or(
+                    ("false".to_string(), SettingSource::Default),
+                    |(_, source)| ("true".to_string(), source),
+                )
+            },
+        ));
+        settings.push(flag("output.quiet", self.quiet, "--quiet"));
+        settings.push(flag("output.ascii", self.ascii, "--ascii"));
+        settings.push(flag("database.lenient", self.lenient, "--lenient"));
+        settings.push(flag("dry_run", self.dry_run, "--dry-run"));
+        settings.push(setting(
+            "actor",
+            self.actor
+                .clone()
+                .map_or(("-".to_string(), SettingSource::Default), |login| {
+                    (login, SettingSource::Flag("--as"))
+                }),
+        ));
+        Ok(settings)
+    }
+
+    /// Format requested by the subcommand, for reporting errors the same
+    /// way. Commands without `--format` print tables.
+    pub fn output_format(&self) -> OutputFormat {
+        match &self.command {
+            Commands::Pr(
+                PrCommands::Show { format, .. } | PrCommands::Search { format, .. },
+            ) => *format,
+            Commands::Wallet(WalletCommands::LookupMany { format, .. }) => *format,
+            _ => OutputFormat::Table,
+        }
+    }
+
+    /// Whether the subcommand honours `--dry-run`: it either only reads or
+    /// skips its writes under a dry run.
+    pub fn supports_dry_run(&self) -> bool {
+        !matches!(
+            &self.command,
+            Commands::Init
+                | Commands::Project(
+                    ProjectCommands::Create { .. }
+                        | ProjectCommands::Delete { .. }
+                        | ProjectCommands::AddOwner { .. }
+                        | ProjectCommands::RemoveOwner { .. }
+                        | ProjectCommands::AddTreasury { .. }
+                        | ProjectCommands::RemoveTreasury { .. }
+                        | ProjectCommands::Link { .. }
+                        | ProjectCommands::Unlink { .. }
+                        | ProjectCommands::Allow { .. }
+                        | ProjectCommands::Block { .. }
+                        | ProjectCommands::ExcludeOrg { .. }
+                        | ProjectCommands::Pause { .. }
+                        | ProjectCommands::Resume { .. }
+                        | ProjectCommands::RequireApproval { .. }
+                        | ProjectCommands::Approve { .. }
+                        | ProjectCommands::Announce { .. }
+                )
+                | Commands::AddressBook(
+                    AddressBookCommands::Set { .. }
+                        | AddressBookCommands::Remove { .. }
+                )
+                | Commands::Contributor(
+                    ContributorCommands::OptOut { .. }
+                        | ContributorCommands::OptIn { .. }
+                        | ContributorCommands::ScanComments { .. }
+                )
+                | Commands::Wallet(
+                    WalletCommands::Rollback { .. }
+                        | WalletCommands::InitProfile { .. }
+                )
+                | Commands::Repo(RepoCommands::Recount)
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Table with clickable links where the terminal supports them
+    Table,
+    /// JSON array with one object per pull request
+    Json,
+    /// CSV with a header row
+    Csv,
 }
 
 #[derive(Subcommand)]
@@ -114,79 +662,806 @@ pub enum ProjectCommands {
         /// GitHub username
         username: String,
     },
-}
 
-#[derive(Subcommand)]
-pub enum WalletCommands {
-    /// Fetch and sync wallet address for a GitHub user
-    ///
-    /// Reads P2PK.pub from <login>/gitcircles-payment-address repository.
-    /// Token can be provided via --token or GITHUB_TOKEN environment variable.
-    Sync {
-        /// GitHub username
-        login: String,
+    /// Register a treasury address for a project
+    AddTreasury {
+        /// Project ID
+        project_id: String,
 
-        /// GitHub personal access token
+        /// Ergo address, P2PK or multi-sig
+        address: String,
+
+        /// Name, e.g. "hot wallet"
         #[arg(short, long)]
-        token: Option<String>,
-    },
+        name: String,
 
-    /// Show current wallet address for a GitHub user
-    Show {
-        /// GitHub username
-        login: String,
+        /// Largest payout in ERG this treasury makes; unlimited when omitted
+        #[arg(long)]
+        max_payout: Option<f64>,
     },
 
-    /// Show wallet address history for a GitHub user
-    History {
-        /// GitHub username
-        login: String,
-    },
+    /// Remove a treasury address from a project
+    RemoveTreasury {
+        /// Project ID
+        project_id: String,
 
-    /// Find all GitHub logins associated with a wallet address
-    Lookup {
-        /// Wallet address
-        wallet: String,
+        /// Ergo address
+        address: String,
     },
-}
 
-pub fn display_pull_requests(prs: &[MergedPullRequest]) {
-    if prs.is_empty() {
-        println!("No merged pull requests found.");
-        return;
-    }
+    /// List a project's treasuries in routing order
+    Treasuries {
+        /// Project ID
+        project_id: String,
 
-    let mut table = Table::new();
-    table
-        .load_preset(UTF8_FULL)
-        .set_content_arrangement(ContentArrangement::Dynamic)
-        .set_header(vec![
-            "PR#",
-            "Title",
-            "Author",
-            "Merged Date",
-            "Base Branch",
-            "Commit SHA",
-        ]);
+        /// Preview which treasury would pay a payout of this many ERG.
+        /// Nothing is paid; payouts are not made yet
+        #[arg(long, value_name = "ERG")]
+        route: Option<f64>,
+    },
 
-    for pr in prs {
-        table.add_row(vec![
-            pr.number.to_string(),
-            if pr.title.len() > 50 {
-                format!("{}...", &pr.title[..47])
-            } else {
-                pr.title.clone()
-            },
-            pr.author.clone(),
-            pr.merged_at.format("%Y-%m-%d %H:%M UTC").to_string(),
-            pr.base_branch.clone(),
-            pr.merge_commit_sha[..8].to_string(),
-        ]);
-    }
+    /// Count a repository's PRs for a project
+    ///
+    /// A repository can feed several projects. Re-linking replaces the
+    /// path claims; without --path the project claims the whole repository.
+    Link {
+        /// Project ID
+        project_id: String,
 
-    println!("\n{}", table);
-    println!("Total merged PRs: {}", prs.len());
-}
+        /// Repository (owner/repo)
+        #[arg(short, long)]
+        repo: String,
+
+        /// Only count PRs touching these paths, e.g. 'packages/foo/**'
+        /// (repeatable)
+        #[arg(long = "path", value_name = "GLOB")]
+        paths: Vec<String>,
+    },
+
+    /// Stop counting a repository's PRs for a project
+    Unlink {
+        /// Project ID
+        project_id: String,
+
+        /// Repository (owner/repo)
+        #[arg(short, long)]
+        repo: String,
+    },
+
+    /// Only count PRs by these logins for the project
+    Allow {
+        /// Project ID
+        project_id: String,
+
+        /// GitHub logins
+        #[arg(required = true)]
+        logins: Vec<String>,
+
+        /// Remove the logins from the allowlist instead
+        #[arg(long)]
+        remove: bool,
+    },
+
+    /// Never count PRs by these logins for the project, e.g. employees who
+    /// are paid separately
+    Block {
+        /// Project ID
+        project_id: String,
+
+        /// GitHub logins
+        #[arg(required = true)]
+        logins: Vec<String>,
+
+        /// Remove the logins from the blocklist instead
+        #[arg(long)]
+        remove: bool,
+    },
+
+    /// Don't count PRs by members of these GitHub organizations, e.g. the
+    /// company behind the project
+    ///
+    /// Authors of stored PRs are checked at once, new authors as their PRs
+    /// are collected. Private memberships are only visible to tokens of
+    /// organization members.
+    ExcludeOrg {
+        /// Project ID
+        project_id: String,
+
+        /// GitHub organization logins
+        #[arg(required = true)]
+        orgs: Vec<String>,
+
+        /// Stop excluding the organizations, dropping their recorded members
+        #[arg(long)]
+        remove: bool,
+
+        /// GitHub personal access token
+        #[arg(short, long)]
+        token: Option<String>,
+    },
+
+    /// Stop scheduling a project's PRs, e.g. during a treasury migration or
+    /// an incident
+    Pause {
+        /// Project ID
+        project_id: String,
+
+        /// Why the project is paused, shown in project show/status
+        #[arg(short, long)]
+        reason: Option<String>,
+    },
+
+    /// Resume a paused project; the next label or status sync schedules its
+    /// PRs again
+    Resume {
+        /// Project ID
+        project_id: String,
+    },
+
+    /// Hold back scheduling of every PR until an owner or admin approves
+    /// it
+    RequireApproval {
+        /// Project ID
+        project_id: String,
+
+        /// Turn the approval gate off again
+        #[arg(long)]
+        off: bool,
+    },
+
+    /// Approve a pull request for a project that requires approval
+    Approve {
+        /// Project ID
+        project_id: String,
+
+        /// Repository in format "owner/repo"
+        repo: String,
+
+        /// Pull request number
+        number: u64,
+    },
+
+    /// List PRs waiting for approval
+    ///
+    /// With --scan, "GitCircles APPROVE" comments by the project's owners
+    /// and admins on the waiting PRs are recorded first.
+    Approvals {
+        /// Project ID
+        project_id: String,
+
+        /// Look for approval comments on GitHub first
+        #[arg(short, long)]
+        scan: bool,
+
+        /// GitHub personal access token (for --scan)
+        #[arg(short, long)]
+        token: Option<String>,
+    },
+
+    /// Open or update a pinned issue explaining GitCircles to contributors
+    ///
+    /// The issue describes the reward policy and how to set up the
+    /// gitcircles-profile wallet repository. Re-running refreshes it.
+    Announce {
+        /// Project ID
+        project_id: String,
+
+        /// Repository to open the issue in (owner/repo)
+        #[arg(short, long)]
+        repo: String,
+
+        /// GitHub personal access token
+        #[arg(short, long)]
+        token: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum TreasuryCommands {
+    /// Fetch treasury transactions not stored yet
+    Sync {
+        /// Sync the project's treasuries instead of treasury.address
+        #[arg(short, long)]
+        project_id: Option<String>,
+    },
+
+    /// Show treasury inflows and outflows, newest first, syncing first when
+    /// the last sync is older than treasury.sync_interval_secs
+    History {
+        /// Show the project's treasuries instead of treasury.address
+        #[arg(short, long)]
+        project_id: Option<String>,
+
+        /// Sync even if the last sync is recent
+        #[arg(short, long)]
+        sync: bool,
+
+        /// Show at most this many transactions; totals cover all of them
+        #[arg(short, long)]
+        limit: Option<usize>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum AddressBookCommands {
+    /// Name an address, replacing any earlier name
+    Set {
+        /// Ergo address
+        address: String,
+
+        /// Human-readable name, e.g. "Project treasury"
+        name: String,
+
+        /// Kind (treasury, exchange, contributor, blacklisted)
+        #[arg(short, long)]
+        kind: String,
+
+        /// Free-form note, e.g. where the name came from
+        #[arg(long)]
+        note: Option<String>,
+    },
+
+    /// Remove an address's name
+    Remove {
+        /// Ergo address
+        address: String,
+    },
+
+    /// List named addresses
+    List,
+}
+
+#[derive(Subcommand)]
+pub enum WalletCommands {
+    /// Fetch and sync wallet address for a GitHub user
+    ///
+    /// Reads P2PK.pub from <login>/gitcircles-payment-address repository.
+    /// Token can be provided via --token or GITHUB_TOKEN environment variable.
+    Sync {
+        /// GitHub username
+        login: String,
+
+        /// GitHub personal access token
+        #[arg(short, long)]
+        token: Option<String>,
+
+        /// Probe GitHub even if the user recently had no profile repository
+        #[arg(short, long)]
+        force: bool,
+
+        /// Open an issue on the profile repository when the address changed
+        #[arg(short, long)]
+        notify: bool,
+
+        /// Hours a changed address waits before new payouts use it
+        #[arg(long, default_value_t = DEFAULT_CONFIRMATION_WINDOW_HOURS)]
+        confirmation_hours: i64,
+
+        /// Read P2PK.pub from this branch, or a tag as "tags/<name>"
+        #[arg(long = "ref", value_name = "REF")]
+        wallet_ref: Option<ProfileRef>,
+    },
+
+    /// Show current wallet address for a GitHub user
+    Show {
+        /// GitHub username
+        login: String,
+    },
+
+    /// Show wallet address history for a GitHub user
+    History {
+        /// GitHub username
+        login: String,
+
+        /// Show each address change and how long the new address was held
+        #[arg(short, long)]
+        diff: bool,
+    },
+
+    /// Make an earlier address current again, e.g. after a hijacked profile
+    ///
+    /// Restores the address that was current at --to and applies it right
+    /// away. The next `wallet sync` reads the profile repository again, so
+    /// fix the profile before syncing.
+    Rollback {
+        /// GitHub username
+        #[arg(short, long)]
+        login: String,
+
+        /// Point in time as shown by `wallet history` ("2024-05-01 12:30",
+        /// in the display time zone) or RFC 3339
+        #[arg(long, value_name = "TIMESTAMP")]
+        to: String,
+    },
+
+    /// Find all GitHub logins associated with a wallet address
+    Lookup {
+        /// Wallet address
+        wallet: String,
+    },
+
+    /// Resolve a list of wallet addresses to GitHub logins in one pass
+    ///
+    /// Reads one address per line; blank lines and `#` comments are skipped.
+    LookupMany {
+        /// File with the addresses; reads stdin when omitted or "-"
+        #[arg(long)]
+        file: Option<std::path::PathBuf>,
+
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+
+        /// Replace logins and addresses with stable ids derived from
+        /// privacy.pseudonym_key, e.g. for publishing the export
+        #[arg(long)]
+        pseudonymize: bool,
+    },
+
+    /// List warnings raised during wallet syncs, such as shared addresses
+    Warnings,
+
+    /// Create your gitcircles-profile repository and publish a wallet
+    ///
+    /// Creates the public repository for the token's user if it is missing,
+    /// then commits P2PK.pub with the address and, if given, gitcircles.toml.
+    InitProfile {
+        /// Ergo mainnet P2PK address (starts with 9)
+        #[arg(short, long)]
+        address: String,
+
+        /// Local gitcircles.toml to commit alongside the address
+        #[arg(short, long)]
+        preferences: Option<std::path::PathBuf>,
+
+        /// GitHub personal access token
+        #[arg(short, long)]
+        token: Option<String>,
+    },
+}
+
+fn parse_timezone(value: &str) -> std::result::Result<Tz, String> {
+    value
+        .parse()
+        .map_err(|_| format!("unknown time zone '{}'", value))
+}
+
+/// Accepts RFC 3339 or the minute-precision form the tables print, read in
+/// `timezone` unless it ends in "UTC". The latter means the end of that
+/// minute, so an entry shown as "12:30" is included when passing "12:30".
+pub fn parse_timestamp(
+    value: &str,
+    timezone: Tz,
+) -> std::result::Result<DateTime<Utc>, String> {
+    if let Ok(at) = DateTime::parse_from_rfc3339(value) {
+        return Ok(at.with_timezone(&Utc));
+    }
+    let (shown, zone) = match value.rsplit_once(' ') {
+        Some((shown, zone)) if shown.contains(' ') => (shown, Some(zone)),
+        _ => (value, None),
+    };
+    let naive =
+        NaiveDateTime::parse_from_str(shown, "%Y-%m-%d %H:%M").map_err(|_| {
+            format!("expected \"YYYY-MM-DD HH:MM\" or RFC 3339, got '{}'", value)
+        })?;
+    let at = if zone == Some("UTC") {
+        naive.and_utc()
+    } else {
+        let local = timezone
+            .from_local_datetime(&naive)
+            .latest()
+            .ok_or_else(|| format!("{} does not exist in {}", shown, timezone))?;
+        let abbreviation = local.format("%Z").to_string();
+        if let Some(zone) = zone.filter(|zone| *zone != abbreviation) {
+            return Err(format!(
+                "'{}' is not the {} zone; pass --timezone to read times in another",
+                zone, timezone
+            ));
+        }
+        local.with_timezone(&Utc)
+    };
+    Ok(at + chrono::Duration::seconds(59))
+}
+
+fn table_preset() -> &'static str {
+    if output().ascii {
+        ASCII_FULL
+    } else {
+        UTF8_FULL
+    }
+}
+
+// OSC-8 hyperlinks are only emitted to an interactive terminal; piped or
+// redirected output stays plain text.
+fn links_enabled() -> bool {
+    output().color
+        && std::io::stdout().is_terminal()
+        && std::env::var("TERM").map_or(true, |term| term != "dumb")
+}
+
+fn link(text: &str, url: &str) -> String {
+    if links_enabled() {
+        format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url, text)
+    } else {
+        text.to_string()
+    }
+}
+
+/// Flat view of a pull request for `--format json/csv`.
+#[derive(Debug, Serialize)]
+struct PullRequestRow<'a> {
+    repository: &'a str,
+    number: u64,
+    title: &'a str,
+    author: &'a str,
+    merged_at: String,
+    base_branch: &'a str,
+    merge_commit_sha: &'a str,
+    url: String,
+    commit_url: String,
+}
+
+impl<'a> From<&'a MergedPullRequest> for PullRequestRow<'a> {
+    fn from(pr: &'a MergedPullRequest) -> Self {
+        Self {
+            repository: &pr.repository,
+            number: pr.number,
+            title: &pr.title,
+            author: &pr.author,
+            merged_at: pr.merged_at.to_rfc3339(),
+            base_branch: &pr.base_branch,
+            merge_commit_sha: &pr.merge_commit_sha,
+            url: pr.url(),
+            commit_url: pr.commit_url(),
+        }
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn pull_requests_csv(prs: &[MergedPullRequest]) -> String {
+    let mut out = String::from(
+        "repository,number,title,author,merged_at,base_branch,\
+         merge_commit_sha,url,commit_url\n",
+    );
+    for pr in prs {
+        let row = PullRequestRow::from(pr);
+        let fields = [
+            row.repository.to_string(),
+            row.number.to_string(),
+            row.title.to_string(),
+            row.author.to_string(),
+            row.merged_at,
+            row.base_branch.to_string(),
+            row.merge_commit_sha.to_string(),
+            row.url,
+            row.commit_url,
+        ];
+        let fields: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+        out.push_str(&fields.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+#[derive(Serialize)]
+struct ErrorReport<'a> {
+    code: &'a str,
+    message: String,
+    exit_code: u8,
+    retryable: bool,
+}
+
+/// Report a failed command on stderr: `{"error": {...}}` for `--format json`,
+/// otherwise a line prefixed with the error code.
+pub fn display_error(err: &GitCirclesError, format: OutputFormat) {
+    match format {
+        OutputFormat::Json => {
+            let report = ErrorReport {
+                code: err.code(),
+                message: err.to_string(),
+                exit_code: err.exit_code(),
+                retryable: err.is_retryable(),
+            };
+            eprintln!("{}", serde_json::json!({ "error": report }));
+        }
+        OutputFormat::Table | OutputFormat::Csv => {
+            eprintln!("{} Error [{}]: {}", Icon::Fail, err.code(), err)
+        }
+    }
+}
+
+/// Summary line of an `import` run; `what` names the imported items.
+pub fn display_import(outcome: &ImportOutcome, what: &str, dry_run: bool) {
+    let verb = if dry_run { "Would import" } else { "Imported" };
+    status(
+        Icon::Ok,
+        format!(
+            "{} {} {} into {}/{} ({} already stored). {} total PRs tracked.",
+            verb,
+            outcome.imported.len(),
+            what,
+            outcome.repository.owner,
+            outcome.repository.name,
+            outcome.skipped,
+            outcome.repository.total_prs
+        ),
+    );
+}
+
+pub fn display_forget_report(report: &ForgetReport, dry_run: bool) {
+    if report.is_empty() {
+        println!("Nothing stored for {}", report.login);
+        return;
+    }
+    let mut table = Table::new();
+    table
+        .load_preset(table_preset())
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec!["Data", "Records", "Action"]);
+    let yes_no = |found: bool| if found { "1" } else { "0" }.to_string();
+    let rows = [
+        ("Wallet", yes_no(report.wallet), "Deleted"),
+        (
+            "Wallet history",
+            report.wallet_history.to_string(),
+            "Deleted",
+        ),
+        ("Wallet links", report.wallet_links.to_string(), "Deleted"),
+        (
+            "Wallet warnings",
+            report.wallet_warnings.to_string(),
+            "Deleted",
+        ),
+        (
+            "Contributor records",
+            report.contributor_records.to_string(),
+            "Deleted",
+        ),
+        ("Project roles", report.project_roles.to_string(), "Deleted"),
+        (
+            "Pull requests",
+            report.pull_requests.to_string(),
+            "Pseudonymized",
+        ),
+        (
+            "Project lists",
+            report.projects.to_string(),
+            "Pseudonymized",
+        ),
+        ("Approvals", report.approvals.to_string(), "Pseudonymized"),
+        (
+            "Contributor stats",
+            yes_no(report.contributor_stats),
+            "Pseudonymized",
+        ),
+    ];
+    for (data, count, action) in rows {
+        table.add_row(vec![data.to_string(), count, action.to_string()]);
+    }
+    println!("\n{}", table);
+
+    let verb = if dry_run { "Would forget" } else { "Forgot" };
+    status(
+        Icon::Ok,
+        format!(
+            "{} {}; remaining records refer to {}",
+            verb, report.login, report.pseudonym
+        ),
+    );
+}
+
+/// Print pull requests in `format`, using `table` for table output.
+pub fn print_pull_requests(
+    prs: &[MergedPullRequest],
+    format: OutputFormat,
+    table: impl FnOnce(&[MergedPullRequest]),
+) {
+    match format {
+        OutputFormat::Json => {
+            let rows: Vec<PullRequestRow> = prs.iter().map(Into::into).collect();
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&rows).unwrap_or_default()
+            );
+        }
+        OutputFormat::Csv => print!("{}", pull_requests_csv(prs)),
+        OutputFormat::Table => table(prs),
+    }
+}
+
+pub fn display_pull_requests(prs: &[MergedPullRequest]) {
+    if prs.is_empty() {
+        println!("No merged pull requests found.");
+        return;
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(table_preset())
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec![
+            "PR#",
+            "Title",
+            "Author",
+            "Merged Date",
+            "Base Branch",
+            "Commit SHA",
+        ]);
+
+    for pr in prs {
+        table.add_row(vec![
+            link(&pr.number.to_string(), &pr.url()),
+            if pr.title.len() > 50 {
+                format!("{}...", &pr.title[..47])
+            } else {
+                pr.title.clone()
+            },
+            pr.author.clone(),
+            local_time(pr.merged_at, "%Y-%m-%d %H:%M %Z"),
+            pr.base_branch.clone(),
+            link(&pr.merge_commit_sha[..8], &pr.commit_url()),
+        ]);
+    }
+
+    println!("\n{}", table);
+    info(format!("Total merged PRs: {}", prs.len()));
+}
+
+pub fn display_pr_search_results(prs: &[MergedPullRequest]) {
+    if prs.is_empty() {
+        println!("No matching pull requests found.");
+        return;
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(table_preset())
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec!["Repository", "PR#", "Title", "Author", "Merged Date"]);
+
+    for pr in prs {
+        table.add_row(vec![
+            pr.repository.clone(),
+            link(&pr.number.to_string(), &pr.url()),
+            pr.title.clone(),
+            pr.author.clone(),
+            local_time(pr.merged_at, "%Y-%m-%d"),
+        ]);
+    }
+
+    println!("\n{}", table);
+    info(format!("Total matches: {}", prs.len()));
+}
+
+pub fn display_pull_request_changes(changes: &[PullRequestChange]) {
+    if changes.is_empty() {
+        return;
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(table_preset())
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec!["Changed", "Field", "Old", "New"]);
+
+    for change in changes {
+        let shown = |value: &str| {
+            if value.is_empty() {
+                "-".to_string()
+            } else {
+                value.to_string()
+            }
+        };
+        table.add_row(vec![
+            local_time(change.changed_at, "%Y-%m-%d %H:%M %Z"),
+            change.field.clone(),
+            shown(&change.old),
+            shown(&change.new),
+        ]);
+    }
+
+    println!("Edits since merge:\n{}", table);
+}
+
+pub fn display_pull_request_details(pr: &MergedPullRequest) {
+    let mut table = Table::new();
+    table
+        .load_preset(table_preset())
+        .set_content_arrangement(ContentArrangement::Dynamic);
+
+    let head = match (&pr.head_repository, &pr.head_branch) {
+        (Some(repo), Some(branch)) => format!("{}:{}", repo, branch),
+        (None, Some(branch)) => format!("(deleted fork):{}", branch),
+        _ => "-".to_string(),
+    };
+
+    table.add_row(vec!["Platform", &pr.platform]);
+    table.add_row(vec!["Repository", &pr.repository]);
+    table.add_row(vec!["PR#", &pr.number.to_string()]);
+    table.add_row(vec!["URL", &link(&pr.url(), &pr.url())]);
+    table.add_row(vec!["Title", &pr.title]);
+    table.add_row(vec!["Author", &pr.author]);
+    table.add_row(vec![
+        "Association",
+        pr.author_association.as_deref().unwrap_or("-"),
+    ]);
+    table.add_row(vec![
+        "Labels",
+        &if pr.labels.is_empty() {
+            "-".to_string()
+        } else {
+            pr.labels.join(", ")
+        },
+    ]);
+    table.add_row(vec![
+        "Commit Authors",
+        &if pr.resolved_authors.is_empty() {
+            "-".to_string()
+        } else {
+            pr.resolved_authors.join(", ")
+        },
+    ]);
+    table.add_row(vec![
+        "Merged",
+        &format!(
+            "{}{}",
+            local_time(pr.merged_at, "%Y-%m-%d %H:%M %Z"),
+            if pr.externally_merged {
+                " (outside GitHub)"
+            } else {
+                ""
+            }
+        ),
+    ]);
+    table.add_row(vec!["Base Branch", &pr.base_branch]);
+    table.add_row(vec!["Head", &head]);
+    table.add_row(vec![
+        "Origin",
+        if pr.from_fork {
+            "fork"
+        } else {
+            "same repository"
+        },
+    ]);
+    table.add_row(vec![
+        "Merge Commit",
+        &link(&pr.merge_commit_sha, &pr.commit_url()),
+    ]);
+    table.add_row(vec![
+        "Linked Issues",
+        &if pr.linked_issues.is_empty() {
+            "-".to_string()
+        } else {
+            pr.linked_issues
+                .iter()
+                .map(|issue| {
+                    if issue.closing {
+                        format!("{} (closes)", issue)
+                    } else {
+                        issue.to_string()
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        },
+    ]);
+    table.add_row(vec![
+        "Description",
+        pr.body
+            .as_deref()
+            .filter(|b| !b.trim().is_empty())
+            .unwrap_or("-"),
+    ]);
+
+    println!("\n{}", table);
+}
 
 pub fn display_repository_status(repos: &[Repository]) {
     if repos.is_empty() {
@@ -199,161 +1474,917 @@ pub fn display_repository_status(repos: &[Repository]) {
 
     let mut table = Table::new();
     table
-        .load_preset(UTF8_FULL)
+        .load_preset(table_preset())
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec![
+            "Repository",
+            "Base Branch",
+            "Last Sync",
+            "Total PRs",
+            "First Tracked",
+        ]);
+
+    for repo in repos {
+        table.add_row(vec![
+            format!("{}/{}", repo.owner, repo.name),
+            repo.current_base_branch.clone(),
+            repo.last_sync
+                .map(|d| local_time(d, "%Y-%m-%d %H:%M %Z"))
+                .unwrap_or_else(|| "Never".to_string()),
+            repo.total_prs.to_string(),
+            local_time(repo.first_sync, "%Y-%m-%d"),
+        ]);
+    }
+
+    println!("\n{}", table);
+    info(format!("Total repositories tracked: {}", repos.len()));
+}
+
+/// Stars, forks, language, license and description per repository, as of
+/// each repository's last collect.
+pub fn display_repository_metadata(repos: &[Repository]) {
+    let mut table = Table::new();
+    table
+        .load_preset(table_preset())
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec![
+            "Repository",
+            "Stars",
+            "Forks",
+            "Language",
+            "License",
+            "Description",
+        ]);
+
+    for repo in repos {
+        let name = format!("{}/{}", repo.owner, repo.name);
+        let row = match &repo.metadata {
+            Some(m) => vec![
+                name,
+                m.stars.to_string(),
+                m.forks.to_string(),
+                m.language.clone().unwrap_or_else(|| "-".to_string()),
+                m.license.clone().unwrap_or_else(|| "-".to_string()),
+                m.description.clone().unwrap_or_default(),
+            ],
+            None => vec![
+                name,
+                "-".to_string(),
+                "-".to_string(),
+                "-".to_string(),
+                "-".to_string(),
+                "Not fetched yet; run collect".to_string(),
+            ],
+        };
+        table.add_row(row);
+    }
+
+    println!("{}", table);
+}
+
+pub fn display_projects(projects: &[(Project, ProjectCounters)]) {
+    if projects.is_empty() {
+        println!("No projects found.");
+        println!(
+            "Use 'gitcircles-github project create <name>' to create a project."
+        );
+        return;
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(table_preset())
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec![
+            "Project ID",
+            "Name",
+            "Description",
+            "PRs",
+            "Created",
+            "Updated",
+        ]);
+
+    for (project, counters) in projects {
+        table.add_row(vec![
+            project.id.clone(),
+            project.name.clone(),
+            project
+                .description
+                .clone()
+                .unwrap_or_else(|| "-".to_string()),
+            counters.prs.to_string(),
+            local_time(project.created_at, "%Y-%m-%d"),
+            local_time(project.updated_at, "%Y-%m-%d"),
+        ]);
+    }
+
+    println!("\n{}", table);
+    info(format!("Total projects: {}", projects.len()));
+}
+
+pub fn display_project_details(
+    project: &Project,
+    owners: &[ProjectOwner],
+    repos: &[Repository],
+    links: &[ProjectLink],
+) {
+    println!("\n{} Project: {}", Icon::Project, project.name);
+    println!("ID: {}", project.id);
+    if let Some(desc) = &project.description {
+        println!("Description: {}", desc);
+    }
+    println!(
+        "Created: {}",
+        local_time(project.created_at, "%Y-%m-%d %H:%M %Z")
+    );
+    println!(
+        "Updated: {}",
+        local_time(project.updated_at, "%Y-%m-%d %H:%M %Z")
+    );
+    if !project.allowed_contributors.is_empty() {
+        println!(
+            "Only counts PRs by: {}",
+            project.allowed_contributors.join(", ")
+        );
+    }
+    if !project.blocked_contributors.is_empty() {
+        println!(
+            "Never counts PRs by: {}",
+            project.blocked_contributors.join(", ")
+        );
+    }
+    if !project.excluded_orgs.is_empty() {
+        println!(
+            "Never counts PRs by members of: {}",
+            project.excluded_orgs.join(", ")
+        );
+        for exclusion in &project.org_exclusions {
+            println!(
+                "  {} excluded: member of {} (checked {})",
+                exclusion.login,
+                exclusion.org,
+                local_time(exclusion.detected_at, "%Y-%m-%d")
+            );
+        }
+    }
+    if project.require_approval {
+        println!("Requires approval: PRs wait for an owner or admin to approve");
+    }
+    print_pause(project);
+
+    println!("\n{} Project Owners ({}):", Icon::Owners, owners.len());
+    if !owners.is_empty() {
+        let mut owners_table = Table::new();
+        owners_table
+            .load_preset(table_preset())
+            .set_content_arrangement(ContentArrangement::Dynamic)
+            .set_header(vec!["Username", "Role", "Added"]);
+
+        for owner in owners {
+            owners_table.add_row(vec![
+                owner.github_username.clone(),
+                owner.role.clone(),
+                local_time(owner.added_at, "%Y-%m-%d"),
+            ]);
+        }
+        println!("{}", owners_table);
+    } else {
+        println!("  No owners added yet.");
+    }
+
+    println!("\n{} Repositories ({}):", Icon::Repos, repos.len());
+    if !repos.is_empty() {
+        display_repository_status(repos);
+        for link in links.iter().filter(|link| !link.paths.is_empty()) {
+            println!("  {} only: {}", link.repository, link.paths.join(", "));
+        }
+    } else {
+        println!("  No repositories tracked for this project yet.");
+        println!(
+            "  Use 'gitcircles-github collect --repo owner/repo --project-id {}' to add repositories.",
+            project.id
+        );
+    }
+}
+
+fn print_pause(project: &Project) {
+    if let Some(pause) = project.current_pause() {
+        println!(
+            "{} Paused since {}{}: no PRs are scheduled",
+            Icon::Warn,
+            local_time(pause.paused_at, "%Y-%m-%d %H:%M %Z"),
+            pause
+                .reason
+                .as_ref()
+                .map_or_else(String::new, |r| format!(" ({})", r))
+        );
+    }
+}
+
+pub fn display_project_status(status: &ProjectStatus) {
+    let project = &status.project;
+    println!(
+        "\n{} Project: {} ({})",
+        Icon::Project,
+        project.name,
+        project.id
+    );
+    print_pause(project);
+
+    println!("\n{} Repositories ({}):", Icon::Repos, status.links.len());
+    if status.links.is_empty() {
+        println!("  No repositories linked to this project yet.");
+        println!(
+            "  Use 'gitcircles-github project link {} --repo owner/repo' to add one.",
+            project.id
+        );
+    } else {
+        let mut table = Table::new();
+        table
+            .load_preset(table_preset())
+            .set_content_arrangement(ContentArrangement::Dynamic)
+            .set_header(vec!["Repository", "Paths", "PRs", "Last Sync"]);
+        for link in &status.links {
+            let repo = status
+                .repositories
+                .iter()
+                .find(|r| format!("{}/{}", r.owner, r.name) == link.repository);
+            table.add_row(vec![
+                link.repository.clone(),
+                if link.paths.is_empty() {
+                    "all".to_string()
+                } else {
+                    link.paths.join(", ")
+                },
+                status
+                    .prs_by_repository
+                    .get(&link.repository)
+                    .copied()
+                    .unwrap_or(0)
+                    .to_string(),
+                match repo.and_then(|r| r.last_sync) {
+                    Some(d) => local_time(d, "%Y-%m-%d %H:%M %Z"),
+                    None if repo.is_some() => "Never".to_string(),
+                    None => "Not collected".to_string(),
+                },
+            ]);
+        }
+        println!("{}", table);
+    }
+
+    let readiness = &status.readiness;
+    let mut table = Table::new();
+    table
+        .load_preset(table_preset())
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec!["Summary", "Count"]);
+    table.add_row(vec!["PRs".to_string(), status.total_prs.to_string()]);
+    table.add_row(vec![
+        "Last merge".to_string(),
+        status.last_merged_at.map_or_else(
+            || "-".to_string(),
+            |d| local_time(d, "%Y-%m-%d %H:%M %Z"),
+        ),
+    ]);
+    table.add_row(vec![
+        "Contributors".to_string(),
+        status.contributors.to_string(),
+    ]);
+    table.add_row(vec![
+        "  ready for payout".to_string(),
+        readiness.ready.to_string(),
+    ]);
+    table.add_row(vec![
+        "  wallet change pending".to_string(),
+        readiness.pending_rotation.to_string(),
+    ]);
+    table.add_row(vec![
+        "  no wallet".to_string(),
+        readiness.missing_wallet.to_string(),
+    ]);
+    table.add_row(vec![
+        "  opted out".to_string(),
+        readiness.opted_out.to_string(),
+    ]);
+    if project.require_approval {
+        table.add_row(vec![
+            "Awaiting approval".to_string(),
+            status.awaiting_approval.to_string(),
+        ]);
+    }
+    println!("\n{}", table);
+}
+
+// Short profile commit SHA, linked to the commit on GitHub
+fn source_commit(source: &WalletSource) -> String {
+    match (source.commit(), source.commit_url()) {
+        (Some(sha), Some(url)) => link(&sha[..sha.len().min(8)], &url),
+        _ => "-".to_string(),
+    }
+}
+
+pub fn display_user_wallet(wallet: &UserWallet) {
+    let mut table = Table::new();
+    table
+        .load_preset(table_preset())
+        .set_content_arrangement(ContentArrangement::Dynamic);
+
+    table.add_row(vec!["Platform", &wallet.platform]);
+    table.add_row(vec!["Login", &wallet.login]);
+    table.add_row(vec!["Wallet Address", wallet.address.as_str()]);
+    table.add_row(vec![
+        "Last Synced",
+        &local_time(wallet.synced_at, "%Y-%m-%d %H:%M %Z"),
+    ]);
+    table.add_row(vec!["Source", &wallet.source.to_string()]);
+    table.add_row(vec!["Commit", &source_commit(&wallet.source)]);
+
+    println!("\n{}", table);
+}
+
+pub fn display_contributor_profile(profile: &ContributorProfile) {
+    let date = |d: chrono::DateTime<chrono::Utc>| local_time(d, "%Y-%m-%d");
+    let mut table = Table::new();
+    table
+        .load_preset(table_preset())
+        .set_content_arrangement(ContentArrangement::Dynamic);
+
+    table.add_row(vec!["Login", &profile.login]);
+    match &profile.stats {
+        Some(stats) => {
+            table.add_row(vec!["PRs".to_string(), stats.prs.to_string()]);
+            table.add_row(vec![
+                "First Contribution".to_string(),
+                date(stats.first_contribution),
+            ]);
+            table.add_row(vec![
+                "Last Contribution".to_string(),
+                date(stats.last_contribution),
+            ]);
+            table.add_row(vec![
+                "Repositories".to_string(),
+                stats.repositories.join(", "),
+            ]);
+        }
+        None => {
+            table.add_row(vec!["PRs", "0"]);
+        }
+    }
+    table.add_row(vec![
+        "Wallet Address",
+        profile.wallet.as_ref().map_or("-", |w| w.address.as_str()),
+    ]);
+    let standing = match profile.standing {
+        PayoutStanding::Ready => "Ready for payout".to_string(),
+        PayoutStanding::PendingRotation => match &profile.rotation {
+            Some(rotation) => format!(
+                "Address changed; new payouts wait until {}",
+                local_time(rotation.effective_at, "%Y-%m-%d %H:%M %Z")
+            ),
+            None => "Address changed recently".to_string(),
+        },
+        PayoutStanding::MissingWallet => "No wallet; run 'wallet sync'".to_string(),
+        PayoutStanding::OptedOut => "Opted out of rewards".to_string(),
+    };
+    table.add_row(vec!["Payout Status".to_string(), standing]);
+
+    println!("\n{}", table);
+    if let Some(prefs) = &profile.preferences {
+        display_contributor_preferences(prefs);
+    }
+}
+
+pub fn display_contributor_preferences(prefs: &ContributorPreferences) {
+    let mut table = Table::new();
+    table
+        .load_preset(table_preset())
+        .set_content_arrangement(ContentArrangement::Dynamic);
+
+    table.add_row(vec![
+        "Display Name",
+        prefs.display_name.as_deref().unwrap_or("-"),
+    ]);
+    table.add_row(vec![
+        "Payout Schedule".to_string(),
+        prefs
+            .payout_schedule
+            .map(|s| format!("{:?}", s))
+            .unwrap_or_else(|| "-".to_string()),
+    ]);
+    table.add_row(vec![
+        "Minimum Payout".to_string(),
+        prefs
+            .min_payout_nanoerg
+            .map(|n| format!("{} ERG", n as f64 / 1_000_000_000.0))
+            .unwrap_or_else(|| "-".to_string()),
+    ]);
+    table.add_row(vec!["Opted Out", if prefs.opt_out { "yes" } else { "no" }]);
+    table.add_row(vec![
+        "Wallet Ref",
+        prefs.wallet_ref.as_deref().unwrap_or("-"),
+    ]);
+
+    println!("Preferences (gitcircles.toml):\n{}", table);
+}
+
+pub fn display_wallet_history(history: &[WalletHistoryEntry]) {
+    if history.is_empty() {
+        println!("No wallet history found.");
+        return;
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(table_preset())
         .set_content_arrangement(ContentArrangement::Dynamic)
-        .set_header(vec![
-            "Repository",
-            "Base Branch",
-            "Last Sync",
-            "Total PRs",
-            "First Tracked",
+        .set_header(vec!["Timestamp", "Wallet Address", "Source", "Commit"]);
+
+    for entry in history {
+        table.add_row(vec![
+            local_time(entry.recorded_at, "%Y-%m-%d %H:%M %Z"),
+            entry.address.as_str().to_string(),
+            entry.source.to_string(),
+            source_commit(&entry.source),
         ]);
+    }
 
-    for repo in repos {
+    println!("\n{}", table);
+    info(format!("Total history entries: {}", history.len()));
+}
+
+fn format_span(span: chrono::Duration) -> String {
+    let (days, hours, minutes) = (
+        span.num_days(),
+        span.num_hours() % 24,
+        span.num_minutes() % 60,
+    );
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m", minutes)
+    } else {
+        "<1m".to_string()
+    }
+}
+
+pub fn display_wallet_transitions(transitions: &[WalletTransition]) {
+    if transitions.is_empty() {
+        println!("No wallet history found.");
+        return;
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(table_preset())
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec!["Changed", "From", "To", "Held For", "Source"]);
+
+    for transition in transitions {
         table.add_row(vec![
-            format!("{}/{}", repo.owner, repo.name),
-            repo.current_base_branch.clone(),
-            repo.last_sync
-                .map(|d| d.format("%Y-%m-%d %H:%M UTC").to_string())
-                .unwrap_or_else(|| "Never".to_string()),
-            repo.total_prs.to_string(),
-            repo.first_sync.format("%Y-%m-%d").to_string(),
+            local_time(transition.changed_at, "%Y-%m-%d %H:%M %Z"),
+            transition
+                .from
+                .as_ref()
+                .map_or("-".to_string(), |from| from.to_string()),
+            transition.to.to_string(),
+            format_span(transition.held_for),
+            transition.source.to_string(),
         ]);
     }
 
     println!("\n{}", table);
-    println!("Total repositories tracked: {}", repos.len());
+    info(format!("Total address changes: {}", transitions.len() - 1));
 }
 
-pub fn display_projects(projects: &[Project]) {
-    if projects.is_empty() {
-        println!("No projects found.");
-        println!(
-            "Use 'gitcircles-github project create <name>' to create a project."
+pub fn display_opt_outs(opt_outs: &[ContributorOptOut]) {
+    if opt_outs.is_empty() {
+        println!("No contributors have opted out.");
+        return;
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(table_preset())
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec!["Login", "Source", "Recorded"]);
+
+    for opt_out in opt_outs {
+        let source = match &opt_out.source {
+            OptOutSource::ProfileRepo => "gitcircles.toml".to_string(),
+            OptOutSource::Comment {
+                repository, number, ..
+            } => format!("comment on {}#{}", repository, number),
+            OptOutSource::Manual => "manual".to_string(),
+        };
+        table.add_row(vec![
+            opt_out.login.clone(),
+            source,
+            local_time(opt_out.recorded_at, "%Y-%m-%d %H:%M %Z"),
+        ]);
+    }
+
+    println!("\n{}", table);
+    info(format!("Total opt-outs: {}", opt_outs.len()));
+}
+
+/// nanoERG as ERG, without trailing zeros, e.g. "-2.0011 ERG".
+pub fn erg(nanoerg: i64) -> String {
+    let sign = if nanoerg < 0 { "-" } else { "" };
+    let abs = nanoerg.unsigned_abs();
+    let fraction = format!("{:09}", abs % 1_000_000_000);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        format!("{}{} ERG", sign, abs / 1_000_000_000)
+    } else {
+        format!("{}{}.{} ERG", sign, abs / 1_000_000_000, fraction)
+    }
+}
+
+pub fn display_treasury_history(history: &TreasuryHistory, limit: Option<usize>) {
+    if let Some(error) = &history.sync_error {
+        status(
+            Icon::Warn,
+            format!("Treasury sync failed, showing stored history: {}", error),
         );
+    }
+    match &history.name {
+        Some(name) => println!("Treasury: {} ({})", name, history.address),
+        None => println!("Treasury: {}", history.address),
+    }
+    match &history.last_sync {
+        Some(state) => println!(
+            "Last sync: {} ({} new)",
+            local_time(state.synced_at, "%Y-%m-%d %H:%M %Z"),
+            history.new
+        ),
+        None => println!("Last sync: never"),
+    }
+    if history.transactions.is_empty() {
+        println!("No treasury transactions stored.");
         return;
     }
 
     let mut table = Table::new();
     table
-        .load_preset(UTF8_FULL)
+        .load_preset(table_preset())
         .set_content_arrangement(ContentArrangement::Dynamic)
-        .set_header(vec![
-            "Project ID",
-            "Name",
-            "Description",
-            "Created",
-            "Updated",
+        .set_header(vec!["Date", "Direction", "Amount", "Counterparties", "Tx"]);
+
+    let shown = limit.unwrap_or(history.transactions.len());
+    for tx in history.transactions.iter().take(shown) {
+        let counterparties = if tx.counterparties.is_empty() {
+            "-".to_string()
+        } else {
+            tx.counterparties
+                .iter()
+                .map(|address| match history.names.get(address) {
+                    Some(name) => format!("{} ({})", name, short_id(address)),
+                    None => address.clone(),
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        table.add_row(vec![
+            local_time(tx.timestamp, "%Y-%m-%d %H:%M %Z"),
+            tx.direction().to_string(),
+            erg(tx.net()),
+            counterparties,
+            short_id(&tx.tx_id),
         ]);
+    }
 
-    for project in projects {
+    println!("\n{}", table);
+    let funded = history.funded();
+    let paid_out = history.paid_out();
+    info(format!(
+        "Funded: {}, paid out: {}, net: {} over {} transactions",
+        erg(funded as i64),
+        erg(paid_out as i64),
+        erg(funded as i64 - paid_out as i64),
+        history.transactions.len()
+    ));
+}
+
+pub fn display_project_treasuries(treasuries: &[ProjectTreasury]) {
+    if treasuries.is_empty() {
+        println!("No treasury addresses registered.");
+        return;
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(table_preset())
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec!["Name", "Address", "Max payout", "Added"]);
+
+    for treasury in treasuries {
         table.add_row(vec![
-            project.id.clone(),
-            project.name.clone(),
-            project
-                .description
-                .clone()
-                .unwrap_or_else(|| "-".to_string()),
-            project.created_at.format("%Y-%m-%d").to_string(),
-            project.updated_at.format("%Y-%m-%d").to_string(),
+            treasury.name.clone(),
+            treasury.address.clone(),
+            treasury
+                .max_payout_nanoerg
+                .map(|n| erg(n as i64))
+                .unwrap_or_else(|| "unlimited".to_string()),
+            local_time(treasury.added_at, "%Y-%m-%d"),
         ]);
     }
 
     println!("\n{}", table);
-    println!("Total projects: {}", projects.len());
+    info(format!("Total treasuries: {}", treasuries.len()));
 }
 
-pub fn display_project_details(
-    project: &Project,
-    owners: &[ProjectOwner],
-    repos: &[Repository],
-) {
-    println!("\n📋 Project: {}", project.name);
-    println!("ID: {}", project.id);
-    if let Some(desc) = &project.description {
-        println!("Description: {}", desc);
+fn short_id(id: &str) -> String {
+    match id.char_indices().nth(12) {
+        Some((end, _)) => format!("{}…", &id[..end]),
+        None => id.to_string(),
     }
-    println!(
-        "Created: {}",
-        project.created_at.format("%Y-%m-%d %H:%M UTC")
-    );
-    println!(
-        "Updated: {}",
-        project.updated_at.format("%Y-%m-%d %H:%M UTC")
-    );
+}
 
-    println!("\n👥 Project Owners ({}):", owners.len());
-    if !owners.is_empty() {
-        let mut owners_table = Table::new();
-        owners_table
-            .load_preset(UTF8_FULL)
-            .set_content_arrangement(ContentArrangement::Dynamic)
-            .set_header(vec!["Username", "Role", "Added"]);
+pub fn display_address_book(entries: &[AddressBookEntry]) {
+    if entries.is_empty() {
+        println!("No addresses in the address book.");
+        return;
+    }
 
-        for owner in owners {
-            owners_table.add_row(vec![
-                owner.github_username.clone(),
-                owner.role.clone(),
-                owner.added_at.format("%Y-%m-%d").to_string(),
-            ]);
-        }
-        println!("{}", owners_table);
-    } else {
-        println!("  No owners added yet.");
+    let mut table = Table::new();
+    table
+        .load_preset(table_preset())
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec!["Name", "Kind", "Address", "Note", "Updated"]);
+
+    for entry in entries {
+        table.add_row(vec![
+            entry.name.clone(),
+            entry.kind.to_string(),
+            entry.address.to_string(),
+            entry.note.clone().unwrap_or_else(|| "-".to_string()),
+            local_time(entry.updated_at, "%Y-%m-%d %H:%M %Z"),
+        ]);
     }
 
-    println!("\n📦 Repositories ({}):", repos.len());
-    if !repos.is_empty() {
-        display_repository_status(repos);
-    } else {
-        println!("  No repositories tracked for this project yet.");
-        println!(
-            "  Use 'gitcircles-github collect --repo owner/repo --project-id {}' to add repositories.",
-            project.id
-        );
+    println!("\n{}", table);
+    info(format!("Total addresses: {}", entries.len()));
+}
+
+pub fn display_effective_settings(settings: &[EffectiveSetting]) {
+    let mut table = Table::new();
+    table
+        .load_preset(table_preset())
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec!["Setting", "Value", "Source"]);
+
+    for setting in settings {
+        table.add_row(vec![
+            setting.key.clone(),
+            setting.value.clone(),
+            setting.source.to_string(),
+        ]);
     }
+
+    println!("\n{}", table);
 }
 
-pub fn display_user_wallet(wallet: &UserWallet) {
+pub fn display_doctor_checks(checks: &[DoctorCheck]) {
+    let mut table = Table::new();
+    table
+        .load_preset(table_preset())
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec!["", "Check", "Result", "Fix"]);
+
+    for check in checks {
+        let icon = match check.status {
+            CheckStatus::Pass => Icon::Ok,
+            CheckStatus::Warn => Icon::Warn,
+            CheckStatus::Fail => Icon::Fail,
+        };
+        table.add_row(vec![
+            icon.to_string(),
+            check.name.to_string(),
+            check.detail.clone(),
+            check.hint.clone().unwrap_or_default(),
+        ]);
+    }
+
+    println!("\n{}", table);
+}
+
+pub fn display_node_health(url: &str, health: &NodeHealth) {
     let mut table = Table::new();
     table
-        .load_preset(UTF8_FULL)
+        .load_preset(table_preset())
         .set_content_arrangement(ContentArrangement::Dynamic);
 
-    table.add_row(vec!["Platform", &wallet.platform]);
-    table.add_row(vec!["Login", &wallet.login]);
-    table.add_row(vec!["Wallet Address", wallet.address.as_str()]);
+    let height =
+        |h: Option<u32>| h.map_or_else(|| "-".to_string(), |h| h.to_string());
+    table.add_row(vec!["Node", url]);
     table.add_row(vec![
-        "Last Synced",
-        &wallet.synced_at.format("%Y-%m-%d %H:%M UTC").to_string(),
+        "Version",
+        health.info.app_version.as_deref().unwrap_or("-"),
+    ]);
+    table.add_row(vec![
+        "Network",
+        health.info.network.as_deref().unwrap_or("-"),
+    ]);
+    table.add_row(vec![
+        "Full Height".to_string(),
+        height(health.info.full_height),
+    ]);
+    table.add_row(vec![
+        "Headers Height".to_string(),
+        height(health.info.headers_height),
+    ]);
+    table.add_row(vec![
+        "Best Peer Height".to_string(),
+        height(health.info.max_peer_height),
     ]);
-    table.add_row(vec!["Source", &format!("{:?}", wallet.source)]);
+    table.add_row(vec![
+        "Peers".to_string(),
+        health.info.peers_count.to_string(),
+    ]);
+    table.add_row(vec!["Synced", if health.synced { "yes" } else { "no" }]);
+    if let Some(wallet) = &health.wallet {
+        table.add_row(vec![
+            "Wallet",
+            match (wallet.is_initialized, wallet.is_unlocked) {
+                (false, _) => "not initialized",
+                (true, false) => "locked",
+                (true, true) => "unlocked",
+            },
+        ]);
+    }
 
     println!("\n{}", table);
 }
 
-pub fn display_wallet_history(history: &[WalletHistoryEntry]) {
-    if history.is_empty() {
-        println!("No wallet history found.");
+pub fn display_wallet_warnings(warnings: &[WalletWarning]) {
+    if warnings.is_empty() {
+        println!("No wallet warnings recorded.");
         return;
     }
 
     let mut table = Table::new();
     table
-        .load_preset(UTF8_FULL)
+        .load_preset(table_preset())
         .set_content_arrangement(ContentArrangement::Dynamic)
-        .set_header(vec!["Timestamp", "Wallet Address", "Source"]);
+        .set_header(vec!["Recorded", "Login", "Wallet Address", "Warning"]);
 
-    for entry in history {
+    for warning in warnings {
+        let detail = match &warning.kind {
+            WalletWarningKind::SharedWallet { other_logins } => {
+                format!("shared with {}", other_logins.join(", "))
+            }
+        };
         table.add_row(vec![
-            entry.recorded_at.format("%Y-%m-%d %H:%M UTC").to_string(),
-            entry.address.as_str().to_string(),
-            format!("{:?}", entry.source),
+            local_time(warning.recorded_at, "%Y-%m-%d %H:%M %Z"),
+            warning.login.clone(),
+            warning.wallet.to_string(),
+            detail,
+        ]);
+    }
+
+    println!("\n{}", table);
+    info(format!("Total warnings: {}", warnings.len()));
+}
+
+pub fn display_sync_log(entries: &[SyncLogEntry]) {
+    if entries.is_empty() {
+        println!("No collect runs recorded for this repository.");
+        return;
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(table_preset())
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec![
+            "Started",
+            "Duration",
+            "Pages",
+            "API Calls",
+            "New PRs",
+            "Error",
         ]);
+
+    for entry in entries {
+        table.add_row(vec![
+            local_time(entry.started_at, "%Y-%m-%d %H:%M:%S %Z"),
+            format!(
+                "{:.1}s",
+                entry.duration().num_milliseconds() as f64 / 1000.0
+            ),
+            entry.pages_fetched.to_string(),
+            entry.api_calls.to_string(),
+            entry.new_prs.to_string(),
+            entry.error.clone().unwrap_or_else(|| "-".to_string()),
+        ]);
+    }
+
+    println!("\n{}", table);
+}
+
+pub fn display_digest(digest: &ProjectDigest) {
+    println!(
+        "\n{} {} ({}): {} to {}",
+        Icon::Digest,
+        digest.project_name,
+        digest.project_id,
+        local_time(digest.period_start, "%Y-%m-%d"),
+        local_time(digest.period_end, "%Y-%m-%d")
+    );
+
+    let list = |logins: &[String]| {
+        if logins.is_empty() {
+            "-".to_string()
+        } else {
+            logins.join(", ")
+        }
+    };
+    println!("New PRs: {}", digest.new_prs.len());
+    println!(
+        "New contributors ({}): {}",
+        digest.new_contributors.len(),
+        list(&digest.new_contributors)
+    );
+    println!(
+        "Missing wallets ({}): {}",
+        digest.missing_wallets.len(),
+        list(&digest.missing_wallets)
+    );
+    if !digest.new_prs.is_empty() {
+        display_pull_requests(&digest.new_prs);
+    }
+}
+
+/// Anomalies, naming wallets found in `book`.
+pub fn display_anomalies(anomalies: &[Anomaly], book: &[AddressBookEntry]) {
+    if anomalies.is_empty() {
+        println!("No anomalies found.");
+        return;
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(table_preset())
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec!["Kind", "Who", "Details"]);
+
+    for anomaly in anomalies {
+        let row = match anomaly {
+            Anomaly::WalletFunnel { wallet, logins } => {
+                let known_as = book
+                    .iter()
+                    .find(|entry| entry.address == *wallet)
+                    .map(|entry| format!(", known as {}", entry.label()))
+                    .unwrap_or_default();
+                vec![
+                    "Shared wallet".to_string(),
+                    logins.join(", "),
+                    format!(
+                        "{} logins pay into {}{}",
+                        logins.len(),
+                        wallet,
+                        known_as
+                    ),
+                ]
+            }
+            Anomaly::PrBurst {
+                repository,
+                author,
+                count,
+                first_merged_at,
+                last_merged_at,
+            } => vec![
+                "PR burst".to_string(),
+                author.clone(),
+                format!(
+                    "{} PRs in {} between {} and {}",
+                    count,
+                    repository,
+                    local_time(*first_merged_at, "%Y-%m-%d %H:%M"),
+                    local_time(*last_merged_at, "%Y-%m-%d %H:%M %Z")
+                ),
+            ],
+            Anomaly::NewAccount {
+                login,
+                account_created_at,
+                first_merged_at,
+            } => vec![
+                "New account".to_string(),
+                login.clone(),
+                format!(
+                    "created {}, first merged PR {}",
+                    local_time(*account_created_at, "%Y-%m-%d"),
+                    local_time(*first_merged_at, "%Y-%m-%d")
+                ),
+            ],
+        };
+        table.add_row(row);
     }
 
     println!("\n{}", table);
-    println!("Total history entries: {}", history.len());
+    info(format!("Total anomalies: {}", anomalies.len()));
 }
 
 pub fn display_wallet_logins(logins: &[(String, String)]) {
@@ -364,7 +2395,7 @@ pub fn display_wallet_logins(logins: &[(String, String)]) {
 
     let mut table = Table::new();
     table
-        .load_preset(UTF8_FULL)
+        .load_preset(table_preset())
         .set_content_arrangement(ContentArrangement::Dynamic)
         .set_header(vec!["Platform", "Login"]);
 
@@ -373,5 +2404,125 @@ pub fn display_wallet_logins(logins: &[(String, String)]) {
     }
 
     println!("\n{}", table);
-    println!("Total logins: {}", logins.len());
+    info(format!("Total logins: {}", logins.len()));
+}
+
+fn wallet_lookups_csv(lookups: &[WalletLookup]) -> String {
+    let mut out = String::from("address,login,status,name,kind\n");
+    for lookup in lookups {
+        let address = csv_field(&lookup.address);
+        let known_as = format!(
+            "{},{}",
+            csv_field(lookup.label.as_deref().unwrap_or_default()),
+            lookup.kind.map(|kind| kind.to_string()).unwrap_or_default()
+        );
+        match (lookup.valid, lookup.logins.as_slice()) {
+            (false, _) => out.push_str(&format!("{},,invalid,,\n", address)),
+            (true, []) => {
+                out.push_str(&format!("{},,unlinked,{}\n", address, known_as))
+            }
+            (true, logins) => {
+                for login in logins {
+                    out.push_str(&format!(
+                        "{},{},linked,{}\n",
+                        address,
+                        csv_field(login),
+                        known_as
+                    ));
+                }
+            }
+        }
+    }
+    out
+}
+
+pub fn print_wallet_lookups(lookups: &[WalletLookup], format: OutputFormat) {
+    match format {
+        OutputFormat::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(lookups).unwrap_or_default()
+        ),
+        OutputFormat::Csv => print!("{}", wallet_lookups_csv(lookups)),
+        OutputFormat::Table => display_wallet_lookups(lookups),
+    }
+}
+
+pub fn display_wallet_lookups(lookups: &[WalletLookup]) {
+    if lookups.is_empty() {
+        println!("No wallet addresses given.");
+        return;
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(table_preset())
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec!["Address", "Logins", "Known as"]);
+
+    for lookup in lookups {
+        let logins = if !lookup.valid {
+            "invalid address".to_string()
+        } else if lookup.logins.is_empty() {
+            "-".to_string()
+        } else {
+            lookup.logins.join(", ")
+        };
+        let known_as = match (&lookup.label, lookup.kind) {
+            (Some(label), Some(kind)) => format!("{} ({})", label, kind),
+            _ => "-".to_string(),
+        };
+        table.add_row(vec![lookup.address.clone(), logins, known_as]);
+    }
+
+    println!("\n{}", table);
+    let linked = lookups.iter().filter(|l| !l.logins.is_empty()).count();
+    let invalid = lookups.iter().filter(|l| !l.valid).count();
+    info(format!(
+        "Addresses: {} ({} linked, {} invalid)",
+        lookups.len(),
+        linked,
+        invalid
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps_match_the_table_format() {
+        let shown = parse_timestamp("2024-05-01 12:30 UTC", Tz::UTC).unwrap();
+        assert_eq!(shown.to_rfc3339(), "2024-05-01T12:30:59+00:00");
+        let exact = parse_timestamp("2024-05-01T14:30:00+02:00", Tz::UTC).unwrap();
+        assert_eq!(exact.to_rfc3339(), "2024-05-01T12:30:00+00:00");
+        assert!(parse_timestamp("yesterday", Tz::UTC).is_err());
+
+        let berlin = Tz::Europe__Berlin;
+        for value in ["2024-05-01 14:30", "2024-05-01 14:30 CEST"] {
+            let at = parse_timestamp(value, berlin).unwrap();
+            assert_eq!(at.to_rfc3339(), "2024-05-01T12:30:59+00:00");
+        }
+        let utc = parse_timestamp("2024-05-01 12:30 UTC", berlin).unwrap();
+        assert_eq!(utc.to_rfc3339(), "2024-05-01T12:30:59+00:00");
+        assert!(parse_timestamp("2024-05-01 14:30 EST", berlin).is_err());
+        assert!(parse_timestamp("2024-03-31 02:30", berlin).is_err());
+    }
+
+    #[test]
+    fn csv_rows_quote_titles_and_carry_urls() {
+        let pr = MergedPullRequest {
+            title: "Fix \"quoted\", comma".into(),
+            ..MergedPullRequest::for_test(7, "alice")
+        };
+
+        let csv = pull_requests_csv(&[pr]);
+        let row = csv.lines().nth(1).unwrap();
+        assert!(
+            row.starts_with("acme/widgets,7,\"Fix \"\"quoted\"\", comma\",alice,")
+        );
+        assert!(row.ends_with(
+            ",https://github.com/acme/widgets/pull/7,\
+             https://github.com/acme/widgets/commit/0123456789abcdef"
+        ));
+    }
 }
@@ -0,0 +1,529 @@
+//! Operator settings from `~/.gitcircles/config.toml`. The file is optional
+//! and every key has a default, e.g.:
+//!
+//! ```toml
+//! [http]
+//! timeout_secs = 30
+//! connect_timeout_secs = 10
+//! pool_max_idle_per_host = 8
+//! pool_idle_timeout_secs = 90
+//!
+//! [hooks]
+//! pr_registered = ["/usr/local/bin/announce-pr", "--channel", "rewards"]
+//! timeout_secs = 30
+//!
+//! [privacy]
+//! pseudonym_key = "a long random string"
+//!
+//! [labels]
+//! enabled = true
+//! scheduled = "gitcircles:scheduled"
+//! stopped = "gitcircles:stopped"
+//!
+//! [statuses]
+//! enabled = true
+//! context = "gitcircles"
+//! details_url = "https://rewards.example.org/{repository}/{number}"
+//!
+//! [price]
+//! source = "http"
+//! url = "https://api.coingecko.com/api/v3/simple/price?ids=ergo&vs_currencies=usd"
+//! json_pointer = "/ergo/usd"
+//! cache_secs = 300
+//! max_age_secs = 3600
+//!
+//! [treasury]
+//! address = "9f..."
+//! explorer_url = "https://api.ergoplatform.com"
+//! page_size = 100
+//! sync_interval_secs = 600
+//!
+//! [display]
+//! timezone = "Europe/Berlin"
+//! ```
+
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::price::PriceSourceKind;
+use crate::types::{GitCirclesError, PipelineLabel, Result, get_database_path};
+
+/// Keys whose values `settings` never prints.
+const SECRET_KEYS: &[&str] = &["privacy.pseudonym_key"];
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub http: HttpConfig,
+    pub hooks: HooksConfig,
+    pub privacy: PrivacyConfig,
+    pub labels: LabelsConfig,
+    pub statuses: StatusesConfig,
+    pub price: PriceConfig,
+    pub treasury: TreasuryConfig,
+    pub display: DisplayConfig,
+}
+
+/// Timeouts and connection pooling for GitHub traffic. Timeouts apply to
+/// both the API client and raw profile-file downloads; Octocrab does not
+/// expose its pool, so the pool settings only affect raw downloads.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpConfig {
+    /// Whole-request timeout for raw downloads; read/write timeout for the
+    /// API client
+    pub timeout_secs: u64,
+    pub connect_timeout_secs: u64,
+    pub pool_max_idle_per_host: usize,
+    pub pool_idle_timeout_secs: u64,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            timeout_secs: 30,
+            connect_timeout_secs: 10,
+            pool_max_idle_per_host: 8,
+            pool_idle_timeout_secs: 90,
+        }
+    }
+}
+
+/// Commands run on lifecycle events (see [`crate::hooks`]), each a program
+/// followed by its arguments. An empty command disables the hook.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HooksConfig {
+    pub pr_registered: Vec<String>,
+    /// How long a hook may run before it is killed
+    pub timeout_secs: u64,
+}
+
+impl Default for HooksConfig {
+    fn default() -> Self {
+        Self {
+            pr_registered: Vec::new(),
+            timeout_secs: 30,
+        }
+    }
+}
+
+/// Settings for `--pseudonymize` (see [`crate::privacy`]).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PrivacyConfig {
+    /// Secret the export pseudonyms are derived from; keep it to get the
+    /// same ids in later exports
+    pub pseudonym_key: String,
+}
+
+/// Labels marking where a PR is in the reward pipeline, so its state shows
+/// up and can be searched for on GitHub. Off unless enabled.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LabelsConfig {
+    pub enabled: bool,
+    pub scheduled: String,
+    pub stopped: String,
+}
+
+impl Default for LabelsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            scheduled: "gitcircles:scheduled".to_string(),
+            stopped: "gitcircles:stopped".to_string(),
+        }
+    }
+}
+
+impl LabelsConfig {
+    /// The GitHub label used for `label`.
+    pub fn name(&self, label: PipelineLabel) -> &str {
+        match label {
+            PipelineLabel::Scheduled => &self.scheduled,
+            PipelineLabel::Stopped => &self.stopped,
+        }
+    }
+}
+
+/// Commit statuses on merge commits showing where a PR is in the reward
+/// pipeline, as an alternative to labels. Off unless enabled.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StatusesConfig {
+    pub enabled: bool,
+    /// Name the status is listed under on GitHub
+    pub context: String,
+    /// Link behind "Details", with `{repository}` and `{number}` filled in;
+    /// empty for no link
+    pub details_url: String,
+}
+
+impl Default for StatusesConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            context: "gitcircles".to_string(),
+            details_url: String::new(),
+        }
+    }
+}
+
+impl StatusesConfig {
+    /// The details link for a PR, if one is configured.
+    pub fn details_url(&self, repository: &str, number: u64) -> Option<String> {
+        (!self.details_url.is_empty()).then(|| {
+            self.details_url
+                .replace("{repository}", repository)
+                .replace("{number}", &number.to_string())
+        })
+    }
+}
+
+/// Where the ERG/USD price comes from (see [`crate::price`]) and how long
+/// a fetched price may be reused.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PriceConfig {
+    pub source: PriceSourceKind,
+    /// JSON endpoint for the `http` source
+    pub url: String,
+    /// Where the price is in the `http` response, e.g. "/ergo/usd"
+    pub json_pointer: String,
+    /// Explorer API for the `oracle` source
+    pub explorer_url: String,
+    /// Token id of the oracle pool's NFT, for the `oracle` source
+    pub oracle_pool_nft: String,
+    /// Reuse a fetched price for this long before fetching again
+    pub cache_secs: u64,
+    /// Oldest cached price to fall back on when the feed is unreachable
+    pub max_age_secs: u64,
+}
+
+impl Default for PriceConfig {
+    fn default() -> Self {
+        Self {
+            source: PriceSourceKind::Http,
+            url: "https://api.coingecko.com/api/v3/simple/price?ids=ergo&vs_currencies=usd"
+                .to_string(),
+            json_pointer: "/ergo/usd".to_string(),
+            explorer_url: "https://api.ergoplatform.com".to_string(),
+            oracle_pool_nft: String::new(),
+            cache_secs: 300,
+            max_age_secs: 3600,
+        }
+    }
+}
+
+impl PriceConfig {
+    pub fn cache_duration(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.cache_secs as i64)
+    }
+
+    pub fn max_age(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.max_age_secs as i64)
+    }
+}
+
+/// The treasury whose transactions `treasury history` reconciles, read
+/// through an Ergo explorer (see [`crate::treasury`]).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TreasuryConfig {
+    /// Treasury address; the `treasury` commands fail while it is unset
+    pub address: String,
+    pub explorer_url: String,
+    /// Transactions fetched per explorer request
+    pub page_size: u64,
+    /// `treasury history` syncs first when the last sync is older
+    pub sync_interval_secs: u64,
+}
+
+impl Default for TreasuryConfig {
+    fn default() -> Self {
+        Self {
+            address: String::new(),
+            explorer_url: "https://api.ergoplatform.com".to_string(),
+            page_size: 100,
+            sync_interval_secs: 600,
+        }
+    }
+}
+
+/// How the CLI shows timestamps; the global `--timezone` flag overrides it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DisplayConfig {
+    /// IANA zone name, e.g. "America/New_York"; storage stays in UTC
+    pub timezone: Tz,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        Self { timezone: Tz::UTC }
+    }
+}
+
+impl TreasuryConfig {
+    pub fn sync_interval(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.sync_interval_secs as i64)
+    }
+}
+
+impl HooksConfig {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+}
+
+impl HttpConfig {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+
+    pub fn connect_timeout(&self) -> Duration {
+        Duration::from_secs(self.connect_timeout_secs)
+    }
+
+    pub fn pool_idle_timeout(&self) -> Duration {
+        Duration::from_secs(self.pool_idle_timeout_secs)
+    }
+}
+
+impl Config {
+    /// Read `path`, falling back to the defaults when the file is missing.
+    pub fn load(path: &Path) -> Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text).map_err(|e| {
+                GitCirclesError::Config(format!("{}: {}", path.display(), e))
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                Ok(Self::default())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn load_default() -> Result<Self> {
+        Self::load(Path::new(&get_config_path()?))
+    }
+
+    fn parse(text: &str) -> std::result::Result<Self, String> {
+        toml::from_str(text).map_err(|e| e.message().to_string())
+    }
+
+    /// Every problem in a config file: a syntax error, or all unknown keys
+    /// and values of the wrong type, then any value the settings reject.
+    pub fn check(text: &str) -> Vec<String> {
+        let table: toml::Table = match text.parse() {
+            Ok(table) => table,
+            Err(e) => {
+                let line = e.span().map(|span| text[..span.start].lines().count());
+                return vec![match line {
+                    Some(line) => format!("line {}: {}", line.max(1), e.message()),
+                    None => e.message().to_string(),
+                }];
+            }
+        };
+
+        let mut problems = Vec::new();
+        check_table("", &table, &Self::default().to_table(), &mut problems);
+        if problems.is_empty()
+            && let Err(e) = Self::parse(text)
+        {
+            problems.push(e);
+        }
+        problems
+    }
+
+    /// Every key with its value, marking those set in `file_text` (the raw
+    /// config file, if any) as coming from the file. Secrets are only
+    /// reported as set or unset.
+    pub fn settings(&self, file_text: Option<&str>) -> Vec<EffectiveSetting> {
+        let file: toml::Table = file_text
+            .and_then(|text| text.parse().ok())
+            .unwrap_or_default();
+        let mut settings = Vec::new();
+        for (section, values) in self.to_table() {
+            let toml::Value::Table(values) = values else {
+                continue;
+            };
+            for (key, value) in values {
+                let in_file =
+                    file.get(&section).and_then(|s| s.get(&key)).is_some();
+                let key = format!("{}.{}", section, key);
+                let value = match value {
+                    _ if !SECRET_KEYS.contains(&key.as_str()) => value.to_string(),
+                    toml::Value::String(secret) if !secret.is_empty() => {
+                        "set".to_string()
+                    }
+                    _ => "unset".to_string(),
+                };
+                settings.push(EffectiveSetting {
+                    key,
+                    value,
+                    source: if in_file {
+                        SettingSource::File
+                    } else {
+                        SettingSource::Default
+                    },
+                });
+            }
+        }
+        settings
+    }
+
+    /// A copy safe to print, with secrets replaced by "(set)".
+    pub fn redacted(&self) -> Self {
+        let mut config = self.clone();
+        if !config.privacy.pseudonym_key.is_empty() {
+            config.privacy.pseudonym_key = "(set)".to_string();
+        }
+        config
+    }
+
+    fn to_table(&self) -> toml::Table {
+        toml::Table::try_from(self).unwrap_or_default()
+    }
+}
+
+fn check_table(
+    prefix: &str,
+    found: &toml::Table,
+    expected: &toml::Table,
+    problems: &mut Vec<String>,
+) {
+    for (key, value) in found {
+        let path = format!("{}{}", prefix, key);
+        match (expected.get(key), value) {
+            (None, _) => problems.push(format!("unknown key `{}`", path)),
+            (Some(toml::Value::Table(expected)), toml::Value::Table(found)) => {
+                check_table(&format!("{}.", path), found, expected, problems)
+            }
+            (Some(expected), value) if expected.type_str() != value.type_str() => {
+                problems.push(format!(
+                    "`{}` should be {}, found {}",
+                    path,
+                    article(expected.type_str()),
+                    article(value.type_str())
+                ))
+            }
+            _ => {}
+        }
+    }
+}
+
+fn article(type_name: &str) -> String {
+    match type_name.chars().next() {
+        Some('a' | 'e' | 'i' | 'o' | 'u') => format!("an {}", type_name),
+        _ => format!("a {}", type_name),
+    }
+}
+
+/// Where an effective setting came from. Flags beat environment variables,
+/// which beat the config file, which beats the defaults.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingSource {
+    Default,
+    File,
+    Env(&'static str),
+    Flag(&'static str),
+}
+
+impl fmt::Display for SettingSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SettingSource::Default => write!(f, "default"),
+            SettingSource::File => write!(f, "config file"),
+            SettingSource::Env(name) => write!(f, "env {}", name),
+            SettingSource::Flag(name) => write!(f, "flag {}", name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EffectiveSetting {
+    pub key: String,
+    pub value: String,
+    pub source: SettingSource,
+}
+
+/// `config.toml` next to the database directory.
+pub fn get_config_path() -> Result<String> {
+    let db_path = get_database_path()?;
+    let dir = Path::new(&db_path).parent().unwrap_or(Path::new("."));
+    Ok(dir.join("config.toml").to_string_lossy().into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_keys_use_defaults_and_unknown_keys_fail() {
+        let config = Config::parse("[http]\ntimeout_secs = 5\n").unwrap();
+        assert_eq!(config.http.timeout(), Duration::from_secs(5));
+        assert_eq!(config.http.connect_timeout_secs, 10);
+
+        let dir = tempfile::tempdir().unwrap();
+        let missing = Config::load(&dir.path().join("config.toml")).unwrap();
+        assert_eq!(missing, Config::default());
+
+        assert!(Config::parse("[http]\ntimeout = 5\n").is_err());
+
+        let config =
+            Config::parse("[display]\ntimezone = \"Asia/Tokyo\"\n").unwrap();
+        assert_eq!(config.display.timezone, Tz::Asia__Tokyo);
+        assert!(Config::parse("[display]\ntimezone = \"Mars/Olympus\"\n").is_err());
+    }
+
+    #[test]
+    fn check_reports_every_unknown_key_and_type_error() {
+        assert!(Config::check("[http]\ntimeout_secs = 5\n").is_empty());
+        assert_eq!(
+            Config::check(
+                "[http]\ntimeout = 5\nconnect_timeout_secs = \"10\"\n[proxy]\n"
+            ),
+            [
+                "`http.connect_timeout_secs` should be an integer, found a string",
+                "unknown key `http.timeout`",
+                "unknown key `proxy`",
+            ]
+        );
+        assert_eq!(
+            Config::check("[http]\ntimeout_secs = -1\n").len(),
+            1,
+            "values the settings reject are reported too"
+        );
+        assert!(Config::check("[http\n")[0].starts_with("line 1: "));
+
+        let text = "[http]\ntimeout_secs = 5\n";
+        let settings = Config::parse(text).unwrap().settings(Some(text));
+        let timeout = settings
+            .iter()
+            .find(|s| s.key == "http.timeout_secs")
+            .unwrap();
+        assert_eq!(
+            (timeout.value.as_str(), timeout.source),
+            ("5", SettingSource::File)
+        );
+        assert!(
+            settings
+                .iter()
+                .filter(|s| s.key != "http.timeout_secs")
+                .all(|s| s.source == SettingSource::Default)
+        );
+
+        let text = "[privacy]\npseudonym_key = \"hunter2\"\n";
+        let settings = Config::parse(text).unwrap().settings(Some(text));
+        let key = settings
+            .iter()
+            .find(|s| s.key == "privacy.pseudonym_key")
+            .unwrap();
+        assert_eq!(key.value, "set");
+    }
+}
@@ -1,10 +1,25 @@
-use chrono::Utc;
+use serde::de::DeserializeOwned;
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, BTreeSet};
 
+use crate::digest::contributors;
+use crate::paths::claims_any;
+use crate::price::PriceQuote;
+use crate::privacy::replace_login;
+use crate::treasury::{TreasurySyncState, TreasuryTransaction};
 use crate::types::{
-    BaseBranchChange, MergedPullRequest, Project, ProjectOwner, Repository, Result,
+    AddressBookEntry, AddressKind, AppliedLabel, BaseBranchChange,
+    ContributorOptOut, ContributorPreferences, ContributorStats, ForgetReport,
+    GitCirclesError, MergedPullRequest, Project, ProjectAnnouncement,
+    ProjectCounters, ProjectLink, ProjectOwner, ProjectTreasury, PublishedStatus,
+    PullRequestApproval, PullRequestChange, Repository, Result, SyncLogEntry,
     UserWallet, WalletAddress, WalletHistoryEntry, WalletLoginLink,
+    WalletProbeMiss, WalletRotation, WalletWarning, WalletWarningKind,
 };
 
+/// Handle to the fjall keyspace. Cloning is cheap: all partitions are
+/// reference counted, so clones can be moved onto blocking worker threads.
+#[derive(Clone)]
 pub struct Database {
     pub keyspace: fjall::Keyspace,
     repositories: fjall::PartitionHandle,
@@ -15,6 +30,26 @@ pub struct Database {
     wallet_index: fjall::PartitionHandle,
     projects: fjall::PartitionHandle,
     project_owners: fjall::PartitionHandle,
+    pr_search_index: fjall::PartitionHandle,
+    wallet_probe_cache: fjall::PartitionHandle,
+    contributor_preferences: fjall::PartitionHandle,
+    contributor_opt_outs: fjall::PartitionHandle,
+    wallet_warnings: fjall::PartitionHandle,
+    project_announcements: fjall::PartitionHandle,
+    wallet_rotations: fjall::PartitionHandle,
+    sync_log: fjall::PartitionHandle,
+    pr_changes: fjall::PartitionHandle,
+    project_links: fjall::PartitionHandle,
+    counters: fjall::PartitionHandle,
+    contributors: fjall::PartitionHandle,
+    pr_approvals: fjall::PartitionHandle,
+    pr_labels: fjall::PartitionHandle,
+    commit_statuses: fjall::PartitionHandle,
+    price_cache: fjall::PartitionHandle,
+    address_book: fjall::PartitionHandle,
+    treasury_txs: fjall::PartitionHandle,
+    project_treasuries: fjall::PartitionHandle,
+    lenient: bool,
 }
 
 impl Database {
@@ -51,8 +86,80 @@ impl Database {
             "project_owners",
             fjall::PartitionCreateOptions::default(),
         )?;
+        let pr_search_index = keyspace.open_partition(
+            "pr_search_index",
+            fjall::PartitionCreateOptions::default(),
+        )?;
+        let wallet_probe_cache = keyspace.open_partition(
+            "wallet_probe_cache",
+            fjall::PartitionCreateOptions::default(),
+        )?;
+        let contributor_preferences = keyspace.open_partition(
+            "contributor_preferences",
+            fjall::PartitionCreateOptions::default(),
+        )?;
+        let contributor_opt_outs = keyspace.open_partition(
+            "contributor_opt_outs",
+            fjall::PartitionCreateOptions::default(),
+        )?;
+        let wallet_warnings = keyspace.open_partition(
+            "wallet_warnings",
+            fjall::PartitionCreateOptions::default(),
+        )?;
+        let project_announcements = keyspace.open_partition(
+            "project_announcements",
+            fjall::PartitionCreateOptions::default(),
+        )?;
+        let wallet_rotations = keyspace.open_partition(
+            "wallet_rotations",
+            fjall::PartitionCreateOptions::default(),
+        )?;
+        let sync_log = keyspace
+            .open_partition("sync_log", fjall::PartitionCreateOptions::default())?;
+        let pr_changes = keyspace.open_partition(
+            "pr_changes",
+            fjall::PartitionCreateOptions::default(),
+        )?;
+        let project_links = keyspace.open_partition(
+            "project_links",
+            fjall::PartitionCreateOptions::default(),
+        )?;
+        let counters = keyspace
+            .open_partition("counters", fjall::PartitionCreateOptions::default())?;
+        let contributors = keyspace.open_partition(
+            "contributors",
+            fjall::PartitionCreateOptions::default(),
+        )?;
+        let pr_approvals = keyspace.open_partition(
+            "pr_approvals",
+            fjall::PartitionCreateOptions::default(),
+        )?;
+        let pr_labels = keyspace.open_partition(
+            "pr_labels",
+            fjall::PartitionCreateOptions::default(),
+        )?;
+        let commit_statuses = keyspace.open_partition(
+            "commit_statuses",
+            fjall::PartitionCreateOptions::default(),
+        )?;
+        let price_cache = keyspace.open_partition(
+            "price_cache",
+            fjall::PartitionCreateOptions::default(),
+        )?;
+        let address_book = keyspace.open_partition(
+            "address_book",
+            fjall::PartitionCreateOptions::default(),
+        )?;
+        let treasury_txs = keyspace.open_partition(
+            "treasury_txs",
+            fjall::PartitionCreateOptions::default(),
+        )?;
+        let project_treasuries = keyspace.open_partition(
+            "project_treasuries",
+            fjall::PartitionCreateOptions::default(),
+        )?;
 
-        Ok(Self {
+        let db = Self {
             keyspace,
             repositories,
             pull_requests,
@@ -62,7 +169,72 @@ impl Database {
             wallet_index,
             projects,
             project_owners,
-        })
+            pr_search_index,
+            wallet_probe_cache,
+            contributor_preferences,
+            contributor_opt_outs,
+            wallet_warnings,
+            project_announcements,
+            wallet_rotations,
+            sync_log,
+            pr_changes,
+            project_links,
+            counters,
+            contributors,
+            pr_approvals,
+            pr_labels,
+            commit_statuses,
+            price_cache,
+            address_book,
+            treasury_txs,
+            project_treasuries,
+            lenient: false,
+        };
+        // Project views read links only, so move older links over right away
+        db.migrate_project_links()?;
+        if db.counters.is_empty()? {
+            for project in db.list_projects()? {
+                db.rebuild_project_counters(&project.id)?;
+            }
+        }
+        if db.contributors.is_empty()? && !db.pull_requests.is_empty()? {
+            db.rebuild_contributor_stats()?;
+        }
+        Ok(db)
+    }
+
+    /// Treat records that fail to deserialize as missing instead of
+    /// returning [`GitCirclesError::CorruptRecord`], e.g. to keep working
+    /// with a partly damaged database until it is repaired.
+    pub fn with_lenient_reads(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    fn decode<T: DeserializeOwned>(
+        &self,
+        key: &str,
+        value: &[u8],
+    ) -> Result<Option<T>> {
+        match serde_json::from_slice(value) {
+            Ok(record) => Ok(Some(record)),
+            Err(_) if self.lenient => Ok(None),
+            Err(cause) => Err(GitCirclesError::CorruptRecord {
+                key: key.to_string(),
+                cause,
+            }),
+        }
+    }
+
+    /// Run `f` on tokio's blocking pool so fjall I/O (notably the `SyncAll`
+    /// persists) never stalls the async executor.
+    pub async fn run<T, F>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&Database) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let db = self.clone();
+        tokio::task::spawn_blocking(move || f(&db)).await?
     }
 
     pub fn upsert_repository(&self, repo: &Repository) -> Result<()> {
@@ -79,12 +251,9 @@ impl Database {
         name: &str,
     ) -> Result<Option<Repository>> {
         let key = format!("repo:{}/{}", owner, name);
-        if let Some(value) = self.repositories.get(&key)?
-            && let Ok(repo) = serde_json::from_slice(&value)
-        {
-            Ok(Some(repo))
-        } else {
-            Ok(None)
+        match self.repositories.get(&key)? {
+            Some(value) => self.decode(&key, &value),
+            None => Ok(None),
         }
     }
 
@@ -99,14 +268,268 @@ impl Database {
             .collect()
     }
 
+    /// Store a PR and refresh its search index entries in one batch.
     pub fn upsert_pull_request(&self, pr: &MergedPullRequest) -> Result<()> {
+        let mut batch = self.keyspace.batch();
+        self.upsert_pull_request_batch(&mut batch, pr)?;
+        batch.commit()?;
+        self.keyspace.persist(fjall::PersistMode::SyncAll)?;
+        Ok(())
+    }
+
+    pub fn upsert_pull_request_batch(
+        &self,
+        batch: &mut fjall::Batch,
+        pr: &MergedPullRequest,
+    ) -> Result<()> {
         let key = format!("pr:{}:{}", pr.repository, pr.number);
         let value = serde_json::to_vec(pr)?;
-        self.pull_requests.insert(&key, &value)?;
+
+        if let Some(old) = self.get_pull_request(&pr.repository, pr.number)? {
+            for term in search_terms(&old) {
+                batch.remove(&self.pr_search_index, search_key(&term, &key));
+            }
+        }
+        for term in search_terms(pr) {
+            batch.insert(&self.pr_search_index, search_key(&term, &key), &key);
+        }
+        batch.insert(&self.pull_requests, &key, &value);
+        Ok(())
+    }
+
+    /// Number of PRs stored for `repo` ("owner/repo").
+    pub fn count_pull_requests(&self, repo: &str) -> Result<u64> {
+        let prefix = format!("pr:{}:", repo);
+        let mut count = 0;
+        for item in self.pull_requests.prefix(prefix.as_bytes()) {
+            item?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Store collected PRs together with their repository record, setting
+    /// `total_prs` from what is stored rather than adding to it, so
+    /// overlapping or failed runs can't make it drift.
+    pub fn store_collected_pull_requests(
+        &self,
+        repo: &mut Repository,
+        prs: &[MergedPullRequest],
+    ) -> Result<()> {
+        let full = format!("{}/{}", repo.owner, repo.name);
+        let mut added = BTreeSet::new();
+        for pr in prs {
+            if !self.pull_request_exists(&pr.repository, pr.number)? {
+                added.insert((pr.repository.as_str(), pr.number));
+            }
+        }
+        repo.total_prs = self.count_pull_requests(&full)?
+            + added.iter().filter(|(r, _)| *r == full).count() as u64;
+
+        let mut batch = self.keyspace.batch();
+        for pr in prs {
+            self.upsert_pull_request_batch(&mut batch, pr)?;
+        }
+        let new_prs: Vec<&MergedPullRequest> = prs
+            .iter()
+            .filter(|pr| added.contains(&(pr.repository.as_str(), pr.number)))
+            .collect();
+        self.record_contributions_batch(&mut batch, &new_prs)?;
+        for link in self.get_links_for_repository(&full)? {
+            let project = self.get_project(&link.project_id)?;
+            let claimed = prs
+                .iter()
+                .filter(|pr| added.contains(&(pr.repository.as_str(), pr.number)))
+                .filter(|pr| claims_any(&link.paths, &pr.changed_files))
+                .filter(|pr| {
+                    project.as_ref().is_none_or(|p| p.counts_author(&pr.author))
+                })
+                .count() as u64;
+            if claimed > 0 {
+                let mut counters = self.get_project_counters(&link.project_id)?;
+                counters.prs += claimed;
+                self.set_project_counters_batch(
+                    &mut batch,
+                    &link.project_id,
+                    &counters,
+                )?;
+            }
+        }
+        let key = format!("repo:{}", full);
+        batch.insert(&self.repositories, key, serde_json::to_vec(repo)?);
+        batch.commit()?;
         self.keyspace.persist(fjall::PersistMode::SyncAll)?;
         Ok(())
     }
 
+    pub fn get_contributor_stats(
+        &self,
+        platform: &str,
+        login: &str,
+    ) -> Result<Option<ContributorStats>> {
+        let key = format!("contributor:{}:{}", platform, login);
+        match self.contributors.get(&key)? {
+            Some(value) => self.decode(&key, &value),
+            None => Ok(None),
+        }
+    }
+
+    // Add newly stored PRs to their contributors' stats
+    fn record_contributions_batch(
+        &self,
+        batch: &mut fjall::Batch,
+        prs: &[&MergedPullRequest],
+    ) -> Result<()> {
+        let mut touched: BTreeMap<String, ContributorStats> = BTreeMap::new();
+        for pr in prs {
+            // The opener is usually among the commit authors too
+            let logins: BTreeSet<&str> = contributors(pr).collect();
+            for login in logins {
+                let stats = match touched.entry(login.to_string()) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => {
+                        let stored =
+                            self.get_contributor_stats(&pr.platform, login)?;
+                        entry.insert(stored.unwrap_or_else(|| {
+                            ContributorStats::new(&pr.platform, login, pr)
+                        }))
+                    }
+                };
+                stats.record(pr);
+            }
+        }
+        for stats in touched.values() {
+            let key = format!("contributor:{}:{}", stats.platform, stats.login);
+            batch.insert(&self.contributors, key, serde_json::to_vec(stats)?);
+        }
+        Ok(())
+    }
+
+    /// Recompute every contributor's stats from the stored PRs. Returns the
+    /// number of contributors.
+    pub fn rebuild_contributor_stats(&self) -> Result<usize> {
+        let mut batch = self.keyspace.batch();
+        for item in self.contributors.iter() {
+            let (key, _) = item?;
+            batch.remove(&self.contributors, key);
+        }
+        batch.commit()?;
+
+        let mut prs = Vec::new();
+        for repo in self.list_repositories()? {
+            prs.extend(
+                self.get_pull_requests(&format!("{}/{}", repo.owner, repo.name))?,
+            );
+        }
+        let refs: Vec<&MergedPullRequest> = prs.iter().collect();
+        let mut batch = self.keyspace.batch();
+        self.record_contributions_batch(&mut batch, &refs)?;
+        batch.commit()?;
+        self.keyspace.persist(fjall::PersistMode::SyncAll)?;
+        Ok(self.contributors.len()?)
+    }
+
+    /// Recompute every repository's `total_prs` from the stored PRs.
+    /// Returns the repositories whose counter was wrong, with the old value.
+    pub fn recount_pull_requests(&self) -> Result<Vec<(Repository, u64)>> {
+        let mut batch = self.keyspace.batch();
+        let mut repaired = Vec::new();
+        for mut repo in self.list_repositories()? {
+            let full = format!("{}/{}", repo.owner, repo.name);
+            let count = self.count_pull_requests(&full)?;
+            if repo.total_prs == count {
+                continue;
+            }
+            let old = std::mem::replace(&mut repo.total_prs, count);
+            batch.insert(
+                &self.repositories,
+                format!("repo:{}", full),
+                serde_json::to_vec(&repo)?,
+            );
+            repaired.push((repo, old));
+        }
+        batch.commit()?;
+        self.keyspace.persist(fjall::PersistMode::SyncAll)?;
+        Ok(repaired)
+    }
+
+    /// PRs matching every word of `query` (as a prefix of a word in the
+    /// title, description or author logins), newest first.
+    pub fn search_pull_requests(
+        &self,
+        query: &str,
+        repo: Option<&str>,
+    ) -> Result<Vec<MergedPullRequest>> {
+        let mut matches: Option<BTreeSet<Vec<u8>>> = None;
+        for word in tokenize(query) {
+            let prefix = format!("term:{}", word);
+            let keys = self
+                .pr_search_index
+                .prefix(prefix.as_bytes())
+                .map(|item| Ok(item?.1.to_vec()))
+                .collect::<Result<BTreeSet<_>>>()?;
+            matches = Some(match matches {
+                Some(prev) => prev.intersection(&keys).cloned().collect(),
+                None => keys,
+            });
+        }
+
+        let mut prs = Vec::new();
+        for key in matches.unwrap_or_default() {
+            if let Some(value) = self.pull_requests.get(&key)? {
+                let pr: MergedPullRequest = serde_json::from_slice(&value)?;
+                if repo.is_none_or(|r| r == pr.repository) {
+                    prs.push(pr);
+                }
+            }
+        }
+        prs.sort_by_key(|pr| std::cmp::Reverse(pr.merged_at));
+        Ok(prs)
+    }
+
+    /// Rebuild the search index from stored PRs, e.g. for databases created
+    /// before the index existed. Returns the number of PRs indexed.
+    pub fn rebuild_pr_search_index(&self) -> Result<usize> {
+        let mut batch = self.keyspace.batch();
+        for item in self.pr_search_index.iter() {
+            let (key, _) = item?;
+            batch.remove(&self.pr_search_index, key);
+        }
+        let mut count = 0;
+        for item in self.pull_requests.prefix("pr:".as_bytes()) {
+            let (key, value) = item?;
+            let pr: MergedPullRequest = serde_json::from_slice(&value)?;
+            let key = String::from_utf8_lossy(&key).to_string();
+            for term in search_terms(&pr) {
+                batch.insert(&self.pr_search_index, search_key(&term, &key), &key);
+            }
+            count += 1;
+        }
+        batch.commit()?;
+        self.keyspace.persist(fjall::PersistMode::SyncAll)?;
+        Ok(count)
+    }
+
+    /// Fill in `html_url` on PRs stored before it was collected. Returns
+    /// the number of records updated.
+    pub fn backfill_pull_request_urls(&self) -> Result<usize> {
+        let mut batch = self.keyspace.batch();
+        let mut count = 0;
+        for item in self.pull_requests.prefix("pr:".as_bytes()) {
+            let (key, value) = item?;
+            let mut pr: MergedPullRequest = serde_json::from_slice(&value)?;
+            if pr.html_url.is_some() {
+                continue;
+            }
+            pr.html_url = Some(pr.url());
+            batch.insert(&self.pull_requests, key, serde_json::to_vec(&pr)?);
+            count += 1;
+        }
+        batch.commit()?;
+        self.keyspace.persist(fjall::PersistMode::SyncAll)?;
+        Ok(count)
+    }
+
     pub fn get_pull_requests(&self, repo: &str) -> Result<Vec<MergedPullRequest>> {
         let prefix = format!("pr:{}:", repo);
         self.pull_requests
@@ -119,6 +542,18 @@ impl Database {
             .collect()
     }
 
+    pub fn get_pull_request(
+        &self,
+        repo: &str,
+        number: u64,
+    ) -> Result<Option<MergedPullRequest>> {
+        let key = format!("pr:{}:{}", repo, number);
+        match self.pull_requests.get(&key)? {
+            Some(value) => Ok(Some(serde_json::from_slice(&value)?)),
+            None => Ok(None),
+        }
+    }
+
     pub fn pull_request_exists(&self, repo: &str, number: u64) -> Result<bool> {
         let key = format!("pr:{}:{}", repo, number);
         Ok(self.pull_requests.contains_key(&key)?)
@@ -126,19 +561,14 @@ impl Database {
 
     pub fn record_base_branch_change(
         &self,
-        repo: &str,
-        old_branch: &str,
-        new_branch: &str,
+        change: &BaseBranchChange,
     ) -> Result<()> {
-        let change = BaseBranchChange {
-            repository: repo.to_string(),
-            old_branch: old_branch.to_string(),
-            new_branch: new_branch.to_string(),
-            changed_at: Utc::now(),
-        };
-
-        let key = format!("base:{}:{}", repo, change.changed_at.timestamp());
-        let value = serde_json::to_vec(&change)?;
+        let key = format!(
+            "base:{}:{}",
+            change.repository,
+            change.changed_at.timestamp()
+        );
+        let value = serde_json::to_vec(change)?;
         self.base_branch_history.insert(&key, &value)?;
         self.keyspace.persist(fjall::PersistMode::SyncAll)?;
         Ok(())
@@ -159,6 +589,68 @@ impl Database {
             .collect()
     }
 
+    pub fn append_sync_log(&self, entry: &SyncLogEntry) -> Result<()> {
+        let key = format!(
+            "sync:{}:{:020}",
+            entry.repository,
+            entry.started_at.timestamp_micros()
+        );
+        let value = serde_json::to_vec(entry)?;
+        self.sync_log.insert(&key, &value)?;
+        self.keyspace.persist(fjall::PersistMode::SyncAll)?;
+        Ok(())
+    }
+
+    /// Collect runs for a repository, oldest first.
+    pub fn get_sync_log(&self, repo: &str) -> Result<Vec<SyncLogEntry>> {
+        let prefix = format!("sync:{}:", repo);
+        self.sync_log
+            .prefix(prefix.as_bytes())
+            .map(|item| {
+                let (_, value) = item?;
+                let entry: SyncLogEntry = serde_json::from_slice(&value)?;
+                Ok(entry)
+            })
+            .collect()
+    }
+
+    pub fn append_pull_request_changes(
+        &self,
+        changes: &[PullRequestChange],
+    ) -> Result<()> {
+        let mut batch = self.keyspace.batch();
+        for change in changes {
+            let key = format!(
+                "change:{}:{}:{:020}:{}",
+                change.repository,
+                change.number,
+                change.changed_at.timestamp_micros(),
+                change.field
+            );
+            batch.insert(&self.pr_changes, key, serde_json::to_vec(change)?);
+        }
+        batch.commit()?;
+        self.keyspace.persist(fjall::PersistMode::SyncAll)?;
+        Ok(())
+    }
+
+    /// Post-merge edits recorded for a PR, oldest first.
+    pub fn get_pull_request_changes(
+        &self,
+        repo: &str,
+        number: u64,
+    ) -> Result<Vec<PullRequestChange>> {
+        let prefix = format!("change:{}:{}:", repo, number);
+        self.pr_changes
+            .prefix(prefix.as_bytes())
+            .map(|item| {
+                let (_, value) = item?;
+                let change: PullRequestChange = serde_json::from_slice(&value)?;
+                Ok(change)
+            })
+            .collect()
+    }
+
     // Wallet methods
     pub fn upsert_user_wallet(&self, wallet: &UserWallet) -> Result<()> {
         let key = format!("login:{}:{}", wallet.platform, wallet.login);
@@ -174,12 +666,9 @@ impl Database {
         login: &str,
     ) -> Result<Option<UserWallet>> {
         let key = format!("login:{}:{}", platform, login);
-        if let Some(value) = self.user_wallets.get(&key)?
-            && let Ok(wallet) = serde_json::from_slice(&value)
-        {
-            Ok(Some(wallet))
-        } else {
-            Ok(None)
+        match self.user_wallets.get(&key)? {
+            Some(value) => self.decode(&key, &value),
+            None => Ok(None),
         }
     }
 
@@ -212,109 +701,616 @@ impl Database {
             .collect()
     }
 
-    pub fn get_logins_for_wallet(
+    pub fn get_logins_for_wallet(
+        &self,
+        address: &WalletAddress,
+        platform: &str,
+    ) -> Result<Vec<WalletLoginLink>> {
+        let prefix = format!("wallet:{}:{}:", address, platform);
+        self.wallet_index
+            .prefix(prefix.as_bytes())
+            .map(|item| {
+                let (_, value) = item?;
+                let link: WalletLoginLink = serde_json::from_slice(&value)?;
+                Ok(link)
+            })
+            .collect()
+    }
+
+    pub fn list_wallet_links(&self) -> Result<Vec<WalletLoginLink>> {
+        self.wallet_index
+            .prefix("wallet:".as_bytes())
+            .map(|item| {
+                let (_, value) = item?;
+                let link: WalletLoginLink = serde_json::from_slice(&value)?;
+                Ok(link)
+            })
+            .collect()
+    }
+
+    pub fn replace_wallet_link(&self, link: &WalletLoginLink) -> Result<()> {
+        let key =
+            format!("wallet:{}:{}:{}", link.wallet, link.platform, link.login);
+        let value = serde_json::to_vec(link)?;
+        self.wallet_index.insert(&key, &value)?;
+        self.keyspace.persist(fjall::PersistMode::SyncAll)?;
+        Ok(())
+    }
+
+    pub fn record_wallet_probe_miss(&self, miss: &WalletProbeMiss) -> Result<()> {
+        let key = format!("probe:{}:{}", miss.platform, miss.login);
+        let value = serde_json::to_vec(miss)?;
+        self.wallet_probe_cache.insert(&key, &value)?;
+        self.keyspace.persist(fjall::PersistMode::SyncAll)?;
+        Ok(())
+    }
+
+    pub fn get_wallet_probe_miss(
+        &self,
+        platform: &str,
+        login: &str,
+    ) -> Result<Option<WalletProbeMiss>> {
+        let key = format!("probe:{}:{}", platform, login);
+        match self.wallet_probe_cache.get(&key)? {
+            Some(value) => self.decode(&key, &value),
+            None => Ok(None),
+        }
+    }
+
+    pub fn get_contributor_preferences(
+        &self,
+        platform: &str,
+        login: &str,
+    ) -> Result<Option<ContributorPreferences>> {
+        let key = format!("prefs:{}:{}", platform, login);
+        match self.contributor_preferences.get(&key)? {
+            Some(value) => Ok(Some(serde_json::from_slice(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// All wallet warnings, oldest first.
+    pub fn list_wallet_warnings(&self) -> Result<Vec<WalletWarning>> {
+        self.wallet_warnings
+            .prefix("warning:".as_bytes())
+            .map(|item| {
+                let (_, value) = item?;
+                let warning: WalletWarning = serde_json::from_slice(&value)?;
+                Ok(warning)
+            })
+            .collect()
+    }
+
+    /// Latest address rotation recorded for a login.
+    pub fn get_wallet_rotation(
+        &self,
+        platform: &str,
+        login: &str,
+    ) -> Result<Option<WalletRotation>> {
+        let key = format!("rotation:{}:{}", platform, login);
+        match self.wallet_rotations.get(&key)? {
+            Some(value) => Ok(Some(serde_json::from_slice(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn list_wallet_rotations(&self) -> Result<Vec<WalletRotation>> {
+        self.wallet_rotations
+            .prefix("rotation:".as_bytes())
+            .map(|item| {
+                let (_, value) = item?;
+                let rotation: WalletRotation = serde_json::from_slice(&value)?;
+                Ok(rotation)
+            })
+            .collect()
+    }
+
+    // Opt-out methods
+    pub fn set_contributor_opt_out(
+        &self,
+        opt_out: &ContributorOptOut,
+    ) -> Result<()> {
+        let key = format!("optout:{}:{}", opt_out.platform, opt_out.login);
+        let value = serde_json::to_vec(opt_out)?;
+        self.contributor_opt_outs.insert(&key, &value)?;
+        self.keyspace.persist(fjall::PersistMode::SyncAll)?;
+        Ok(())
+    }
+
+    pub fn get_contributor_opt_out(
+        &self,
+        platform: &str,
+        login: &str,
+    ) -> Result<Option<ContributorOptOut>> {
+        let key = format!("optout:{}:{}", platform, login);
+        match self.contributor_opt_outs.get(&key)? {
+            Some(value) => Ok(Some(serde_json::from_slice(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn remove_contributor_opt_out(
+        &self,
+        platform: &str,
+        login: &str,
+    ) -> Result<()> {
+        let key = format!("optout:{}:{}", platform, login);
+        self.contributor_opt_outs.remove(&key)?;
+        self.keyspace.persist(fjall::PersistMode::SyncAll)?;
+        Ok(())
+    }
+
+    pub fn list_contributor_opt_outs(&self) -> Result<Vec<ContributorOptOut>> {
+        self.contributor_opt_outs
+            .prefix("optout:".as_bytes())
+            .map(|item| {
+                let (_, value) = item?;
+                let opt_out: ContributorOptOut = serde_json::from_slice(&value)?;
+                Ok(opt_out)
+            })
+            .collect()
+    }
+
+    // Batch-aware methods for atomic wallet operations
+    pub fn set_contributor_opt_out_batch(
+        &self,
+        batch: &mut fjall::Batch,
+        opt_out: &ContributorOptOut,
+    ) -> Result<()> {
+        let key = format!("optout:{}:{}", opt_out.platform, opt_out.login);
+        let value = serde_json::to_vec(opt_out)?;
+        batch.insert(&self.contributor_opt_outs, key, value);
+        Ok(())
+    }
+
+    pub fn remove_contributor_opt_out_batch(
+        &self,
+        batch: &mut fjall::Batch,
+        platform: &str,
+        login: &str,
+    ) {
+        let key = format!("optout:{}:{}", platform, login);
+        batch.remove(&self.contributor_opt_outs, key);
+    }
+
+    /// Store preferences, or drop them when the profile no longer has any.
+    pub fn set_contributor_preferences_batch(
+        &self,
+        batch: &mut fjall::Batch,
+        platform: &str,
+        login: &str,
+        preferences: Option<&ContributorPreferences>,
+    ) -> Result<()> {
+        let key = format!("prefs:{}:{}", platform, login);
+        match preferences {
+            Some(prefs) => {
+                batch.insert(
+                    &self.contributor_preferences,
+                    key,
+                    serde_json::to_vec(prefs)?,
+                );
+            }
+            None => batch.remove(&self.contributor_preferences, key),
+        }
+        Ok(())
+    }
+
+    pub fn clear_wallet_probe_miss_batch(
+        &self,
+        batch: &mut fjall::Batch,
+        platform: &str,
+        login: &str,
+    ) {
+        let key = format!("probe:{}:{}", platform, login);
+        batch.remove(&self.wallet_probe_cache, key);
+    }
+
+    pub fn set_wallet_rotation_batch(
+        &self,
+        batch: &mut fjall::Batch,
+        rotation: &WalletRotation,
+    ) -> Result<()> {
+        let key = format!("rotation:{}:{}", rotation.platform, rotation.login);
+        let value = serde_json::to_vec(rotation)?;
+        batch.insert(&self.wallet_rotations, key, value);
+        Ok(())
+    }
+
+    pub fn remove_wallet_rotation_batch(
+        &self,
+        batch: &mut fjall::Batch,
+        platform: &str,
+        login: &str,
+    ) {
+        let key = format!("rotation:{}:{}", platform, login);
+        batch.remove(&self.wallet_rotations, key);
+    }
+
+    pub fn upsert_user_wallet_batch(
+        &self,
+        batch: &mut fjall::Batch,
+        wallet: &UserWallet,
+    ) -> Result<()> {
+        let key = format!("login:{}:{}", wallet.platform, wallet.login);
+        let value = serde_json::to_vec(wallet)?;
+        batch.insert(&self.user_wallets, key, value);
+        Ok(())
+    }
+
+    pub fn append_wallet_history_batch(
+        &self,
+        batch: &mut fjall::Batch,
+        entry: &WalletHistoryEntry,
+    ) -> Result<()> {
+        let key = format!(
+            "history:{}:{}:{}",
+            entry.platform,
+            entry.login,
+            entry.recorded_at.timestamp()
+        );
+        let value = serde_json::to_vec(entry)?;
+        batch.insert(&self.user_wallet_history, key, value);
+        Ok(())
+    }
+
+    pub fn append_wallet_warning_batch(
+        &self,
+        batch: &mut fjall::Batch,
+        warning: &WalletWarning,
+    ) -> Result<()> {
+        // Zero-padded so keys sort chronologically
+        let key = format!(
+            "warning:{:020}:{}:{}",
+            warning.recorded_at.timestamp_micros(),
+            warning.platform,
+            warning.login
+        );
+        let value = serde_json::to_vec(warning)?;
+        batch.insert(&self.wallet_warnings, key, value);
+        Ok(())
+    }
+
+    pub fn replace_wallet_link_batch(
+        &self,
+        batch: &mut fjall::Batch,
+        link: &WalletLoginLink,
+    ) -> Result<()> {
+        let key =
+            format!("wallet:{}:{}:{}", link.wallet, link.platform, link.login);
+        let value = serde_json::to_vec(link)?;
+        batch.insert(&self.wallet_index, key, value);
+        Ok(())
+    }
+
+    pub fn remove_wallet_link_batch(
+        &self,
+        batch: &mut fjall::Batch,
+        address: &WalletAddress,
+        platform: &str,
+        login: &str,
+    ) {
+        let key = format!("wallet:{}:{}:{}", address, platform, login);
+        batch.remove(&self.wallet_index, key);
+    }
+
+    // Project methods
+    pub fn upsert_project(&self, project: &Project) -> Result<()> {
+        let key = format!("project:{}", project.id);
+        let value = serde_json::to_vec(project)?;
+        self.projects.insert(&key, &value)?;
+        self.keyspace.persist(fjall::PersistMode::SyncAll)?;
+        Ok(())
+    }
+
+    pub fn get_project(&self, project_id: &str) -> Result<Option<Project>> {
+        let key = format!("project:{}", project_id);
+        match self.projects.get(&key)? {
+            Some(value) => self.decode(&key, &value),
+            None => Ok(None),
+        }
+    }
+
+    pub fn list_projects(&self) -> Result<Vec<Project>> {
+        self.projects
+            .prefix("project:".as_bytes())
+            .map(|item| {
+                let (_, value) = item?;
+                let project: Project = serde_json::from_slice(&value)?;
+                Ok(project)
+            })
+            .collect()
+    }
+
+    pub fn delete_project(&self, project_id: &str) -> Result<()> {
+        let mut batch = self.keyspace.batch();
+        batch.remove(&self.projects, format!("project:{}", project_id));
+        batch.remove(&self.counters, format!("counter:{}", project_id));
+        batch.commit()?;
+        self.keyspace.persist(fjall::PersistMode::SyncAll)?;
+        Ok(())
+    }
+
+    // Counter methods. Keys are `counter:{project_id}`.
+    pub fn get_project_counters(
+        &self,
+        project_id: &str,
+    ) -> Result<ProjectCounters> {
+        let key = format!("counter:{}", project_id);
+        match self.counters.get(&key)? {
+            Some(value) => Ok(self.decode(&key, &value)?.unwrap_or_default()),
+            None => Ok(ProjectCounters::default()),
+        }
+    }
+
+    pub fn list_projects_with_counters(
+        &self,
+    ) -> Result<Vec<(Project, ProjectCounters)>> {
+        self.list_projects()?
+            .into_iter()
+            .map(|project| {
+                let counters = self.get_project_counters(&project.id)?;
+                Ok((project, counters))
+            })
+            .collect()
+    }
+
+    pub fn set_project_counters_batch(
+        &self,
+        batch: &mut fjall::Batch,
+        project_id: &str,
+        counters: &ProjectCounters,
+    ) -> Result<()> {
+        let key = format!("counter:{}", project_id);
+        batch.insert(&self.counters, key, serde_json::to_vec(counters)?);
+        Ok(())
+    }
+
+    /// Recount a project's totals from the stored PRs, e.g. after its links
+    /// or path claims changed.
+    pub fn rebuild_project_counters(
+        &self,
+        project_id: &str,
+    ) -> Result<ProjectCounters> {
+        let counters = ProjectCounters {
+            prs: self.get_pull_requests_for_project(project_id)?.len() as u64,
+        };
+        let mut batch = self.keyspace.batch();
+        self.set_project_counters_batch(&mut batch, project_id, &counters)?;
+        batch.commit()?;
+        self.keyspace.persist(fjall::PersistMode::SyncAll)?;
+        Ok(counters)
+    }
+
+    pub fn upsert_project_announcement(
+        &self,
+        announcement: &ProjectAnnouncement,
+    ) -> Result<()> {
+        let key = format!("announce:{}", announcement.repository);
+        let value = serde_json::to_vec(announcement)?;
+        self.project_announcements.insert(&key, &value)?;
+        self.keyspace.persist(fjall::PersistMode::SyncAll)?;
+        Ok(())
+    }
+
+    pub fn get_project_announcement(
+        &self,
+        repository: &str,
+    ) -> Result<Option<ProjectAnnouncement>> {
+        let key = format!("announce:{}", repository);
+        match self.project_announcements.get(&key)? {
+            Some(value) => Ok(Some(serde_json::from_slice(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn set_pull_request_approval(
+        &self,
+        approval: &PullRequestApproval,
+    ) -> Result<()> {
+        let key = format!(
+            "approval:{}:{}:{}",
+            approval.project_id, approval.repository, approval.number
+        );
+        let value = serde_json::to_vec(approval)?;
+        self.pr_approvals.insert(&key, &value)?;
+        self.keyspace.persist(fjall::PersistMode::SyncAll)?;
+        Ok(())
+    }
+
+    pub fn get_pull_request_approval(
+        &self,
+        project_id: &str,
+        repository: &str,
+        number: u64,
+    ) -> Result<Option<PullRequestApproval>> {
+        let key = format!("approval:{}:{}:{}", project_id, repository, number);
+        match self.pr_approvals.get(&key)? {
+            Some(value) => self.decode(&key, &value),
+            None => Ok(None),
+        }
+    }
+
+    pub fn get_approvals_for_project(
         &self,
-        address: &WalletAddress,
-        platform: &str,
-    ) -> Result<Vec<WalletLoginLink>> {
-        let prefix = format!("wallet:{}:{}:", address, platform);
-        self.wallet_index
+        project_id: &str,
+    ) -> Result<Vec<PullRequestApproval>> {
+        let prefix = format!("approval:{}:", project_id);
+        self.pr_approvals
             .prefix(prefix.as_bytes())
             .map(|item| {
                 let (_, value) = item?;
-                let link: WalletLoginLink = serde_json::from_slice(&value)?;
-                Ok(link)
+                let approval: PullRequestApproval = serde_json::from_slice(&value)?;
+                Ok(approval)
             })
             .collect()
     }
 
-    pub fn replace_wallet_link(&self, link: &WalletLoginLink) -> Result<()> {
-        let key =
-            format!("wallet:{}:{}:{}", link.wallet, link.platform, link.login);
-        let value = serde_json::to_vec(link)?;
-        self.wallet_index.insert(&key, &value)?;
+    pub fn remove_approvals_for_project(&self, project_id: &str) -> Result<usize> {
+        let prefix = format!("approval:{}:", project_id);
+        let mut removed = 0;
+        for item in self.pr_approvals.prefix(prefix.as_bytes()) {
+            let (key, _) = item?;
+            self.pr_approvals.remove(key)?;
+            removed += 1;
+        }
+        if removed > 0 {
+            self.keyspace.persist(fjall::PersistMode::SyncAll)?;
+        }
+        Ok(removed)
+    }
+
+    pub fn set_applied_label(&self, label: &AppliedLabel) -> Result<()> {
+        let key = format!("label:{}:{}", label.repository, label.number);
+        let value = serde_json::to_vec(label)?;
+        self.pr_labels.insert(&key, &value)?;
         self.keyspace.persist(fjall::PersistMode::SyncAll)?;
         Ok(())
     }
 
-    // Batch-aware methods for atomic wallet operations
-    pub fn upsert_user_wallet_batch(
+    pub fn get_applied_label(
         &self,
-        batch: &mut fjall::Batch,
-        wallet: &UserWallet,
-    ) -> Result<()> {
-        let key = format!("login:{}:{}", wallet.platform, wallet.login);
-        let value = serde_json::to_vec(wallet)?;
-        batch.insert(&self.user_wallets, key, value);
-        Ok(())
+        repository: &str,
+        number: u64,
+    ) -> Result<Option<AppliedLabel>> {
+        let key = format!("label:{}:{}", repository, number);
+        match self.pr_labels.get(&key)? {
+            Some(value) => self.decode(&key, &value),
+            None => Ok(None),
+        }
     }
 
-    pub fn append_wallet_history_batch(
-        &self,
-        batch: &mut fjall::Batch,
-        entry: &WalletHistoryEntry,
-    ) -> Result<()> {
-        let key = format!(
-            "history:{}:{}:{}",
-            entry.platform,
-            entry.login,
-            entry.recorded_at.timestamp()
-        );
-        let value = serde_json::to_vec(entry)?;
-        batch.insert(&self.user_wallet_history, key, value);
+    pub fn set_published_status(&self, status: &PublishedStatus) -> Result<()> {
+        let key = format!("status:{}:{}", status.repository, status.number);
+        let value = serde_json::to_vec(status)?;
+        self.commit_statuses.insert(&key, &value)?;
+        self.keyspace.persist(fjall::PersistMode::SyncAll)?;
         Ok(())
     }
 
-    pub fn replace_wallet_link_batch(
+    pub fn get_published_status(
         &self,
-        batch: &mut fjall::Batch,
-        link: &WalletLoginLink,
-    ) -> Result<()> {
-        let key =
-            format!("wallet:{}:{}:{}", link.wallet, link.platform, link.login);
-        let value = serde_json::to_vec(link)?;
-        batch.insert(&self.wallet_index, key, value);
+        repository: &str,
+        number: u64,
+    ) -> Result<Option<PublishedStatus>> {
+        let key = format!("status:{}:{}", repository, number);
+        match self.commit_statuses.get(&key)? {
+            Some(value) => self.decode(&key, &value),
+            None => Ok(None),
+        }
+    }
+
+    pub fn set_price_quote(&self, quote: &PriceQuote) -> Result<()> {
+        let value = serde_json::to_vec(quote)?;
+        self.price_cache.insert("quote:ERG/USD", &value)?;
+        self.keyspace.persist(fjall::PersistMode::SyncAll)?;
         Ok(())
     }
 
-    // Project methods
-    pub fn upsert_project(&self, project: &Project) -> Result<()> {
-        let key = format!("project:{}", project.id);
-        let value = serde_json::to_vec(project)?;
-        self.projects.insert(&key, &value)?;
+    pub fn get_price_quote(&self) -> Result<Option<PriceQuote>> {
+        let key = "quote:ERG/USD";
+        match self.price_cache.get(key)? {
+            Some(value) => self.decode(key, &value),
+            None => Ok(None),
+        }
+    }
+
+    pub fn set_address_book_entry(&self, entry: &AddressBookEntry) -> Result<()> {
+        let key = format!("address:{}", entry.address);
+        let value = serde_json::to_vec(entry)?;
+        self.address_book.insert(&key, &value)?;
         self.keyspace.persist(fjall::PersistMode::SyncAll)?;
         Ok(())
     }
 
-    pub fn get_project(&self, project_id: &str) -> Result<Option<Project>> {
-        let key = format!("project:{}", project_id);
-        if let Some(value) = self.projects.get(&key)?
-            && let Ok(project) = serde_json::from_slice(&value)
-        {
-            Ok(Some(project))
-        } else {
-            Ok(None)
+    pub fn get_address_book_entry(
+        &self,
+        address: &WalletAddress,
+    ) -> Result<Option<AddressBookEntry>> {
+        let key = format!("address:{}", address);
+        match self.address_book.get(&key)? {
+            Some(value) => self.decode(&key, &value),
+            None => Ok(None),
         }
     }
 
-    pub fn list_projects(&self) -> Result<Vec<Project>> {
-        self.projects
-            .prefix("project:".as_bytes())
+    pub fn remove_address_book_entry(&self, address: &WalletAddress) -> Result<()> {
+        let key = format!("address:{}", address);
+        self.address_book.remove(&key)?;
+        self.keyspace.persist(fjall::PersistMode::SyncAll)?;
+        Ok(())
+    }
+
+    pub fn list_address_book(&self) -> Result<Vec<AddressBookEntry>> {
+        self.address_book
+            .prefix("address:".as_bytes())
             .map(|item| {
                 let (_, value) = item?;
-                let project: Project = serde_json::from_slice(&value)?;
-                Ok(project)
+                let entry: AddressBookEntry = serde_json::from_slice(&value)?;
+                Ok(entry)
             })
             .collect()
     }
 
-    pub fn delete_project(&self, project_id: &str) -> Result<()> {
-        let key = format!("project:{}", project_id);
-        self.projects.remove(&key)?;
+    /// Store synced treasury transactions and the sync that found them in
+    /// one batch.
+    pub fn store_treasury_sync(
+        &self,
+        transactions: &[TreasuryTransaction],
+        state: &TreasurySyncState,
+    ) -> Result<()> {
+        let mut batch = self.keyspace.batch();
+        for tx in transactions {
+            let key = format!("tx:{}:{}", tx.address, tx.tx_id);
+            batch.insert(&self.treasury_txs, key, serde_json::to_vec(tx)?);
+        }
+        let key = format!("sync:{}", state.address);
+        batch.insert(&self.treasury_txs, key, serde_json::to_vec(state)?);
+        batch.commit()?;
         self.keyspace.persist(fjall::PersistMode::SyncAll)?;
         Ok(())
     }
 
+    pub fn has_treasury_transaction(
+        &self,
+        address: &str,
+        tx_id: &str,
+    ) -> Result<bool> {
+        let key = format!("tx:{}:{}", address, tx_id);
+        Ok(self.treasury_txs.contains_key(&key)?)
+    }
+
+    /// Transactions stored for `address`, newest first.
+    pub fn get_treasury_transactions(
+        &self,
+        address: &str,
+    ) -> Result<Vec<TreasuryTransaction>> {
+        let prefix = format!("tx:{}:", address);
+        let mut transactions = self
+            .treasury_txs
+            .prefix(prefix.as_bytes())
+            .map(|item| {
+                let (_, value) = item?;
+                let tx: TreasuryTransaction = serde_json::from_slice(&value)?;
+                Ok(tx)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        transactions.sort_by(|a, b| {
+            (b.inclusion_height, &b.tx_id).cmp(&(a.inclusion_height, &a.tx_id))
+        });
+        Ok(transactions)
+    }
+
+    pub fn get_treasury_sync_state(
+        &self,
+        address: &str,
+    ) -> Result<Option<TreasurySyncState>> {
+        let key = format!("sync:{}", address);
+        match self.treasury_txs.get(&key)? {
+            Some(value) => self.decode(&key, &value),
+            None => Ok(None),
+        }
+    }
+
     // Project owner methods
     pub fn add_project_owner(&self, owner: &ProjectOwner) -> Result<()> {
         let key = format!("owner:{}:{}", owner.project_id, owner.github_username);
@@ -350,6 +1346,40 @@ impl Database {
         Ok(())
     }
 
+    pub fn add_project_treasury(&self, treasury: &ProjectTreasury) -> Result<()> {
+        let key = format!("treasury:{}:{}", treasury.project_id, treasury.address);
+        let value = serde_json::to_vec(treasury)?;
+        self.project_treasuries.insert(&key, &value)?;
+        self.keyspace.persist(fjall::PersistMode::SyncAll)?;
+        Ok(())
+    }
+
+    pub fn get_project_treasuries(
+        &self,
+        project_id: &str,
+    ) -> Result<Vec<ProjectTreasury>> {
+        let prefix = format!("treasury:{}:", project_id);
+        self.project_treasuries
+            .prefix(prefix.as_bytes())
+            .map(|item| {
+                let (_, value) = item?;
+                let treasury: ProjectTreasury = serde_json::from_slice(&value)?;
+                Ok(treasury)
+            })
+            .collect()
+    }
+
+    pub fn remove_project_treasury(
+        &self,
+        project_id: &str,
+        address: &str,
+    ) -> Result<()> {
+        let key = format!("treasury:{}:{}", project_id, address);
+        self.project_treasuries.remove(&key)?;
+        self.keyspace.persist(fjall::PersistMode::SyncAll)?;
+        Ok(())
+    }
+
     pub fn get_projects_for_owner(&self, username: &str) -> Result<Vec<String>> {
         self.project_owners
             .iter()
@@ -368,49 +1398,384 @@ impl Database {
             .collect()
     }
 
-    // Repository methods updated for project context
-    pub fn list_repositories_for_project(
+    // Project link methods. Keys are `link:{project_id}:{owner/repo}`.
+    pub fn upsert_project_link(&self, link: &ProjectLink) -> Result<()> {
+        let key = format!("link:{}:{}", link.project_id, link.repository);
+        let value = serde_json::to_vec(link)?;
+        self.project_links.insert(&key, &value)?;
+        self.keyspace.persist(fjall::PersistMode::SyncAll)?;
+        Ok(())
+    }
+
+    pub fn get_project_link(
         &self,
         project_id: &str,
-    ) -> Result<Vec<Repository>> {
-        self.repositories
-            .prefix("repo:".as_bytes())
+        repository: &str,
+    ) -> Result<Option<ProjectLink>> {
+        let key = format!("link:{}:{}", project_id, repository);
+        match self.project_links.get(&key)? {
+            Some(value) => self.decode(&key, &value),
+            None => Ok(None),
+        }
+    }
+
+    pub fn remove_project_link(
+        &self,
+        project_id: &str,
+        repository: &str,
+    ) -> Result<()> {
+        let key = format!("link:{}:{}", project_id, repository);
+        self.project_links.remove(&key)?;
+        self.keyspace.persist(fjall::PersistMode::SyncAll)?;
+        Ok(())
+    }
+
+    pub fn get_project_links(&self, project_id: &str) -> Result<Vec<ProjectLink>> {
+        let prefix = format!("link:{}:", project_id);
+        self.project_links
+            .prefix(prefix.as_bytes())
             .map(|item| {
                 let (_, value) = item?;
-                let repo: Repository = serde_json::from_slice(&value)?;
-                if repo.project_id.as_deref() != Some(project_id) {
-                    return Ok(None);
-                }
-                Ok(Some(repo))
+                let link: ProjectLink = serde_json::from_slice(&value)?;
+                Ok(link)
+            })
+            .collect()
+    }
+
+    /// Links of every project fed by `repository` ("owner/repo").
+    pub fn get_links_for_repository(
+        &self,
+        repository: &str,
+    ) -> Result<Vec<ProjectLink>> {
+        self.project_links
+            .prefix("link:".as_bytes())
+            .map(|item| {
+                let (_, value) = item?;
+                let link: ProjectLink = serde_json::from_slice(&value)?;
+                Ok(link)
+            })
+            .filter(|result| {
+                result
+                    .as_ref()
+                    .map_or(true, |link| link.repository == repository)
             })
-            .filter_map(Result::transpose)
             .collect()
     }
 
+    /// Move the single `project_id` (and path claims) older versions kept on
+    /// each repository record into the link partition. Returns the number of
+    /// repositories migrated; records that don't decode are left alone.
+    pub fn migrate_project_links(&self) -> Result<usize> {
+        #[derive(serde::Deserialize)]
+        struct LegacyLink {
+            #[serde(default)]
+            project_id: Option<String>,
+            #[serde(default)]
+            paths: Vec<String>,
+        }
+
+        let mut batch = self.keyspace.batch();
+        let mut count = 0;
+        for item in self.repositories.prefix("repo:".as_bytes()) {
+            let (key, value) = item?;
+            let (Ok(legacy), Ok(repo)) = (
+                serde_json::from_slice::<LegacyLink>(&value),
+                serde_json::from_slice::<Repository>(&value),
+            ) else {
+                continue;
+            };
+            let Some(project_id) = legacy.project_id else {
+                continue;
+            };
+            let link = ProjectLink {
+                project_id,
+                repository: format!("{}/{}", repo.owner, repo.name),
+                paths: legacy.paths,
+                linked_at: repo.first_sync,
+            };
+            let link_key = format!("link:{}:{}", link.project_id, link.repository);
+            batch.insert(&self.project_links, link_key, serde_json::to_vec(&link)?);
+            // Rewriting the record drops the legacy fields
+            batch.insert(&self.repositories, key, serde_json::to_vec(&repo)?);
+            count += 1;
+        }
+        if count > 0 {
+            batch.commit()?;
+            self.keyspace.persist(fjall::PersistMode::SyncAll)?;
+        }
+        Ok(count)
+    }
+
+    pub fn list_repositories_for_project(
+        &self,
+        project_id: &str,
+    ) -> Result<Vec<Repository>> {
+        let mut repos = Vec::new();
+        for link in self.get_project_links(project_id)? {
+            if let Some((owner, name)) = link.repository.split_once('/')
+                && let Some(repo) = self.get_repository(owner, name)?
+            {
+                repos.push(repo);
+            }
+        }
+        Ok(repos)
+    }
+
     pub fn get_pull_requests_for_project(
         &self,
         project_id: &str,
     ) -> Result<Vec<MergedPullRequest>> {
-        let repos = self.list_repositories_for_project(project_id)?;
+        let links = self.get_project_links(project_id)?;
+        let project = self.get_project(project_id)?;
 
-        let mut all_prs = repos
+        // PRs stored before a path claim have no file list and are left out
+        let mut all_prs = links
             .into_iter()
-            .flat_map(|repo| {
-                let repo_str = format!("{}/{}", repo.owner, repo.name);
-                self.get_pull_requests(&repo_str).unwrap_or_default()
+            .flat_map(|link| {
+                let mut prs =
+                    self.get_pull_requests(&link.repository).unwrap_or_default();
+                prs.retain(|pr| {
+                    claims_any(&link.paths, &pr.changed_files)
+                        && project
+                            .as_ref()
+                            .is_none_or(|p| p.counts_author(&pr.author))
+                });
+                prs
             })
             .collect::<Vec<_>>();
 
-        all_prs.sort_by(|a, b| b.merged_at.cmp(&a.merged_at));
+        all_prs.sort_by_key(|pr| std::cmp::Reverse(pr.merged_at));
         Ok(all_prs)
     }
+
+    /// Stage the deletion of everything stored about `login`: wallet records,
+    /// contributor records and project roles are removed, while PRs, project
+    /// lists and contributor stats keep `pseudonym` in its place so counters
+    /// don't change. Nothing is written until the batch is committed.
+    pub fn forget_login_batch(
+        &self,
+        batch: &mut fjall::Batch,
+        platform: &str,
+        login: &str,
+        pseudonym: &str,
+    ) -> Result<ForgetReport> {
+        // Logins compare case-insensitively, as on GitHub, so records
+        // stored under another casing are forgotten too
+        let same = |other: &str| other.eq_ignore_ascii_case(login);
+        // Records keyed `{prefix}{login}` or `{prefix}{login}:...`
+        let records_naming = |partition: &fjall::PartitionHandle,
+                              prefix: String| {
+            let mut records = Vec::new();
+            for item in partition.prefix(prefix.as_bytes()) {
+                let (key, value) = item?;
+                let rest = String::from_utf8_lossy(&key[prefix.len()..]);
+                if rest.split(':').next().is_some_and(same) {
+                    records.push((key, value));
+                }
+            }
+            Ok::<_, GitCirclesError>(records)
+        };
+        let mut report = ForgetReport {
+            login: login.to_string(),
+            pseudonym: pseudonym.to_string(),
+            ..Default::default()
+        };
+
+        let prefix = format!("login:{}:", platform);
+        for (key, _) in records_naming(&self.user_wallets, prefix)? {
+            batch.remove(&self.user_wallets, key);
+            report.wallet = true;
+        }
+        let prefix = format!("history:{}:", platform);
+        for (key, _) in records_naming(&self.user_wallet_history, prefix)? {
+            batch.remove(&self.user_wallet_history, key);
+            report.wallet_history += 1;
+        }
+        for link in self.list_wallet_links()? {
+            if link.platform == platform && same(&link.login) {
+                let key =
+                    format!("wallet:{}:{}:{}", link.wallet, platform, link.login);
+                batch.remove(&self.wallet_index, key);
+                report.wallet_links += 1;
+                // A contributor entry names the person behind the address
+                if let Some(entry) = self.get_address_book_entry(&link.wallet)?
+                    && entry.kind == AddressKind::Contributor
+                {
+                    let key = format!("address:{}", link.wallet);
+                    batch.remove(&self.address_book, key);
+                    report.contributor_records += 1;
+                }
+            }
+        }
+        for item in self.wallet_warnings.prefix("warning:".as_bytes()) {
+            let (key, value) = item?;
+            let mut warning: WalletWarning = serde_json::from_slice(&value)?;
+            let WalletWarningKind::SharedWallet { other_logins } =
+                &mut warning.kind;
+            if warning.platform == platform && same(&warning.login) {
+                batch.remove(&self.wallet_warnings, key);
+            } else if other_logins.iter().any(|l| same(l)) {
+                for other in other_logins.iter_mut().filter(|l| same(l)) {
+                    *other = pseudonym.to_string();
+                }
+                batch.insert(
+                    &self.wallet_warnings,
+                    key,
+                    serde_json::to_vec(&warning)?,
+                );
+            } else {
+                continue;
+            }
+            report.wallet_warnings += 1;
+        }
+        for (partition, key) in [
+            (&self.contributor_preferences, "prefs"),
+            (&self.contributor_opt_outs, "optout"),
+            (&self.wallet_rotations, "rotation"),
+            (&self.wallet_probe_cache, "probe"),
+        ] {
+            let prefix = format!("{}:{}:", key, platform);
+            for (key, _) in records_naming(partition, prefix)? {
+                batch.remove(partition, key);
+                report.contributor_records += 1;
+            }
+        }
+
+        for item in self.project_owners.iter() {
+            let (key, value) = item?;
+            let owner: ProjectOwner = serde_json::from_slice(&value)?;
+            if same(&owner.github_username) {
+                batch.remove(&self.project_owners, key);
+                report.project_roles += 1;
+            }
+        }
+        for mut project in self.list_projects()? {
+            let mut named = false;
+            let lists = [
+                &mut project.allowed_contributors,
+                &mut project.blocked_contributors,
+            ];
+            for entry in lists.into_iter().flatten() {
+                if same(entry) {
+                    *entry = pseudonym.to_string();
+                    named = true;
+                }
+            }
+            for exclusion in &mut project.org_exclusions {
+                if same(&exclusion.login) {
+                    exclusion.login = pseudonym.to_string();
+                    named = true;
+                }
+            }
+            for pause in &mut project.pauses {
+                if pause.paused_by.as_deref().is_some_and(same) {
+                    pause.paused_by = Some(pseudonym.to_string());
+                    named = true;
+                }
+            }
+            if named {
+                let key = format!("project:{}", project.id);
+                batch.insert(&self.projects, key, serde_json::to_vec(&project)?);
+                report.projects += 1;
+            }
+        }
+        for item in self.pr_approvals.iter() {
+            let (key, value) = item?;
+            let mut approval: PullRequestApproval = serde_json::from_slice(&value)?;
+            if approval.approved_by.as_deref().is_some_and(same) {
+                approval.approved_by = Some(pseudonym.to_string());
+                batch.insert(
+                    &self.pr_approvals,
+                    key,
+                    serde_json::to_vec(&approval)?,
+                );
+                report.approvals += 1;
+            }
+        }
+
+        for repo in self.list_repositories()? {
+            let full = format!("{}/{}", repo.owner, repo.name);
+            for mut pr in self.get_pull_requests(&full)? {
+                let fork = pr
+                    .head_repository
+                    .as_deref()
+                    .and_then(|head| head.split_once('/'))
+                    .is_some_and(|(owner, _)| same(owner));
+                // Titles and bodies are free text, e.g. "Thanks @alice"
+                let title = replace_login(&pr.title, login, pseudonym);
+                let body = pr
+                    .body
+                    .as_deref()
+                    .map(|body| replace_login(body, login, pseudonym));
+                let mentioned = title != pr.title || body != pr.body;
+                if pr.platform != platform
+                    || !(same(&pr.author)
+                        || pr.resolved_authors.iter().any(|l| same(l))
+                        || fork
+                        || mentioned)
+                {
+                    continue;
+                }
+                (pr.title, pr.body) = (title, body);
+                if same(&pr.author) {
+                    pr.author = pseudonym.to_string();
+                }
+                for author in &mut pr.resolved_authors {
+                    if same(author) {
+                        *author = pseudonym.to_string();
+                    }
+                }
+                if fork {
+                    pr.head_repository = None;
+                }
+                self.upsert_pull_request_batch(batch, &pr)?;
+                report.pull_requests += 1;
+            }
+        }
+
+        let prefix = format!("contributor:{}:", platform);
+        for (key, value) in records_naming(&self.contributors, prefix)? {
+            let name = String::from_utf8_lossy(&key).into_owned();
+            let Some(mut stats) = self.decode::<ContributorStats>(&name, &value)?
+            else {
+                continue;
+            };
+            batch.remove(&self.contributors, key);
+            stats.login = pseudonym.to_string();
+            let key = format!("contributor:{}:{}", platform, pseudonym);
+            batch.insert(&self.contributors, key, serde_json::to_vec(&stats)?);
+            report.contributor_stats = true;
+        }
+        Ok(report)
+    }
+}
+
+// Index keys are `term:{term}:{pr key}`; terms never contain ':'.
+fn search_key(term: &str, pr_key: &str) -> String {
+    format!("term:{}:{}", term, pr_key)
+}
+
+fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+}
+
+fn search_terms(pr: &MergedPullRequest) -> BTreeSet<String> {
+    let mut terms: BTreeSet<String> = tokenize(&pr.title).collect();
+    terms.extend(tokenize(pr.body.as_deref().unwrap_or_default()));
+    terms.extend(tokenize(&pr.author));
+    for login in &pr.resolved_authors {
+        terms.extend(tokenize(login));
+    }
+    terms
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::WalletSource;
-    use chrono::TimeZone;
+    use crate::types::{RefKind, WalletSource};
+    use chrono::{TimeZone, Utc};
     use tempfile::tempdir;
 
     /// FIXME(Kivooeo): This is placeholder function I don't know why this needed
@@ -437,6 +1802,8 @@ mod tests {
             source: WalletSource::GitHubProfileRepo {
                 login: "alice".into(),
                 branch: "main".into(),
+                ref_kind: RefKind::Branch,
+                commit: None,
             },
             synced_at: Utc::now(),
         };
@@ -446,6 +1813,171 @@ mod tests {
         assert_eq!(fetched.address, uw.address);
     }
 
+    #[tokio::test]
+    async fn run_moves_work_off_the_executor() {
+        let dir = tempdir().unwrap();
+        let db = Database::new(dir.path().to_str().unwrap()).unwrap();
+
+        let project = Project::for_test("demo_1", "Demo");
+        let stored = project.clone();
+        // The test runtime has a single thread, so any other is the pool
+        let executor = std::thread::current().id();
+        db.run(move |db| {
+            assert_ne!(std::thread::current().id(), executor);
+            db.upsert_project(&stored)
+        })
+        .await
+        .unwrap();
+
+        let fetched = db.run(|db| db.get_project("demo_1")).await.unwrap();
+        assert_eq!(fetched.unwrap().name, project.name);
+    }
+
+    fn pr(repo: &str, number: u64, title: &str, author: &str) -> MergedPullRequest {
+        MergedPullRequest {
+            title: title.into(),
+            merged_at: Utc.timestamp_opt(1_700_000_000 + number as i64, 0).unwrap(),
+            repository: repo.into(),
+            ..MergedPullRequest::for_test(number, author)
+        }
+    }
+
+    #[test]
+    fn pr_search_matches_prefixes_across_fields() {
+        let dir = tempdir().unwrap();
+        let db = Database::new(dir.path().to_str().unwrap()).unwrap();
+
+        let mut parser = pr("acme/widgets", 1, "Speed up the parser", "alice");
+        parser.body = Some("Avoids quadratic Backtracking.".into());
+        db.upsert_pull_request(&parser).unwrap();
+        db.upsert_pull_request(&pr("acme/widgets", 2, "Parse dates", "bob"))
+            .unwrap();
+        db.upsert_pull_request(&pr("acme/gadgets", 3, "Parser docs", "bob"))
+            .unwrap();
+
+        let numbers = |query: &str, repo: Option<&str>| -> Vec<u64> {
+            db.search_pull_requests(query, repo)
+                .unwrap()
+                .iter()
+                .map(|pr| pr.number)
+                .collect()
+        };
+        assert_eq!(numbers("pars", None), [3, 2, 1]);
+        assert_eq!(numbers("pars", Some("acme/widgets")), [2, 1]);
+        assert_eq!(numbers("parser BOB", None), [3]);
+        assert_eq!(numbers("backtrack", None), [1]);
+        assert!(numbers("", None).is_empty());
+
+        // Re-upserting drops terms that are no longer present
+        parser.body = None;
+        db.upsert_pull_request(&parser).unwrap();
+        assert!(numbers("backtrack", None).is_empty());
+
+        assert_eq!(db.rebuild_pr_search_index().unwrap(), 3);
+        assert_eq!(numbers("parser", None), [3, 1]);
+    }
+
+    #[test]
+    fn old_pr_records_get_platform_and_url() {
+        let dir = tempdir().unwrap();
+        let db = Database::new(dir.path().to_str().unwrap()).unwrap();
+
+        // A record written before platform and html_url existed
+        let mut legacy =
+            serde_json::to_value(pr("acme/widgets", 7, "Old", "bob")).unwrap();
+        let fields = legacy.as_object_mut().unwrap();
+        fields.remove("platform");
+        fields.remove("html_url");
+        db.pull_requests
+            .insert("pr:acme/widgets:7", serde_json::to_vec(&legacy).unwrap())
+            .unwrap();
+        let mut fresh = pr("acme/widgets", 8, "New", "bob");
+        fresh.html_url = Some("https://github.com/acme/widgets/pull/8".into());
+        db.upsert_pull_request(&fresh).unwrap();
+
+        assert_eq!(db.backfill_pull_request_urls().unwrap(), 1);
+        assert_eq!(db.backfill_pull_request_urls().unwrap(), 0);
+        let migrated = db.get_pull_request("acme/widgets", 7).unwrap().unwrap();
+        assert_eq!(migrated.platform, "github");
+        assert_eq!(
+            migrated.html_url.as_deref(),
+            Some("https://github.com/acme/widgets/pull/7")
+        );
+    }
+
+    #[test]
+    fn pr_counters_follow_stored_prs() {
+        let dir = tempdir().unwrap();
+        let db = Database::new(dir.path().to_str().unwrap()).unwrap();
+        let mut repo = Repository {
+            owner: "acme".into(),
+            name: "widgets".into(),
+            current_base_branch: "main".into(),
+            last_sync: None,
+            total_prs: 0,
+            first_sync: Utc::now(),
+            metadata: None,
+        };
+
+        let first = [
+            pr("acme/widgets", 1, "One", "bob"),
+            pr("acme/widgets", 2, "Two", "bob"),
+        ];
+        db.store_collected_pull_requests(&mut repo, &first).unwrap();
+        // An overlapping run stores #2 again
+        let second = [
+            pr("acme/widgets", 2, "Two", "bob"),
+            pr("acme/widgets", 3, "Three", "bob"),
+        ];
+        db.store_collected_pull_requests(&mut repo, &second)
+            .unwrap();
+        assert_eq!(repo.total_prs, 3);
+        assert!(db.recount_pull_requests().unwrap().is_empty());
+
+        repo.total_prs = 7;
+        db.upsert_repository(&repo).unwrap();
+        let repaired = db.recount_pull_requests().unwrap();
+        assert_eq!(repaired.len(), 1);
+        assert_eq!((repaired[0].0.total_prs, repaired[0].1), (3, 7));
+        let stored = db.get_repository("acme", "widgets").unwrap().unwrap();
+        assert_eq!(stored.total_prs, 3);
+    }
+
+    #[test]
+    fn repository_project_ids_move_to_links() {
+        let dir = tempdir().unwrap();
+        let db = Database::new(dir.path().to_str().unwrap()).unwrap();
+
+        // A record written while repositories had a single project
+        let legacy = serde_json::json!({
+            "owner": "acme",
+            "name": "widgets",
+            "current_base_branch": "main",
+            "last_sync": null,
+            "total_prs": 0,
+            "first_sync": "2025-01-01T00:00:00Z",
+            "project_id": "widgets-1a2b",
+            "paths": ["packages/foo/**"],
+        });
+        db.repositories
+            .insert("repo:acme/widgets", serde_json::to_vec(&legacy).unwrap())
+            .unwrap();
+
+        assert_eq!(db.migrate_project_links().unwrap(), 1);
+        assert_eq!(db.migrate_project_links().unwrap(), 0);
+        let link = db
+            .get_project_link("widgets-1a2b", "acme/widgets")
+            .unwrap()
+            .unwrap();
+        assert_eq!(link.paths, ["packages/foo/**"]);
+        assert_eq!(
+            db.list_repositories_for_project("widgets-1a2b")
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
     #[test]
     fn wallet_history_ordering() {
         let dir = tempdir().unwrap();
@@ -463,8 +1995,10 @@ mod tests {
             source: WalletSource::GitHubProfileRepo {
                 login: login.into(),
                 branch: "main".into(),
+                ref_kind: RefKind::Branch,
+                commit: None,
             },
-            recorded_at: Utc.timestamp(1000, 0),
+            recorded_at: Utc.timestamp_opt(1000, 0).unwrap(),
         };
         let e2 = WalletHistoryEntry {
             login: login.into(),
@@ -473,8 +2007,10 @@ mod tests {
             source: WalletSource::GitHubProfileRepo {
                 login: login.into(),
                 branch: "main".into(),
+                ref_kind: RefKind::Branch,
+                commit: None,
             },
-            recorded_at: Utc.timestamp(1001, 0),
+            recorded_at: Utc.timestamp_opt(1001, 0).unwrap(),
         };
 
         db.append_wallet_history(&e1).unwrap();
@@ -521,8 +2057,8 @@ mod tests {
         let platform = "github";
         let address = addr();
 
-        let early = Utc.timestamp(2000, 0);
-        let late = Utc.timestamp(3000, 0);
+        let early = Utc.timestamp_opt(2000, 0).unwrap();
+        let late = Utc.timestamp_opt(3000, 0).unwrap();
 
         let mut link = WalletLoginLink {
             wallet: address.clone(),
@@ -556,6 +2092,8 @@ mod tests {
             source: WalletSource::GitHubProfileRepo {
                 login: login.into(),
                 branch: "main".into(),
+                ref_kind: RefKind::Branch,
+                commit: None,
             },
             synced_at: now,
         };
@@ -586,4 +2124,26 @@ mod tests {
             1
         );
     }
+
+    #[test]
+    fn corrupt_records_fail_unless_lenient() {
+        let dir = tempdir().unwrap();
+        let db = Database::new(dir.path().to_str().unwrap()).unwrap();
+        db.user_wallets
+            .insert("login:github:alice", b"{\"login\":")
+            .unwrap();
+        db.projects.insert("project:demo", b"[]").unwrap();
+
+        let err = db.get_user_wallet("github", "alice").unwrap_err();
+        assert!(matches!(
+            err,
+            GitCirclesError::CorruptRecord { ref key, .. } if key == "login:github:alice"
+        ));
+        assert!(db.get_project("demo").is_err());
+        assert!(db.get_repository("acme", "widgets").unwrap().is_none());
+
+        let db = db.with_lenient_reads(true);
+        assert!(db.get_user_wallet("github", "alice").unwrap().is_none());
+        assert!(db.get_project("demo").unwrap().is_none());
+    }
 }
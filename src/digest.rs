@@ -0,0 +1,109 @@
+//! Periodic per-project summary for `report digest`.
+
+use chrono::{DateTime, Utc};
+use std::collections::BTreeSet;
+
+use crate::types::{MergedPullRequest, Project};
+
+#[derive(Debug, Clone)]
+pub struct ProjectDigest {
+    pub project_id: String,
+    pub project_name: String,
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    /// PRs merged during the period, newest first
+    pub new_prs: Vec<MergedPullRequest>,
+    /// Contributors whose first PR in the project landed during the period
+    pub new_contributors: Vec<String>,
+    /// Contributors active during the period without a synced wallet
+    pub missing_wallets: Vec<String>,
+}
+
+/// Summarise `prs` (every stored PR of the project) for the period
+/// `[start, end)`. `has_wallet` reports whether a login has a synced wallet.
+pub fn build_digest(
+    project: &Project,
+    prs: &[MergedPullRequest],
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    has_wallet: impl Fn(&str) -> bool,
+) -> ProjectDigest {
+    let in_period =
+        |pr: &&MergedPullRequest| pr.merged_at >= start && pr.merged_at < end;
+
+    let mut new_prs: Vec<MergedPullRequest> =
+        prs.iter().filter(in_period).cloned().collect();
+    new_prs.sort_by_key(|pr| std::cmp::Reverse(pr.merged_at));
+
+    let earlier: BTreeSet<&str> = prs
+        .iter()
+        .filter(|pr| pr.merged_at < start)
+        .flat_map(contributors)
+        .collect();
+    let active: BTreeSet<&str> = new_prs.iter().flat_map(contributors).collect();
+
+    ProjectDigest {
+        project_id: project.id.clone(),
+        project_name: project.name.clone(),
+        period_start: start,
+        period_end: end,
+        new_contributors: active
+            .iter()
+            .filter(|login| !earlier.contains(*login))
+            .map(|login| login.to_string())
+            .collect(),
+        missing_wallets: active
+            .iter()
+            .filter(|login| !has_wallet(login))
+            .map(|login| login.to_string())
+            .collect(),
+        new_prs,
+    }
+}
+
+// The PR opener plus every resolved commit author
+pub(crate) fn contributors(pr: &MergedPullRequest) -> impl Iterator<Item = &str> {
+    std::iter::once(pr.author.as_str())
+        .chain(pr.resolved_authors.iter().map(String::as_str))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    fn at(days: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(1_700_000_000, 0).unwrap() + Duration::days(days)
+    }
+
+    fn pr(number: u64, author: &str, days: i64) -> MergedPullRequest {
+        MergedPullRequest {
+            merged_at: at(days),
+            ..MergedPullRequest::for_test(number, author)
+        }
+    }
+
+    #[test]
+    fn digest_covers_only_the_period() {
+        let project = Project {
+            created_at: at(0),
+            updated_at: at(0),
+            ..Project::for_test("widgets-1a2b", "Widgets")
+        };
+        let mut co_authored = pr(3, "alice", 9);
+        co_authored.resolved_authors = vec!["alice".into(), "carol".into()];
+        let prs = [
+            pr(1, "alice", 1),
+            pr(2, "bob", 8),
+            co_authored,
+            pr(4, "dan", 20),
+        ];
+
+        let digest =
+            build_digest(&project, &prs, at(7), at(14), |login| login == "alice");
+        let numbers: Vec<u64> = digest.new_prs.iter().map(|pr| pr.number).collect();
+        assert_eq!(numbers, [3, 2]);
+        assert_eq!(digest.new_contributors, ["bob", "carol"]);
+        assert_eq!(digest.missing_wallets, ["bob", "carol"]);
+    }
+}
@@ -0,0 +1,281 @@
+//! `doctor`: end-to-end checks of the local setup, each reported with a hint
+//! for fixing it. A failing check never stops the ones after it.
+
+use std::path::PathBuf;
+
+use crate::config::Config;
+use crate::database::Database;
+use crate::ergo::ErgoNodeClient;
+use crate::gitcircles::resolve_github_token;
+use crate::github::{GitHubClient, RAW_CONTENT_BASE};
+
+/// Scopes of a classic token, any one of which covers the issues, comments
+/// and profile repositories written by this tool.
+pub const WRITE_SCOPES: &[&str] = &["public_repo", "repo"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Pass,
+    /// Works, but something may need attention
+    Warn,
+    Fail,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DoctorCheck {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+    /// How to fix a warning or failure
+    pub hint: Option<String>,
+}
+
+impl DoctorCheck {
+    fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Pass,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    fn problem(
+        name: &'static str,
+        status: CheckStatus,
+        detail: impl Into<String>,
+        hint: impl Into<String>,
+    ) -> Self {
+        Self {
+            name,
+            status,
+            detail: detail.into(),
+            hint: Some(hint.into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DoctorOptions {
+    pub config_path: PathBuf,
+    pub database_path: String,
+    /// Falls back to `GITHUB_TOKEN`
+    pub token: Option<String>,
+    /// API root of a GitHub Enterprise instance (or a replay server);
+    /// api.github.com when unset
+    pub github_api_base: Option<String>,
+    pub node_url: String,
+    pub node_api_key: Option<String>,
+}
+
+/// Run every check in order: config, database, GitHub token and its scopes,
+/// Ergo node.
+pub async fn run_doctor(opts: &DoctorOptions) -> Vec<DoctorCheck> {
+    let mut checks = Vec::new();
+
+    let config = match Config::load(&opts.config_path) {
+        Ok(config) => {
+            let detail = if opts.config_path.exists() {
+                format!("{} parsed", opts.config_path.display())
+            } else {
+                format!("{} not found, using defaults", opts.config_path.display())
+            };
+            checks.push(DoctorCheck::pass("Config", detail));
+            config
+        }
+        Err(e) => {
+            checks.push(DoctorCheck::problem(
+                "Config",
+                CheckStatus::Fail,
+                e.to_string(),
+                "Fix the reported key or remove the file to use the defaults",
+            ));
+            Config::default()
+        }
+    };
+
+    // Opening the database also runs its migrations
+    checks.push(match Database::new(&opts.database_path) {
+        Ok(_) => DoctorCheck::pass(
+            "Database",
+            format!("{} opened, migrations applied", opts.database_path),
+        ),
+        Err(e) => DoctorCheck::problem(
+            "Database",
+            CheckStatus::Fail,
+            e.to_string(),
+            "Check the directory permissions and that no other \
+             gitcircles-github process has it open",
+        ),
+    });
+
+    checks.extend(check_github(opts, &config).await);
+
+    let node = ErgoNodeClient::new(&opts.node_url, opts.node_api_key.clone());
+    checks.push(match node.health(false).await {
+        Ok(health) if health.is_healthy() => {
+            DoctorCheck::pass("Ergo node", format!("{} is synced", opts.node_url))
+        }
+        Ok(health) => DoctorCheck::problem(
+            "Ergo node",
+            CheckStatus::Warn,
+            health.problems.join("; "),
+            "Wait for the node to sync and find peers before paying out",
+        ),
+        Err(e) => DoctorCheck::problem(
+            "Ergo node",
+            CheckStatus::Fail,
+            e.to_string(),
+            "Start the node or point --node-url (or ERGO_NODE_URL) at one",
+        ),
+    });
+
+    checks
+}
+
+async fn check_github(opts: &DoctorOptions, config: &Config) -> Vec<DoctorCheck> {
+    let token = match resolve_github_token(opts.token.as_deref()) {
+        Ok(token) => token,
+        Err(e) => {
+            return vec![DoctorCheck::problem(
+                "GitHub token",
+                CheckStatus::Fail,
+                e.to_string(),
+                "Pass --token or set GITHUB_TOKEN",
+            )];
+        }
+    };
+    let client = match &opts.github_api_base {
+        Some(base) => GitHubClient::with_base_urls(&token, base, RAW_CONTENT_BASE),
+        None => GitHubClient::with_http_config(&token, &config.http),
+    };
+    let client = match client {
+        Ok(client) => client,
+        Err(e) => {
+            return vec![DoctorCheck::problem(
+                "GitHub token",
+                CheckStatus::Fail,
+                e.to_string(),
+                "Check the [http] settings in config.toml",
+            )];
+        }
+    };
+    let login = match client.test_token().await {
+        Ok(login) => login,
+        Err(e) => {
+            return vec![DoctorCheck::problem(
+                "GitHub token",
+                CheckStatus::Fail,
+                e.to_string(),
+                "Create a new token at https://github.com/settings/tokens",
+            )];
+        }
+    };
+
+    let scopes = match client.token_scopes().await {
+        Ok(scopes) => check_scopes(scopes.as_deref()),
+        Err(e) => DoctorCheck::problem(
+            "Token scopes",
+            CheckStatus::Warn,
+            e.to_string(),
+            "Scopes could not be read; try again later",
+        ),
+    };
+    vec![
+        DoctorCheck::pass("GitHub token", format!("authenticated as {}", login)),
+        scopes,
+    ]
+}
+
+/// `scopes` is `None` for fine-grained tokens, which don't report them.
+pub fn check_scopes(scopes: Option<&[String]>) -> DoctorCheck {
+    match scopes {
+        None => DoctorCheck::problem(
+            "Token scopes",
+            CheckStatus::Warn,
+            "fine-grained token, scopes not reported",
+            "Make sure it can write issues and create repositories where needed",
+        ),
+        Some(scopes)
+            if scopes.iter().any(|s| WRITE_SCOPES.contains(&s.as_str())) =>
+        {
+            DoctorCheck::pass("Token scopes", scopes.join(", "))
+        }
+        Some(scopes) => DoctorCheck::problem(
+            "Token scopes",
+            CheckStatus::Fail,
+            if scopes.is_empty() {
+                "no scopes granted".to_string()
+            } else {
+                format!("only {}", scopes.join(", "))
+            },
+            "Add the public_repo scope (repo for private repositories) so \
+             comments, issues and init-profile work",
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::HttpHarness;
+    use std::path::Path;
+
+    #[test]
+    fn classic_tokens_need_a_write_scope() {
+        let scopes = |list: &[&str]| -> Vec<String> {
+            list.iter().map(|s| s.to_string()).collect()
+        };
+        let status =
+            |list: Option<Vec<String>>| check_scopes(list.as_deref()).status;
+
+        assert_eq!(status(Some(scopes(&["public_repo"]))), CheckStatus::Pass);
+        assert_eq!(
+            status(Some(scopes(&["read:user", "repo"]))),
+            CheckStatus::Pass
+        );
+        assert_eq!(status(Some(scopes(&["read:user"]))), CheckStatus::Fail);
+        assert_eq!(status(Some(Vec::new())), CheckStatus::Fail);
+        assert_eq!(status(None), CheckStatus::Warn);
+    }
+
+    #[tokio::test]
+    async fn failed_checks_do_not_stop_the_rest() {
+        let harness = HttpHarness::from_env(
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("tests/fixtures/doctor.json"),
+        )
+        .await
+        .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.toml");
+        std::fs::write(&config_path, "[http]\ntimeout = 5\n").unwrap();
+        let opts = DoctorOptions {
+            config_path,
+            database_path: dir.path().join("db").to_string_lossy().into_owned(),
+            token: Some("test-token".into()),
+            github_api_base: Some(harness.api_base()),
+            node_url: "http://127.0.0.1:1".into(),
+            node_api_key: None,
+        };
+
+        let checks = run_doctor(&opts).await;
+        let names: Vec<_> = checks.iter().map(|c| (c.name, c.status)).collect();
+        assert_eq!(
+            names,
+            [
+                ("Config", CheckStatus::Fail),
+                ("Database", CheckStatus::Pass),
+                ("GitHub token", CheckStatus::Pass),
+                ("Token scopes", CheckStatus::Fail),
+                ("Ergo node", CheckStatus::Fail),
+            ]
+        );
+        assert_eq!(checks[3].detail, "only read:user, user:email");
+        assert!(
+            checks
+                .iter()
+                .all(|c| c.status == CheckStatus::Pass || c.hint.is_some())
+        );
+    }
+}
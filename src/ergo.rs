@@ -0,0 +1,228 @@
+use serde::Deserialize;
+
+use crate::types::{GitCirclesError, Result};
+
+pub const DEFAULT_NODE_URL: &str = "http://127.0.0.1:9053";
+
+/// Blocks the node may trail its best-known peer and still count as synced.
+const SYNC_TOLERANCE_BLOCKS: u32 = 2;
+
+/// Subset of the node's `GET /info` response.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeInfo {
+    pub full_height: Option<u32>,
+    pub headers_height: Option<u32>,
+    #[serde(default)]
+    pub max_peer_height: Option<u32>,
+    #[serde(default)]
+    pub peers_count: u32,
+    #[serde(default)]
+    pub app_version: Option<String>,
+    #[serde(default)]
+    pub network: Option<String>,
+}
+
+/// Subset of the node's `GET /wallet/status` response.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeWalletStatus {
+    pub is_initialized: bool,
+    pub is_unlocked: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct NodeHealth {
+    pub info: NodeInfo,
+    /// `None` when the wallet was not checked
+    pub wallet: Option<NodeWalletStatus>,
+    pub synced: bool,
+    /// Reasons the node is not fit for executing payouts
+    pub problems: Vec<String>,
+}
+
+impl NodeHealth {
+    pub fn evaluate(
+        info: NodeInfo,
+        wallet: Option<NodeWalletStatus>,
+        require_wallet: bool,
+    ) -> Self {
+        let mut problems = Vec::new();
+
+        let synced = match (info.full_height, info.headers_height) {
+            (Some(full), Some(headers)) => {
+                let target = info.max_peer_height.unwrap_or(headers).max(headers);
+                full + SYNC_TOLERANCE_BLOCKS >= target
+            }
+            _ => false,
+        };
+        if !synced {
+            problems.push(format!(
+                "node is not synced (full height {}, headers {}, best peer {})",
+                fmt_height(info.full_height),
+                fmt_height(info.headers_height),
+                fmt_height(info.max_peer_height)
+            ));
+        }
+        if info.peers_count == 0 {
+            problems.push("node has no peers".to_string());
+        }
+        if require_wallet {
+            match &wallet {
+                Some(w) if !w.is_initialized => {
+                    problems.push("node wallet is not initialized".to_string())
+                }
+                Some(w) if !w.is_unlocked => {
+                    problems.push("node wallet is locked".to_string())
+                }
+                Some(_) => {}
+                None => problems.push("node wallet status unavailable".to_string()),
+            }
+        }
+
+        Self {
+            info,
+            wallet,
+            synced,
+            problems,
+        }
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+fn fmt_height(height: Option<u32>) -> String {
+    height.map_or_else(|| "-".to_string(), |h| h.to_string())
+}
+
+/// Minimal client for the Ergo node REST API.
+pub struct ErgoNodeClient {
+    http: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+}
+
+impl ErgoNodeClient {
+    pub fn new(base_url: &str, api_key: Option<String>) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+        }
+    }
+
+    pub async fn info(&self) -> Result<NodeInfo> {
+        self.get("/info", false).await
+    }
+
+    /// Requires the node API key.
+    pub async fn wallet_status(&self) -> Result<NodeWalletStatus> {
+        self.get("/wallet/status", true).await
+    }
+
+    /// Check reachability and sync state, plus the wallet when
+    /// `require_wallet` is set. Unreachable nodes are an error.
+    pub async fn health(&self, require_wallet: bool) -> Result<NodeHealth> {
+        let info = self.info().await?;
+        let wallet = if require_wallet {
+            self.wallet_status().await.ok()
+        } else {
+            None
+        };
+        Ok(NodeHealth::evaluate(info, wallet, require_wallet))
+    }
+
+    async fn get<T: serde::de::DeserializeOwned>(
+        &self,
+        path: &str,
+        authenticated: bool,
+    ) -> Result<T> {
+        let url = format!("{}{}", self.base_url, path);
+        let mut request = self.http.get(&url);
+        if authenticated {
+            let key = self.api_key.as_deref().ok_or_else(|| {
+                GitCirclesError::Node(format!("{} requires an API key", path))
+            })?;
+            request = request.header("api_key", key);
+        }
+
+        let response = request.send().await.map_err(|e| {
+            GitCirclesError::Node(format!(
+                "{} is unreachable: {}",
+                self.base_url, e
+            ))
+        })?;
+        let status = response.status();
+        let body = response.text().await.map_err(|e| {
+            GitCirclesError::Node(format!("Failed to read {}: {}", url, e))
+        })?;
+        if !status.is_success() {
+            return Err(GitCirclesError::Node(format!(
+                "{} returned HTTP {}",
+                url,
+                status.as_u16()
+            )));
+        }
+        Ok(serde_json::from_str(&body)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(full: Option<u32>, headers: u32, peer: Option<u32>) -> NodeInfo {
+        NodeInfo {
+            full_height: full,
+            headers_height: Some(headers),
+            max_peer_height: peer,
+            peers_count: 8,
+            app_version: None,
+            network: None,
+        }
+    }
+
+    #[test]
+    fn sync_allows_a_small_lag_behind_peers() {
+        assert!(
+            NodeHealth::evaluate(info(Some(1000), 1000, Some(1002)), None, false)
+                .is_healthy()
+        );
+
+        let behind = NodeHealth::evaluate(info(Some(900), 1000, None), None, false);
+        assert!(!behind.synced);
+
+        let no_blocks = NodeHealth::evaluate(info(None, 1000, None), None, false);
+        assert!(!no_blocks.is_healthy());
+    }
+
+    #[test]
+    fn wallet_is_only_checked_when_required() {
+        let locked = NodeWalletStatus {
+            is_initialized: true,
+            is_unlocked: false,
+        };
+        let health = NodeHealth::evaluate(
+            info(Some(10), 10, None),
+            Some(locked.clone()),
+            false,
+        );
+        assert!(health.is_healthy());
+
+        let health =
+            NodeHealth::evaluate(info(Some(10), 10, None), Some(locked), true);
+        assert_eq!(health.problems, ["node wallet is locked"]);
+    }
+
+    #[test]
+    fn info_parses_node_response() {
+        let json = r#"{"name":"ergo-node","appVersion":"5.0.22","fullHeight":1234,
+            "headersHeight":1235,"maxPeerHeight":1235,"peersCount":30,
+            "network":"mainnet","isMining":false}"#;
+        let info: NodeInfo = serde_json::from_str(json).unwrap();
+        assert_eq!(info.full_height, Some(1234));
+        assert_eq!(info.peers_count, 30);
+    }
+}
@@ -0,0 +1,4462 @@
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::Arc;
+
+use crate::announcement::{announcement_body, announcement_title};
+use crate::anomalies::{
+    Anomaly, AnomalyThresholds, detect_new_accounts, detect_pr_bursts,
+    detect_wallet_funnels,
+};
+use crate::clock::{Clock, SystemClock};
+use crate::config::{
+    Config, HooksConfig, HttpConfig, LabelsConfig, PriceConfig, StatusesConfig,
+    TreasuryConfig,
+};
+use crate::database::Database;
+use crate::digest::{ProjectDigest, build_digest, contributors};
+use crate::github::{
+    GitHubClient, MergedRange, PROFILE_REPO_NAME, PageLimits, PullRequestSource,
+    RepoInteraction, WALLET_FILE_PATH, is_approval_comment, is_opt_out_comment,
+    resolve_commit_authors,
+};
+use crate::hooks::{HookEvent, run_hook};
+use crate::import::{
+    current_branch, merge_to_pull_request, origin_repository, read_archive,
+    read_merge_commits,
+};
+use crate::output::{Progress, local_time};
+use crate::price::{PriceLookup, PriceQuote, PriceSource};
+use crate::privacy::forgotten_pseudonym;
+use crate::treasury::{
+    TransactionSource, TreasuryHistory, TreasurySync, TreasurySyncState,
+    TreasuryTransaction,
+};
+use crate::types::{
+    AddressBookEntry, AddressKind, AppliedLabel, ApprovalSource, BaseBranchChange,
+    CommitState, CommitStatus, ContributorList, ContributorOptOut,
+    ContributorPreferences, ContributorStats, ForgetReport, GitCirclesError,
+    MergedPullRequest, OptOutSource, OrgExclusion, PipelineLabel,
+    ProfileInitOutcome, ProfileRef, Project, ProjectAnnouncement, ProjectLink,
+    ProjectOwner, ProjectPause, ProjectRole, ProjectTreasury, PublishedStatus,
+    PullRequestApproval, PullRequestChange, Repository, Result, SyncLogEntry,
+    UserWallet, WalletAddress, WalletHistoryEntry, WalletLoginLink, WalletRotation,
+    WalletSource, WalletSyncResult, generate_project_id, get_database_path,
+    parse_repo,
+};
+use crate::wallet::{DEFAULT_CONFIRMATION_WINDOW_HOURS, WalletService};
+
+/// Library entry point bundling the database with the GitHub client and the
+/// services built on top of them. The CLI is a thin layer over this type, so
+/// other GitCircles components can embed the same logic.
+pub struct GitCircles {
+    db: Database,
+    github: Option<GitHubClient>,
+    clock: Arc<dyn Clock>,
+    wallet_confirmation_window: Duration,
+    actor: Option<String>,
+    http_config: HttpConfig,
+    hooks: HooksConfig,
+    labels: LabelsConfig,
+    statuses: StatusesConfig,
+    price: PriceConfig,
+    treasury: TreasuryConfig,
+    dry_run: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct CollectOptions {
+    pub owner: String,
+    pub repo: String,
+    pub base_branch: String,
+    pub days: Option<u64>,
+    pub project_id: Option<String>,
+    /// Fetch each new PR's commits to record all code authors (one extra
+    /// API call per new PR).
+    pub resolve_authors: bool,
+    /// Truncate stored PR descriptions to this many characters. Linked
+    /// issues are parsed from the full text first.
+    pub max_body_chars: Option<usize>,
+    /// Update stored PRs in the listing whose title, labels or author
+    /// association changed since they were collected.
+    pub refresh: bool,
+    /// Also count closed PRs whose head commit is on the base branch even
+    /// though GitHub shows no merge (one compare request per such PR).
+    pub detect_external_merges: bool,
+    /// Globs the linked project claims in this repository, replacing the
+    /// stored ones; empty keeps them. New PRs' changed files are fetched
+    /// while any are set (one extra API call per new PR).
+    pub paths: Vec<String>,
+    /// Collect PRs merged in this range via the Search API instead of
+    /// listing closed PRs; `days` and `detect_external_merges` are ignored.
+    pub merged_range: Option<MergedRange>,
+    /// Page size and page cap for the PR listing
+    pub limits: PageLimits,
+    /// Store at most this many new PRs; the rest are picked up by the next
+    /// run.
+    pub max_prs: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CollectOutcome {
+    pub repository: Repository,
+    pub new_prs: Vec<MergedPullRequest>,
+    /// Changes applied to stored PRs when refreshing
+    pub refreshed: Vec<PullRequestChange>,
+    pub base_branch_change: Option<BaseBranchChange>,
+    /// New PRs left for a later run because of `max_prs`
+    pub deferred: usize,
+    /// Hooks that failed after the PRs were stored
+    pub hook_failures: Vec<String>,
+    /// New authors found in a linked project's excluded organizations, by
+    /// project ID
+    pub org_exclusions: Vec<(String, OrgExclusion)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ImportOutcome {
+    pub repository: Repository,
+    pub imported: Vec<MergedPullRequest>,
+    /// Merges already stored, e.g. PRs collected from GitHub
+    pub skipped: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct AnnouncementOutcome {
+    pub announcement: ProjectAnnouncement,
+    /// False when an existing announcement issue was updated
+    pub created: bool,
+    pub pinned: bool,
+}
+
+/// Where a project's contributors stand for the next payout.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PayoutReadiness {
+    /// Synced wallet with no pending address change
+    pub ready: usize,
+    /// Wallet changed recently and is still in its confirmation window
+    pub pending_rotation: usize,
+    pub missing_wallet: usize,
+    pub opted_out: usize,
+}
+
+/// Where one contributor stands for the next payout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayoutStanding {
+    Ready,
+    /// Wallet changed recently and is still in its confirmation window
+    PendingRotation,
+    MissingWallet,
+    OptedOut,
+}
+
+/// Everything known about one contributor, for `contributor show`.
+#[derive(Debug, Clone)]
+pub struct ContributorProfile {
+    pub login: String,
+    /// `None` when none of their PRs are stored
+    pub stats: Option<ContributorStats>,
+    pub wallet: Option<UserWallet>,
+    /// The latest address change, if any
+    pub rotation: Option<WalletRotation>,
+    pub preferences: Option<ContributorPreferences>,
+    pub standing: PayoutStanding,
+}
+
+/// One project's slice of the tracked data, for `status --project-id`.
+#[derive(Debug, Clone)]
+pub struct ProjectStatus {
+    pub project: Project,
+    pub repositories: Vec<Repository>,
+    pub links: Vec<ProjectLink>,
+    /// PRs counted for the project per "owner/repo", after path claims
+    pub prs_by_repository: BTreeMap<String, usize>,
+    pub total_prs: usize,
+    pub contributors: usize,
+    pub last_merged_at: Option<DateTime<Utc>>,
+    pub readiness: PayoutReadiness,
+    /// PRs still waiting for an approval, when the project requires one
+    pub awaiting_approval: usize,
+}
+
+// An opt-out outranks a missing wallet, which outranks a pending rotation
+fn payout_standing(
+    db: &Database,
+    login: &str,
+    now: DateTime<Utc>,
+) -> Result<PayoutStanding> {
+    Ok(if db.get_contributor_opt_out("github", login)?.is_some() {
+        PayoutStanding::OptedOut
+    } else if db.get_user_wallet("github", login)?.is_none() {
+        PayoutStanding::MissingWallet
+    } else if db
+        .get_wallet_rotation("github", login)?
+        .is_some_and(|r| r.is_pending(now))
+    {
+        PayoutStanding::PendingRotation
+    } else {
+        PayoutStanding::Ready
+    })
+}
+
+// The ("owner/repo", number) of every PR approved for the project
+fn approved(db: &Database, project_id: &str) -> Result<BTreeSet<(String, u64)>> {
+    Ok(db
+        .get_approvals_for_project(project_id)?
+        .into_iter()
+        .map(|a| (a.repository, a.number))
+        .collect())
+}
+
+// The PRs among `prs` that have no approval for the project
+fn unapproved(
+    db: &Database,
+    project_id: &str,
+    mut prs: Vec<MergedPullRequest>,
+) -> Result<Vec<MergedPullRequest>> {
+    let approved = approved(db, project_id)?;
+    prs.retain(|pr| !approved.contains(&(pr.repository.clone(), pr.number)));
+    Ok(prs)
+}
+
+// The pipeline state of every PR in a linked repository (or only in
+// `repository`): scheduled once a project that is not paused counts it,
+// after approval where required, and stopped when the author opted out or
+// no linked project counts their PRs. PRs in neither state are left out.
+fn pipeline_states(
+    db: &Database,
+    repository: Option<&str>,
+) -> Result<Vec<(MergedPullRequest, PipelineLabel)>> {
+    let mut scheduled = BTreeSet::new();
+    // Each PR with whether any linked project counts its author
+    let mut linked: BTreeMap<(String, u64), (MergedPullRequest, bool)> =
+        BTreeMap::new();
+    for project in db.list_projects()? {
+        let mut prs = if project.current_pause().is_some() {
+            Vec::new()
+        } else {
+            db.get_pull_requests_for_project(&project.id)?
+        };
+        if project.require_approval {
+            let approved = approved(db, &project.id)?;
+            prs.retain(|pr| approved.contains(&(pr.repository.clone(), pr.number)));
+        }
+        scheduled.extend(prs.into_iter().map(|pr| (pr.repository, pr.number)));
+
+        for link in db.get_project_links(&project.id)? {
+            if repository.is_some_and(|r| r != link.repository) {
+                continue;
+            }
+            for pr in db.get_pull_requests(&link.repository)? {
+                let counts = project.counts_author(&pr.author);
+                linked
+                    .entry((pr.repository.clone(), pr.number))
+                    .or_insert((pr, false))
+                    .1 |= counts;
+            }
+        }
+    }
+
+    let mut states = Vec::new();
+    for (key, (pr, counted)) in linked {
+        let state = if !counted
+            || db.get_contributor_opt_out("github", &pr.author)?.is_some()
+        {
+            PipelineLabel::Stopped
+        } else if scheduled.contains(&key) {
+            PipelineLabel::Scheduled
+        } else {
+            continue;
+        };
+        states.push((pr, state));
+    }
+    Ok(states)
+}
+
+/// Resolve a GitHub token from an explicit value or the `GITHUB_TOKEN`
+/// environment variable.
+pub fn resolve_github_token(token: Option<&str>) -> Result<String> {
+    token
+        .map(str::to_string)
+        .or_else(|| std::env::var("GITHUB_TOKEN").ok())
+        .ok_or_else(|| {
+            GitCirclesError::Auth(
+                "GitHub token required. Use --token or set GITHUB_TOKEN environment variable"
+                    .to_string(),
+            )
+        })
+}
+
+fn truncate_chars(text: &mut String, max: usize) {
+    if let Some((idx, _)) = text.char_indices().nth(max) {
+        text.truncate(idx);
+    }
+}
+
+impl GitCircles {
+    pub fn new(db: Database) -> Self {
+        Self {
+            db,
+            github: None,
+            clock: Arc::new(SystemClock),
+            wallet_confirmation_window: Duration::hours(
+                DEFAULT_CONFIRMATION_WINDOW_HOURS,
+            ),
+            actor: None,
+            http_config: HttpConfig::default(),
+            hooks: HooksConfig::default(),
+            labels: LabelsConfig::default(),
+            statuses: StatusesConfig::default(),
+            price: PriceConfig::default(),
+            treasury: TreasuryConfig::default(),
+            dry_run: false,
+        }
+    }
+
+    /// Open the database at the standard location (`~/.gitcircles/db`) with
+    /// the settings from `~/.gitcircles/config.toml`.
+    pub fn open_default() -> Result<Self> {
+        let config = Config::load_default()?;
+        Ok(Self::new(Database::new(&get_database_path()?)?)
+            .with_http_config(config.http)
+            .with_hooks(config.hooks)
+            .with_labels(config.labels)
+            .with_statuses(config.statuses)
+            .with_price(config.price)
+            .with_treasury(config.treasury))
+    }
+
+    pub fn with_github(mut self, client: GitHubClient) -> Self {
+        self.github = Some(client);
+        self
+    }
+
+    /// Build the GitHub client with the current HTTP settings, so call
+    /// `with_http_config` first.
+    pub fn with_github_token(self, token: &str) -> Result<Self> {
+        let client = GitHubClient::with_http_config(token, &self.http_config)?;
+        Ok(self.with_github(client))
+    }
+
+    /// Timeouts and pooling for GitHub clients created from a token.
+    pub fn with_http_config(mut self, config: HttpConfig) -> Self {
+        self.http_config = config;
+        self
+    }
+
+    /// Commands to run on lifecycle events such as a newly stored PR.
+    pub fn with_hooks(mut self, hooks: HooksConfig) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    /// Pipeline labels to put on PRs; off by default.
+    pub fn with_labels(mut self, labels: LabelsConfig) -> Self {
+        self.labels = labels;
+        self
+    }
+
+    /// Commit statuses to publish on merge commits; off by default.
+    pub fn with_statuses(mut self, statuses: StatusesConfig) -> Self {
+        self.statuses = statuses;
+        self
+    }
+
+    /// ERG/USD price feed and caching; see [`Self::erg_price`].
+    pub fn with_price(mut self, price: PriceConfig) -> Self {
+        self.price = price;
+        self
+    }
+
+    /// Treasury address and explorer; see [`Self::sync_treasury`].
+    pub fn with_treasury(mut self, treasury: TreasuryConfig) -> Self {
+        self.treasury = treasury;
+        self
+    }
+
+    /// See [`Database::with_lenient_reads`].
+    pub fn with_lenient_reads(mut self, lenient: bool) -> Self {
+        self.db = self.db.with_lenient_reads(lenient);
+        self
+    }
+
+    /// Perform GitHub reads as usual but skip database writes during
+    /// collects and wallet syncs, which then report what would change.
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// How long a replaced wallet address is held back from new payouts.
+    pub fn with_wallet_confirmation_window(mut self, window: Duration) -> Self {
+        self.wallet_confirmation_window = window;
+        self
+    }
+
+    /// Act as `login` for role checks on project operations. Without an
+    /// actor every operation is allowed, as for a local operator.
+    /// Projects created by an actor are owned by it from the start.
+    pub fn with_actor(mut self, login: &str) -> Self {
+        self.actor = Some(login.to_string());
+        self
+    }
+
+    pub fn database(&self) -> &Database {
+        &self.db
+    }
+
+    /// Check that the actor holds at least `required` on a project.
+    /// Logins match case-insensitively. Projects without any owners can
+    /// only be changed by the local operator, who adds the first owner.
+    pub async fn authorize(
+        &self,
+        project_id: &str,
+        required: ProjectRole,
+    ) -> Result<()> {
+        let Some(actor) = &self.actor else {
+            return Ok(());
+        };
+        let pid = project_id.to_string();
+        let owners = self.db.run(move |db| db.get_project_owners(&pid)).await?;
+        if owners.is_empty() {
+            return Err(GitCirclesError::Forbidden(format!(
+                "Project {} has no owners yet; add the first one without --as",
+                project_id
+            )));
+        }
+
+        let role = owners
+            .iter()
+            .find(|o| o.github_username.eq_ignore_ascii_case(actor))
+            .and_then(|o| o.role.parse::<ProjectRole>().ok());
+        if role.is_some_and(|r| r >= required) {
+            return Ok(());
+        }
+        Err(GitCirclesError::Forbidden(format!(
+            "{} needs the {} role on project {}",
+            actor, required, project_id
+        )))
+    }
+
+    pub fn github(&self) -> Result<&GitHubClient> {
+        self.github.as_ref().ok_or_else(|| {
+            GitCirclesError::Auth("GitHub client not configured".to_string())
+        })
+    }
+
+    /// Returns the login the configured token authenticates as.
+    pub async fn test_token(&self) -> Result<String> {
+        self.github()?.test_token().await
+    }
+
+    pub async fn collect(&self, opts: &CollectOptions) -> Result<CollectOutcome> {
+        self.collect_from(self.github()?, opts).await
+    }
+
+    /// Collect merged PRs from any [`PullRequestSource`], e.g. a mock in tests.
+    /// Every run, including failed ones, is recorded in the sync log.
+    pub async fn collect_from<S: PullRequestSource>(
+        &self,
+        source: &S,
+        opts: &CollectOptions,
+    ) -> Result<CollectOutcome> {
+        if let Some(pid) = &opts.project_id {
+            self.require_project(pid).await?;
+            self.authorize(pid, ProjectRole::Admin).await?;
+        } else if !opts.paths.is_empty() {
+            return Err(GitCirclesError::InvalidInput(
+                "Path claims belong to one project's link. Use --project-id."
+                    .to_string(),
+            ));
+        }
+
+        let started_at = self.clock.now();
+        let calls_before = source.api_calls();
+        let mut pages_fetched = 0;
+        let result = self.collect_run(source, opts, &mut pages_fetched).await;
+        if self.dry_run {
+            return result;
+        }
+
+        let entry = SyncLogEntry {
+            repository: format!("{}/{}", opts.owner, opts.repo),
+            started_at,
+            finished_at: self.clock.now(),
+            pages_fetched,
+            api_calls: source.api_calls() - calls_before,
+            new_prs: result.as_ref().map_or(0, |o| o.new_prs.len() as u64),
+            error: result.as_ref().err().map(|e| e.to_string()),
+        };
+        let logged = self.db.run(move |db| db.append_sync_log(&entry)).await;
+        // A failed run reports its own error rather than the log write's
+        let outcome = result?;
+        logged?;
+        Ok(outcome)
+    }
+
+    async fn collect_run<S: PullRequestSource>(
+        &self,
+        source: &S,
+        opts: &CollectOptions,
+        pages_fetched: &mut u64,
+    ) -> Result<CollectOutcome> {
+        let repo_full = format!("{}/{}", opts.owner, opts.repo);
+
+        // Check/update repository tracking
+        let (owner, name) = (opts.owner.clone(), opts.repo.clone());
+        let mut repo_record = self
+            .db
+            .run(move |db| db.get_repository(&owner, &name))
+            .await?
+            .unwrap_or_else(|| Repository {
+                owner: opts.owner.clone(),
+                name: opts.repo.clone(),
+                current_base_branch: opts.base_branch.clone(),
+                last_sync: None,
+                total_prs: 0,
+                first_sync: self.clock.now(),
+                metadata: None,
+            });
+
+        if let Some(pid) = &opts.project_id
+            && !self.dry_run
+        {
+            let paths = (!opts.paths.is_empty()).then_some(opts.paths.as_slice());
+            self.link_repository(pid, &repo_full, paths).await?;
+        }
+        let full = repo_full.clone();
+        let links = self
+            .db
+            .run(move |db| db.get_links_for_repository(&full))
+            .await?;
+
+        // Detect base branch changes
+        let mut base_branch_change = None;
+        if repo_record.current_base_branch != opts.base_branch {
+            let change = BaseBranchChange {
+                repository: repo_full.clone(),
+                old_branch: repo_record.current_base_branch.clone(),
+                new_branch: opts.base_branch.clone(),
+                changed_at: self.clock.now(),
+            };
+            let recorded = change.clone();
+            if !self.dry_run {
+                self.db
+                    .run(move |db| db.record_base_branch_change(&recorded))
+                    .await?;
+            }
+            repo_record.current_base_branch = opts.base_branch.clone();
+            base_branch_change = Some(change);
+        }
+
+        let calls_before = source.api_calls();
+        let fetched_prs = if let Some(range) = opts.merged_range {
+            let numbers = source
+                .search_merged_pull_requests(
+                    &opts.owner,
+                    &opts.repo,
+                    &opts.base_branch,
+                    range,
+                    opts.limits,
+                )
+                .await?;
+            *pages_fetched = source.api_calls() - calls_before;
+
+            // Search results lack merge details, so fetch each PR that is
+            // new (or all of them when refreshing)
+            let repo = repo_full.clone();
+            let stored: BTreeSet<u64> = self
+                .db
+                .run(move |db| {
+                    Ok(db
+                        .get_pull_requests(&repo)?
+                        .into_iter()
+                        .map(|pr| pr.number)
+                        .collect())
+                })
+                .await?;
+            let mut prs = Vec::new();
+            for number in numbers {
+                if stored.contains(&number) && !opts.refresh {
+                    continue;
+                }
+                prs.extend(
+                    source
+                        .fetch_pull_request(&opts.owner, &opts.repo, number)
+                        .await?,
+                );
+            }
+            prs
+        } else if opts.detect_external_merges {
+            source
+                .fetch_pull_requests_with_external_merges(
+                    &opts.owner,
+                    &opts.repo,
+                    &opts.base_branch,
+                    opts.days,
+                    opts.limits,
+                )
+                .await?
+        } else {
+            source
+                .fetch_merged_pull_requests(
+                    &opts.owner,
+                    &opts.repo,
+                    &opts.base_branch,
+                    opts.days,
+                    opts.limits,
+                )
+                .await?
+        };
+        if opts.merged_range.is_none() {
+            *pages_fetched = source.api_calls() - calls_before;
+        }
+
+        // Filter out already-stored PRs (deduplication), updating their
+        // metadata in place when refreshing
+        let (refresh, now, dry_run) =
+            (opts.refresh, self.clock.now(), self.dry_run);
+        let (mut new_prs, refreshed) = self
+            .db
+            .run(move |db| {
+                let mut new_prs = Vec::new();
+                let mut refreshed = Vec::new();
+                for pr in fetched_prs {
+                    if !db.pull_request_exists(&pr.repository, pr.number)? {
+                        new_prs.push(pr);
+                        continue;
+                    }
+                    if !refresh {
+                        continue;
+                    }
+                    if let Some(mut stored) =
+                        db.get_pull_request(&pr.repository, pr.number)?
+                    {
+                        let changes = stored.refresh_from(&pr, now);
+                        if !changes.is_empty() && !dry_run {
+                            db.upsert_pull_request(&stored)?;
+                            db.append_pull_request_changes(&changes)?;
+                        }
+                        refreshed.extend(changes);
+                    }
+                }
+                Ok((new_prs, refreshed))
+            })
+            .await?;
+
+        let mut deferred = 0;
+        if let Some(max) = opts.max_prs
+            && new_prs.len() > max
+        {
+            deferred = new_prs.len() - max;
+            new_prs.truncate(max);
+        }
+
+        if let Some(max) = opts.max_body_chars {
+            for pr in &mut new_prs {
+                if let Some(body) = &mut pr.body {
+                    truncate_chars(body, max);
+                }
+            }
+        }
+
+        // Monorepo projects only get the PRs touching their paths
+        // (--path claims count too: a dry run does not store the link)
+        if !opts.paths.is_empty() || links.iter().any(|link| !link.paths.is_empty())
+        {
+            for pr in &mut new_prs {
+                pr.changed_files = source
+                    .fetch_pull_request_files(&opts.owner, &opts.repo, pr.number)
+                    .await?;
+            }
+        }
+
+        // Squash merges hide co-authors behind the PR opener
+        if opts.resolve_authors {
+            for pr in &mut new_prs {
+                let authors = source
+                    .fetch_pull_request_commit_authors(
+                        &opts.owner,
+                        &opts.repo,
+                        pr.number,
+                    )
+                    .await?;
+                pr.resolved_authors = resolve_commit_authors(&authors);
+            }
+        }
+
+        // Record organization members before storing, so the project
+        // counters already leave their PRs out
+        let authors: BTreeSet<String> =
+            new_prs.iter().map(|pr| pr.author.clone()).collect();
+        let mut org_exclusions = Vec::new();
+        for link in &links {
+            let pid = link.project_id.clone();
+            let Some(mut project) =
+                self.db.run(move |db| db.get_project(&pid)).await?
+            else {
+                continue;
+            };
+            let found = self
+                .detect_org_members(source, &mut project, &authors)
+                .await?;
+            if !found.is_empty() && !self.dry_run {
+                let stored = project.clone();
+                self.db.run(move |db| db.upsert_project(&stored)).await?;
+            }
+            org_exclusions
+                .extend(found.into_iter().map(|e| (project.id.clone(), e)));
+        }
+
+        // Stars, language etc. for the dashboard, refreshed every run
+        if let Some(mut metadata) = source
+            .fetch_repository_metadata(&opts.owner, &opts.repo)
+            .await?
+        {
+            metadata.fetched_at = self.clock.now();
+            repo_record.metadata = Some(metadata);
+        }
+
+        repo_record.last_sync = Some(self.clock.now());
+        let stored = new_prs.clone();
+        let (dry_run, full) = (self.dry_run, repo_full.clone());
+        let repo_record = self
+            .db
+            .run(move |db| {
+                if dry_run {
+                    repo_record.total_prs =
+                        db.count_pull_requests(&full)? + stored.len() as u64;
+                } else {
+                    db.store_collected_pull_requests(&mut repo_record, &stored)?;
+                }
+                Ok(repo_record)
+            })
+            .await?;
+
+        let mut hook_failures = Vec::new();
+        if !self.dry_run && !self.hooks.pr_registered.is_empty() {
+            for pr in &new_prs {
+                let event = HookEvent::PrRegistered {
+                    pull_request: pr.clone(),
+                };
+                if let Err(failure) = run_hook(&self.hooks, &event).await {
+                    hook_failures.push(failure);
+                }
+            }
+        }
+
+        Ok(CollectOutcome {
+            repository: repo_record,
+            new_prs,
+            refreshed,
+            base_branch_change,
+            deferred,
+            hook_failures,
+            org_exclusions,
+        })
+    }
+
+    /// Store the merge commits on `base_branch` of the local clone at
+    /// `clone` as imported PRs of `repository` ("owner/repo"). The
+    /// repository defaults to the clone's origin remote and the branch to
+    /// the one checked out. Merges already stored are left alone, so
+    /// importing again only adds new ones.
+    pub async fn import_git_log(
+        &self,
+        clone: &std::path::Path,
+        repository: Option<&str>,
+        base_branch: Option<&str>,
+    ) -> Result<ImportOutcome> {
+        let repository = match repository {
+            Some(repository) => repository.to_string(),
+            None => origin_repository(clone).await.ok_or_else(|| {
+                GitCirclesError::InvalidRepo(format!(
+                    "no GitHub origin remote in {}; pass --name",
+                    clone.display()
+                ))
+            })?,
+        };
+        parse_repo(&repository)?;
+        let base_branch = match base_branch {
+            Some(branch) => branch.to_string(),
+            None => current_branch(clone).await?,
+        };
+
+        let merges = read_merge_commits(clone, &base_branch).await?;
+        let prs: Vec<MergedPullRequest> = merges
+            .iter()
+            .map(|merge| merge_to_pull_request(merge, &repository, &base_branch))
+            .collect();
+
+        self.store_imported(repository, base_branch, prs).await
+    }
+
+    /// Store the merged PRs of `repository` ("owner/repo") found in a GitHub
+    /// export at `path` as imported PRs; see [`crate::import::read_archive`]
+    /// for the formats read. PRs already stored are left alone.
+    pub async fn import_archive(
+        &self,
+        path: &std::path::Path,
+        repository: &str,
+    ) -> Result<ImportOutcome> {
+        parse_repo(repository)?;
+        let (file, repo) = (path.to_path_buf(), repository.to_string());
+        let prs = tokio::task::spawn_blocking(move || read_archive(&file, &repo))
+            .await??;
+        // A repository seen for the first time tracks its latest PR's base
+        let base_branch = prs
+            .iter()
+            .max_by_key(|pr| pr.merged_at)
+            .map_or_else(|| "main".to_string(), |pr| pr.base_branch.clone());
+        self.store_imported(repository.to_string(), base_branch, prs)
+            .await
+    }
+
+    // Store the imported PRs not stored yet, creating the repository record
+    // if needed. A dry run only counts them.
+    async fn store_imported(
+        &self,
+        repository: String,
+        base_branch: String,
+        prs: Vec<MergedPullRequest>,
+    ) -> Result<ImportOutcome> {
+        let (owner, name) = parse_repo(&repository)?;
+        let (now, dry_run) = (self.clock.now(), self.dry_run);
+        self.db
+            .run(move |db| {
+                let mut repo_record = db
+                    .get_repository(&owner, &name)?
+                    .unwrap_or_else(|| Repository {
+                        owner: owner.clone(),
+                        name: name.clone(),
+                        current_base_branch: base_branch.clone(),
+                        last_sync: None,
+                        total_prs: 0,
+                        first_sync: now,
+                        metadata: None,
+                    });
+                let total = prs.len();
+                let mut seen = BTreeSet::new();
+                let mut imported = Vec::new();
+                for pr in prs {
+                    if seen.insert(pr.number)
+                        && !db.pull_request_exists(&pr.repository, pr.number)?
+                    {
+                        imported.push(pr);
+                    }
+                }
+                if dry_run {
+                    repo_record.total_prs = db.count_pull_requests(&repository)?
+                        + imported.len() as u64;
+                } else {
+                    db.store_collected_pull_requests(&mut repo_record, &imported)?;
+                }
+                Ok(ImportOutcome {
+                    repository: repo_record,
+                    skipped: total - imported.len(),
+                    imported,
+                })
+            })
+            .await
+    }
+
+    /// Recompute every repository's PR counter from the stored PRs,
+    /// returning the repaired repositories with their old count.
+    pub async fn recount_pull_requests(&self) -> Result<Vec<(Repository, u64)>> {
+        self.db.run(|db| db.recount_pull_requests()).await
+    }
+
+    /// Recorded collect runs for `owner/repo`, newest first.
+    pub async fn sync_log(&self, repo: &str) -> Result<Vec<SyncLogEntry>> {
+        let repo = repo.to_string();
+        let mut entries = self.db.run(move |db| db.get_sync_log(&repo)).await?;
+        entries.reverse();
+        Ok(entries)
+    }
+
+    /// Full-text search over stored PR titles, descriptions and authors.
+    pub async fn search_pull_requests(
+        &self,
+        query: &str,
+        repo: Option<&str>,
+    ) -> Result<Vec<MergedPullRequest>> {
+        let (query, repo) = (query.to_string(), repo.map(str::to_string));
+        self.db
+            .run(move |db| db.search_pull_requests(&query, repo.as_deref()))
+            .await
+    }
+
+    /// Sync a login's wallet. `force` ignores a cached "no profile repo"
+    /// result; `wallet_ref` reads the wallet from that branch or tag only.
+    pub async fn sync_wallet(
+        &self,
+        login: &str,
+        force: bool,
+        wallet_ref: Option<ProfileRef>,
+    ) -> Result<Option<WalletSyncResult>> {
+        WalletService::with_clock(&self.db, self.github()?, &*self.clock)
+            .with_confirmation_window(self.wallet_confirmation_window)
+            .with_wallet_ref(wallet_ref)
+            .with_dry_run(self.dry_run)
+            .sync_github_login(login, force)
+            .await
+    }
+
+    /// Make the address that was current for `login` at `at` current again,
+    /// e.g. after a compromised profile repository was fixed. The restored
+    /// address is recorded with a manual source and takes effect at once,
+    /// cancelling any pending rotation. A later `sync_wallet` reads the
+    /// profile repository as usual.
+    pub async fn rollback_wallet(
+        &self,
+        login: &str,
+        at: DateTime<Utc>,
+    ) -> Result<WalletSyncResult> {
+        let login = login.to_string();
+        let now = self.clock.now();
+        let actor = self.actor.clone();
+        self.db
+            .run(move |db| {
+                let history = db.get_wallet_history("github", &login)?;
+                let target = history
+                    .iter()
+                    .rev()
+                    .find(|entry| entry.recorded_at <= at)
+                    .ok_or_else(|| {
+                        GitCirclesError::WalletNotFound(format!(
+                            "{} at {}",
+                            login,
+                            local_time(at, "%Y-%m-%d %H:%M %Z")
+                        ))
+                    })?;
+                let previous = db
+                    .get_user_wallet("github", &login)?
+                    .map(|wallet| wallet.address);
+                let source = WalletSource::Manual {
+                    restored_from: target.recorded_at,
+                    actor,
+                };
+                let mut result = WalletSyncResult {
+                    current: target.address.clone(),
+                    changed: previous.as_ref() != Some(&target.address),
+                    previous,
+                    source: source.clone(),
+                    preferences: None,
+                    shared_with: Vec::new(),
+                    rotation: None,
+                };
+                if !result.changed {
+                    return Ok(result);
+                }
+
+                result.shared_with = db
+                    .get_logins_for_wallet(&result.current, "github")?
+                    .into_iter()
+                    .map(|link| link.login)
+                    .filter(|other| *other != login)
+                    .collect();
+
+                let wallet = UserWallet {
+                    login: login.clone(),
+                    platform: "github".to_string(),
+                    address: result.current.clone(),
+                    source: source.clone(),
+                    synced_at: now,
+                };
+                let entry = WalletHistoryEntry {
+                    login: login.clone(),
+                    platform: "github".to_string(),
+                    address: result.current.clone(),
+                    source,
+                    recorded_at: now,
+                };
+                let link = WalletLoginLink {
+                    wallet: result.current.clone(),
+                    platform: "github".to_string(),
+                    login: login.clone(),
+                    linked_at: now,
+                };
+                let mut batch = db.keyspace.batch();
+                db.upsert_user_wallet_batch(&mut batch, &wallet)?;
+                db.append_wallet_history_batch(&mut batch, &entry)?;
+                db.replace_wallet_link_batch(&mut batch, &link)?;
+                // The replaced address must stop resolving to the login,
+                // or `wallet lookup` keeps pointing a hijacker's address at it
+                if let Some(previous) = &result.previous {
+                    db.remove_wallet_link_batch(
+                        &mut batch, previous, "github", &login,
+                    );
+                }
+                db.remove_wallet_rotation_batch(&mut batch, "github", &login);
+                batch.commit()?;
+                Ok(result)
+            })
+            .await
+    }
+
+    /// The address new payouts to `login` should use. `None` while the
+    /// login has no wallet or a recent address change is still pending.
+    pub async fn payout_address(
+        &self,
+        login: &str,
+    ) -> Result<Option<WalletAddress>> {
+        let login = login.to_string();
+        let (wallet, rotation) = self
+            .db
+            .run(move |db| {
+                Ok((
+                    db.get_user_wallet("github", &login)?,
+                    db.get_wallet_rotation("github", &login)?,
+                ))
+            })
+            .await?;
+        let now = self.clock.now();
+        if rotation.is_some_and(|r| r.is_pending(now)) {
+            return Ok(None);
+        }
+        Ok(wallet.map(|w| w.address))
+    }
+
+    /// Open an issue on the contributor's profile repository asking them to
+    /// confirm an address change. Returns the issue number.
+    pub async fn notify_wallet_rotation(
+        &self,
+        rotation: &WalletRotation,
+    ) -> Result<u64> {
+        let body = format!(
+            "GitCircles detected a new wallet address in this repository.\n\n\
+             - Previous: `{}`\n\
+             - New: `{}`\n\n\
+             Pending and future payouts will use the new address from {}. \
+             If you did not make this change, revert `{}` and secure your \
+             GitHub account before then.",
+            rotation.previous,
+            rotation.current,
+            rotation.effective_at.format("%Y-%m-%d %H:%M UTC"),
+            WALLET_FILE_PATH
+        );
+        let (number, _) = self
+            .github()?
+            .create_issue(
+                &rotation.login,
+                PROFILE_REPO_NAME,
+                "GitCircles: wallet address changed",
+                &body,
+            )
+            .await?;
+        Ok(number)
+    }
+
+    /// Set up the token owner's profile repository so `wallet sync` finds
+    /// `address`. `preferences` is the raw `gitcircles.toml` to commit.
+    pub async fn init_profile(
+        &self,
+        address: &str,
+        preferences: Option<&str>,
+    ) -> Result<ProfileInitOutcome> {
+        let address = WalletAddress::try_from(address)?;
+        self.github()?
+            .init_profile_repository(&address, preferences)
+            .await
+    }
+
+    pub async fn is_opted_out(&self, login: &str) -> Result<bool> {
+        let login = login.to_string();
+        self.db
+            .run(move |db| db.get_contributor_opt_out("github", &login))
+            .await
+            .map(|opt_out| opt_out.is_some())
+    }
+
+    /// Record an operator-initiated opt-out. An existing opt-out is kept as is.
+    pub async fn opt_out(&self, login: &str) -> Result<ContributorOptOut> {
+        let opt_out = ContributorOptOut {
+            platform: "github".to_string(),
+            login: login.to_string(),
+            source: OptOutSource::Manual,
+            recorded_at: self.clock.now(),
+        };
+        self.db
+            .run(move |db| {
+                if let Some(existing) =
+                    db.get_contributor_opt_out("github", &opt_out.login)?
+                {
+                    return Ok(existing);
+                }
+                db.set_contributor_opt_out(&opt_out)?;
+                Ok(opt_out)
+            })
+            .await
+    }
+
+    /// Withdraw an opt-out. Returns false if the login had not opted out.
+    pub async fn opt_in(&self, login: &str) -> Result<bool> {
+        let login = login.to_string();
+        self.db
+            .run(move |db| {
+                if db.get_contributor_opt_out("github", &login)?.is_none() {
+                    return Ok(false);
+                }
+                db.remove_contributor_opt_out("github", &login)?;
+                Ok(true)
+            })
+            .await
+    }
+
+    /// Record opt-outs for everyone who commented `OPT OUT` on a PR.
+    /// Returns the opt-outs that were newly recorded.
+    pub async fn scan_opt_out_comments<S: PullRequestSource>(
+        &self,
+        source: &S,
+        owner: &str,
+        repo: &str,
+        number: u64,
+    ) -> Result<Vec<ContributorOptOut>> {
+        let repository = format!("{}/{}", owner, repo);
+        let now = self.clock.now();
+        let requests: Vec<ContributorOptOut> = source
+            .fetch_pull_request_comments(owner, repo, number)
+            .await?
+            .into_iter()
+            .filter(|c| is_opt_out_comment(&c.body))
+            .map(|c| ContributorOptOut {
+                platform: "github".to_string(),
+                login: c.author,
+                source: OptOutSource::Comment {
+                    repository: repository.clone(),
+                    number,
+                    comment_id: c.id,
+                },
+                recorded_at: now,
+            })
+            .collect();
+
+        self.db
+            .run(move |db| {
+                let mut recorded: Vec<ContributorOptOut> = Vec::new();
+                for opt_out in requests {
+                    if recorded.iter().any(|r| r.login == opt_out.login)
+                        || db
+                            .get_contributor_opt_out("github", &opt_out.login)?
+                            .is_some()
+                    {
+                        continue;
+                    }
+                    db.set_contributor_opt_out(&opt_out)?;
+                    recorded.push(opt_out);
+                }
+                Ok(recorded)
+            })
+            .await
+    }
+
+    /// Run the sybil/farming heuristics over stored data, optionally limited
+    /// to one project. `check_accounts` looks up each author's account age on
+    /// GitHub (one API call per author).
+    pub async fn anomaly_report(
+        &self,
+        project_id: Option<&str>,
+        check_accounts: bool,
+    ) -> Result<Vec<Anomaly>> {
+        if let Some(pid) = project_id {
+            self.require_project(pid).await?;
+        }
+        let pid = project_id.map(str::to_string);
+        let (prs, links) = self
+            .db
+            .run(move |db| {
+                let prs = match &pid {
+                    Some(pid) => db.get_pull_requests_for_project(pid)?,
+                    None => {
+                        let mut prs = Vec::new();
+                        for repo in db.list_repositories()? {
+                            let full = format!("{}/{}", repo.owner, repo.name);
+                            prs.extend(db.get_pull_requests(&full)?);
+                        }
+                        prs
+                    }
+                };
+                Ok((prs, db.list_wallet_links()?))
+            })
+            .await?;
+
+        let thresholds = AnomalyThresholds::default();
+        let mut anomalies = detect_wallet_funnels(&links, &thresholds);
+        anomalies.extend(detect_pr_bursts(&prs, &thresholds));
+
+        if check_accounts {
+            let github = self.github()?;
+            let authors: BTreeSet<&str> =
+                prs.iter().map(|pr| pr.author.as_str()).collect();
+            let progress = Progress::new();
+            let bar = progress.bar(authors.len() as u64, "Checking account ages");
+            let mut created = HashMap::new();
+            for author in authors {
+                bar.set_message(author.to_string());
+                let at = github.fetch_account_created_at(author).await?;
+                created.insert(author.to_string(), at);
+                bar.inc(1);
+            }
+            bar.finish_and_clear();
+            anomalies.extend(detect_new_accounts(&prs, &created, &thresholds));
+        }
+        Ok(anomalies)
+    }
+
+    /// The ERG/USD price from the configured feed. A price fetched less
+    /// than `price.cache_secs` ago is reused unless `refresh` is set. When
+    /// the feed fails, the cached price is used while it is younger than
+    /// `price.max_age_secs`; older prices are an error.
+    pub async fn erg_price(&self, refresh: bool) -> Result<PriceLookup> {
+        let feed = self.price.feed(&self.http_config)?;
+        self.erg_price_via(&feed, refresh).await
+    }
+
+    /// [`Self::erg_price`] from any [`PriceSource`].
+    pub async fn erg_price_via<P: PriceSource>(
+        &self,
+        source: &P,
+        refresh: bool,
+    ) -> Result<PriceLookup> {
+        let cached = self.db.run(|db| db.get_price_quote()).await?;
+        let now = self.clock.now();
+        let age = |quote: &PriceQuote| now - quote.fetched_at;
+        if !refresh
+            && let Some(quote) = &cached
+            && age(quote) < self.price.cache_duration()
+        {
+            return Ok(PriceLookup {
+                quote: quote.clone(),
+                fresh: false,
+                fetch_error: None,
+            });
+        }
+
+        let error = match source.fetch_usd_per_erg().await {
+            Ok(usd_per_erg) => {
+                let quote = PriceQuote {
+                    usd_per_erg,
+                    source: source.describe(),
+                    fetched_at: now,
+                };
+                if !self.dry_run {
+                    let stored = quote.clone();
+                    self.db.run(move |db| db.set_price_quote(&stored)).await?;
+                }
+                return Ok(PriceLookup {
+                    quote,
+                    fresh: true,
+                    fetch_error: None,
+                });
+            }
+            Err(e) => e,
+        };
+        match cached {
+            Some(quote) if age(&quote) <= self.price.max_age() => Ok(PriceLookup {
+                quote,
+                fresh: false,
+                fetch_error: Some(error.to_string()),
+            }),
+            Some(quote) => Err(GitCirclesError::Price(format!(
+                "{}; the cached price from {} is too old to use",
+                error,
+                local_time(quote.fetched_at, "%Y-%m-%d %H:%M %Z")
+            ))),
+            None => Err(error),
+        }
+    }
+
+    /// The treasuries of `project_id`, or the one at `treasury.address`
+    /// without a project.
+    pub async fn treasuries(
+        &self,
+        project_id: Option<&str>,
+    ) -> Result<Vec<ProjectTreasury>> {
+        if let Some(pid) = project_id {
+            let treasuries = self.project_treasuries(pid).await?;
+            if treasuries.is_empty() {
+                return Err(GitCirclesError::NotFound(format!(
+                    "Project '{}' has no treasury addresses",
+                    pid
+                )));
+            }
+            return Ok(treasuries);
+        }
+        let address = self.treasury.address.trim();
+        if address.is_empty() {
+            return Err(GitCirclesError::Config(
+                "set treasury.address or pass a project to sync a treasury"
+                    .to_string(),
+            ));
+        }
+        Ok(vec![ProjectTreasury {
+            project_id: String::new(),
+            address: address.to_string(),
+            name: "treasury".to_string(),
+            max_payout_nanoerg: None,
+            added_at: self.clock.now(),
+        }])
+    }
+
+    /// Fetch each treasury's transactions from the explorer, newest first,
+    /// down to the first one already stored. Until a sync of an address has
+    /// completed, its whole history is fetched. In a dry run nothing is
+    /// stored.
+    pub async fn sync_treasury(
+        &self,
+        project_id: Option<&str>,
+    ) -> Result<Vec<TreasurySync>> {
+        let explorer = self.treasury.explorer(&self.http_config)?;
+        let mut syncs = Vec::new();
+        for treasury in self.treasuries(project_id).await? {
+            syncs.push(self.sync_treasury_via(&explorer, &treasury.address).await?);
+        }
+        Ok(syncs)
+    }
+
+    /// [`Self::sync_treasury`] for one address from any
+    /// [`TransactionSource`].
+    pub async fn sync_treasury_via<S: TransactionSource>(
+        &self,
+        source: &S,
+        address: &str,
+    ) -> Result<TreasurySync> {
+        let address = address.to_string();
+        let key = address.clone();
+        let (state, stored) = self
+            .db
+            .run(move |db| {
+                let state = db.get_treasury_sync_state(&key)?;
+                let stored: BTreeSet<String> = db
+                    .get_treasury_transactions(&key)?
+                    .into_iter()
+                    .map(|tx| tx.tx_id)
+                    .collect();
+                Ok((state, stored))
+            })
+            .await?;
+
+        let limit = self.treasury.page_size.max(1);
+        let mut new = Vec::new();
+        let mut offset = 0;
+        'pages: loop {
+            let page = source.fetch_transactions(&address, offset, limit).await?;
+            if page.items.is_empty() {
+                break;
+            }
+            offset += page.items.len() as u64;
+            for tx in page.items {
+                if stored.contains(&tx.tx_id) {
+                    // Everything older was stored by a completed sync
+                    if state.is_some() {
+                        break 'pages;
+                    }
+                } else if !new
+                    .iter()
+                    .any(|n: &TreasuryTransaction| n.tx_id == tx.tx_id)
+                {
+                    new.push(tx);
+                }
+            }
+            if offset >= page.total {
+                break;
+            }
+        }
+
+        let synced_at = self.clock.now();
+        if !self.dry_run {
+            let state = TreasurySyncState {
+                address: address.clone(),
+                synced_at,
+                transactions: (stored.len() + new.len()) as u64,
+            };
+            let stored = new.clone();
+            self.db
+                .run(move |db| db.store_treasury_sync(&stored, &state))
+                .await?;
+        }
+        Ok(TreasurySync {
+            address,
+            new,
+            synced_at,
+        })
+    }
+
+    /// The stored history of each treasury, synced first when `sync` is
+    /// set or the last sync is older than `treasury.sync_interval_secs`.
+    /// When that sync fails, the stored history is returned with the error,
+    /// unless nothing was ever synced.
+    pub async fn treasury_history(
+        &self,
+        project_id: Option<&str>,
+        sync: bool,
+    ) -> Result<Vec<TreasuryHistory>> {
+        let explorer = self.treasury.explorer(&self.http_config)?;
+        let mut histories = Vec::new();
+        for treasury in self.treasuries(project_id).await? {
+            let mut history = self
+                .treasury_history_via(&explorer, &treasury.address, sync)
+                .await?;
+            history.name = Some(treasury.name);
+            histories.push(history);
+        }
+        Ok(histories)
+    }
+
+    /// [`Self::treasury_history`] for one address from any
+    /// [`TransactionSource`].
+    pub async fn treasury_history_via<S: TransactionSource>(
+        &self,
+        source: &S,
+        address: &str,
+        sync: bool,
+    ) -> Result<TreasuryHistory> {
+        let address = address.to_string();
+        let key = address.clone();
+        let last_sync = self
+            .db
+            .run(move |db| db.get_treasury_sync_state(&key))
+            .await?;
+        let due = last_sync.as_ref().is_none_or(|state| {
+            self.clock.now() - state.synced_at >= self.treasury.sync_interval()
+        });
+
+        let (mut new, mut sync_error) = (0, None);
+        if sync || due {
+            match self.sync_treasury_via(source, &address).await {
+                Ok(report) => new = report.new.len(),
+                Err(e) if last_sync.is_some() => sync_error = Some(e.to_string()),
+                Err(e) => return Err(e),
+            }
+        }
+
+        let key = address.clone();
+        let (transactions, last_sync, book, links) = self
+            .db
+            .run(move |db| {
+                Ok((
+                    db.get_treasury_transactions(&key)?,
+                    db.get_treasury_sync_state(&key)?,
+                    db.list_address_book()?,
+                    db.list_wallet_links()?,
+                ))
+            })
+            .await?;
+
+        let mut names = BTreeMap::new();
+        for counterparty in transactions.iter().flat_map(|tx| &tx.counterparties) {
+            if names.contains_key(counterparty) {
+                continue;
+            }
+            let name =
+                match book.iter().find(|e| e.address.as_str() == counterparty) {
+                    Some(entry) => entry.label(),
+                    None => links
+                        .iter()
+                        .filter(|link| {
+                            link.platform == "github"
+                                && link.wallet.as_str() == counterparty
+                        })
+                        .map(|link| link.login.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                };
+            if !name.is_empty() {
+                names.insert(counterparty.clone(), name);
+            }
+        }
+
+        Ok(TreasuryHistory {
+            address,
+            name: None,
+            transactions,
+            last_sync,
+            new,
+            sync_error,
+            names,
+        })
+    }
+
+    /// Name an address in the address book, replacing any earlier name.
+    pub async fn set_address_label(
+        &self,
+        address: &str,
+        name: &str,
+        kind: AddressKind,
+        note: Option<&str>,
+    ) -> Result<AddressBookEntry> {
+        let address = WalletAddress::try_from(address)?;
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(GitCirclesError::InvalidInput(
+                "Address name cannot be empty".to_string(),
+            ));
+        }
+        let entry = AddressBookEntry {
+            address,
+            name: name.to_string(),
+            kind,
+            note: note.map(str::to_string),
+            updated_at: self.clock.now(),
+        };
+        let stored = entry.clone();
+        self.db
+            .run(move |db| db.set_address_book_entry(&stored))
+            .await?;
+        Ok(entry)
+    }
+
+    /// Remove an address's name. Returns false if it had none.
+    pub async fn remove_address_label(&self, address: &str) -> Result<bool> {
+        let address = WalletAddress::try_from(address)?;
+        self.db
+            .run(move |db| {
+                if db.get_address_book_entry(&address)?.is_none() {
+                    return Ok(false);
+                }
+                db.remove_address_book_entry(&address)?;
+                Ok(true)
+            })
+            .await
+    }
+
+    /// Every named address, sorted by kind and then name.
+    pub async fn address_book(&self) -> Result<Vec<AddressBookEntry>> {
+        let mut entries = self.db.run(|db| db.list_address_book()).await?;
+        entries.sort_by(|a, b| (a.kind, &a.name).cmp(&(b.kind, &b.name)));
+        Ok(entries)
+    }
+
+    /// Open or refresh the pinned onboarding issue for a project in
+    /// `owner/repo`. A recorded issue that has since been deleted is
+    /// replaced by a new one.
+    pub async fn announce_project(
+        &self,
+        project_id: &str,
+        owner: &str,
+        repo: &str,
+    ) -> Result<AnnouncementOutcome> {
+        self.announce_project_via(self.github()?, project_id, owner, repo)
+            .await
+    }
+
+    /// [`Self::announce_project`] through any [`RepoInteraction`], e.g. a
+    /// recording mock in tests.
+    pub async fn announce_project_via<I: RepoInteraction>(
+        &self,
+        github: &I,
+        project_id: &str,
+        owner: &str,
+        repo: &str,
+    ) -> Result<AnnouncementOutcome> {
+        let project = self.require_project(project_id).await?;
+        self.authorize(project_id, ProjectRole::Admin).await?;
+        let repository = format!("{}/{}", owner, repo);
+
+        let (pid, full) = (project_id.to_string(), repository.clone());
+        let (repos, existing) = self
+            .db
+            .run(move |db| {
+                Ok((
+                    db.list_repositories_for_project(&pid)?,
+                    db.get_project_announcement(&full)?,
+                ))
+            })
+            .await?;
+
+        let title = announcement_title(&project);
+        let body = announcement_body(&project, &repos);
+
+        let mut updated = None;
+        if let Some(existing) = existing {
+            match github
+                .update_issue(owner, repo, existing.issue_number, &title, &body)
+                .await
+            {
+                Ok(node_id) => updated = Some((existing.issue_number, node_id)),
+                Err(e) if e.is_not_found() => {}
+                Err(e) => return Err(e),
+            }
+        }
+        let created = updated.is_none();
+        let (issue_number, node_id) = match updated {
+            Some(issue) => issue,
+            None => github.create_issue(owner, repo, &title, &body).await?,
+        };
+        let pinned = github.pin_issue(&node_id).await?;
+
+        let announcement = ProjectAnnouncement {
+            project_id: project_id.to_string(),
+            repository,
+            issue_number,
+            updated_at: self.clock.now(),
+        };
+        let stored = announcement.clone();
+        self.db
+            .run(move |db| db.upsert_project_announcement(&stored))
+            .await?;
+        Ok(AnnouncementOutcome {
+            announcement,
+            created,
+            pinned,
+        })
+    }
+
+    /// Summaries of the last `days` days for one project, or for every
+    /// project when `project_id` is `None`.
+    pub async fn digest(
+        &self,
+        project_id: Option<&str>,
+        days: i64,
+    ) -> Result<Vec<ProjectDigest>> {
+        let projects = match project_id {
+            Some(pid) => vec![self.require_project(pid).await?],
+            None => self.db.run(|db| db.list_projects()).await?,
+        };
+        let end = self.clock.now();
+        let start = end - Duration::days(days);
+
+        self.db
+            .run(move |db| {
+                let mut digests = Vec::new();
+                for project in &projects {
+                    let prs = db.get_pull_requests_for_project(&project.id)?;
+                    let mut wallets = HashMap::new();
+                    for pr in &prs {
+                        for login in
+                            std::iter::once(&pr.author).chain(&pr.resolved_authors)
+                        {
+                            if !wallets.contains_key(login) {
+                                let wallet = db.get_user_wallet("github", login)?;
+                                wallets.insert(login.clone(), wallet.is_some());
+                            }
+                        }
+                    }
+                    digests.push(build_digest(
+                        project,
+                        &prs,
+                        start,
+                        end,
+                        |login| wallets.get(login).copied().unwrap_or(false),
+                    ));
+                }
+                Ok(digests)
+            })
+            .await
+    }
+
+    /// Repositories, PR counts and contributor payout readiness for one
+    /// project.
+    pub async fn project_status(&self, project_id: &str) -> Result<ProjectStatus> {
+        let project = self.require_project(project_id).await?;
+        let (pid, now) = (project_id.to_string(), self.clock.now());
+        self.db
+            .run(move |db| {
+                let prs = db.get_pull_requests_for_project(&pid)?;
+                let mut prs_by_repository = BTreeMap::new();
+                for pr in &prs {
+                    *prs_by_repository.entry(pr.repository.clone()).or_default() +=
+                        1;
+                }
+
+                let logins: BTreeSet<&str> =
+                    prs.iter().flat_map(contributors).collect();
+                let mut readiness = PayoutReadiness::default();
+                for login in &logins {
+                    let counter = match payout_standing(db, login, now)? {
+                        PayoutStanding::OptedOut => &mut readiness.opted_out,
+                        PayoutStanding::MissingWallet => {
+                            &mut readiness.missing_wallet
+                        }
+                        PayoutStanding::PendingRotation => {
+                            &mut readiness.pending_rotation
+                        }
+                        PayoutStanding::Ready => &mut readiness.ready,
+                    };
+                    *counter += 1;
+                }
+
+                let awaiting_approval = if project.require_approval {
+                    unapproved(db, &pid, prs.clone())?.len()
+                } else {
+                    0
+                };
+
+                Ok(ProjectStatus {
+                    awaiting_approval,
+                    repositories: db.list_repositories_for_project(&pid)?,
+                    links: db.get_project_links(&pid)?,
+                    total_prs: prs.len(),
+                    contributors: logins.len(),
+                    last_merged_at: prs.iter().map(|pr| pr.merged_at).max(),
+                    prs_by_repository,
+                    readiness,
+                    project,
+                })
+            })
+            .await
+    }
+
+    /// Contribution stats and wallet state of `login`. Fails when the login
+    /// has neither stored PRs nor a wallet.
+    pub async fn contributor_profile(
+        &self,
+        login: &str,
+    ) -> Result<ContributorProfile> {
+        let (login, now) = (login.to_string(), self.clock.now());
+        self.db
+            .run(move |db| {
+                let stats = db.get_contributor_stats("github", &login)?;
+                let wallet = db.get_user_wallet("github", &login)?;
+                if stats.is_none() && wallet.is_none() {
+                    return Err(GitCirclesError::NotFound(format!(
+                        "No contributions or wallet found for '{}'",
+                        login
+                    )));
+                }
+                Ok(ContributorProfile {
+                    stats,
+                    wallet,
+                    rotation: db.get_wallet_rotation("github", &login)?,
+                    preferences: db
+                        .get_contributor_preferences("github", &login)?,
+                    standing: payout_standing(db, &login, now)?,
+                    login,
+                })
+            })
+            .await
+    }
+
+    /// Delete everything stored about `login`, e.g. on a GDPR erasure
+    /// request. Its PRs stay, under a pseudonym, so project and contributor
+    /// counts don't change. In a dry run nothing is written.
+    pub async fn forget_login(&self, login: &str) -> Result<ForgetReport> {
+        let (login, dry_run) = (login.to_string(), self.dry_run);
+        let pseudonym = forgotten_pseudonym(&login, self.clock.now());
+        self.db
+            .run(move |db| {
+                let mut batch = db.keyspace.batch();
+                let report = db
+                    .forget_login_batch(&mut batch, "github", &login, &pseudonym)?;
+                if !dry_run && !report.is_empty() {
+                    batch.commit()?;
+                    db.keyspace.persist(fjall::PersistMode::SyncAll)?;
+                }
+                Ok(report)
+            })
+            .await
+    }
+
+    pub async fn create_project(
+        &self,
+        name: &str,
+        description: Option<String>,
+    ) -> Result<Project> {
+        let now = self.clock.now();
+        let project = Project {
+            id: generate_project_id(name),
+            name: name.to_string(),
+            description,
+            created_at: now,
+            updated_at: now,
+            pauses: Vec::new(),
+            allowed_contributors: Vec::new(),
+            blocked_contributors: Vec::new(),
+            excluded_orgs: Vec::new(),
+            org_exclusions: Vec::new(),
+            require_approval: false,
+        };
+        let owner = self.actor.as_ref().map(|login| ProjectOwner {
+            project_id: project.id.clone(),
+            github_username: login.clone(),
+            role: ProjectRole::Owner.to_string(),
+            added_at: now,
+        });
+        let stored = project.clone();
+        self.db
+            .run(move |db| {
+                db.upsert_project(&stored)?;
+                if let Some(owner) = &owner {
+                    db.add_project_owner(owner)?;
+                }
+                Ok(())
+            })
+            .await?;
+        Ok(project)
+    }
+
+    pub async fn require_project(&self, project_id: &str) -> Result<Project> {
+        let pid = project_id.to_string();
+        self.db
+            .run(move |db| db.get_project(&pid))
+            .await?
+            .ok_or_else(|| {
+                GitCirclesError::NotFound(format!(
+                    "Project '{}' not found",
+                    project_id
+                ))
+            })
+    }
+
+    /// Delete a project with its owners and approvals. Fails while
+    /// repositories are still linked to it.
+    pub async fn delete_project(&self, project_id: &str) -> Result<Project> {
+        let project = self.require_project(project_id).await?;
+        self.authorize(project_id, ProjectRole::Owner).await?;
+
+        let pid = project_id.to_string();
+        self.db
+            .run(move |db| {
+                let links = db.get_project_links(&pid)?;
+                if !links.is_empty() {
+                    return Err(GitCirclesError::InvalidInput(format!(
+                        "Cannot delete project '{}': {} repositories are still linked. Unlink them first.",
+                        pid,
+                        links.len()
+                    )));
+                }
+
+                for owner in db.get_project_owners(&pid)? {
+                    db.remove_project_owner(&pid, &owner.github_username)?;
+                }
+                db.remove_approvals_for_project(&pid)?;
+                for treasury in db.get_project_treasuries(&pid)? {
+                    db.remove_project_treasury(&pid, &treasury.address)?;
+                }
+
+                db.delete_project(&pid)
+            })
+            .await?;
+        Ok(project)
+    }
+
+    /// Stop scheduling the project's PRs until it is resumed, e.g. during a
+    /// treasury migration or an incident: label and status syncs leave
+    /// them out, so they get no scheduled label or pending status. Labels
+    /// and statuses already published stay.
+    pub async fn pause_project(
+        &self,
+        project_id: &str,
+        reason: Option<String>,
+    ) -> Result<Project> {
+        let mut project = self.require_project(project_id).await?;
+        self.authorize(project_id, ProjectRole::Admin).await?;
+        if let Some(pause) = project.current_pause() {
+            return Err(GitCirclesError::InvalidInput(format!(
+                "Project '{}' is already paused since {}",
+                project_id,
+                local_time(pause.paused_at, "%Y-%m-%d %H:%M %Z")
+            )));
+        }
+
+        let now = self.clock.now();
+        project.pauses.push(ProjectPause {
+            paused_at: now,
+            resumed_at: None,
+            reason,
+            paused_by: self.actor.clone(),
+        });
+        project.updated_at = now;
+        let stored = project.clone();
+        self.db.run(move |db| db.upsert_project(&stored)).await?;
+        Ok(project)
+    }
+
+    /// End the project's ongoing pause; the next label or status sync
+    /// schedules its PRs again.
+    pub async fn resume_project(&self, project_id: &str) -> Result<Project> {
+        let mut project = self.require_project(project_id).await?;
+        self.authorize(project_id, ProjectRole::Admin).await?;
+        let now = self.clock.now();
+        match project.pauses.last_mut() {
+            Some(pause) if pause.resumed_at.is_none() => {
+                pause.resumed_at = Some(now)
+            }
+            _ => {
+                return Err(GitCirclesError::InvalidInput(format!(
+                    "Project '{}' is not paused",
+                    project_id
+                )));
+            }
+        }
+        project.updated_at = now;
+        let stored = project.clone();
+        self.db.run(move |db| db.upsert_project(&stored)).await?;
+        Ok(project)
+    }
+
+    /// Turn the approval gate on or off. While it is on, the project's PRs
+    /// are not scheduled until an owner or admin approves them.
+    pub async fn set_approval_required(
+        &self,
+        project_id: &str,
+        required: bool,
+    ) -> Result<Project> {
+        let mut project = self.require_project(project_id).await?;
+        self.authorize(project_id, ProjectRole::Admin).await?;
+        project.require_approval = required;
+        project.updated_at = self.clock.now();
+        let stored = project.clone();
+        self.db.run(move |db| db.upsert_project(&stored)).await?;
+        Ok(project)
+    }
+
+    /// Approve a PR the project counts. An existing approval is kept as is.
+    pub async fn approve_pull_request(
+        &self,
+        project_id: &str,
+        repository: &str,
+        number: u64,
+    ) -> Result<PullRequestApproval> {
+        self.require_project(project_id).await?;
+        self.authorize(project_id, ProjectRole::Admin).await?;
+        parse_repo(repository)?;
+
+        let approval = PullRequestApproval {
+            project_id: project_id.to_string(),
+            repository: repository.to_string(),
+            number,
+            approved_by: self.actor.clone(),
+            source: ApprovalSource::Manual,
+            approved_at: self.clock.now(),
+        };
+        self.db
+            .run(move |db| {
+                let counted = db
+                    .get_pull_requests_for_project(&approval.project_id)?
+                    .iter()
+                    .any(|pr| {
+                        pr.repository == approval.repository
+                            && pr.number == approval.number
+                    });
+                if !counted {
+                    return Err(GitCirclesError::NotFound(format!(
+                        "Project '{}' does not count {}#{}",
+                        approval.project_id, approval.repository, approval.number
+                    )));
+                }
+                if let Some(existing) = db.get_pull_request_approval(
+                    &approval.project_id,
+                    &approval.repository,
+                    approval.number,
+                )? {
+                    return Ok(existing);
+                }
+                db.set_pull_request_approval(&approval)?;
+                Ok(approval)
+            })
+            .await
+    }
+
+    /// The project's PRs still waiting for an approval, newest first.
+    /// Empty unless the project requires approval.
+    pub async fn pending_approvals(
+        &self,
+        project_id: &str,
+    ) -> Result<Vec<MergedPullRequest>> {
+        let project = self.require_project(project_id).await?;
+        if !project.require_approval {
+            return Ok(Vec::new());
+        }
+        let pid = project_id.to_string();
+        self.db
+            .run(move |db| {
+                let prs = db.get_pull_requests_for_project(&pid)?;
+                unapproved(db, &pid, prs)
+            })
+            .await
+    }
+
+    /// Record approvals from `GitCircles APPROVE` comments by the project's
+    /// owners and admins on its pending PRs. Comments by anyone else are
+    /// ignored. Returns the approvals that were newly recorded, or would be
+    /// under dry run.
+    pub async fn scan_approval_comments<S: PullRequestSource>(
+        &self,
+        source: &S,
+        project_id: &str,
+    ) -> Result<Vec<PullRequestApproval>> {
+        let pending = self.pending_approvals(project_id).await?;
+        let pid = project_id.to_string();
+        let approvers: Vec<String> = self
+            .db
+            .run(move |db| db.get_project_owners(&pid))
+            .await?
+            .into_iter()
+            .filter(|o| {
+                o.role
+                    .parse::<ProjectRole>()
+                    .is_ok_and(|r| r >= ProjectRole::Admin)
+            })
+            .map(|o| o.github_username)
+            .collect();
+        if approvers.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let now = self.clock.now();
+        let mut approvals = Vec::new();
+        for pr in pending {
+            let (owner, name) = parse_repo(&pr.repository)?;
+            let comment = source
+                .fetch_pull_request_comments(&owner, &name, pr.number)
+                .await?
+                .into_iter()
+                .find(|c| {
+                    is_approval_comment(&c.body)
+                        && approvers
+                            .iter()
+                            .any(|a| a.eq_ignore_ascii_case(&c.author))
+                });
+            if let Some(comment) = comment {
+                approvals.push(PullRequestApproval {
+                    project_id: project_id.to_string(),
+                    repository: pr.repository,
+                    number: pr.number,
+                    approved_by: Some(comment.author),
+                    source: ApprovalSource::Comment {
+                        comment_id: comment.id,
+                    },
+                    approved_at: now,
+                });
+            }
+        }
+
+        if self.dry_run {
+            return Ok(approvals);
+        }
+        let stored = approvals.clone();
+        self.db
+            .run(move |db| {
+                for approval in &stored {
+                    db.set_pull_request_approval(approval)?;
+                }
+                Ok(())
+            })
+            .await?;
+        Ok(approvals)
+    }
+
+    /// Put the label for `state` on a PR, taking off the pipeline label
+    /// applied before. Does nothing unless labels are enabled, or when the
+    /// PR already carries the label. Returns the label applied (or that
+    /// would be, in a dry run).
+    pub async fn apply_label(
+        &self,
+        repository: &str,
+        number: u64,
+        state: PipelineLabel,
+    ) -> Result<Option<AppliedLabel>> {
+        self.apply_label_via(self.github()?, repository, number, state)
+            .await
+    }
+
+    /// [`Self::apply_label`] through any [`RepoInteraction`].
+    pub async fn apply_label_via<I: RepoInteraction>(
+        &self,
+        github: &I,
+        repository: &str,
+        number: u64,
+        state: PipelineLabel,
+    ) -> Result<Option<AppliedLabel>> {
+        if !self.labels.enabled {
+            return Ok(None);
+        }
+        let (owner, name) = parse_repo(repository)?;
+        let applied = AppliedLabel {
+            repository: repository.to_string(),
+            number,
+            state,
+            label: self.labels.name(state).to_string(),
+            applied_at: self.clock.now(),
+        };
+        let repo = repository.to_string();
+        let previous = self
+            .db
+            .run(move |db| db.get_applied_label(&repo, number))
+            .await?;
+        if previous.as_ref().is_some_and(|p| p.label == applied.label) {
+            return Ok(None);
+        }
+        if self.dry_run {
+            return Ok(Some(applied));
+        }
+
+        if let Some(previous) = previous {
+            match github
+                .remove_label(&owner, &name, number, &previous.label)
+                .await
+            {
+                Ok(()) => {}
+                // Already taken off by hand
+                Err(e) if e.is_not_found() => {}
+                Err(e) => return Err(e),
+            }
+        }
+        github
+            .add_label(&owner, &name, number, &applied.label)
+            .await?;
+        let stored = applied.clone();
+        self.db.run(move |db| db.set_applied_label(&stored)).await?;
+        Ok(Some(applied))
+    }
+
+    /// Label the PRs of every linked repository (or only `repository`)
+    /// with their pipeline state: scheduled once a project counts them,
+    /// after approval where required, and stopped when the author opted
+    /// out or no linked project counts their PRs. Returns the labels
+    /// applied.
+    pub async fn sync_labels(
+        &self,
+        repository: Option<&str>,
+    ) -> Result<Vec<AppliedLabel>> {
+        self.sync_labels_via(self.github()?, repository).await
+    }
+
+    /// [`Self::sync_labels`] through any [`RepoInteraction`].
+    pub async fn sync_labels_via<I: RepoInteraction>(
+        &self,
+        github: &I,
+        repository: Option<&str>,
+    ) -> Result<Vec<AppliedLabel>> {
+        if !self.labels.enabled {
+            return Err(GitCirclesError::Config(
+                "set labels.enabled = true to label PRs".to_string(),
+            ));
+        }
+        let only = repository.map(str::to_string);
+        let states = self
+            .db
+            .run(move |db| {
+                Ok(pipeline_states(db, only.as_deref())?
+                    .into_iter()
+                    .map(|(pr, state)| (pr.repository, pr.number, state))
+                    .collect::<Vec<_>>())
+            })
+            .await?;
+
+        let mut applied = Vec::new();
+        for (repo, number, state) in states {
+            if let Some(label) =
+                self.apply_label_via(github, &repo, number, state).await?
+            {
+                applied.push(label);
+            }
+        }
+        Ok(applied)
+    }
+
+    /// Publish `state` as a commit status on the PR's merge commit, with
+    /// the configured details link. Does nothing unless statuses are
+    /// enabled, or when the commit already shows the state. Returns the
+    /// status published (or that would be, in a dry run).
+    pub async fn publish_status(
+        &self,
+        repository: &str,
+        number: u64,
+        state: PipelineLabel,
+    ) -> Result<Option<PublishedStatus>> {
+        self.publish_status_via(self.github()?, repository, number, state)
+            .await
+    }
+
+    /// [`Self::publish_status`] through any [`RepoInteraction`].
+    pub async fn publish_status_via<I: RepoInteraction>(
+        &self,
+        github: &I,
+        repository: &str,
+        number: u64,
+        state: PipelineLabel,
+    ) -> Result<Option<PublishedStatus>> {
+        if !self.statuses.enabled {
+            return Ok(None);
+        }
+        let (owner, name) = parse_repo(repository)?;
+        let repo = repository.to_string();
+        let (pr, previous) = self
+            .db
+            .run(move |db| {
+                Ok((
+                    db.get_pull_request(&repo, number)?,
+                    db.get_published_status(&repo, number)?,
+                ))
+            })
+            .await?;
+        let pr = pr.ok_or_else(|| {
+            GitCirclesError::NotFound(format!(
+                "Pull request {}#{} not found",
+                repository, number
+            ))
+        })?;
+        if previous
+            .is_some_and(|p| p.state == state && p.sha == pr.merge_commit_sha)
+        {
+            return Ok(None);
+        }
+
+        let published = PublishedStatus {
+            repository: repository.to_string(),
+            number,
+            sha: pr.merge_commit_sha,
+            state,
+            published_at: self.clock.now(),
+        };
+        if self.dry_run {
+            return Ok(Some(published));
+        }
+        // A stopped PR is not a broken build, so it stays green
+        let (commit_state, description) = match state {
+            PipelineLabel::Scheduled => {
+                (CommitState::Pending, "Scheduled for a GitCircles reward")
+            }
+            PipelineLabel::Stopped => {
+                (CommitState::Success, "Not rewarded by GitCircles")
+            }
+        };
+        let status = CommitStatus {
+            state: commit_state,
+            context: self.statuses.context.clone(),
+            description: description.to_string(),
+            target_url: self.statuses.details_url(repository, number),
+        };
+        github
+            .create_commit_status(&owner, &name, &published.sha, &status)
+            .await?;
+        let stored = published.clone();
+        self.db
+            .run(move |db| db.set_published_status(&stored))
+            .await?;
+        Ok(Some(published))
+    }
+
+    /// Publish the pipeline state of the PRs of every linked repository
+    /// (or only `repository`) as commit statuses, like
+    /// [`Self::sync_labels`] does with labels. Returns the statuses
+    /// published.
+    pub async fn sync_statuses(
+        &self,
+        repository: Option<&str>,
+    ) -> Result<Vec<PublishedStatus>> {
+        self.sync_statuses_via(self.github()?, repository).await
+    }
+
+    /// [`Self::sync_statuses`] through any [`RepoInteraction`].
+    pub async fn sync_statuses_via<I: RepoInteraction>(
+        &self,
+        github: &I,
+        repository: Option<&str>,
+    ) -> Result<Vec<PublishedStatus>> {
+        if !self.statuses.enabled {
+            return Err(GitCirclesError::Config(
+                "set statuses.enabled = true to publish commit statuses"
+                    .to_string(),
+            ));
+        }
+        let only = repository.map(str::to_string);
+        let states = self
+            .db
+            .run(move |db| {
+                Ok(pipeline_states(db, only.as_deref())?
+                    .into_iter()
+                    .map(|(pr, state)| (pr.repository, pr.number, state))
+                    .collect::<Vec<_>>())
+            })
+            .await?;
+
+        let mut published = Vec::new();
+        for (repo, number, state) in states {
+            if let Some(status) = self
+                .publish_status_via(github, &repo, number, state)
+                .await?
+            {
+                published.push(status);
+            }
+        }
+        Ok(published)
+    }
+
+    /// Add `logins` to (or with `remove`, drop them from) the project's
+    /// allow or block list, then recount the project's PRs.
+    pub async fn update_contributor_list(
+        &self,
+        project_id: &str,
+        list: ContributorList,
+        logins: &[String],
+        remove: bool,
+    ) -> Result<Project> {
+        let mut project = self.require_project(project_id).await?;
+        self.authorize(project_id, ProjectRole::Admin).await?;
+
+        let entries = project.contributor_list_mut(list);
+        for login in logins {
+            let position =
+                entries.iter().position(|l| l.eq_ignore_ascii_case(login));
+            match position {
+                Some(i) if remove => {
+                    entries.remove(i);
+                }
+                None if !remove => entries.push(login.clone()),
+                _ => {}
+            }
+        }
+        project.updated_at = self.clock.now();
+        let stored = project.clone();
+        self.db
+            .run(move |db| {
+                db.upsert_project(&stored)?;
+                db.rebuild_project_counters(&stored.id)
+            })
+            .await?;
+        Ok(project)
+    }
+
+    /// Add `orgs` to (or with `remove`, drop them from) the project's
+    /// excluded organizations. Authors of the project's stored PRs are
+    /// checked against added organizations right away; later authors are
+    /// checked as their PRs are collected. Returns the project and the
+    /// authors newly excluded.
+    pub async fn update_excluded_orgs(
+        &self,
+        project_id: &str,
+        orgs: &[String],
+        remove: bool,
+    ) -> Result<(Project, Vec<OrgExclusion>)> {
+        self.update_excluded_orgs_via(self.github()?, project_id, orgs, remove)
+            .await
+    }
+
+    /// [`Self::update_excluded_orgs`] checking memberships through any
+    /// [`PullRequestSource`].
+    pub async fn update_excluded_orgs_via<S: PullRequestSource>(
+        &self,
+        source: &S,
+        project_id: &str,
+        orgs: &[String],
+        remove: bool,
+    ) -> Result<(Project, Vec<OrgExclusion>)> {
+        let mut project = self.require_project(project_id).await?;
+        self.authorize(project_id, ProjectRole::Admin).await?;
+
+        let pid = project_id.to_string();
+        let authors: BTreeSet<String> = self
+            .db
+            .run(move |db| db.get_pull_requests_for_project(&pid))
+            .await?
+            .into_iter()
+            .map(|pr| pr.author)
+            .collect();
+
+        for org in orgs {
+            let position = project
+                .excluded_orgs
+                .iter()
+                .position(|o| o.eq_ignore_ascii_case(org));
+            match position {
+                Some(i) if remove => {
+                    let dropped = project.excluded_orgs.remove(i);
+                    project
+                        .org_exclusions
+                        .retain(|e| !e.org.eq_ignore_ascii_case(&dropped));
+                }
+                None if !remove => project.excluded_orgs.push(org.clone()),
+                _ => {}
+            }
+        }
+        let found = if remove {
+            Vec::new()
+        } else {
+            self.detect_org_members(source, &mut project, &authors)
+                .await?
+        };
+
+        project.updated_at = self.clock.now();
+        let stored = project.clone();
+        self.db
+            .run(move |db| {
+                db.upsert_project(&stored)?;
+                db.rebuild_project_counters(&stored.id)
+            })
+            .await?;
+        Ok((project, found))
+    }
+
+    // Check `logins` not yet excluded against each of the project's
+    // excluded organizations (one API call per pair), recording members on
+    // `project`. Returns the new exclusions.
+    async fn detect_org_members<S: PullRequestSource>(
+        &self,
+        source: &S,
+        project: &mut Project,
+        logins: &BTreeSet<String>,
+    ) -> Result<Vec<OrgExclusion>> {
+        let mut found = Vec::new();
+        for login in logins {
+            if project.org_exclusion(login).is_some() {
+                continue;
+            }
+            for org in &project.excluded_orgs {
+                if source.is_org_member(org, login).await? {
+                    found.push(OrgExclusion {
+                        login: login.clone(),
+                        org: org.clone(),
+                        detected_at: self.clock.now(),
+                    });
+                    break;
+                }
+            }
+        }
+        project.org_exclusions.extend(found.iter().cloned());
+        Ok(found)
+    }
+
+    /// Link `repository` ("owner/repo") to a project, so the project counts
+    /// its PRs. `paths` replaces the link's path claims; `None` keeps them,
+    /// and an empty list claims the whole repository.
+    pub async fn link_repository(
+        &self,
+        project_id: &str,
+        repository: &str,
+        paths: Option<&[String]>,
+    ) -> Result<ProjectLink> {
+        self.require_project(project_id).await?;
+        self.authorize(project_id, ProjectRole::Admin).await?;
+
+        let (pid, repo) = (project_id.to_string(), repository.to_string());
+        let existing = self
+            .db
+            .run(move |db| db.get_project_link(&pid, &repo))
+            .await?;
+        let mut link = existing.unwrap_or_else(|| ProjectLink {
+            project_id: project_id.to_string(),
+            repository: repository.to_string(),
+            paths: Vec::new(),
+            linked_at: self.clock.now(),
+        });
+        if let Some(paths) = paths {
+            link.paths = paths.to_vec();
+        }
+        let stored = link.clone();
+        self.db
+            .run(move |db| {
+                db.upsert_project_link(&stored)?;
+                db.rebuild_project_counters(&stored.project_id)?;
+                Ok(())
+            })
+            .await?;
+        Ok(link)
+    }
+
+    /// Stop counting a repository's PRs for a project. Stored PRs are kept.
+    pub async fn unlink_repository(
+        &self,
+        project_id: &str,
+        repository: &str,
+    ) -> Result<()> {
+        self.require_project(project_id).await?;
+        self.authorize(project_id, ProjectRole::Admin).await?;
+        let (pid, repo) = (project_id.to_string(), repository.to_string());
+        self.db
+            .run(move |db| match db.get_project_link(&pid, &repo)? {
+                Some(_) => {
+                    db.remove_project_link(&pid, &repo)?;
+                    db.rebuild_project_counters(&pid)?;
+                    Ok(())
+                }
+                None => Err(GitCirclesError::NotFound(format!(
+                    "{} is not linked to project '{}'",
+                    repo, pid
+                ))),
+            })
+            .await
+    }
+
+    pub async fn add_project_owner(
+        &self,
+        project_id: &str,
+        username: &str,
+        role: &str,
+    ) -> Result<ProjectOwner> {
+        self.require_project(project_id).await?;
+        self.authorize(project_id, ProjectRole::Owner).await?;
+        role.parse::<ProjectRole>()?;
+
+        let owner = ProjectOwner {
+            project_id: project_id.to_string(),
+            github_username: username.to_string(),
+            role: role.to_string(),
+            added_at: self.clock.now(),
+        };
+        let stored = owner.clone();
+        self.db.run(move |db| db.add_project_owner(&stored)).await?;
+        Ok(owner)
+    }
+
+    pub async fn remove_project_owner(
+        &self,
+        project_id: &str,
+        username: &str,
+    ) -> Result<()> {
+        self.require_project(project_id).await?;
+        self.authorize(project_id, ProjectRole::Owner).await?;
+        let (pid, username) = (project_id.to_string(), username.to_string());
+        self.db
+            .run(move |db| db.remove_project_owner(&pid, &username))
+            .await
+    }
+
+    /// Register a treasury address for a project, replacing an earlier
+    /// registration of the same address. Payouts up to `max_payout_nanoerg`
+    /// may be routed to it; see [`crate::treasury::route_payout`].
+    pub async fn add_project_treasury(
+        &self,
+        project_id: &str,
+        address: &str,
+        name: &str,
+        max_payout_nanoerg: Option<u64>,
+    ) -> Result<ProjectTreasury> {
+        self.require_project(project_id).await?;
+        self.authorize(project_id, ProjectRole::Admin).await?;
+        let address = address.trim();
+        // Treasuries are often multi-sig (P2S), so only the encoding is checked
+        if address.is_empty() || bs58::decode(address).into_vec().is_err() {
+            return Err(GitCirclesError::WalletInvalidFormat(
+                address.to_string(),
+                "expected a Base58 Ergo address".into(),
+            ));
+        }
+
+        let treasury = ProjectTreasury {
+            project_id: project_id.to_string(),
+            address: address.to_string(),
+            name: name.trim().to_string(),
+            max_payout_nanoerg,
+            added_at: self.clock.now(),
+        };
+        let stored = treasury.clone();
+        self.db
+            .run(move |db| db.add_project_treasury(&stored))
+            .await?;
+        Ok(treasury)
+    }
+
+    /// Returns false if the address was not registered for the project.
+    pub async fn remove_project_treasury(
+        &self,
+        project_id: &str,
+        address: &str,
+    ) -> Result<bool> {
+        self.require_project(project_id).await?;
+        self.authorize(project_id, ProjectRole::Admin).await?;
+        let (pid, address) = (project_id.to_string(), address.trim().to_string());
+        self.db
+            .run(move |db| {
+                let registered = db
+                    .get_project_treasuries(&pid)?
+                    .iter()
+                    .any(|t| t.address == address);
+                if registered {
+                    db.remove_project_treasury(&pid, &address)?;
+                }
+                Ok(registered)
+            })
+            .await
+    }
+
+    /// A project's treasuries by payout cap, smallest first and uncapped
+    /// last, the order [`crate::treasury::route_payout`] considers them in.
+    pub async fn project_treasuries(
+        &self,
+        project_id: &str,
+    ) -> Result<Vec<ProjectTreasury>> {
+        self.require_project(project_id).await?;
+        let pid = project_id.to_string();
+        let mut treasuries = self
+            .db
+            .run(move |db| db.get_project_treasuries(&pid))
+            .await?;
+        treasuries.sort_by_key(|t| t.max_payout_nanoerg.unwrap_or(u64::MAX));
+        Ok(treasuries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use tempfile::tempdir;
+
+    fn app() -> (tempfile::TempDir, GitCircles) {
+        let dir = tempdir().unwrap();
+        let db = Database::new(dir.path().to_str().unwrap()).unwrap();
+        (dir, GitCircles::new(db))
+    }
+
+    #[derive(Default)]
+    struct MockSource {
+        prs: Vec<MergedPullRequest>,
+        comments: Vec<crate::types::PullRequestComment>,
+        fail_listing: bool,
+        /// (org, login) memberships
+        org_members: Vec<(&'static str, &'static str)>,
+        calls: AtomicU64,
+    }
+
+    impl PullRequestSource for MockSource {
+        async fn fetch_merged_pull_requests(
+            &self,
+            _owner: &str,
+            _repo: &str,
+            _base_branch: &str,
+            _days_back: Option<u64>,
+            _limits: PageLimits,
+        ) -> Result<Vec<MergedPullRequest>> {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            if self.fail_listing {
+                return Err(GitCirclesError::Auth("rate limit exceeded".into()));
+            }
+            Ok(self.prs.clone())
+        }
+
+        async fn fetch_pull_request_comments(
+            &self,
+            _owner: &str,
+            _repo: &str,
+            _number: u64,
+        ) -> Result<Vec<crate::types::PullRequestComment>> {
+            Ok(self.comments.clone())
+        }
+
+        async fn fetch_pull_request_reviews(
+            &self,
+            _owner: &str,
+            _repo: &str,
+            _number: u64,
+        ) -> Result<Vec<crate::types::PullRequestReview>> {
+            Ok(Vec::new())
+        }
+
+        async fn fetch_pull_request_commit_authors(
+            &self,
+            _owner: &str,
+            _repo: &str,
+            number: u64,
+        ) -> Result<Vec<crate::types::PullRequestCommitAuthor>> {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            Ok(vec![
+                crate::types::PullRequestCommitAuthor {
+                    login: Some("alice".into()),
+                    name: "Alice".into(),
+                    email: "alice@example.com".into(),
+                },
+                crate::types::PullRequestCommitAuthor {
+                    login: None,
+                    name: format!("Helper {}", number),
+                    email: format!(
+                        "{}+helper{}@users.noreply.github.com",
+                        number, number
+                    ),
+                },
+            ])
+        }
+
+        async fn search_merged_pull_requests(
+            &self,
+            _owner: &str,
+            _repo: &str,
+            _base_branch: &str,
+            range: MergedRange,
+            _limits: PageLimits,
+        ) -> Result<Vec<u64>> {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            Ok(self
+                .prs
+                .iter()
+                .filter(|pr| {
+                    let day = pr.merged_at.date_naive();
+                    day >= range.from && day <= range.to
+                })
+                .map(|pr| pr.number)
+                .collect())
+        }
+
+        async fn fetch_pull_request(
+            &self,
+            _owner: &str,
+            _repo: &str,
+            number: u64,
+        ) -> Result<Option<MergedPullRequest>> {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            Ok(self.prs.iter().find(|pr| pr.number == number).cloned())
+        }
+
+        // Odd PRs touch packages/foo, even ones packages/bar
+        async fn fetch_pull_request_files(
+            &self,
+            _owner: &str,
+            _repo: &str,
+            number: u64,
+        ) -> Result<Vec<String>> {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            let package = if number % 2 == 1 { "foo" } else { "bar" };
+            Ok(vec![format!("packages/{}/src/lib.rs", package)])
+        }
+
+        // One star per PR, so tests can tell refreshes apart
+        async fn fetch_repository_metadata(
+            &self,
+            _owner: &str,
+            _repo: &str,
+        ) -> Result<Option<crate::types::RepositoryMetadata>> {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            Ok(Some(crate::types::RepositoryMetadata {
+                description: Some("Widgets".into()),
+                stars: self.prs.len() as u32,
+                forks: 0,
+                language: Some("Rust".into()),
+                license: Some("MIT".into()),
+                fetched_at: Utc::now(),
+            }))
+        }
+
+        async fn is_org_member(&self, org: &str, login: &str) -> Result<bool> {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            Ok(self.org_members.contains(&(org, login)))
+        }
+
+        fn api_calls(&self) -> u64 {
+            self.calls.load(Ordering::Relaxed)
+        }
+    }
+
+    /// Records repository writes; issue numbers count up from 1.
+    #[derive(Default)]
+    struct RecordingRepo {
+        calls: std::sync::Mutex<Vec<String>>,
+    }
+
+    impl RecordingRepo {
+        fn record(&self, call: String) {
+            self.calls.lock().unwrap().push(call);
+        }
+
+        fn calls(&self) -> Vec<String> {
+            self.calls.lock().unwrap().clone()
+        }
+    }
+
+    impl RepoInteraction for RecordingRepo {
+        async fn create_comment(
+            &self,
+            owner: &str,
+            repo: &str,
+            number: u64,
+            _body: &str,
+        ) -> Result<u64> {
+            self.record(format!("comment {}/{}#{}", owner, repo, number));
+            Ok(1)
+        }
+
+        async fn edit_comment(
+            &self,
+            owner: &str,
+            repo: &str,
+            comment_id: u64,
+            _body: &str,
+        ) -> Result<()> {
+            self.record(format!("edit {}/{} {}", owner, repo, comment_id));
+            Ok(())
+        }
+
+        async fn list_comments(
+            &self,
+            _owner: &str,
+            _repo: &str,
+            _number: u64,
+        ) -> Result<Vec<crate::types::PullRequestComment>> {
+            Ok(Vec::new())
+        }
+
+        async fn create_issue(
+            &self,
+            owner: &str,
+            repo: &str,
+            title: &str,
+            _body: &str,
+        ) -> Result<(u64, String)> {
+            self.record(format!("create {}/{} {}", owner, repo, title));
+            Ok((1, "I_1".into()))
+        }
+
+        async fn update_issue(
+            &self,
+            owner: &str,
+            repo: &str,
+            number: u64,
+            _title: &str,
+            _body: &str,
+        ) -> Result<String> {
+            self.record(format!("update {}/{}#{}", owner, repo, number));
+            Ok(format!("I_{}", number))
+        }
+
+        async fn pin_issue(&self, node_id: &str) -> Result<bool> {
+            self.record(format!("pin {}", node_id));
+            Ok(true)
+        }
+
+        async fn add_label(
+            &self,
+            owner: &str,
+            repo: &str,
+            number: u64,
+            label: &str,
+        ) -> Result<()> {
+            self.record(format!("label {}/{}#{} {}", owner, repo, number, label));
+            Ok(())
+        }
+
+        async fn remove_label(
+            &self,
+            owner: &str,
+            repo: &str,
+            number: u64,
+            label: &str,
+        ) -> Result<()> {
+            self.record(format!("unlabel {}/{}#{} {}", owner, repo, number, label));
+            Ok(())
+        }
+
+        async fn create_commit_status(
+            &self,
+            owner: &str,
+            repo: &str,
+            sha: &str,
+            status: &crate::types::CommitStatus,
+        ) -> Result<()> {
+            self.record(format!(
+                "status {}/{} {} {:?} {}",
+                owner,
+                repo,
+                sha,
+                status.state,
+                status.target_url.as_deref().unwrap_or("-")
+            ));
+            Ok(())
+        }
+    }
+
+    fn merged_pr(number: u64) -> MergedPullRequest {
+        MergedPullRequest {
+            head_repository: Some("acme/widgets".into()),
+            head_branch: Some(format!("feature-{}", number)),
+            body: Some(format!("Fixes #{} — détails inside", number)),
+            ..MergedPullRequest::for_test(number, "alice")
+        }
+    }
+
+    fn collect_opts(base_branch: &str) -> CollectOptions {
+        CollectOptions {
+            owner: "acme".into(),
+            repo: "widgets".into(),
+            base_branch: base_branch.into(),
+            days: None,
+            project_id: None,
+            resolve_authors: true,
+            max_body_chars: Some(12),
+            refresh: false,
+            detect_external_merges: false,
+            paths: Vec::new(),
+            merged_range: None,
+            limits: PageLimits::default(),
+            max_prs: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn collect_runs_are_logged_with_failures() {
+        let (_dir, app) = app();
+        let source = MockSource {
+            prs: vec![merged_pr(1), merged_pr(2)],
+            ..Default::default()
+        };
+        app.collect_from(&source, &collect_opts("main"))
+            .await
+            .unwrap();
+
+        let failing = MockSource {
+            fail_listing: true,
+            ..Default::default()
+        };
+        assert!(
+            app.collect_from(&failing, &collect_opts("main"))
+                .await
+                .is_err()
+        );
+
+        let log = app.sync_log("acme/widgets").await.unwrap();
+        assert_eq!(log.len(), 2);
+        assert!(log[0].error.as_deref().unwrap().contains("rate limit"));
+        assert_eq!(log[0].new_prs, 0);
+        // One listing page, a commit lookup per new PR and the repository
+        // metadata
+        assert_eq!(
+            (log[1].pages_fetched, log[1].api_calls, log[1].new_prs),
+            (1, 4, 2)
+        );
+        assert!(log[1].error.is_none());
+    }
+
+    #[tokio::test]
+    async fn max_prs_defers_the_rest_to_the_next_run() {
+        let (_dir, app) = app();
+        let source = MockSource {
+            prs: (1..=5).map(merged_pr).collect(),
+            ..Default::default()
+        };
+        let mut opts = collect_opts("main");
+        opts.max_prs = Some(2);
+
+        let first = app.collect_from(&source, &opts).await.unwrap();
+        assert_eq!(first.new_prs.len(), 2);
+        assert_eq!(first.deferred, 3);
+
+        let second = app.collect_from(&source, &opts).await.unwrap();
+        let numbers: Vec<u64> = second.new_prs.iter().map(|pr| pr.number).collect();
+        assert_eq!(numbers, [3, 4]);
+        assert_eq!(second.deferred, 1);
+    }
+
+    #[tokio::test]
+    async fn date_range_backfills_only_new_prs_in_range() {
+        let (_dir, app) = app();
+        let month = |m: u32| {
+            chrono::NaiveDate::from_ymd_opt(2021, m, 10)
+                .unwrap()
+                .and_hms_opt(12, 0, 0)
+                .unwrap()
+                .and_utc()
+        };
+        let source = MockSource {
+            prs: (1..=4)
+                .map(|n| MergedPullRequest {
+                    merged_at: month(n as u32),
+                    ..merged_pr(n)
+                })
+                .collect(),
+            ..Default::default()
+        };
+        app.collect_from(
+            &MockSource {
+                prs: vec![source.prs[1].clone()],
+                ..Default::default()
+            },
+            &collect_opts("main"),
+        )
+        .await
+        .unwrap();
+
+        let mut opts = collect_opts("main");
+        opts.merged_range = Some(MergedRange {
+            from: month(1).date_naive(),
+            to: month(3).date_naive(),
+        });
+        let outcome = app.collect_from(&source, &opts).await.unwrap();
+        // #2 is stored already and #4 merged after the range
+        let numbers: Vec<u64> =
+            outcome.new_prs.iter().map(|pr| pr.number).collect();
+        assert_eq!(numbers, [1, 3]);
+    }
+
+    #[tokio::test]
+    async fn collect_deduplicates_and_counts() {
+        let (_dir, app) = app();
+        let source = MockSource {
+            prs: vec![merged_pr(1), merged_pr(2)],
+            ..Default::default()
+        };
+
+        let first = app
+            .collect_from(&source, &collect_opts("main"))
+            .await
+            .unwrap();
+        assert_eq!(first.new_prs.len(), 2);
+        assert_eq!(first.repository.total_prs, 2);
+        let stored = app
+            .database()
+            .get_pull_request("acme/widgets", 2)
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.resolved_authors, ["alice", "helper2"]);
+        assert_eq!(stored.body.as_deref(), Some("Fixes #2 — d"));
+
+        let second = app
+            .collect_from(&source, &collect_opts("main"))
+            .await
+            .unwrap();
+        assert!(second.new_prs.is_empty());
+        assert_eq!(second.repository.total_prs, 2);
+        assert!(second.base_branch_change.is_none());
+
+        let moved = app
+            .collect_from(&source, &collect_opts("develop"))
+            .await
+            .unwrap();
+        let change = moved.base_branch_change.unwrap();
+        assert_eq!(
+            (change.old_branch.as_str(), change.new_branch.as_str()),
+            ("main", "develop")
+        );
+        assert_eq!(
+            app.database()
+                .get_base_branch_history("acme/widgets")
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn refresh_updates_edited_prs_and_logs_changes() {
+        let (_dir, app) = app();
+        let mut source = MockSource {
+            prs: vec![merged_pr(1), merged_pr(2)],
+            ..Default::default()
+        };
+        app.collect_from(&source, &collect_opts("main"))
+            .await
+            .unwrap();
+
+        source.prs[0].title = "PR 1, retitled".into();
+        source.prs[0].labels = vec!["bounty".into()];
+        let plain = app
+            .collect_from(&source, &collect_opts("main"))
+            .await
+            .unwrap();
+        assert!(plain.refreshed.is_empty());
+
+        let opts = CollectOptions {
+            refresh: true,
+            ..collect_opts("main")
+        };
+        let refreshed = app.collect_from(&source, &opts).await.unwrap();
+        assert!(refreshed.new_prs.is_empty());
+        let fields: Vec<_> = refreshed
+            .refreshed
+            .iter()
+            .map(|c| c.field.as_str())
+            .collect();
+        assert_eq!(fields, ["title", "labels"]);
+
+        let db = app.database();
+        let stored = db.get_pull_request("acme/widgets", 1).unwrap().unwrap();
+        assert_eq!(stored.title, "PR 1, retitled");
+        assert_eq!(stored.labels, ["bounty"]);
+        // Fields only filled in on first collection are kept
+        assert_eq!(stored.resolved_authors, ["alice", "helper1"]);
+        assert_eq!(
+            db.get_pull_request_changes("acme/widgets", 1)
+                .unwrap()
+                .len(),
+            2
+        );
+        assert_eq!(db.search_pull_requests("retitled", None).unwrap().len(), 1);
+
+        let again = app.collect_from(&source, &opts).await.unwrap();
+        assert!(again.refreshed.is_empty());
+    }
+
+    #[tokio::test]
+    async fn dry_run_collect_reports_without_writing() {
+        let (_dir, app) = app();
+        let mut source = MockSource {
+            prs: vec![merged_pr(1), merged_pr(2)],
+            ..Default::default()
+        };
+        app.collect_from(&source, &collect_opts("main"))
+            .await
+            .unwrap();
+
+        source.prs[0].title = "PR 1, retitled".into();
+        source.prs.push(merged_pr(3));
+        let app = app.with_dry_run(true);
+        let opts = CollectOptions {
+            refresh: true,
+            ..collect_opts("develop")
+        };
+        let outcome = app.collect_from(&source, &opts).await.unwrap();
+        assert_eq!(outcome.new_prs.len(), 1);
+        assert_eq!(outcome.refreshed.len(), 1);
+        assert!(outcome.base_branch_change.is_some());
+        assert_eq!(outcome.repository.total_prs, 3);
+
+        let db = app.database();
+        assert_eq!(db.get_pull_requests("acme/widgets").unwrap().len(), 2);
+        let stored = db.get_pull_request("acme/widgets", 1).unwrap().unwrap();
+        assert_eq!(stored.title, merged_pr(1).title);
+        let repo = db.get_repository("acme", "widgets").unwrap().unwrap();
+        assert_eq!(
+            (repo.total_prs, repo.current_base_branch.as_str()),
+            (2, "main")
+        );
+        assert_eq!(app.sync_log("acme/widgets").await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn collect_stamps_sync_times_from_clock() {
+        let (_dir, app) = app();
+        let clock = Arc::new(crate::clock::ManualClock::new(Utc::now()));
+        let app = app.with_clock(clock.clone());
+        let mut source = MockSource {
+            prs: vec![merged_pr(1)],
+            ..Default::default()
+        };
+
+        let first = app
+            .collect_from(&source, &collect_opts("main"))
+            .await
+            .unwrap();
+        clock.advance(chrono::Duration::days(7));
+        source.prs.push(merged_pr(2));
+        let second = app
+            .collect_from(&source, &collect_opts("main"))
+            .await
+            .unwrap();
+
+        assert_eq!(second.repository.first_sync, first.repository.first_sync);
+        assert_eq!(second.repository.last_sync, Some(clock.now()));
+
+        // Metadata is refreshed on every run
+        let stored = app
+            .database()
+            .get_repository("acme", "widgets")
+            .unwrap()
+            .unwrap();
+        let metadata = stored.metadata.unwrap();
+        assert_eq!((metadata.stars, metadata.fetched_at), (2, clock.now()));
+    }
+
+    #[tokio::test]
+    async fn opt_out_comments_are_recorded_once() {
+        let (_dir, app) = app();
+        let comment =
+            |id: u64, author: &str, body: &str| crate::types::PullRequestComment {
+                id,
+                author: author.into(),
+                body: body.into(),
+                created_at: Utc::now(),
+            };
+        let source = MockSource {
+            comments: vec![
+                comment(1, "alice", "OPT OUT"),
+                comment(2, "bob", "LGTM"),
+                comment(3, "alice", "opt out"),
+                comment(4, "carol", "Opt out"),
+            ],
+            ..Default::default()
+        };
+        app.opt_out("carol").await.unwrap();
+
+        let recorded = app
+            .scan_opt_out_comments(&source, "acme", "widgets", 42)
+            .await
+            .unwrap();
+        assert_eq!(recorded.len(), 1);
+        assert_eq!(
+            recorded[0].source,
+            OptOutSource::Comment {
+                repository: "acme/widgets".into(),
+                number: 42,
+                comment_id: 1,
+            }
+        );
+        assert!(app.is_opted_out("alice").await.unwrap());
+        assert!(!app.is_opted_out("bob").await.unwrap());
+
+        // carol's earlier manual opt-out is kept
+        let carol = app.opt_out("carol").await.unwrap();
+        assert_eq!(carol.source, OptOutSource::Manual);
+        assert!(app.opt_in("carol").await.unwrap());
+        assert!(!app.opt_in("carol").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn announcements_update_the_recorded_issue() {
+        let (_dir, app) = app();
+        let project = app.create_project("Demo", None).await.unwrap();
+        let repo = RecordingRepo::default();
+
+        let first = app
+            .announce_project_via(&repo, &project.id, "acme", "widgets")
+            .await
+            .unwrap();
+        assert!(first.created && first.pinned);
+        let second = app
+            .announce_project_via(&repo, &project.id, "acme", "widgets")
+            .await
+            .unwrap();
+        assert!(!second.created);
+        assert_eq!(second.announcement.issue_number, 1);
+
+        let calls = repo.calls();
+        assert!(calls[0].starts_with("create acme/widgets "));
+        assert_eq!(calls[1..], ["pin I_1", "update acme/widgets#1", "pin I_1"]);
+    }
+
+    #[tokio::test]
+    async fn add_owner_rejects_unknown_role() {
+        let (_dir, app) = app();
+        let project = app.create_project("Demo", None).await.unwrap();
+
+        let bad = app.add_project_owner(&project.id, "alice", "root").await;
+        assert!(bad.is_err());
+        let good = app.add_project_owner(&project.id, "alice", "admin").await;
+        assert!(good.is_ok());
+    }
+
+    #[tokio::test]
+    async fn roles_gate_project_changes() {
+        let (_dir, app) = app();
+        let project = app.create_project("Demo", None).await.unwrap();
+        let as_user =
+            |login| GitCircles::new(app.database().clone()).with_actor(login);
+        let bob = as_user("bob");
+
+        // Only the local operator may add the first owner
+        let denied = bob.add_project_owner(&project.id, "bob", "owner").await;
+        assert!(matches!(denied, Err(GitCirclesError::Forbidden(_))));
+        app.add_project_owner(&project.id, "alice", "owner")
+            .await
+            .unwrap();
+        let denied = bob.add_project_owner(&project.id, "bob", "owner").await;
+        assert!(matches!(denied, Err(GitCirclesError::Forbidden(_))));
+
+        as_user("ALICE")
+            .add_project_owner(&project.id, "bob", "admin")
+            .await
+            .unwrap();
+        let opts = CollectOptions {
+            project_id: Some(project.id.clone()),
+            ..collect_opts("main")
+        };
+        bob.collect_from(&MockSource::default(), &opts)
+            .await
+            .unwrap();
+        assert!(matches!(
+            bob.delete_project(&project.id).await,
+            Err(GitCirclesError::Forbidden(_))
+        ));
+        assert!(matches!(
+            as_user("mallory")
+                .collect_from(&MockSource::default(), &opts)
+                .await,
+            Err(GitCirclesError::Forbidden(_))
+        ));
+
+        // A project created as someone is owned by them
+        let own = as_user("carol").create_project("Own", None).await.unwrap();
+        as_user("Carol")
+            .add_project_owner(&own.id, "dave", "admin")
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn path_claims_limit_a_project_to_matching_prs() {
+        let (_dir, app) = app();
+        let source = MockSource {
+            prs: vec![merged_pr(1), merged_pr(2), merged_pr(3)],
+            ..Default::default()
+        };
+        let mut opts = collect_opts("main");
+        opts.paths = vec!["packages/foo/**".into()];
+        // Claims need a linked project
+        assert!(app.collect_from(&source, &opts).await.is_err());
+
+        let project = app.create_project("Foo team", None).await.unwrap();
+        opts.project_id = Some(project.id.clone());
+        let outcome = app.collect_from(&source, &opts).await.unwrap();
+        let link = app
+            .database()
+            .get_project_link(&project.id, "acme/widgets")
+            .unwrap()
+            .unwrap();
+        assert_eq!(link.paths, ["packages/foo/**"]);
+        assert_eq!(
+            outcome.new_prs[1].changed_files,
+            ["packages/bar/src/lib.rs"]
+        );
+
+        let prs = app
+            .database()
+            .get_pull_requests_for_project(&project.id)
+            .unwrap();
+        let mut numbers: Vec<u64> = prs.iter().map(|pr| pr.number).collect();
+        numbers.sort();
+        assert_eq!(numbers, [1, 3]);
+    }
+
+    #[tokio::test]
+    async fn contributor_lists_filter_a_projects_prs() {
+        let (_dir, app) = app();
+        let project = app.create_project("Demo", None).await.unwrap();
+        let employee = MergedPullRequest {
+            author: "Bob".into(),
+            ..merged_pr(2)
+        };
+        let mut source = MockSource {
+            prs: vec![merged_pr(1), employee],
+            ..Default::default()
+        };
+        let mut opts = collect_opts("main");
+        opts.project_id = Some(project.id.clone());
+        app.collect_from(&source, &opts).await.unwrap();
+
+        let block = [String::from("bob")];
+        app.update_contributor_list(
+            &project.id,
+            ContributorList::Block,
+            &block,
+            false,
+        )
+        .await
+        .unwrap();
+        let db = app.database();
+        let prs = db.get_pull_requests_for_project(&project.id).unwrap();
+        assert_eq!(prs.iter().map(|pr| pr.number).collect::<Vec<_>>(), [1]);
+        assert_eq!(db.get_project_counters(&project.id).unwrap().prs, 1);
+
+        // New PRs by blocked logins are not counted either
+        source.prs.push(MergedPullRequest {
+            author: "bob".into(),
+            ..merged_pr(3)
+        });
+        app.collect_from(&source, &opts).await.unwrap();
+        assert_eq!(db.get_project_counters(&project.id).unwrap().prs, 1);
+
+        // An allowlist leaves out everyone else
+        let allow = [String::from("carol")];
+        app.update_contributor_list(
+            &project.id,
+            ContributorList::Allow,
+            &allow,
+            false,
+        )
+        .await
+        .unwrap();
+        assert_eq!(db.get_project_counters(&project.id).unwrap().prs, 0);
+
+        app.update_contributor_list(
+            &project.id,
+            ContributorList::Allow,
+            &allow,
+            true,
+        )
+        .await
+        .unwrap();
+        let project = app
+            .update_contributor_list(
+                &project.id,
+                ContributorList::Block,
+                &block,
+                true,
+            )
+            .await
+            .unwrap();
+        assert!(project.blocked_contributors.is_empty());
+        assert_eq!(db.get_project_counters(&project.id).unwrap().prs, 3);
+    }
+
+    #[tokio::test]
+    async fn org_members_are_excluded_with_a_reason() {
+        let (_dir, app) = app();
+        let project = app.create_project("Demo", None).await.unwrap();
+        let by = |author: &str, number| MergedPullRequest {
+            author: author.into(),
+            ..merged_pr(number)
+        };
+        let mut source = MockSource {
+            prs: vec![merged_pr(1), by("bob", 2)],
+            org_members: vec![("acme", "bob"), ("acme", "dave")],
+            ..Default::default()
+        };
+        let mut opts = collect_opts("main");
+        opts.project_id = Some(project.id.clone());
+        app.collect_from(&source, &opts).await.unwrap();
+
+        // Authors of stored PRs are checked when the org is added
+        let orgs = [String::from("acme")];
+        let (project, found) = app
+            .update_excluded_orgs_via(&source, &project.id, &orgs, false)
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(
+            (found[0].login.as_str(), found[0].org.as_str()),
+            ("bob", "acme")
+        );
+        let db = app.database();
+        assert_eq!(db.get_project_counters(&project.id).unwrap().prs, 1);
+
+        // New authors are checked as their PRs are collected
+        source.prs.push(by("dave", 3));
+        source.prs.push(by("carol", 4));
+        let outcome = app.collect_from(&source, &opts).await.unwrap();
+        let excluded: Vec<_> = outcome
+            .org_exclusions
+            .iter()
+            .map(|(pid, e)| (pid.as_str(), e.login.as_str()))
+            .collect();
+        assert_eq!(excluded, [(project.id.as_str(), "dave")]);
+        assert_eq!(db.get_project_counters(&project.id).unwrap().prs, 2);
+        let stored = db.get_project(&project.id).unwrap().unwrap();
+        assert!(stored.org_exclusion("Dave").is_some());
+
+        // Dropping the org drops its exclusions
+        let (project, _) = app
+            .update_excluded_orgs_via(&source, &project.id, &orgs, true)
+            .await
+            .unwrap();
+        assert!(project.org_exclusions.is_empty());
+        assert_eq!(db.get_project_counters(&project.id).unwrap().prs, 4);
+    }
+
+    #[tokio::test]
+    async fn merge_commits_import_as_pull_requests_once() {
+        let clone = tempdir().unwrap();
+        let git = |args: &[&str]| {
+            let status = std::process::Command::new("git")
+                .args([
+                    "-c",
+                    "user.name=Maintainer",
+                    "-c",
+                    "user.email=m@example.com",
+                ])
+                .arg("-C")
+                .arg(clone.path())
+                .args(args)
+                .output()
+                .unwrap();
+            assert!(status.status.success(), "{:?}", status);
+        };
+        git(&["init", "-q", "-b", "main"]);
+        git(&["remote", "add", "origin", "git@github.com:acme/widgets.git"]);
+        git(&["commit", "-q", "--allow-empty", "-m", "Initial commit"]);
+        for (branch, author, message) in [
+            (
+                "feature",
+                "Carol <5+carol@users.noreply.github.com>",
+                "Merge pull request #7 from carol/feature\n\nAdd feature",
+            ),
+            ("docs", "Dan <dan@example.com>", "Merge branch 'docs'"),
+        ] {
+            git(&["checkout", "-q", "-b", branch]);
+            git(&[
+                "commit",
+                "-q",
+                "--allow-empty",
+                "--author",
+                author,
+                "-m",
+                "Work",
+            ]);
+            git(&["checkout", "-q", "main"]);
+            git(&["merge", "-q", "--no-ff", branch, "-m", message]);
+        }
+
+        let (_dir, app) = app();
+        let outcome = app.import_git_log(clone.path(), None, None).await.unwrap();
+        assert_eq!(
+            (
+                outcome.repository.owner.as_str(),
+                outcome.repository.name.as_str()
+            ),
+            ("acme", "widgets")
+        );
+        assert_eq!(outcome.repository.total_prs, 2);
+        let mut prs: Vec<_> = outcome
+            .imported
+            .iter()
+            .map(|pr| (pr.author.as_str(), pr.title.as_str(), pr.imported))
+            .collect();
+        prs.sort();
+        assert_eq!(
+            prs,
+            [
+                ("Dan", "Merge branch 'docs'", true),
+                ("carol", "Add feature", true)
+            ]
+        );
+        let stored = app.database().get_pull_request("acme/widgets", 7).unwrap();
+        assert_eq!(stored.unwrap().head_branch.as_deref(), Some("feature"));
+
+        let again = app.import_git_log(clone.path(), None, None).await.unwrap();
+        assert!(again.imported.is_empty());
+        assert_eq!((again.skipped, again.repository.total_prs), (2, 2));
+    }
+
+    #[tokio::test]
+    async fn contributor_stats_are_kept_per_login() {
+        let (_dir, app) = app();
+        let source = MockSource {
+            prs: vec![merged_pr(1), merged_pr(2)],
+            ..Default::default()
+        };
+        app.collect_from(&source, &collect_opts("main"))
+            .await
+            .unwrap();
+
+        let profile = app.contributor_profile("alice").await.unwrap();
+        let stats = profile.stats.unwrap();
+        assert_eq!(stats.prs, 2);
+        assert_eq!(stats.repositories, ["acme/widgets"]);
+        assert!(stats.first_contribution <= stats.last_contribution);
+        assert_eq!(profile.standing, PayoutStanding::MissingWallet);
+        // Co-authors resolved from commits count too
+        let helper = app.contributor_profile("helper2").await.unwrap();
+        assert_eq!(helper.stats.unwrap().prs, 1);
+        assert!(app.contributor_profile("nobody").await.is_err());
+
+        // Rebuilding from the stored PRs gives the same totals
+        let db = app.database();
+        let before = db.get_contributor_stats("github", "alice").unwrap();
+        assert_eq!(db.rebuild_contributor_stats().unwrap(), 3);
+        assert_eq!(db.get_contributor_stats("github", "alice").unwrap(), before);
+    }
+
+    #[tokio::test]
+    async fn forgetting_a_login_keeps_counts_under_a_pseudonym() {
+        let (_dir, app) = app();
+        let project = app.create_project("Demo", None).await.unwrap();
+        app.update_contributor_list(
+            &project.id,
+            ContributorList::Allow,
+            &[String::from("alice")],
+            false,
+        )
+        .await
+        .unwrap();
+        let source = MockSource {
+            prs: vec![merged_pr(1), merged_pr(2)],
+            ..Default::default()
+        };
+        let mut opts = collect_opts("main");
+        opts.project_id = Some(project.id.clone());
+        app.collect_from(&source, &opts).await.unwrap();
+
+        let db = app.database().clone();
+        let address = WalletAddress::try_from(
+            "9hUzb5RvSgDqJdtyCN9Ke496Yy63mpcUJKbRq4swzQ5EQKgygKT",
+        )
+        .unwrap();
+        let source = WalletSource::Manual {
+            restored_from: Utc::now(),
+            actor: None,
+        };
+        let mut batch = db.keyspace.batch();
+        db.upsert_user_wallet_batch(
+            &mut batch,
+            &UserWallet {
+                login: "alice".into(),
+                platform: "github".into(),
+                address: address.clone(),
+                source: source.clone(),
+                synced_at: Utc::now(),
+            },
+        )
+        .unwrap();
+        db.append_wallet_history_batch(
+            &mut batch,
+            &WalletHistoryEntry {
+                login: "alice".into(),
+                platform: "github".into(),
+                address: address.clone(),
+                source,
+                recorded_at: Utc::now(),
+            },
+        )
+        .unwrap();
+        db.replace_wallet_link_batch(
+            &mut batch,
+            &WalletLoginLink {
+                wallet: address,
+                platform: "github".into(),
+                login: "alice".into(),
+                linked_at: Utc::now(),
+            },
+        )
+        .unwrap();
+        batch.commit().unwrap();
+        app.opt_out("alice").await.unwrap();
+        app.set_address_label(
+            "9hUzb5RvSgDqJdtyCN9Ke496Yy63mpcUJKbRq4swzQ5EQKgygKT",
+            "Alice",
+            AddressKind::Contributor,
+            None,
+        )
+        .await
+        .unwrap();
+        let mut paused = db.get_project(&project.id).unwrap().unwrap();
+        paused.pauses.push(ProjectPause {
+            paused_at: Utc::now(),
+            resumed_at: Some(Utc::now()),
+            reason: None,
+            paused_by: Some("Alice".into()),
+        });
+        db.upsert_project(&paused).unwrap();
+        db.set_pull_request_approval(&PullRequestApproval {
+            project_id: project.id.clone(),
+            repository: "acme/widgets".into(),
+            number: 1,
+            approved_by: Some("Alice".into()),
+            source: ApprovalSource::Manual,
+            approved_at: Utc::now(),
+        })
+        .unwrap();
+
+        // A dry run reports without deleting anything
+        let app = app.with_dry_run(true);
+        assert!(app.forget_login("alice").await.unwrap().wallet);
+        assert!(db.get_user_wallet("github", "alice").unwrap().is_some());
+        let app = app.with_dry_run(false);
+
+        let report = app.forget_login("alice").await.unwrap();
+        assert!(report.wallet && report.contributor_stats);
+        assert_eq!((report.wallet_history, report.wallet_links), (1, 1));
+        assert_eq!(report.contributor_records, 2);
+        assert_eq!((report.pull_requests, report.projects), (2, 1));
+        assert_eq!(report.approvals, 1);
+        assert!(report.pseudonym.starts_with("forgotten-"));
+
+        assert!(db.get_user_wallet("github", "alice").unwrap().is_none());
+        assert!(db.get_wallet_history("github", "alice").unwrap().is_empty());
+        assert!(db.list_wallet_links().unwrap().is_empty());
+        assert!(db.list_address_book().unwrap().is_empty());
+        assert!(
+            db.get_contributor_opt_out("github", "alice")
+                .unwrap()
+                .is_none()
+        );
+        assert!(app.contributor_profile("alice").await.is_err());
+        assert!(db.search_pull_requests("alice", None).unwrap().is_empty());
+
+        // The allow list names the pseudonym, so the PRs still count
+        assert_eq!(db.get_project_counters(&project.id).unwrap().prs, 2);
+        assert_eq!(db.rebuild_project_counters(&project.id).unwrap().prs, 2);
+        let pseudonym = app.contributor_profile(&report.pseudonym).await.unwrap();
+        assert_eq!(pseudonym.stats.unwrap().prs, 2);
+        let prs = db.get_pull_requests("acme/widgets").unwrap();
+        assert!(prs.iter().all(|pr| pr.author == report.pseudonym));
+        let project = db.get_project(&project.id).unwrap().unwrap();
+        assert_eq!(project.pauses[0].paused_by, Some(report.pseudonym.clone()));
+        let approval = db
+            .get_pull_request_approval(&project.id, "acme/widgets", 1)
+            .unwrap()
+            .unwrap();
+        assert_eq!(approval.approved_by, Some(report.pseudonym.clone()));
+
+        assert!(app.forget_login("alice").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn forgetting_a_login_ignores_its_case() {
+        let (_dir, app) = app();
+        let mut thanks = merged_pr(2);
+        thanks.author = "bob".into();
+        thanks.body = Some("Thanks @ALICE for the review".into());
+        let source = MockSource {
+            prs: vec![merged_pr(1), thanks],
+            ..Default::default()
+        };
+        let mut opts = collect_opts("main");
+        opts.max_body_chars = None;
+        app.collect_from(&source, &opts).await.unwrap();
+
+        let db = app.database().clone();
+        let address = WalletAddress::try_from(
+            "9hUzb5RvSgDqJdtyCN9Ke496Yy63mpcUJKbRq4swzQ5EQKgygKT",
+        )
+        .unwrap();
+        let source = WalletSource::Manual {
+            restored_from: Utc::now(),
+            actor: None,
+        };
+        db.upsert_user_wallet(&UserWallet {
+            login: "alice".into(),
+            platform: "github".into(),
+            address: address.clone(),
+            source: source.clone(),
+            synced_at: Utc::now(),
+        })
+        .unwrap();
+        db.append_wallet_history(&WalletHistoryEntry {
+            login: "alice".into(),
+            platform: "github".into(),
+            address: address.clone(),
+            source,
+            recorded_at: Utc::now(),
+        })
+        .unwrap();
+        db.replace_wallet_link(&WalletLoginLink {
+            wallet: address,
+            platform: "github".into(),
+            login: "alice".into(),
+            linked_at: Utc::now(),
+        })
+        .unwrap();
+        app.opt_out("alice").await.unwrap();
+
+        let report = app.forget_login("Alice").await.unwrap();
+        assert!(report.wallet && report.contributor_stats);
+        assert_eq!((report.wallet_history, report.wallet_links), (1, 1));
+        assert_eq!(report.contributor_records, 1);
+        assert_eq!(report.pull_requests, 2);
+
+        assert!(db.get_user_wallet("github", "alice").unwrap().is_none());
+        assert!(db.get_wallet_history("github", "alice").unwrap().is_empty());
+        assert!(db.list_wallet_links().unwrap().is_empty());
+        assert!(
+            db.get_contributor_opt_out("github", "alice")
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            db.get_contributor_stats("github", "alice")
+                .unwrap()
+                .is_none()
+        );
+        let thanks = db.get_pull_request("acme/widgets", 2).unwrap().unwrap();
+        assert_eq!(thanks.author, "bob");
+        assert_eq!(
+            thanks.body,
+            Some(format!("Thanks @{} for the review", report.pseudonym))
+        );
+        assert!(db.search_pull_requests("alice", None).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn paused_projects_schedule_nothing() {
+        let (_dir, app) = app();
+        let clock = Arc::new(crate::clock::ManualClock::new(Utc::now()));
+        let app = app.with_clock(clock.clone()).with_labels(LabelsConfig {
+            enabled: true,
+            ..Default::default()
+        });
+        let project = app.create_project("Demo", None).await.unwrap();
+        assert!(app.resume_project(&project.id).await.is_err());
+        app.link_repository(&project.id, "acme/widgets", None)
+            .await
+            .unwrap();
+        let source = MockSource {
+            prs: vec![merged_pr(1)],
+            ..Default::default()
+        };
+        app.collect_from(&source, &collect_opts("main"))
+            .await
+            .unwrap();
+
+        clock.advance(Duration::hours(1));
+        app.pause_project(&project.id, Some("treasury migration".into()))
+            .await
+            .unwrap();
+        assert!(app.pause_project(&project.id, None).await.is_err());
+
+        clock.advance(Duration::hours(5));
+        let paused = app.require_project(&project.id).await.unwrap();
+        assert_eq!(
+            paused.current_pause().unwrap().reason.as_deref(),
+            Some("treasury migration")
+        );
+        let repo = RecordingRepo::default();
+        assert!(app.sync_labels_via(&repo, None).await.unwrap().is_empty());
+
+        let resumed = app.resume_project(&project.id).await.unwrap();
+        assert!(resumed.current_pause().is_none());
+        assert_eq!(resumed.pauses[0].resumed_at, Some(clock.now()));
+        app.sync_labels_via(&repo, None).await.unwrap();
+        assert_eq!(repo.calls(), ["label acme/widgets#1 gitcircles:scheduled"]);
+    }
+
+    #[tokio::test]
+    async fn approval_gate_waits_for_an_owner() {
+        let (_dir, app) = app();
+        let project = app.create_project("Demo", None).await.unwrap();
+        app.add_project_owner(&project.id, "maint", "admin")
+            .await
+            .unwrap();
+        app.link_repository(&project.id, "acme/widgets", None)
+            .await
+            .unwrap();
+        let comment = |id: u64, author: &str| crate::types::PullRequestComment {
+            id,
+            author: author.into(),
+            body: "Looks good\nGitCircles APPROVE".into(),
+            created_at: Utc::now(),
+        };
+        let source = MockSource {
+            prs: vec![merged_pr(1), merged_pr(2)],
+            comments: vec![comment(10, "mallory"), comment(11, "Maint")],
+            ..Default::default()
+        };
+        app.collect_from(&source, &collect_opts("main"))
+            .await
+            .unwrap();
+        assert!(app.pending_approvals(&project.id).await.unwrap().is_empty());
+
+        app.set_approval_required(&project.id, true).await.unwrap();
+        assert_eq!(app.pending_approvals(&project.id).await.unwrap().len(), 2);
+
+        let outsider = GitCircles::new(app.database().clone()).with_actor("bob");
+        let denied = outsider
+            .approve_pull_request(&project.id, "acme/widgets", 1)
+            .await;
+        assert!(matches!(denied, Err(GitCirclesError::Forbidden(_))));
+        assert!(
+            app.approve_pull_request(&project.id, "acme/widgets", 99)
+                .await
+                .is_err()
+        );
+        let manual = app
+            .approve_pull_request(&project.id, "acme/widgets", 1)
+            .await
+            .unwrap();
+        assert_eq!(manual.source, ApprovalSource::Manual);
+
+        // A dry run finds the approval without recording it
+        let dry = GitCircles::new(app.database().clone())
+            .with_dry_run(true)
+            .scan_approval_comments(&source, &project.id)
+            .await
+            .unwrap();
+        assert_eq!(dry.len(), 1);
+        assert_eq!(app.pending_approvals(&project.id).await.unwrap().len(), 1);
+
+        // Only PR 2 is still pending, and mallory's comment doesn't count
+        let scanned = app
+            .scan_approval_comments(&source, &project.id)
+            .await
+            .unwrap();
+        assert_eq!(scanned.len(), 1);
+        assert_eq!(scanned[0].number, 2);
+        assert_eq!(scanned[0].approved_by.as_deref(), Some("Maint"));
+        assert_eq!(
+            scanned[0].source,
+            ApprovalSource::Comment { comment_id: 11 }
+        );
+        assert!(app.pending_approvals(&project.id).await.unwrap().is_empty());
+        let status = app.project_status(&project.id).await.unwrap();
+        assert_eq!(status.awaiting_approval, 0);
+    }
+
+    #[tokio::test]
+    async fn pipeline_labels_follow_pr_state() {
+        let (_dir, app) = app();
+        let app = app.with_labels(LabelsConfig {
+            enabled: true,
+            ..Default::default()
+        });
+        let project = app.create_project("Demo", None).await.unwrap();
+        app.link_repository(&project.id, "acme/widgets", None)
+            .await
+            .unwrap();
+        let mut by_bob = merged_pr(2);
+        by_bob.author = "bob".into();
+        let source = MockSource {
+            prs: vec![merged_pr(1), by_bob],
+            ..Default::default()
+        };
+        app.collect_from(&source, &collect_opts("main"))
+            .await
+            .unwrap();
+        app.opt_out("bob").await.unwrap();
+
+        let repo = RecordingRepo::default();
+        let applied = app.sync_labels_via(&repo, None).await.unwrap();
+        assert_eq!(applied.len(), 2);
+        assert_eq!(
+            repo.calls(),
+            [
+                "label acme/widgets#1 gitcircles:scheduled",
+                "label acme/widgets#2 gitcircles:stopped"
+            ]
+        );
+        assert!(app.sync_labels_via(&repo, None).await.unwrap().is_empty());
+
+        // Opting in again moves the PR back, taking the old label off
+        app.opt_in("bob").await.unwrap();
+        app.sync_labels_via(&repo, None).await.unwrap();
+        assert_eq!(
+            repo.calls()[2..],
+            [
+                "unlabel acme/widgets#2 gitcircles:stopped",
+                "label acme/widgets#2 gitcircles:scheduled"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn commit_statuses_follow_pr_state() {
+        let (_dir, app) = app();
+        let app = app.with_statuses(StatusesConfig {
+            enabled: true,
+            details_url: "https://rewards.example.org/{repository}/{number}".into(),
+            ..Default::default()
+        });
+        let project = app.create_project("Demo", None).await.unwrap();
+        app.link_repository(&project.id, "acme/widgets", None)
+            .await
+            .unwrap();
+        let source = MockSource {
+            prs: vec![merged_pr(1)],
+            ..Default::default()
+        };
+        app.collect_from(&source, &collect_opts("main"))
+            .await
+            .unwrap();
+
+        let repo = RecordingRepo::default();
+        let published = app.sync_statuses_via(&repo, None).await.unwrap();
+        assert_eq!(published[0].state, PipelineLabel::Scheduled);
+        assert!(app.sync_statuses_via(&repo, None).await.unwrap().is_empty());
+
+        app.opt_out("alice").await.unwrap();
+        app.sync_statuses_via(&repo, None).await.unwrap();
+        assert_eq!(
+            repo.calls(),
+            [
+                "status acme/widgets 0123456789abcdef Pending https://rewards.example.org/acme/widgets/1",
+                "status acme/widgets 0123456789abcdef Success https://rewards.example.org/acme/widgets/1",
+            ]
+        );
+
+        // Labels and statuses are configured separately
+        assert!(matches!(
+            app.sync_labels_via(&repo, None).await,
+            Err(GitCirclesError::Config(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn erg_price_is_cached_and_falls_back_while_recent() {
+        struct Feed(std::sync::Mutex<Vec<Result<f64>>>);
+        impl crate::price::PriceSource for Feed {
+            fn describe(&self) -> String {
+                "test feed".into()
+            }
+            async fn fetch_usd_per_erg(&self) -> Result<f64> {
+                self.0.lock().unwrap().remove(0)
+            }
+        }
+        let down = || Err(GitCirclesError::Price("feed is down".into()));
+        let feed = Feed(std::sync::Mutex::new(vec![
+            Ok(1.25),
+            Ok(1.5),
+            down(),
+            down(),
+        ]));
+
+        let (_dir, app) = app();
+        let clock = Arc::new(crate::clock::ManualClock::new(Utc::now()));
+        let app = app.with_clock(clock.clone());
+        let first = app.erg_price_via(&feed, false).await.unwrap();
+        assert!(first.fresh);
+        assert_eq!(first.quote.usd_per_erg, 1.25);
+
+        clock.advance(Duration::minutes(1));
+        let cached = app.erg_price_via(&feed, false).await.unwrap();
+        assert!(!cached.fresh);
+        assert_eq!(cached.quote, first.quote);
+        let refreshed = app.erg_price_via(&feed, true).await.unwrap();
+        assert_eq!(refreshed.quote.usd_per_erg, 1.5);
+
+        // Past the cache time the feed is asked again; while it is down
+        // the last price stands in until it is too old
+        clock.advance(Duration::minutes(10));
+        let fallback = app.erg_price_via(&feed, false).await.unwrap();
+        assert_eq!(fallback.quote.usd_per_erg, 1.5);
+        assert!(fallback.fetch_error.unwrap().contains("feed is down"));
+        clock.advance(Duration::hours(1));
+        assert!(matches!(
+            app.erg_price_via(&feed, false).await,
+            Err(GitCirclesError::Price(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn address_book_names_addresses() {
+        let (_dir, app) = app();
+        let treasury = "9hUzb5RvSgDqJdtyCN9Ke496Yy63mpcUJKbRq4swzQ5EQKgygKT";
+        let exchange = "9fZZEJVg7z29LARcVTffLKaxBW19dL1wiX34zSnE2rrWfMd2qcz";
+        app.set_address_label(
+            exchange,
+            "Exchange hot wallet",
+            AddressKind::Exchange,
+            None,
+        )
+        .await
+        .unwrap();
+        app.set_address_label(
+            treasury,
+            "Old treasury",
+            AddressKind::Treasury,
+            None,
+        )
+        .await
+        .unwrap();
+        let entry = app
+            .set_address_label(
+                treasury,
+                " Project treasury ",
+                AddressKind::Treasury,
+                Some("multisig"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(entry.label(), "Project treasury (treasury)");
+
+        let book = app.address_book().await.unwrap();
+        let names: Vec<&str> = book.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["Project treasury", "Exchange hot wallet"]);
+        assert_eq!(book[0].note.as_deref(), Some("multisig"));
+
+        assert!(
+            app.set_address_label(
+                "not-an-address",
+                "x",
+                AddressKind::Treasury,
+                None
+            )
+            .await
+            .is_err()
+        );
+        assert!(
+            app.set_address_label(treasury, "  ", AddressKind::Treasury, None)
+                .await
+                .is_err()
+        );
+
+        assert!(app.remove_address_label(exchange).await.unwrap());
+        assert!(!app.remove_address_label(exchange).await.unwrap());
+        assert_eq!(app.address_book().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn treasury_sync_stops_at_stored_transactions() {
+        struct Chain {
+            txs: std::sync::Mutex<Vec<TreasuryTransaction>>,
+            offsets: std::sync::Mutex<Vec<u64>>,
+            down: std::sync::atomic::AtomicBool,
+        }
+        impl TransactionSource for Chain {
+            fn describe(&self) -> String {
+                "test chain".into()
+            }
+            async fn fetch_transactions(
+                &self,
+                _address: &str,
+                offset: u64,
+                limit: u64,
+            ) -> Result<crate::treasury::TransactionPage> {
+                if self.down.load(std::sync::atomic::Ordering::SeqCst) {
+                    return Err(GitCirclesError::Explorer(
+                        "explorer is down".into(),
+                    ));
+                }
+                self.offsets.lock().unwrap().push(offset);
+                let txs = self.txs.lock().unwrap();
+                Ok(crate::treasury::TransactionPage {
+                    items: txs
+                        .iter()
+                        .skip(offset as usize)
+                        .take(limit as usize)
+                        .cloned()
+                        .collect(),
+                    total: txs.len() as u64,
+                })
+            }
+        }
+        let contributor = "9hUzb5RvSgDqJdtyCN9Ke496Yy63mpcUJKbRq4swzQ5EQKgygKT";
+        let tx = |id: &str, height: u64, received: u64, spent: u64, to: &str| {
+            TreasuryTransaction {
+                address: "T".into(),
+                tx_id: id.into(),
+                inclusion_height: height,
+                timestamp: Utc::now(),
+                received,
+                spent,
+                counterparties: vec![to.into()],
+            }
+        };
+        let chain = Chain {
+            txs: std::sync::Mutex::new(vec![
+                tx("payout-2", 3, 5_000, 7_000, contributor),
+                tx("payout-1", 2, 7_000, 10_000, "elsewhere"),
+                tx("funding", 1, 10_000, 0, "donor"),
+            ]),
+            offsets: Default::default(),
+            down: Default::default(),
+        };
+
+        let (_dir, app) = app();
+        assert!(matches!(
+            app.treasuries(None).await,
+            Err(GitCirclesError::Config(_))
+        ));
+        let clock = Arc::new(crate::clock::ManualClock::new(Utc::now()));
+        let app = app.with_clock(clock.clone()).with_treasury(TreasuryConfig {
+            address: "T".into(),
+            page_size: 2,
+            ..TreasuryConfig::default()
+        });
+
+        let first = app.sync_treasury_via(&chain, "T").await.unwrap();
+        assert_eq!(first.new.len(), 3);
+        assert_eq!(*chain.offsets.lock().unwrap(), [0, 2]);
+
+        // Later syncs stop at the newest stored transaction
+        chain
+            .txs
+            .lock()
+            .unwrap()
+            .insert(0, tx("funding-2", 4, 2_000, 0, "donor"));
+        chain.offsets.lock().unwrap().clear();
+        let second = app.sync_treasury_via(&chain, "T").await.unwrap();
+        assert_eq!(second.new.len(), 1);
+        assert_eq!(second.new[0].tx_id, "funding-2");
+        assert_eq!(*chain.offsets.lock().unwrap(), [0]);
+
+        app.set_address_label(contributor, "Alice", AddressKind::Contributor, None)
+            .await
+            .unwrap();
+        let history = app.treasury_history_via(&chain, "T", false).await.unwrap();
+        assert_eq!(*chain.offsets.lock().unwrap(), [0], "recent sync reused");
+        let ids: Vec<&str> = history
+            .transactions
+            .iter()
+            .map(|t| t.tx_id.as_str())
+            .collect();
+        assert_eq!(ids, ["funding-2", "payout-2", "payout-1", "funding"]);
+        assert_eq!(history.names[contributor], "Alice (contributor)");
+        assert_eq!((history.funded(), history.paid_out()), (12_000, 5_000));
+
+        chain.down.store(true, std::sync::atomic::Ordering::SeqCst);
+        clock.advance(Duration::hours(1));
+        let stale = app.treasury_history_via(&chain, "T", false).await.unwrap();
+        assert!(stale.sync_error.unwrap().contains("explorer is down"));
+        assert_eq!(stale.transactions.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn project_treasuries_route_payouts_by_amount() {
+        let (_dir, app) = app();
+        let project = app.create_project("Demo", None).await.unwrap();
+        let hot = "9hUzb5RvSgDqJdtyCN9Ke496Yy63mpcUJKbRq4swzQ5EQKgygKT";
+        let multisig = "9fZZEJVg7z29LARcVTffLKaxBW19dL1wiX34zSnE2rrWfMd2qcz";
+        app.add_project_treasury(&project.id, multisig, "multi-sig", None)
+            .await
+            .unwrap();
+        app.add_project_treasury(
+            &project.id,
+            hot,
+            "hot wallet",
+            Some(50_000_000_000),
+        )
+        .await
+        .unwrap();
+        assert!(
+            app.add_project_treasury(&project.id, "not base58!", "x", None)
+                .await
+                .is_err()
+        );
+
+        let treasuries = app.project_treasuries(&project.id).await.unwrap();
+        let names: Vec<&str> = treasuries.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["hot wallet", "multi-sig"]);
+        let route = |amount| {
+            crate::treasury::route_payout(&treasuries, amount)
+                .map(|t| t.name.as_str())
+        };
+        assert_eq!(route(50_000_000_000), Some("hot wallet"));
+        assert_eq!(route(50_000_000_001), Some("multi-sig"));
+
+        let addresses: Vec<String> = app
+            .treasuries(Some(&project.id))
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|t| t.address)
+            .collect();
+        assert_eq!(addresses, [hot, multisig]);
+
+        assert!(
+            app.remove_project_treasury(&project.id, multisig)
+                .await
+                .unwrap()
+        );
+        assert!(
+            !app.remove_project_treasury(&project.id, multisig)
+                .await
+                .unwrap()
+        );
+        let treasuries = app.project_treasuries(&project.id).await.unwrap();
+        assert_eq!(
+            crate::treasury::route_payout(&treasuries, 60_000_000_000),
+            None
+        );
+
+        app.delete_project(&project.id).await.unwrap();
+        let db = app.database();
+        assert!(db.get_project_treasuries(&project.id).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn delete_project_refuses_linked_repositories() {
+        let (_dir, app) = app();
+        let project = app.create_project("Demo", None).await.unwrap();
+        app.link_repository(&project.id, "o/r", None).await.unwrap();
+
+        assert!(app.delete_project(&project.id).await.is_err());
+        assert!(app.database().get_project(&project.id).unwrap().is_some());
+
+        app.unlink_repository(&project.id, "o/r").await.unwrap();
+        app.delete_project(&project.id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn one_repository_feeds_several_projects() {
+        let (_dir, app) = app();
+        let foo = app.create_project("Foo", None).await.unwrap();
+        let bar = app.create_project("Bar", None).await.unwrap();
+        let source = MockSource {
+            prs: vec![merged_pr(1), merged_pr(2)],
+            ..Default::default()
+        };
+        let mut opts = collect_opts("main");
+        opts.project_id = Some(foo.id.clone());
+        app.collect_from(&source, &opts).await.unwrap();
+        let claim = ["packages/bar/**".to_string()];
+        app.link_repository(&bar.id, "acme/widgets", Some(&claim))
+            .await
+            .unwrap();
+
+        let db = app.database();
+        assert_eq!(
+            db.get_links_for_repository("acme/widgets").unwrap().len(),
+            2
+        );
+        assert_eq!(db.list_repositories_for_project(&bar.id).unwrap().len(), 1);
+        assert_eq!(db.get_pull_requests_for_project(&foo.id).unwrap().len(), 2);
+        // Collected before the claim, so no file lists to match
+        assert!(
+            db.get_pull_requests_for_project(&bar.id)
+                .unwrap()
+                .is_empty()
+        );
+
+        // Re-linking without paths keeps the claim
+        let link = app.link_repository(&bar.id, "acme/widgets", None).await;
+        assert_eq!(link.unwrap().paths, claim);
+    }
+
+    #[tokio::test]
+    async fn project_counters_follow_collects_and_links() {
+        let (_dir, app) = app();
+        let foo = app.create_project("Foo", None).await.unwrap();
+        let bar = app.create_project("Bar", None).await.unwrap();
+        let mut source = MockSource {
+            prs: vec![merged_pr(1), merged_pr(2)],
+            ..Default::default()
+        };
+        let mut opts = collect_opts("main");
+        opts.project_id = Some(foo.id.clone());
+        app.collect_from(&source, &opts).await.unwrap();
+        let claim = ["packages/bar/**".to_string()];
+        app.link_repository(&bar.id, "acme/widgets", Some(&claim))
+            .await
+            .unwrap();
+
+        let db = app.database();
+        assert_eq!(db.get_project_counters(&foo.id).unwrap().prs, 2);
+        assert_eq!(db.get_project_counters(&bar.id).unwrap().prs, 0);
+
+        // New PRs carry file lists, and only the even one touches bar
+        source.prs.extend([merged_pr(3), merged_pr(4)]);
+        app.collect_from(&source, &opts).await.unwrap();
+        assert_eq!(db.get_project_counters(&foo.id).unwrap().prs, 4);
+        assert_eq!(db.get_project_counters(&bar.id).unwrap().prs, 1);
+
+        app.unlink_repository(&bar.id, "acme/widgets")
+            .await
+            .unwrap();
+        assert_eq!(db.get_project_counters(&bar.id).unwrap().prs, 0);
+    }
+
+    #[tokio::test]
+    async fn project_status_counts_prs_and_payout_readiness() {
+        let (_dir, app) = app();
+        let project = app.create_project("Demo", None).await.unwrap();
+        let source = MockSource {
+            prs: vec![merged_pr(1), merged_pr(2)],
+            ..Default::default()
+        };
+        let mut opts = collect_opts("main");
+        opts.project_id = Some(project.id.clone());
+        app.collect_from(&source, &opts).await.unwrap();
+
+        app.database()
+            .upsert_user_wallet(&UserWallet {
+                login: "alice".into(),
+                platform: "github".into(),
+                address: WalletAddress::try_from(
+                    "9hUzb5RvSgDqJdtyCN9Ke496Yy63mpcUJKbRq4swzQ5EQKgygKT",
+                )
+                .unwrap(),
+                source: WalletSource::GitHubProfileRepo {
+                    login: "alice".into(),
+                    branch: "main".into(),
+                    ref_kind: Default::default(),
+                    commit: None,
+                },
+                synced_at: Utc::now(),
+            })
+            .unwrap();
+        app.opt_out("helper1").await.unwrap();
+
+        let status = app.project_status(&project.id).await.unwrap();
+        assert_eq!(status.total_prs, 2);
+        assert_eq!(status.prs_by_repository["acme/widgets"], 2);
+        // alice plus the co-authors helper1 and helper2
+        assert_eq!(status.contributors, 3);
+        assert_eq!(
+            status.readiness,
+            PayoutReadiness {
+                ready: 1,
+                pending_rotation: 0,
+                missing_wallet: 1,
+                opted_out: 1,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn rollback_restores_an_earlier_address_at_once() {
+        let (_dir, app) = app();
+        let start = Utc::now();
+        let clock = Arc::new(crate::clock::ManualClock::new(start));
+        let app = app.with_clock(clock.clone()).with_actor("ops");
+        let good = WalletAddress::try_from(
+            "9hUzb5RvSgDqJdtyCN9Ke496Yy63mpcUJKbRq4swzQ5EQKgygKT",
+        )
+        .unwrap();
+        let hijacked = WalletAddress::try_from(
+            "9fZZEJVg7z29LARcVTffLKaxBW19dL1wiX34zSnE2rrWfMd2qcz",
+        )
+        .unwrap();
+        let source = WalletSource::GitHubProfileRepo {
+            login: "alice".into(),
+            branch: "main".into(),
+            ref_kind: Default::default(),
+            commit: None,
+        };
+        let db = app.database();
+        for (address, at) in [
+            (&good, start - Duration::days(30)),
+            (&hijacked, start - Duration::hours(2)),
+        ] {
+            db.append_wallet_history(&WalletHistoryEntry {
+                login: "alice".into(),
+                platform: "github".into(),
+                address: address.clone(),
+                source: source.clone(),
+                recorded_at: at,
+            })
+            .unwrap();
+        }
+        db.upsert_user_wallet(&UserWallet {
+            login: "alice".into(),
+            platform: "github".into(),
+            address: hijacked.clone(),
+            source: source.clone(),
+            synced_at: start - Duration::hours(2),
+        })
+        .unwrap();
+        db.replace_wallet_link(&WalletLoginLink {
+            wallet: hijacked.clone(),
+            platform: "github".into(),
+            login: "alice".into(),
+            linked_at: start - Duration::hours(2),
+        })
+        .unwrap();
+        let mut batch = db.keyspace.batch();
+        db.set_wallet_rotation_batch(
+            &mut batch,
+            &WalletRotation {
+                platform: "github".into(),
+                login: "alice".into(),
+                previous: good.clone(),
+                current: hijacked.clone(),
+                detected_at: start - Duration::hours(2),
+                effective_at: start + Duration::days(3),
+            },
+        )
+        .unwrap();
+        batch.commit().unwrap();
+
+        assert!(matches!(
+            app.rollback_wallet("alice", start - Duration::days(31))
+                .await,
+            Err(GitCirclesError::WalletNotFound(_))
+        ));
+
+        let result = app
+            .rollback_wallet("alice", start - Duration::days(1))
+            .await
+            .unwrap();
+        assert!(result.changed);
+        assert_eq!(result.previous, Some(hijacked.clone()));
+        assert_eq!(
+            app.payout_address("alice").await.unwrap(),
+            Some(good.clone())
+        );
+        // A lookup of the hijacked address no longer finds alice
+        assert!(
+            db.get_logins_for_wallet(&hijacked, "github")
+                .unwrap()
+                .is_empty()
+        );
+        let links = db.get_logins_for_wallet(&good, "github").unwrap();
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].login, "alice");
+
+        let history = db.get_wallet_history("github", "alice").unwrap();
+        let transitions = crate::wallet::wallet_transitions(&history, start);
+        assert_eq!(transitions.len(), 3);
+        assert_eq!(transitions[1].held_for, Duration::hours(2));
+        assert_eq!(transitions[2].from, Some(hijacked));
+        assert_eq!(
+            transitions[2].source,
+            WalletSource::Manual {
+                restored_from: start - Duration::days(30),
+                actor: Some("ops".into()),
+            }
+        );
+
+        // Rolling back to the address already in use changes nothing
+        let again = app.rollback_wallet("alice", start).await.unwrap();
+        assert!(!again.changed);
+    }
+
+    #[test]
+    fn github_client_is_required_for_remote_calls() {
+        let (_dir, app) = app();
+        assert!(matches!(app.github(), Err(GitCirclesError::Auth(_))));
+    }
+}
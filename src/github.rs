@@ -1,18 +1,33 @@
-use chrono::Utc;
-use indicatif::{ProgressBar, ProgressStyle};
+use chrono::{DateTime, NaiveDate, Utc};
 use octocrab::{Octocrab, Page};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Duration;
 
+use crate::config::HttpConfig;
+use crate::output::{Icon, spinner};
 use crate::types::{
-    GitCirclesError, MergedPullRequest, Result, WalletAddress, WalletFetchOutcome,
+    CommitState, CommitStatus, ContributorPreferences, GitCirclesError,
+    IssueReference, MergedPullRequest, PayoutSchedule, ProfileInitOutcome,
+    ProfileRef, PullRequestComment, PullRequestCommitAuthor, PullRequestReview,
+    RefKind, RepositoryMetadata, Result, WalletAddress, WalletFetchOutcome,
 };
 
 // Minimal, local constants for wallet fetch path
-const PROFILE_REPO_NAME: &str = "gitcircles-profile";
-const WALLET_FILE_PATH: &str = "P2PK.pub";
+pub(crate) const PROFILE_REPO_NAME: &str = "gitcircles-profile";
+pub(crate) const WALLET_FILE_PATH: &str = "P2PK.pub";
+pub(crate) const PREFERENCES_FILE_PATH: &str = "gitcircles.toml";
+pub(crate) const RAW_CONTENT_BASE: &str = "https://raw.githubusercontent.com";
+const NANOERG_PER_ERG: f64 = 1_000_000_000.0;
+/// Tries per raw profile-file download before giving up as unavailable
+const RAW_FETCH_ATTEMPTS: u32 = 3;
+const RAW_RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
 
 pub struct GitHubClient {
     octocrab: Octocrab,
+    http: reqwest::Client,
+    raw_base: String,
+    raw_retry_delay: Duration,
+    api_calls: AtomicU64,
 }
 
 // Trait to allow testing Wallet fetch logic without real network
@@ -21,43 +36,770 @@ pub trait WalletFetcher: Send + Sync {
     fn fetch_wallet_address(
         &self,
         login: &str,
+        wallet_ref: Option<&ProfileRef>,
     ) -> impl std::future::Future<Output = Result<Option<WalletFetchOutcome>>> + Send;
 }
 
+/// Inclusive range of merge dates for `collect --from/--to`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MergedRange {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+}
+
+/// Bounds on a paginated PR listing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageLimits {
+    /// Items per request, 1 to 100
+    pub per_page: u8,
+    /// Stop after this many pages, even if more remain
+    pub max_pages: Option<u32>,
+}
+
+impl Default for PageLimits {
+    fn default() -> Self {
+        Self {
+            per_page: 100,
+            max_pages: None,
+        }
+    }
+}
+
+// Trait covering everything the collect pipeline reads about pull requests.
+// Implemented by GitHubClient; tests can feed the pipeline mock data.
+pub trait PullRequestSource: Send + Sync {
+    fn fetch_merged_pull_requests(
+        &self,
+        owner: &str,
+        repo: &str,
+        base_branch: &str,
+        days_back: Option<u64>,
+        limits: PageLimits,
+    ) -> impl std::future::Future<Output = Result<Vec<MergedPullRequest>>> + Send;
+
+    /// Also return closed PRs whose head commit reached the base branch
+    /// without GitHub recording a merge. Sources that can't tell return the
+    /// merged PRs only.
+    fn fetch_pull_requests_with_external_merges(
+        &self,
+        owner: &str,
+        repo: &str,
+        base_branch: &str,
+        days_back: Option<u64>,
+        limits: PageLimits,
+    ) -> impl std::future::Future<Output = Result<Vec<MergedPullRequest>>> + Send
+    {
+        self.fetch_merged_pull_requests(owner, repo, base_branch, days_back, limits)
+    }
+
+    /// Numbers of the PRs merged into `base_branch` within `range`.
+    fn search_merged_pull_requests(
+        &self,
+        owner: &str,
+        repo: &str,
+        base_branch: &str,
+        range: MergedRange,
+        limits: PageLimits,
+    ) -> impl std::future::Future<Output = Result<Vec<u64>>> + Send;
+
+    /// One PR, or `None` if it was not merged.
+    fn fetch_pull_request(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+    ) -> impl std::future::Future<Output = Result<Option<MergedPullRequest>>> + Send;
+
+    fn fetch_pull_request_comments(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+    ) -> impl std::future::Future<Output = Result<Vec<PullRequestComment>>> + Send;
+
+    fn fetch_pull_request_reviews(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+    ) -> impl std::future::Future<Output = Result<Vec<PullRequestReview>>> + Send;
+
+    fn fetch_pull_request_commit_authors(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+    ) -> impl std::future::Future<Output = Result<Vec<PullRequestCommitAuthor>>> + Send;
+
+    /// Paths the PR changed; renamed files are listed under both names.
+    fn fetch_pull_request_files(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+    ) -> impl std::future::Future<Output = Result<Vec<String>>> + Send;
+
+    /// Stars, forks, language, license and description of a repository.
+    /// Sources without repository data return `None`.
+    fn fetch_repository_metadata(
+        &self,
+        _owner: &str,
+        _repo: &str,
+    ) -> impl std::future::Future<Output = Result<Option<RepositoryMetadata>>> + Send
+    {
+        async { Ok(None) }
+    }
+
+    /// Whether `login` is a member of the `org` organization. Private
+    /// memberships are only visible to tokens of the organization's
+    /// members; sources without membership data report none.
+    fn is_org_member(
+        &self,
+        _org: &str,
+        _login: &str,
+    ) -> impl std::future::Future<Output = Result<bool>> + Send {
+        async { Ok(false) }
+    }
+
+    /// API requests made so far, for sync statistics. Sources that don't
+    /// track requests report 0.
+    fn api_calls(&self) -> u64 {
+        0
+    }
+}
+
+// Trait covering the writes GitCircles makes to repositories: comments,
+// issues and labels. Implemented by GitHubClient; tests can record calls.
+pub trait RepoInteraction: Send + Sync {
+    /// Comment on an issue or pull request, returning the comment id.
+    fn create_comment(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+        body: &str,
+    ) -> impl std::future::Future<Output = Result<u64>> + Send;
+
+    fn edit_comment(
+        &self,
+        owner: &str,
+        repo: &str,
+        comment_id: u64,
+        body: &str,
+    ) -> impl std::future::Future<Output = Result<()>> + Send;
+
+    fn list_comments(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+    ) -> impl std::future::Future<Output = Result<Vec<PullRequestComment>>> + Send;
+
+    /// Open an issue, returning its number and GraphQL node id.
+    fn create_issue(
+        &self,
+        owner: &str,
+        repo: &str,
+        title: &str,
+        body: &str,
+    ) -> impl std::future::Future<Output = Result<(u64, String)>> + Send;
+
+    /// Replace an issue's title and body, returning its GraphQL node id.
+    fn update_issue(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+        title: &str,
+        body: &str,
+    ) -> impl std::future::Future<Output = Result<String>> + Send;
+
+    /// Pin an issue; false when the repository refuses more pins.
+    fn pin_issue(
+        &self,
+        node_id: &str,
+    ) -> impl std::future::Future<Output = Result<bool>> + Send;
+
+    fn add_label(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+        label: &str,
+    ) -> impl std::future::Future<Output = Result<()>> + Send;
+
+    /// Take a label off; a label that isn't on the PR is a not-found error.
+    fn remove_label(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+        label: &str,
+    ) -> impl std::future::Future<Output = Result<()>> + Send;
+
+    fn create_commit_status(
+        &self,
+        owner: &str,
+        repo: &str,
+        sha: &str,
+        status: &CommitStatus,
+    ) -> impl std::future::Future<Output = Result<()>> + Send;
+}
+
 impl GitHubClient {
     pub fn new(token: &str) -> Result<Self> {
-        let octocrab = Octocrab::builder()
+        Self::with_http_config(token, &HttpConfig::default())
+    }
+
+    pub fn with_http_config(token: &str, config: &HttpConfig) -> Result<Self> {
+        let octocrab = Self::build_octocrab(token, config, None)?;
+
+        Ok(Self {
+            octocrab,
+            http: Self::raw_client(config)?,
+            raw_base: RAW_CONTENT_BASE.to_string(),
+            raw_retry_delay: RAW_RETRY_BASE_DELAY,
+            api_calls: AtomicU64::new(0),
+        })
+    }
+
+    /// Point the client at alternative API and raw-content hosts, e.g. a
+    /// GitHub Enterprise instance or the replay server used in tests.
+    pub fn with_base_urls(
+        token: &str,
+        api_base: &str,
+        raw_base: &str,
+    ) -> Result<Self> {
+        let config = HttpConfig::default();
+        let octocrab = Self::build_octocrab(token, &config, Some(api_base))?;
+
+        Ok(Self {
+            octocrab,
+            http: Self::raw_client(&config)?,
+            raw_base: raw_base.trim_end_matches('/').to_string(),
+            raw_retry_delay: RAW_RETRY_BASE_DELAY,
+            api_calls: AtomicU64::new(0),
+        })
+    }
+
+    /// First backoff delay between raw download retries; it doubles after
+    /// each failed attempt.
+    pub fn with_raw_retry_delay(mut self, delay: Duration) -> Self {
+        self.raw_retry_delay = delay;
+        self
+    }
+
+    fn build_octocrab(
+        token: &str,
+        config: &HttpConfig,
+        api_base: Option<&str>,
+    ) -> Result<Octocrab> {
+        let mut builder = Octocrab::builder()
             .personal_token(token.to_string())
-            .build()?;
+            .set_connect_timeout(Some(config.connect_timeout()))
+            .set_read_timeout(Some(config.timeout()))
+            .set_write_timeout(Some(config.timeout()));
+        if let Some(base) = api_base {
+            builder = builder.base_uri(base)?;
+        }
+        Ok(builder.build()?)
+    }
+
+    fn raw_client(config: &HttpConfig) -> Result<reqwest::Client> {
+        reqwest::Client::builder()
+            .timeout(config.timeout())
+            .connect_timeout(config.connect_timeout())
+            .pool_max_idle_per_host(config.pool_max_idle_per_host)
+            .pool_idle_timeout(config.pool_idle_timeout())
+            .build()
+            .map_err(|e| {
+                GitCirclesError::Config(format!("Cannot build HTTP client: {}", e))
+            })
+    }
+
+    // Every REST/GraphQL request goes through here so it is counted
+    fn api(&self) -> &Octocrab {
+        self.api_calls.fetch_add(1, Ordering::Relaxed);
+        &self.octocrab
+    }
+
+    /// API requests made by this client so far. Raw-content downloads are
+    /// not counted; they don't use the API rate limit.
+    pub fn api_calls(&self) -> u64 {
+        self.api_calls.load(Ordering::Relaxed)
+    }
+
+    // Convert an API error. Rate-limit rejections become RateLimited, with
+    // the core quota read from /rate_limit, which doesn't count against it.
+    async fn api_error(&self, err: octocrab::Error) -> GitCirclesError {
+        let octocrab::Error::GitHub { source, .. } = &err else {
+            return err.into();
+        };
+        let message = source.message.to_lowercase();
+        if !matches!(source.status_code.as_u16(), 403 | 429)
+            || !message.contains("rate limit")
+        {
+            return err.into();
+        }
+        if message.contains("secondary rate limit") {
+            return GitCirclesError::RateLimited {
+                reset_at: None,
+                remaining: None,
+            };
+        }
+        match self.octocrab.ratelimit().get().await {
+            Ok(limits) => GitCirclesError::RateLimited {
+                reset_at: chrono::DateTime::from_timestamp(
+                    limits.resources.core.reset as i64,
+                    0,
+                ),
+                remaining: Some(limits.resources.core.remaining as u64),
+            },
+            Err(_) => GitCirclesError::RateLimited {
+                reset_at: None,
+                remaining: Some(0),
+            },
+        }
+    }
+
+    async fn checked<T>(&self, result: octocrab::Result<T>) -> Result<T> {
+        match result {
+            Ok(value) => Ok(value),
+            Err(e) => Err(self.api_error(e).await),
+        }
+    }
 
-        Ok(Self { octocrab })
+    // GET a raw profile file. 5xx responses, timeouts and connection errors
+    // are retried with exponential backoff and end up as
+    // TemporarilyUnavailable; every other response is returned as is.
+    async fn get_raw(
+        &self,
+        url: &str,
+        repo_full: &str,
+    ) -> Result<reqwest::Response> {
+        let mut delay = self.raw_retry_delay;
+        let mut attempt = 1;
+        loop {
+            let problem = match self.http.get(url).send().await {
+                Ok(response) if response.status().is_server_error() => {
+                    format!("HTTP status {}", response.status().as_u16())
+                }
+                Ok(response) => return Ok(response),
+                Err(e) if e.is_builder() => {
+                    return Err(GitCirclesError::InvalidRepo(
+                        repo_full.to_string(),
+                    ));
+                }
+                Err(e) if e.is_timeout() => "request timed out".to_string(),
+                Err(e) => format!("request failed: {}", e),
+            };
+            if attempt >= RAW_FETCH_ATTEMPTS {
+                return Err(GitCirclesError::TemporarilyUnavailable(
+                    repo_full.to_string(),
+                    problem,
+                ));
+            }
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+            attempt += 1;
+        }
     }
 
     /// Test if the GitHub token is valid by fetching the authenticated user
     pub async fn test_token(&self) -> Result<String> {
-        let user = self.octocrab.current().user().await?;
+        let user = self.checked(self.api().current().user().await).await?;
         Ok(user.login)
     }
 
+    /// Scopes of a classic token, from the `X-OAuth-Scopes` header of
+    /// `GET /user`. `None` for fine-grained tokens, which don't send it.
+    pub async fn token_scopes(&self) -> Result<Option<Vec<String>>> {
+        let response = self.checked(self.api()._get("/user").await).await?;
+        let response = self
+            .checked(octocrab::map_github_error(response).await)
+            .await?;
+        Ok(response
+            .headers()
+            .get("x-oauth-scopes")
+            .and_then(|value| value.to_str().ok())
+            .map(|scopes| {
+                scopes
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|scope| !scope.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            }))
+    }
+
+    pub async fn fetch_account_created_at(
+        &self,
+        login: &str,
+    ) -> Result<chrono::DateTime<Utc>> {
+        let profile = self
+            .checked(self.api().users(login).profile().await)
+            .await?;
+        Ok(profile.created_at)
+    }
+
+    /// Open an issue, returning its number and GraphQL node id.
+    pub async fn create_issue(
+        &self,
+        owner: &str,
+        repo: &str,
+        title: &str,
+        body: &str,
+    ) -> Result<(u64, String)> {
+        let issue = self
+            .checked(
+                self.api()
+                    .issues(owner, repo)
+                    .create(title)
+                    .body(body)
+                    .send()
+                    .await,
+            )
+            .await?;
+        Ok((issue.number, issue.node_id))
+    }
+
+    /// Replace an issue's title and body, returning its GraphQL node id.
+    pub async fn update_issue(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+        title: &str,
+        body: &str,
+    ) -> Result<String> {
+        let issue = self
+            .checked(
+                self.api()
+                    .issues(owner, repo)
+                    .update(number)
+                    .title(title)
+                    .body(body)
+                    .send()
+                    .await,
+            )
+            .await?;
+        Ok(issue.node_id)
+    }
+
+    /// Comment on an issue or pull request, returning the comment id.
+    pub async fn create_comment(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+        body: &str,
+    ) -> Result<u64> {
+        let comment = self
+            .checked(
+                self.api()
+                    .issues(owner, repo)
+                    .create_comment(number, body)
+                    .await,
+            )
+            .await?;
+        Ok(comment.id.into_inner())
+    }
+
+    pub async fn edit_comment(
+        &self,
+        owner: &str,
+        repo: &str,
+        comment_id: u64,
+        body: &str,
+    ) -> Result<()> {
+        self.checked(
+            self.api()
+                .issues(owner, repo)
+                .update_comment(comment_id.into(), body)
+                .await,
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Add a label to an issue or pull request, creating the label if the
+    /// repository doesn't have it yet.
+    pub async fn add_label(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+        label: &str,
+    ) -> Result<()> {
+        self.checked(
+            self.api()
+                .issues(owner, repo)
+                .add_labels(number, &[label.to_string()])
+                .await,
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn remove_label(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+        label: &str,
+    ) -> Result<()> {
+        self.checked(
+            self.api()
+                .issues(owner, repo)
+                .remove_label(number, label)
+                .await,
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Publish a commit status. A newer status with the same context
+    /// replaces the old one in GitHub's UI.
+    pub async fn create_commit_status(
+        &self,
+        owner: &str,
+        repo: &str,
+        sha: &str,
+        status: &CommitStatus,
+    ) -> Result<()> {
+        let state = match status.state {
+            CommitState::Pending => octocrab::models::StatusState::Pending,
+            CommitState::Success => octocrab::models::StatusState::Success,
+            CommitState::Failure => octocrab::models::StatusState::Failure,
+            CommitState::Error => octocrab::models::StatusState::Error,
+        };
+        let repos = self.api().repos(owner, repo);
+        let mut request = repos
+            .create_status(sha.to_string(), state)
+            .context(status.context.clone())
+            .description(status.description.clone());
+        if let Some(url) = &status.target_url {
+            request = request.target(url.clone());
+        }
+        self.checked(request.send().await).await?;
+        Ok(())
+    }
+
+    /// Pin an issue to its repository. Pinning is only exposed through
+    /// GraphQL; returns false when GitHub refuses, e.g. because the
+    /// repository already has three pinned issues.
+    pub async fn pin_issue(&self, node_id: &str) -> Result<bool> {
+        let response: serde_json::Value = self
+            .checked(
+                self.api()
+                    .graphql(&serde_json::json!({
+                        "query": "mutation($id: ID!) { pinIssue(input: {issueId: $id}) { issue { number } } }",
+                        "variables": { "id": node_id },
+                    }))
+                    .await,
+            )
+            .await?;
+        Ok(response.get("errors").is_none())
+    }
+
     pub async fn fetch_merged_pull_requests(
         &self,
         owner: &str,
         repo: &str,
         base_branch: &str,
         days_back: Option<u64>,
+        limits: PageLimits,
     ) -> Result<Vec<MergedPullRequest>> {
-        let pb = ProgressBar::new_spinner();
-        pb.set_style(
-            ProgressStyle::with_template("{spinner:.green} {msg}")
-                .unwrap()
-                .tick_strings(&["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"]),
+        let (merged_prs, _) = self
+            .list_closed_pull_requests(
+                owner,
+                repo,
+                base_branch,
+                days_back,
+                limits,
+                false,
+            )
+            .await?;
+        Ok(merged_prs)
+    }
+
+    /// Merged PRs plus closed PRs that GitHub doesn't show as merged but
+    /// whose head commit is on the base branch, e.g. because the branch was
+    /// merged and pushed outside GitHub. Costs one compare request per such
+    /// closed PR.
+    pub async fn fetch_pull_requests_with_external_merges(
+        &self,
+        owner: &str,
+        repo: &str,
+        base_branch: &str,
+        days_back: Option<u64>,
+        limits: PageLimits,
+    ) -> Result<Vec<MergedPullRequest>> {
+        let (mut prs, candidates) = self
+            .list_closed_pull_requests(
+                owner,
+                repo,
+                base_branch,
+                days_back,
+                limits,
+                true,
+            )
+            .await?;
+        for candidate in candidates {
+            if self
+                .commit_on_branch(
+                    owner,
+                    repo,
+                    base_branch,
+                    &candidate.merge_commit_sha,
+                )
+                .await?
+            {
+                prs.push(candidate);
+            }
+        }
+        Ok(prs)
+    }
+
+    /// Numbers of PRs merged into `base_branch` within `range`, found with
+    /// the Search API instead of paging through every closed PR. Search
+    /// returns at most 1000 results per query, so larger ranges are split
+    /// in half until each part fits.
+    pub async fn search_merged_pull_requests(
+        &self,
+        owner: &str,
+        repo: &str,
+        base_branch: &str,
+        range: MergedRange,
+        limits: PageLimits,
+    ) -> Result<Vec<u64>> {
+        const SEARCH_RESULT_CAP: u64 = 1000;
+
+        let pb = spinner("Searching merged pull requests...");
+        let per_page = limits.per_page.clamp(1, 100);
+        let mut numbers = Vec::new();
+        let mut pages = 0u32;
+        let mut ranges = vec![(range.from, range.to)];
+
+        'ranges: while let Some((from, to)) = ranges.pop() {
+            let query = format!(
+                "repo:{}/{} is:pr is:merged base:{} merged:{}..{}",
+                owner, repo, base_branch, from, to
+            );
+            let mut page = 1u32;
+            loop {
+                if limits.max_pages.is_some_and(|max| pages >= max) {
+                    break 'ranges;
+                }
+                pb.set_message(format!(
+                    "Searching {}..{}, page {}",
+                    from, to, page
+                ));
+                let results = self
+                    .checked(
+                        self.api()
+                            .search()
+                            .issues_and_pull_requests(&query)
+                            .per_page(per_page)
+                            .page(page)
+                            .send()
+                            .await,
+                    )
+                    .await?;
+                pages += 1;
+
+                if page == 1
+                    && results.total_count.unwrap_or(0) > SEARCH_RESULT_CAP
+                    && from < to
+                {
+                    let mid = from + (to - from) / 2;
+                    ranges.push((mid.succ_opt().unwrap_or(to), to));
+                    ranges.push((from, mid));
+                    continue 'ranges;
+                }
+
+                let len = results.items.len();
+                numbers.extend(results.items.into_iter().map(|issue| issue.number));
+                if len < per_page as usize {
+                    break;
+                }
+                page += 1;
+            }
+        }
+
+        numbers.sort_unstable();
+        numbers.dedup();
+        pb.finish_with_message(format!(
+            "{} Found {} merged PRs",
+            Icon::Ok,
+            numbers.len()
+        ));
+        Ok(numbers)
+    }
+
+    pub async fn fetch_pull_request(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+    ) -> Result<Option<MergedPullRequest>> {
+        let pr = self
+            .checked(self.api().pulls(owner, repo).get(number).await)
+            .await?;
+        Ok(pr.merged_at.map(|merged_at| {
+            merged_pull_request(owner, repo, pr, merged_at, false)
+        }))
+    }
+
+    // Whether `sha` is reachable from `branch`, i.e. the branch is level
+    // with or ahead of it. Unknown commits count as not merged.
+    async fn commit_on_branch(
+        &self,
+        owner: &str,
+        repo: &str,
+        branch: &str,
+        sha: &str,
+    ) -> Result<bool> {
+        #[derive(serde::Deserialize)]
+        struct Comparison {
+            status: String,
+        }
+
+        let route = format!(
+            "/repos/{}/{}/compare/{}...{}?per_page=1",
+            owner, repo, branch, sha
         );
-        pb.set_message("Fetching pull requests from GitHub...");
-        pb.enable_steady_tick(Duration::from_millis(100));
+        match self.api().get::<Comparison, _, ()>(route, None).await {
+            Ok(comparison) => {
+                Ok(matches!(comparison.status.as_str(), "behind" | "identical"))
+            }
+            Err(octocrab::Error::GitHub { source, .. })
+                if source.message.contains("Not Found") =>
+            {
+                Ok(false)
+            }
+            Err(e) => Err(self.api_error(e).await),
+        }
+    }
+
+    // Closed PRs against `base_branch`: the merged ones and, if asked for,
+    // the unmerged ones as merge candidates, dated by when they were closed
+    // and with the head commit as their merge commit.
+    async fn list_closed_pull_requests(
+        &self,
+        owner: &str,
+        repo: &str,
+        base_branch: &str,
+        days_back: Option<u64>,
+        limits: PageLimits,
+        include_unmerged: bool,
+    ) -> Result<(Vec<MergedPullRequest>, Vec<MergedPullRequest>)> {
+        let pb = spinner("Fetching pull requests from GitHub...");
 
         let mut merged_prs = Vec::new();
+        let mut candidates = Vec::new();
         let mut page = 1u32;
-        let per_page = 100u8;
+        let per_page = limits.per_page.clamp(1, 100);
 
         // Calculate cutoff date if days_back is specified
         let cutoff_date =
@@ -67,14 +809,17 @@ impl GitHubClient {
             pb.set_message(format!("Fetching page {} from GitHub API...", page));
 
             let pulls_page: Page<octocrab::models::pulls::PullRequest> = self
-                .octocrab
-                .pulls(owner, repo)
-                .list()
-                .state(octocrab::params::State::Closed)
-                .base(base_branch)
-                .per_page(per_page)
-                .page(page)
-                .send()
+                .checked(
+                    self.api()
+                        .pulls(owner, repo)
+                        .list()
+                        .state(octocrab::params::State::Closed)
+                        .base(base_branch)
+                        .per_page(per_page)
+                        .page(page)
+                        .send()
+                        .await,
+                )
                 .await?;
 
             let pulls = pulls_page.items;
@@ -90,8 +835,13 @@ impl GitHubClient {
             let pulls_len = pulls.len();
 
             for pr in pulls {
-                // Only include merged PRs
-                if let Some(merged_at) = pr.merged_at {
+                let externally_merged = pr.merged_at.is_none();
+                let merged_at = match (pr.merged_at, pr.closed_at) {
+                    (Some(merged_at), _) => Some(merged_at),
+                    (None, Some(closed_at)) if include_unmerged => Some(closed_at),
+                    _ => None,
+                };
+                if let Some(merged_at) = merged_at {
                     // Check if within date range if specified
                     if let Some(cutoff) = cutoff_date
                         && merged_at < cutoff
@@ -99,133 +849,1044 @@ impl GitHubClient {
                         continue;
                     }
 
-                    let merged_pr = MergedPullRequest {
-                        number: pr.number,
-                        title: pr.title.unwrap_or_else(|| "No title".to_string()),
-                        author: pr
-                            .user
-                            .map(|u| u.login)
-                            .unwrap_or_else(|| "unknown".to_string()),
+                    let merged_pr = merged_pull_request(
+                        owner,
+                        repo,
+                        pr,
                         merged_at,
-                        base_branch: pr.base.ref_field,
-                        merge_commit_sha: pr
-                            .merge_commit_sha
-                            .unwrap_or_else(|| "unknown".to_string()),
-                        repository: format!("{}/{}", owner, repo),
-                    };
-
-                    merged_prs.push(merged_pr);
+                        externally_merged,
+                    );
+
+                    if externally_merged {
+                        candidates.push(merged_pr);
+                    } else {
+                        merged_prs.push(merged_pr);
+                    }
                 }
             }
 
             // If this page wasn't full, we've reached the end
-            if pulls_len < per_page as usize {
+            if pulls_len < per_page as usize
+                || limits.max_pages.is_some_and(|max| page >= max)
+            {
                 break;
             }
 
             page += 1;
         }
 
-        pb.finish_with_message(format!("✓ Found {} merged PRs", merged_prs.len()));
-        Ok(merged_prs)
+        pb.finish_with_message(format!(
+            "{} Found {} merged PRs",
+            Icon::Ok,
+            merged_prs.len()
+        ));
+        Ok((merged_prs, candidates))
     }
 
-    pub async fn fetch_wallet_address(
+    /// Conversation comments on a pull request, oldest first.
+    pub async fn fetch_pull_request_comments(
         &self,
-        login: &str,
-    ) -> Result<Option<WalletFetchOutcome>> {
-        let repo_full = format!("{}/{}", login, PROFILE_REPO_NAME);
+        owner: &str,
+        repo: &str,
+        number: u64,
+    ) -> Result<Vec<PullRequestComment>> {
+        let mut comments = Vec::new();
+        let mut page = 1u32;
 
-        // Step 1: Get repository metadata to find default branch
-        let repo_result = self.octocrab.repos(login, PROFILE_REPO_NAME).get().await;
+        loop {
+            let items = self
+                .checked(
+                    self.api()
+                        .issues(owner, repo)
+                        .list_comments(number)
+                        .per_page(100u8)
+                        .page(page)
+                        .send()
+                        .await,
+                )
+                .await?
+                .items;
+            let len = items.len();
 
-        let default_branch = match repo_result {
-            Ok(repo) => repo.default_branch.unwrap_or_else(|| "main".to_string()),
-            Err(octocrab::Error::GitHub { source, .. })
-                if source.message.contains("Not Found") =>
-            {
-                // Repository doesn't exist - not an error, just means no wallet configured
-                return Ok(None);
+            comments.extend(items.into_iter().map(|c| PullRequestComment {
+                id: c.id.into_inner(),
+                author: c.user.login,
+                body: c.body.unwrap_or_default(),
+                created_at: c.created_at,
+            }));
+
+            if len < 100 {
+                break;
             }
-            Err(e) => return Err(e.into()),
-        };
+            page += 1;
+        }
 
-        // Step 2: Build branch list with deduplication
-        let branches = compute_branch_priority(default_branch);
+        Ok(comments)
+    }
 
-        // Step 3: Try fetching raw file from each branch
-        let client = reqwest::Client::new();
+    pub async fn fetch_pull_request_reviews(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+    ) -> Result<Vec<PullRequestReview>> {
+        use octocrab::models::pulls::ReviewState;
 
-        for branch in &branches {
-            let url = format!(
-                "https://raw.githubusercontent.com/{}/{}/{}/{}",
-                login, PROFILE_REPO_NAME, branch, WALLET_FILE_PATH
-            );
+        let mut reviews = Vec::new();
+        let mut page = 1u32;
 
-            match client.get(&url).send().await {
-                Ok(response) => {
-                    match response.status().as_u16() {
-                        200 => {
-                            // Step 4: Validate wallet address
-                            let content = response.text().await.map_err(|e| {
-                                GitCirclesError::WalletInvalidFormat(
-                                    repo_full.clone(),
-                                    format!("Failed to read response: {}", e),
-                                )
-                            })?;
-
-                            let trimmed = content.trim();
-                            let address = WalletAddress::try_from(trimmed)?;
-
-                            // Step 5: Return outcome
-                            return Ok(Some(WalletFetchOutcome {
-                                address,
-                                branch: branch.clone(),
-                            }));
-                        }
-                        404 => {
-                            // File not found on this branch, try next
-                            continue;
-                        }
-                        401 | 403 => {
-                            // Authentication/permission issue - repo must be public
-                            return Err(GitCirclesError::RepoNotAccessible(
-                                repo_full,
-                            ));
-                        }
-                        status => {
-                            // Other unexpected errors
-                            return Err(GitCirclesError::WalletInvalidFormat(
-                                repo_full,
-                                format!("Unexpected HTTP status: {}", status),
-                            ));
-                        }
+        loop {
+            let items = self
+                .checked(
+                    self.api()
+                        .pulls(owner, repo)
+                        .list_reviews(number)
+                        .per_page(100u8)
+                        .page(page)
+                        .send()
+                        .await,
+                )
+                .await?
+                .items;
+            let len = items.len();
+
+            reviews.extend(items.into_iter().map(|r| {
+                PullRequestReview {
+                    id: r.id.into_inner(),
+                    author: r
+                        .user
+                        .map(|u| u.login)
+                        .unwrap_or_else(|| "unknown".to_string()),
+                    state: match r.state {
+                        Some(ReviewState::Approved) => "approved",
+                        Some(ReviewState::ChangesRequested) => "changes_requested",
+                        Some(ReviewState::Commented) => "commented",
+                        Some(ReviewState::Dismissed) => "dismissed",
+                        Some(ReviewState::Pending) => "pending",
+                        _ => "unknown",
                     }
+                    .to_string(),
+                    submitted_at: r.submitted_at,
                 }
-                Err(e) => {
-                    // Network or other reqwest errors
-                    return Err(GitCirclesError::WalletInvalidFormat(
-                        repo_full,
-                        format!("Request failed: {}", e),
-                    ));
+            }));
+
+            if len < 100 {
+                break;
+            }
+            page += 1;
+        }
+
+        Ok(reviews)
+    }
+
+    /// Authors of every commit in a pull request, including co-authors named
+    /// in `Co-authored-by:` trailers. GitHub caps this listing at 250 commits.
+    pub async fn fetch_repository_metadata(
+        &self,
+        owner: &str,
+        repo: &str,
+    ) -> Result<RepositoryMetadata> {
+        let repository = self
+            .checked(self.api().repos(owner, repo).get().await)
+            .await?;
+        Ok(RepositoryMetadata {
+            description: repository.description.filter(|d| !d.is_empty()),
+            stars: repository.stargazers_count.unwrap_or(0),
+            forks: repository.forks_count.unwrap_or(0),
+            language: repository
+                .language
+                .and_then(|l| l.as_str().map(str::to_string)),
+            license: repository.license.map(|l| l.spdx_id),
+            fetched_at: Utc::now(),
+        })
+    }
+
+    pub async fn is_org_member(&self, org: &str, login: &str) -> Result<bool> {
+        self.checked(self.api().orgs(org).check_membership(login).await)
+            .await
+    }
+
+    pub async fn fetch_pull_request_commit_authors(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+    ) -> Result<Vec<PullRequestCommitAuthor>> {
+        let mut authors = Vec::new();
+        let mut page = 1u32;
+
+        loop {
+            let items = self
+                .checked(
+                    self.api()
+                        .pulls(owner, repo)
+                        .pr_commits(number)
+                        .per_page(100u8)
+                        .page(page)
+                        .send()
+                        .await,
+                )
+                .await?
+                .items;
+            let len = items.len();
+
+            for commit in items {
+                if let Some(git_author) = commit.commit.author {
+                    authors.push(PullRequestCommitAuthor {
+                        login: commit.author.map(|a| a.login),
+                        name: git_author.name,
+                        email: git_author.email,
+                    });
                 }
+                authors.extend(parse_co_authors(&commit.commit.message));
+            }
+
+            if len < 100 {
+                break;
             }
+            page += 1;
         }
 
+        Ok(authors)
+    }
+
+    /// Paths changed by a PR, including the old name of renamed files.
+    pub async fn fetch_pull_request_files(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+    ) -> Result<Vec<String>> {
+        let mut files = Vec::new();
+        let mut page = 1u32;
+
+        // Octocrab's list_files can't page, so call the route directly
+        loop {
+            let route = format!(
+                "/repos/{}/{}/pulls/{}/files?per_page=100&page={}",
+                owner, repo, number, page
+            );
+            let entries: Vec<octocrab::models::repos::DiffEntry> = self
+                .checked(self.api().get(route, None::<&()>).await)
+                .await?;
+            let len = entries.len();
+
+            for entry in entries {
+                files.extend(entry.previous_filename);
+                files.push(entry.filename);
+            }
+
+            if len < 100 {
+                break;
+            }
+            page += 1;
+        }
+
+        Ok(files)
+    }
+
+    /// Read the wallet from the login's profile repository. Without
+    /// `wallet_ref`, a `wallet_ref` key in the default branch's
+    /// `gitcircles.toml` is followed; otherwise `main`, `master` and the
+    /// default branch are tried in turn. A pinned ref without the wallet
+    /// file is an error rather than "no wallet".
+    pub async fn fetch_wallet_address(
+        &self,
+        login: &str,
+        wallet_ref: Option<&ProfileRef>,
+    ) -> Result<Option<WalletFetchOutcome>> {
+        let repo_full = format!("{}/{}", login, PROFILE_REPO_NAME);
+
+        // Step 1: Get repository metadata to find default branch
+        let repo_result = self.api().repos(login, PROFILE_REPO_NAME).get().await;
+
+        let default_branch = match repo_result {
+            // The token may be able to read a private repository, but the
+            // wallet must be published
+            Ok(repo) if repo.private == Some(true) => {
+                return Err(GitCirclesError::RepoNotAccessible(repo_full));
+            }
+            Ok(repo) => repo.default_branch.unwrap_or_else(|| "main".to_string()),
+            Err(octocrab::Error::GitHub { source, .. })
+                if source.message.contains("Not Found") =>
+            {
+                // Repository doesn't exist - not an error, just means no wallet configured
+                return Ok(None);
+            }
+            Err(e) => return Err(self.api_error(e).await),
+        };
+
+        // Step 2: Pick the refs to try. Without an explicit ref, the default
+        // branch's preferences are read first for a `wallet_ref` pointer
+        let mut default_prefs = None;
+        let pinned = match wallet_ref {
+            Some(pinned) => Some(pinned.clone()),
+            None => {
+                default_prefs = self
+                    .fetch_profile_preferences(login, &default_branch)
+                    .await?;
+                default_prefs
+                    .as_ref()
+                    .and_then(|prefs| prefs.wallet_ref.as_deref())
+                    .map(|pointer| {
+                        pointer.parse::<ProfileRef>().map_err(|e| {
+                            GitCirclesError::PreferencesInvalid(
+                                repo_full.clone(),
+                                e,
+                            )
+                        })
+                    })
+                    .transpose()?
+            }
+        };
+        let candidates = match &pinned {
+            Some(pinned) => vec![pinned.clone()],
+            None => compute_branch_priority(default_branch.clone())
+                .into_iter()
+                .map(|name| ProfileRef {
+                    kind: RefKind::Branch,
+                    name,
+                })
+                .collect(),
+        };
+
+        // Step 3: Try fetching the wallet file from each ref
+        for candidate in &candidates {
+            let Some(content) = self
+                .fetch_profile_file(
+                    login,
+                    &candidate.name,
+                    WALLET_FILE_PATH,
+                    GitCirclesError::WalletInvalidFormat,
+                )
+                .await?
+            else {
+                // File not found on this ref, try next
+                continue;
+            };
+
+            // Step 4: Validate wallet address and pin the commit it came from
+            let address = WalletAddress::try_from(content.trim())?;
+            let commit = self.latest_profile_commit(login, &candidate.name).await;
+
+            // Step 5: Read optional preferences from the same ref; a
+            // `wallet_ref` pointer keeps those of the default branch
+            let preferences = if wallet_ref.is_none()
+                && (pinned.is_some() || candidate.name == default_branch)
+            {
+                default_prefs.take()
+            } else {
+                self.fetch_profile_preferences(login, &candidate.name)
+                    .await?
+            };
+
+            // Step 6: Return outcome
+            return Ok(Some(WalletFetchOutcome {
+                address,
+                branch: candidate.name.clone(),
+                ref_kind: candidate.kind,
+                commit,
+                preferences,
+            }));
+        }
+
+        if let Some(pinned) = pinned {
+            return Err(GitCirclesError::WalletNotFound(format!(
+                "{} at {} '{}'",
+                repo_full, pinned.kind, pinned.name
+            )));
+        }
         // All branches returned 404 - file doesn't exist
         Ok(None)
     }
+
+    /// Create the authenticated user's public profile repository if needed
+    /// and commit `P2PK.pub` (and optionally `gitcircles.toml`) to its
+    /// default branch.
+    pub async fn init_profile_repository(
+        &self,
+        address: &WalletAddress,
+        preferences: Option<&str>,
+    ) -> Result<ProfileInitOutcome> {
+        let login = self.test_token().await?;
+        let repo_full = format!("{}/{}", login, PROFILE_REPO_NAME);
+        if let Some(content) = preferences {
+            parse_contributor_preferences(content).map_err(|e| {
+                GitCirclesError::PreferencesInvalid(repo_full.clone(), e)
+            })?;
+        }
+
+        let (repo, created_repository) =
+            match self.api().repos(&login, PROFILE_REPO_NAME).get().await {
+                Ok(repo) => (repo, false),
+                Err(octocrab::Error::GitHub { source, .. })
+                    if source.message.contains("Not Found") =>
+                {
+                    let created = self
+                    .api()
+                    .post(
+                        "/user/repos",
+                        Some(&serde_json::json!({
+                            "name": PROFILE_REPO_NAME,
+                            "description": "Wallet address for GitCircles rewards",
+                            "private": false,
+                            "auto_init": true,
+                        })),
+                    )
+                    .await;
+                    let repo: octocrab::models::Repository =
+                        self.checked(created).await?;
+                    (repo, true)
+                }
+                Err(e) => return Err(self.api_error(e).await),
+            };
+        if repo.private == Some(true) {
+            return Err(GitCirclesError::RepoNotAccessible(repo_full));
+        }
+        let branch = repo.default_branch.unwrap_or_else(|| "main".to_string());
+
+        let mut files = vec![(WALLET_FILE_PATH, format!("{}\n", address))];
+        if let Some(content) = preferences {
+            files.push((PREFERENCES_FILE_PATH, content.to_string()));
+        }
+
+        let mut committed_files = Vec::new();
+        for (path, content) in files {
+            if self
+                .put_profile_file(&login, &branch, path, &content)
+                .await?
+            {
+                committed_files.push(path.to_string());
+            }
+        }
+
+        Ok(ProfileInitOutcome {
+            login,
+            branch,
+            created_repository,
+            committed_files,
+        })
+    }
+
+    // Create or update one file in the profile repository. Returns false when
+    // the file already has the requested content.
+    async fn put_profile_file(
+        &self,
+        login: &str,
+        branch: &str,
+        path: &str,
+        content: &str,
+    ) -> Result<bool> {
+        let repos = self.api().repos(login, PROFILE_REPO_NAME);
+        let existing =
+            match repos.get_content().path(path).r#ref(branch).send().await {
+                Ok(mut items) => items.take_items().into_iter().next(),
+                Err(octocrab::Error::GitHub { source, .. })
+                    if source.message.contains("Not Found") =>
+                {
+                    None
+                }
+                Err(e) => return Err(self.api_error(e).await),
+            };
+
+        let message = format!("Update {} via gitcircles", path);
+        let repos = self.api().repos(login, PROFILE_REPO_NAME);
+        let written = match existing {
+            Some(file) if file.decoded_content().as_deref() == Some(content) => {
+                return Ok(false);
+            }
+            Some(file) => {
+                repos
+                    .update_file(path, message, content, file.sha)
+                    .branch(branch)
+                    .send()
+                    .await
+            }
+            None => {
+                repos
+                    .create_file(path, message, content)
+                    .branch(branch)
+                    .send()
+                    .await
+            }
+        };
+        self.checked(written).await?;
+        Ok(true)
+    }
+
+    async fn fetch_profile_preferences(
+        &self,
+        login: &str,
+        branch: &str,
+    ) -> Result<Option<ContributorPreferences>> {
+        let repo_full = format!("{}/{}", login, PROFILE_REPO_NAME);
+        let Some(content) = self
+            .fetch_profile_file(
+                login,
+                branch,
+                PREFERENCES_FILE_PATH,
+                GitCirclesError::PreferencesInvalid,
+            )
+            .await?
+        else {
+            return Ok(None);
+        };
+        parse_contributor_preferences(&content)
+            .map(Some)
+            .map_err(|e| GitCirclesError::PreferencesInvalid(repo_full, e))
+    }
+
+    // Last commit on `git_ref` that touched the wallet file. Provenance is
+    // best effort: `None` when the lookup fails, e.g. while rate limited.
+    async fn latest_profile_commit(
+        &self,
+        login: &str,
+        git_ref: &str,
+    ) -> Option<String> {
+        let page = self
+            .api()
+            .repos(login, PROFILE_REPO_NAME)
+            .list_commits()
+            .sha(git_ref)
+            .path(WALLET_FILE_PATH)
+            .per_page(1)
+            .send()
+            .await
+            .ok()?;
+        page.items.into_iter().next().map(|commit| commit.sha)
+    }
+
+    /// Text of `path` on `branch` of the login's profile repository, or
+    /// `None` if the file isn't there. Reads through the authenticated
+    /// contents API; when that call fails for another reason (rate limit,
+    /// outage), falls back to raw.githubusercontent.com. `invalid` builds
+    /// the error for unreadable responses.
+    async fn fetch_profile_file(
+        &self,
+        login: &str,
+        branch: &str,
+        path: &str,
+        invalid: fn(String, String) -> GitCirclesError,
+    ) -> Result<Option<String>> {
+        let repo_full = format!("{}/{}", login, PROFILE_REPO_NAME);
+        let contents = self
+            .api()
+            .repos(login, PROFILE_REPO_NAME)
+            .get_content()
+            .path(path)
+            .r#ref(branch)
+            .send()
+            .await;
+        match contents {
+            Ok(mut items) => {
+                return match items.take_items().into_iter().next() {
+                    Some(file) => {
+                        file.decoded_content().map(Some).ok_or_else(|| {
+                            invalid(
+                                repo_full,
+                                "Failed to decode file contents".into(),
+                            )
+                        })
+                    }
+                    None => Ok(None),
+                };
+            }
+            Err(octocrab::Error::GitHub { source, .. })
+                if source.message.contains("Not Found") =>
+            {
+                return Ok(None);
+            }
+            Err(_) => {}
+        }
+
+        let url = format!(
+            "{}/{}/{}/{}/{}",
+            self.raw_base, login, PROFILE_REPO_NAME, branch, path
+        );
+        // Transient failures surface as TemporarilyUnavailable
+        let response = self.get_raw(&url, &repo_full).await?;
+        match response.status().as_u16() {
+            200 => response.text().await.map(Some).map_err(|e| {
+                invalid(repo_full, format!("Failed to read response: {}", e))
+            }),
+            404 => Ok(None),
+            // Authentication/permission issue - repo must be public
+            401 | 403 => Err(GitCirclesError::RepoNotAccessible(repo_full)),
+            status => Err(invalid(
+                repo_full,
+                format!("Unexpected HTTP status: {}", status),
+            )),
+        }
+    }
+}
+
+// On-disk layout of gitcircles.toml; amounts are written in ERG for humans
+#[derive(serde::Deserialize)]
+struct PreferencesFile {
+    display_name: Option<String>,
+    payout_schedule: Option<PayoutSchedule>,
+    min_payout_erg: Option<f64>,
+    #[serde(default)]
+    opt_out: bool,
+    wallet_ref: Option<String>,
+}
+
+/// Parse the contents of a profile repository's `gitcircles.toml`. Unknown
+/// keys are ignored so the format can grow.
+pub fn parse_contributor_preferences(
+    content: &str,
+) -> std::result::Result<ContributorPreferences, String> {
+    let file: PreferencesFile =
+        toml::from_str(content).map_err(|e| e.to_string())?;
+
+    let min_payout_nanoerg = match file.min_payout_erg {
+        Some(erg) if !erg.is_finite() || erg < 0.0 => {
+            return Err(format!(
+                "min_payout_erg must be non-negative, got {}",
+                erg
+            ));
+        }
+        Some(erg) => Some((erg * NANOERG_PER_ERG).round() as u64),
+        None => None,
+    };
+
+    let wallet_ref = match file.wallet_ref {
+        Some(pointer) => {
+            let parsed: ProfileRef =
+                pointer.parse().map_err(|e| format!("wallet_ref: {}", e))?;
+            Some(match parsed.kind {
+                RefKind::Tag => format!("tags/{}", parsed.name),
+                RefKind::Branch => parsed.name,
+            })
+        }
+        None => None,
+    };
+
+    Ok(ContributorPreferences {
+        display_name: file
+            .display_name
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty()),
+        payout_schedule: file.payout_schedule,
+        min_payout_nanoerg,
+        opt_out: file.opt_out,
+        wallet_ref,
+    })
 }
 
 impl WalletFetcher for GitHubClient {
     async fn fetch_wallet_address(
         &self,
         login: &str,
+        wallet_ref: Option<&ProfileRef>,
     ) -> Result<Option<WalletFetchOutcome>> {
-        Self::fetch_wallet_address(self, login).await
+        Self::fetch_wallet_address(self, login, wallet_ref).await
     }
 }
 
+impl PullRequestSource for GitHubClient {
+    async fn fetch_merged_pull_requests(
+        &self,
+        owner: &str,
+        repo: &str,
+        base_branch: &str,
+        days_back: Option<u64>,
+        limits: PageLimits,
+    ) -> Result<Vec<MergedPullRequest>> {
+        Self::fetch_merged_pull_requests(
+            self,
+            owner,
+            repo,
+            base_branch,
+            days_back,
+            limits,
+        )
+        .await
+    }
+
+    async fn fetch_pull_requests_with_external_merges(
+        &self,
+        owner: &str,
+        repo: &str,
+        base_branch: &str,
+        days_back: Option<u64>,
+        limits: PageLimits,
+    ) -> Result<Vec<MergedPullRequest>> {
+        Self::fetch_pull_requests_with_external_merges(
+            self,
+            owner,
+            repo,
+            base_branch,
+            days_back,
+            limits,
+        )
+        .await
+    }
+
+    async fn fetch_pull_request_comments(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+    ) -> Result<Vec<PullRequestComment>> {
+        Self::fetch_pull_request_comments(self, owner, repo, number).await
+    }
+
+    async fn fetch_pull_request_reviews(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+    ) -> Result<Vec<PullRequestReview>> {
+        Self::fetch_pull_request_reviews(self, owner, repo, number).await
+    }
+
+    async fn fetch_pull_request_commit_authors(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+    ) -> Result<Vec<PullRequestCommitAuthor>> {
+        Self::fetch_pull_request_commit_authors(self, owner, repo, number).await
+    }
+
+    async fn fetch_pull_request_files(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+    ) -> Result<Vec<String>> {
+        Self::fetch_pull_request_files(self, owner, repo, number).await
+    }
+
+    async fn search_merged_pull_requests(
+        &self,
+        owner: &str,
+        repo: &str,
+        base_branch: &str,
+        range: MergedRange,
+        limits: PageLimits,
+    ) -> Result<Vec<u64>> {
+        Self::search_merged_pull_requests(
+            self,
+            owner,
+            repo,
+            base_branch,
+            range,
+            limits,
+        )
+        .await
+    }
+
+    async fn fetch_pull_request(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+    ) -> Result<Option<MergedPullRequest>> {
+        Self::fetch_pull_request(self, owner, repo, number).await
+    }
+
+    async fn fetch_repository_metadata(
+        &self,
+        owner: &str,
+        repo: &str,
+    ) -> Result<Option<RepositoryMetadata>> {
+        Self::fetch_repository_metadata(self, owner, repo)
+            .await
+            .map(Some)
+    }
+
+    async fn is_org_member(&self, org: &str, login: &str) -> Result<bool> {
+        Self::is_org_member(self, org, login).await
+    }
+
+    fn api_calls(&self) -> u64 {
+        Self::api_calls(self)
+    }
+}
+
+impl RepoInteraction for GitHubClient {
+    async fn create_comment(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+        body: &str,
+    ) -> Result<u64> {
+        Self::create_comment(self, owner, repo, number, body).await
+    }
+
+    async fn edit_comment(
+        &self,
+        owner: &str,
+        repo: &str,
+        comment_id: u64,
+        body: &str,
+    ) -> Result<()> {
+        Self::edit_comment(self, owner, repo, comment_id, body).await
+    }
+
+    async fn list_comments(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+    ) -> Result<Vec<PullRequestComment>> {
+        Self::fetch_pull_request_comments(self, owner, repo, number).await
+    }
+
+    async fn create_issue(
+        &self,
+        owner: &str,
+        repo: &str,
+        title: &str,
+        body: &str,
+    ) -> Result<(u64, String)> {
+        Self::create_issue(self, owner, repo, title, body).await
+    }
+
+    async fn update_issue(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+        title: &str,
+        body: &str,
+    ) -> Result<String> {
+        Self::update_issue(self, owner, repo, number, title, body).await
+    }
+
+    async fn pin_issue(&self, node_id: &str) -> Result<bool> {
+        Self::pin_issue(self, node_id).await
+    }
+
+    async fn add_label(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+        label: &str,
+    ) -> Result<()> {
+        Self::add_label(self, owner, repo, number, label).await
+    }
+
+    async fn remove_label(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+        label: &str,
+    ) -> Result<()> {
+        Self::remove_label(self, owner, repo, number, label).await
+    }
+
+    async fn create_commit_status(
+        &self,
+        owner: &str,
+        repo: &str,
+        sha: &str,
+        status: &CommitStatus,
+    ) -> Result<()> {
+        Self::create_commit_status(self, owner, repo, sha, status).await
+    }
+}
+
+// Map an API pull request to the stored form. `merged_at` is the close
+// time for PRs merged outside GitHub, whose head commit stands in for the
+// merge commit.
+fn merged_pull_request(
+    owner: &str,
+    repo: &str,
+    pr: octocrab::models::pulls::PullRequest,
+    merged_at: DateTime<Utc>,
+    externally_merged: bool,
+) -> MergedPullRequest {
+    let repository = format!("{}/{}", owner, repo);
+    let head_repository = pr.head.repo.as_ref().and_then(|r| r.full_name.clone());
+    // A missing head repo means the fork has since been deleted
+    let from_fork = !head_repository
+        .as_deref()
+        .is_some_and(|h| h.eq_ignore_ascii_case(&repository));
+
+    MergedPullRequest {
+        number: pr.number,
+        title: pr.title.unwrap_or_else(|| "No title".to_string()),
+        author: pr
+            .user
+            .map(|u| u.login)
+            .unwrap_or_else(|| "unknown".to_string()),
+        merged_at,
+        base_branch: pr.base.ref_field,
+        merge_commit_sha: if externally_merged {
+            pr.head.sha
+        } else {
+            pr.merge_commit_sha.unwrap_or_else(|| "unknown".to_string())
+        },
+        repository: repository.clone(),
+        head_repository,
+        head_branch: Some(pr.head.ref_field),
+        from_fork,
+        resolved_authors: Vec::new(),
+        linked_issues: pr
+            .body
+            .as_deref()
+            .map(|body| parse_issue_references(body, &repository))
+            .unwrap_or_default(),
+        body: pr.body,
+        platform: "github".to_string(),
+        html_url: pr.html_url.map(|url| url.to_string()),
+        labels: pr
+            .labels
+            .unwrap_or_default()
+            .into_iter()
+            .map(|label| label.name)
+            .collect(),
+        author_association: pr
+            .author_association
+            .and_then(|a| serde_json::to_value(a).ok())
+            .and_then(|a| a.as_str().map(str::to_string)),
+        externally_merged,
+        changed_files: Vec::new(),
+        imported: false,
+    }
+}
+
+fn parse_co_authors(message: &str) -> Vec<PullRequestCommitAuthor> {
+    message
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.trim().split_once(':')?;
+            if !key.trim().eq_ignore_ascii_case("co-authored-by") {
+                return None;
+            }
+            let (name, rest) = value.split_once('<')?;
+            let email = rest.strip_suffix('>').unwrap_or(rest);
+            Some(PullRequestCommitAuthor {
+                login: None,
+                name: name.trim().to_string(),
+                email: email.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Extract the login from GitHub noreply addresses
+/// (`login@users.noreply.github.com` or `12345+login@users.noreply.github.com`).
+pub(crate) fn login_from_noreply(email: &str) -> Option<&str> {
+    let local = email.strip_suffix("@users.noreply.github.com")?;
+    let login = local.split_once('+').map_or(local, |(_, login)| login);
+    (!login.is_empty()).then_some(login)
+}
+
+/// Resolve commit authors to unique GitHub logins in first-seen order.
+/// Emails are matched against other commits GitHub already linked to an
+/// account, then against noreply addresses; anything else is dropped.
+pub fn resolve_commit_authors(authors: &[PullRequestCommitAuthor]) -> Vec<String> {
+    let known: std::collections::HashMap<String, &str> = authors
+        .iter()
+        .filter_map(|a| Some((a.email.to_lowercase(), a.login.as_deref()?)))
+        .collect();
+
+    let mut logins: Vec<String> = Vec::new();
+    for author in authors {
+        let login = author
+            .login
+            .as_deref()
+            .or_else(|| known.get(&author.email.to_lowercase()).copied())
+            .or_else(|| login_from_noreply(&author.email));
+
+        if let Some(login) = login
+            && !logins.iter().any(|l| l.eq_ignore_ascii_case(login))
+        {
+            logins.push(login.to_string());
+        }
+    }
+    logins
+}
+
+/// True when a comment contains a line reading just `OPT OUT` (any case),
+/// a contributor's request to never receive rewards.
+pub fn is_opt_out_comment(body: &str) -> bool {
+    body.lines().any(|line| {
+        line.split_whitespace()
+            .map(str::to_ascii_lowercase)
+            .eq(["opt", "out"])
+    })
+}
+
+/// Whether a comment approves its pull request for a project that requires
+/// approval: a line reading `GitCircles APPROVE`, in any case.
+pub fn is_approval_comment(body: &str) -> bool {
+    body.lines().any(|line| {
+        line.split_whitespace()
+            .map(str::to_ascii_lowercase)
+            .eq(["gitcircles", "approve"])
+    })
+}
+
+const CLOSING_KEYWORDS: [&str; 9] = [
+    "close", "closes", "closed", "fix", "fixes", "fixed", "resolve", "resolves",
+    "resolved",
+];
+
+/// Parse issue references out of a PR description: `#12`, `owner/repo#12`
+/// and `https://github.com/owner/repo/issues/12`. Bare numbers refer to
+/// `repository`. Each issue appears once, in first-seen order.
+pub fn parse_issue_references(body: &str, repository: &str) -> Vec<IssueReference> {
+    let mut refs: Vec<IssueReference> = Vec::new();
+    let mut previous = "";
+
+    for word in body.split_whitespace() {
+        let token = word
+            .trim_start_matches(['(', '['])
+            .trim_end_matches(['.', ',', ';', ':', ')', ']', '!', '?']);
+
+        if let Some((repo, number)) = parse_issue_token(token, repository) {
+            let closing = CLOSING_KEYWORDS
+                .contains(&previous.trim_end_matches(':').to_lowercase().as_str());
+            match refs.iter_mut().find(|r| {
+                r.number == number && r.repository.eq_ignore_ascii_case(&repo)
+            }) {
+                Some(existing) => existing.closing |= closing,
+                None => refs.push(IssueReference {
+                    repository: repo,
+                    number,
+                    closing,
+                }),
+            }
+        }
+        previous = word;
+    }
+    refs
+}
+
+fn parse_issue_token(token: &str, repository: &str) -> Option<(String, u64)> {
+    if let Some(path) = token.strip_prefix("https://github.com/") {
+        let mut parts = path.split('/');
+        let (owner, repo, kind, number) =
+            (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+        if kind != "issues" || parts.next().is_some() {
+            return None;
+        }
+        return Some((format!("{}/{}", owner, repo), number.parse().ok()?));
+    }
+
+    let (repo, number) = token.split_once('#')?;
+    let number: u64 = number.parse().ok()?;
+    if repo.is_empty() {
+        return Some((repository.to_string(), number));
+    }
+    let (owner, name) = repo.split_once('/')?;
+    let valid = |part: &str| {
+        !part.is_empty()
+            && part
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    };
+    (valid(owner) && valid(name)).then(|| (repo.to_string(), number))
+}
+
 // Small helper for testing branch priority logic deterministically without network
 pub(crate) fn compute_branch_priority(default_branch: String) -> Vec<String> {
     match default_branch.as_str() {
@@ -236,7 +1897,440 @@ pub(crate) fn compute_branch_priority(default_branch: String) -> Vec<String> {
 
 #[cfg(test)]
 mod tests {
-    use super::compute_branch_priority;
+    use super::{
+        MergedRange, PageLimits, compute_branch_priority, is_approval_comment,
+        is_opt_out_comment, parse_co_authors, parse_contributor_preferences,
+        parse_issue_references, resolve_commit_authors,
+    };
+    use crate::replay::HttpHarness;
+    use crate::types::GitCirclesError;
+    use crate::types::PayoutSchedule;
+    use crate::types::PullRequestCommitAuthor;
+    use crate::types::WalletAddress;
+    use crate::types::{ProfileRef, RefKind};
+    use chrono::NaiveDate;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    fn fixture(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(name)
+    }
+
+    // Any token works on replay; recording needs a real one.
+    fn token() -> String {
+        std::env::var("GITHUB_TOKEN").unwrap_or_else(|_| "replay-token".into())
+    }
+
+    #[tokio::test]
+    async fn merged_prs_paginate_and_skip_unmerged() {
+        let harness = HttpHarness::from_env(fixture("pulls_pagination.json"))
+            .await
+            .unwrap();
+        let client = harness.client(&token()).unwrap();
+
+        let prs = client
+            .fetch_merged_pull_requests(
+                "acme",
+                "widgets",
+                "main",
+                None,
+                PageLimits::default(),
+            )
+            .await
+            .unwrap();
+
+        // Page 1 is full (100 PRs, 25 closed without merging), page 2 has 3.
+        assert_eq!(prs.len(), 78);
+        assert!(prs.iter().all(|pr| pr.repository == "acme/widgets"));
+        assert!(prs.iter().any(|pr| pr.number == 101));
+        assert!(!prs.iter().any(|pr| pr.number == 200));
+
+        // Even numbers come from acme/widgets itself, odd ones from forks,
+        // and multiples of five from forks that have since been deleted.
+        let by_number = |n| prs.iter().find(|pr| pr.number == n).unwrap();
+        assert!(!by_number(202).from_fork);
+        assert!(by_number(203).from_fork);
+        assert_eq!(
+            by_number(203).head_repository.as_deref(),
+            Some("contrib2/widgets")
+        );
+        assert!(by_number(105).from_fork);
+        assert!(by_number(105).head_repository.is_none());
+        assert_eq!(by_number(105).head_branch.as_deref(), Some("feature-105"));
+
+        // Descriptions are kept and their issue references parsed
+        assert!(by_number(202).body.is_none());
+        assert!(by_number(202).linked_issues.is_empty());
+        let linked: Vec<String> = by_number(203)
+            .linked_issues
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            linked,
+            ["acme/widgets#7", "acme/gadgets#12", "acme/widgets#19"]
+        );
+        assert!(by_number(203).linked_issues[1].closing);
+
+        // Capped at one page, only page 1's merged PRs are listed
+        let limits = PageLimits {
+            per_page: 100,
+            max_pages: Some(1),
+        };
+        let first_page = client
+            .fetch_merged_pull_requests("acme", "widgets", "main", None, limits)
+            .await
+            .unwrap();
+        assert_eq!(first_page.len(), 75);
+        harness.finish().unwrap();
+    }
+
+    #[tokio::test]
+    async fn closed_prs_on_the_base_branch_count_as_external_merges() {
+        let harness = HttpHarness::from_env(fixture("external_merges.json"))
+            .await
+            .unwrap();
+        let client = harness.client(&token()).unwrap();
+
+        let prs = client
+            .fetch_pull_requests_with_external_merges(
+                "acme",
+                "widgets",
+                "main",
+                None,
+                PageLimits::default(),
+            )
+            .await
+            .unwrap();
+
+        // #2's head is behind main, #3 diverged and never landed
+        let numbers: Vec<u64> = prs.iter().map(|pr| pr.number).collect();
+        assert_eq!(numbers, [1, 2]);
+        assert!(!prs[0].externally_merged);
+        assert!(prs[1].externally_merged);
+        assert_eq!(prs[1].merged_at.to_rfc3339(), "2025-06-11T12:00:00+00:00");
+        harness.finish().unwrap();
+    }
+
+    #[tokio::test]
+    async fn searches_over_the_result_cap_are_split() {
+        let harness = HttpHarness::from_env(fixture("search_backfill.json"))
+            .await
+            .unwrap();
+        let client = harness.client(&token()).unwrap();
+
+        let range = MergedRange {
+            from: NaiveDate::from_ymd_opt(2021, 1, 1).unwrap(),
+            to: NaiveDate::from_ymd_opt(2021, 1, 4).unwrap(),
+        };
+        let numbers = client
+            .search_merged_pull_requests(
+                "acme",
+                "widgets",
+                "main",
+                range,
+                PageLimits::default(),
+            )
+            .await
+            .unwrap();
+        // The full range reports more than 1000 hits, so each half is
+        // searched on its own
+        assert_eq!(numbers, [11, 12, 31]);
+
+        let pr = client
+            .fetch_pull_request("acme", "widgets", 31)
+            .await
+            .unwrap();
+        assert_eq!(
+            pr.unwrap().merged_at.to_rfc3339(),
+            "2021-01-03T09:00:00+00:00"
+        );
+        harness.finish().unwrap();
+    }
+
+    #[tokio::test]
+    async fn comments_and_reviews_are_mapped() {
+        let harness = HttpHarness::from_env(fixture("pr_discussion.json"))
+            .await
+            .unwrap();
+        let client = harness.client(&token()).unwrap();
+
+        let comments = client
+            .fetch_pull_request_comments("acme", "widgets", 42)
+            .await
+            .unwrap();
+        assert_eq!(comments.len(), 2);
+        assert_eq!(comments[0].author, "carol");
+
+        let reviews = client
+            .fetch_pull_request_reviews("acme", "widgets", 42)
+            .await
+            .unwrap();
+        let states: Vec<_> = reviews.iter().map(|r| r.state.as_str()).collect();
+        assert_eq!(states, ["changes_requested", "approved"]);
+        harness.finish().unwrap();
+    }
+
+    #[tokio::test]
+    async fn repository_metadata_reads_stars_language_and_license() {
+        let harness = HttpHarness::from_env(fixture("repo_metadata.json"))
+            .await
+            .unwrap();
+        let client = harness.client(&token()).unwrap();
+
+        let metadata = client
+            .fetch_repository_metadata("acme", "widgets")
+            .await
+            .unwrap();
+        assert_eq!((metadata.stars, metadata.forks), (1284, 97));
+        assert_eq!(metadata.language.as_deref(), Some("Rust"));
+        assert_eq!(metadata.license.as_deref(), Some("Apache-2.0"));
+        assert_eq!(
+            metadata.description.as_deref(),
+            Some("Widgets for every occasion")
+        );
+        harness.finish().unwrap();
+    }
+
+    #[tokio::test]
+    async fn wallet_fetch_falls_back_through_branches() {
+        let harness = HttpHarness::from_env(fixture("wallet_fetch.json"))
+            .await
+            .unwrap();
+        let client = harness.client(&token()).unwrap();
+
+        let outcome = client
+            .fetch_wallet_address("alice", None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(outcome.branch, "master");
+        assert_eq!(
+            outcome.commit.as_deref(),
+            Some("3f2a9c1e8b7d6a5f4e3d2c1b0a9f8e7d6c5b4a39")
+        );
+        assert!(outcome.address.starts_with('9'));
+        let prefs = outcome.preferences.unwrap();
+        assert_eq!(prefs.display_name.as_deref(), Some("Alice Liddell"));
+        assert_eq!(prefs.payout_schedule, Some(PayoutSchedule::Monthly));
+        assert_eq!(prefs.min_payout_nanoerg, Some(250_000_000));
+        assert!(!prefs.opt_out);
+
+        assert!(
+            client
+                .fetch_wallet_address("ghost", None)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        harness.finish().unwrap();
+    }
+
+    #[tokio::test]
+    async fn wallet_fetch_follows_pinned_refs() {
+        let harness = HttpHarness::from_env(fixture("wallet_fetch.json"))
+            .await
+            .unwrap();
+        let client = harness.client(&token()).unwrap();
+
+        // gitcircles.toml on the default branch points at a tag
+        let outcome = client
+            .fetch_wallet_address("hana", None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(outcome.branch, "wallet-v1");
+        assert_eq!(outcome.ref_kind, RefKind::Tag);
+        assert!(outcome.commit.is_some());
+        let prefs = outcome.preferences.unwrap();
+        assert_eq!(prefs.display_name.as_deref(), Some("Hana"));
+        assert_eq!(prefs.wallet_ref.as_deref(), Some("tags/wallet-v1"));
+
+        // An explicit ref without the file is an error, not "no wallet"
+        let pinned: ProfileRef = "tags/v2".parse().unwrap();
+        let missing = client
+            .fetch_wallet_address("alice", Some(&pinned))
+            .await
+            .unwrap_err();
+        assert!(matches!(missing, GitCirclesError::WalletNotFound(_)));
+        harness.finish().unwrap();
+    }
+
+    #[tokio::test]
+    async fn wallet_fetch_error_paths() {
+        let harness = HttpHarness::from_env(fixture("wallet_fetch.json"))
+            .await
+            .unwrap();
+        let client = harness.client(&token()).unwrap();
+
+        // The contents API is rate limited here, so both go through the raw
+        // fallback
+        let forbidden = client.fetch_wallet_address("bob", None).await.unwrap_err();
+        assert!(matches!(forbidden, GitCirclesError::RepoNotAccessible(_)));
+
+        let private = client.fetch_wallet_address("gina", None).await.unwrap_err();
+        assert!(matches!(private, GitCirclesError::RepoNotAccessible(_)));
+
+        let invalid = client.fetch_wallet_address("dave", None).await.unwrap_err();
+        assert!(matches!(
+            invalid,
+            GitCirclesError::WalletInvalidFormat(_, _)
+        ));
+        harness.finish().unwrap();
+    }
+
+    #[tokio::test]
+    async fn wallet_fetch_retries_server_errors() {
+        let harness = HttpHarness::from_env(fixture("wallet_fetch.json"))
+            .await
+            .unwrap();
+        let client = harness
+            .client(&token())
+            .unwrap()
+            .with_raw_retry_delay(Duration::from_millis(1));
+
+        // 503, then 502, then the file
+        let outcome = client
+            .fetch_wallet_address("carol", None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(outcome.address.starts_with('9'));
+        // The commit lookup fails too, which doesn't fail the fetch
+        assert!(outcome.commit.is_none());
+
+        let down = client
+            .fetch_wallet_address("frank", None)
+            .await
+            .unwrap_err();
+        assert!(down.is_retryable());
+        assert!(matches!(
+            down,
+            GitCirclesError::TemporarilyUnavailable(repo, _)
+                if repo == "frank/gitcircles-profile"
+        ));
+        harness.finish().unwrap();
+    }
+
+    #[tokio::test]
+    async fn rate_limited_requests_report_the_reset_time() {
+        let harness = HttpHarness::from_env(fixture("wallet_fetch.json"))
+            .await
+            .unwrap();
+        let client = harness.client(&token()).unwrap();
+
+        let err = client.fetch_wallet_address("ivan", None).await.unwrap_err();
+        assert!(err.is_retryable());
+        assert!(matches!(
+            err,
+            GitCirclesError::RateLimited {
+                reset_at: Some(reset_at),
+                remaining: Some(0),
+            } if reset_at.timestamp() == 1_767_225_600
+        ));
+        harness.finish().unwrap();
+    }
+
+    #[tokio::test]
+    async fn profile_init_creates_repo_and_skips_unchanged_files() {
+        let harness = HttpHarness::from_env(fixture("profile_init.json"))
+            .await
+            .unwrap();
+        let client = harness.client(&token()).unwrap();
+        let address = WalletAddress::try_from(
+            "9hUzb5RvSgDqJdtyCN9Ke496Yy63mpcUJKbRq4swzQ5EQKgygKT",
+        )
+        .unwrap();
+
+        let invalid = client
+            .init_profile_repository(&address, Some("min_payout_erg = -1"))
+            .await
+            .unwrap_err();
+        assert!(matches!(invalid, GitCirclesError::PreferencesInvalid(_, _)));
+
+        let preferences = "display_name = \"Erin\"\npayout_schedule = \"weekly\"\n";
+        let outcome = client
+            .init_profile_repository(&address, Some(preferences))
+            .await
+            .unwrap();
+        assert_eq!(outcome.login, "erin");
+        assert_eq!(outcome.branch, "main");
+        assert!(outcome.created_repository);
+        // gitcircles.toml already holds the same content
+        assert_eq!(outcome.committed_files, ["P2PK.pub"]);
+        harness.finish().unwrap();
+    }
+
+    fn commit_author(login: Option<&str>, email: &str) -> PullRequestCommitAuthor {
+        PullRequestCommitAuthor {
+            login: login.map(str::to_string),
+            name: "Someone".to_string(),
+            email: email.to_string(),
+        }
+    }
+
+    #[test]
+    fn co_author_trailers_are_parsed() {
+        let message = "Fix parser\n\nCo-authored-by: Bob Builder <bob@example.com>\nco-authored-by: Eve <eve@example.com>";
+        let authors = parse_co_authors(message);
+        assert_eq!(authors.len(), 2);
+        assert_eq!(authors[0].name, "Bob Builder");
+        assert_eq!(authors[1].email, "eve@example.com");
+    }
+
+    #[test]
+    fn commit_authors_resolve_via_known_emails_and_noreply() {
+        let authors = vec![
+            commit_author(Some("alice"), "alice@example.com"),
+            commit_author(None, "ALICE@example.com"),
+            commit_author(None, "1234+bob@users.noreply.github.com"),
+            commit_author(None, "carol@users.noreply.github.com"),
+            commit_author(None, "stranger@example.com"),
+        ];
+        assert_eq!(resolve_commit_authors(&authors), ["alice", "bob", "carol"]);
+    }
+
+    #[test]
+    fn opt_out_comments_need_a_line_of_their_own() {
+        assert!(is_opt_out_comment("Thanks!\n\n  opt   OUT \n"));
+        assert!(!is_opt_out_comment("Should we opt out of CI here?"));
+        assert!(is_approval_comment("LGTM\n\nGitCircles APPROVE"));
+        assert!(!is_approval_comment("Will GitCircles approve this?"));
+    }
+
+    #[test]
+    fn preferences_file_is_validated() {
+        let prefs =
+            parse_contributor_preferences("opt_out = true\nfuture_key = 1\n")
+                .unwrap();
+        assert!(prefs.opt_out);
+        assert_eq!(prefs.payout_schedule, None);
+
+        assert!(
+            parse_contributor_preferences("payout_schedule = \"daily\"").is_err()
+        );
+        assert!(parse_contributor_preferences("min_payout_erg = -1.0").is_err());
+    }
+
+    #[test]
+    fn issue_references_are_parsed_and_deduplicated() {
+        let body = "Fixes: #3, refs acme/other#4 and (#3).\n\
+                    Closes https://github.com/acme/widgets/issues/5\n\
+                    Not issues: #abc, a/b/c#6, https://github.com/acme/widgets/pull/7";
+        let refs = parse_issue_references(body, "acme/widgets");
+        let summary: Vec<(String, bool)> =
+            refs.iter().map(|r| (r.to_string(), r.closing)).collect();
+        assert_eq!(
+            summary,
+            [
+                ("acme/widgets#3".to_string(), true),
+                ("acme/other#4".to_string(), false),
+                ("acme/widgets#5".to_string(), true),
+            ]
+        );
+    }
 
     #[test]
     fn branch_priority_dedups_default_main() {
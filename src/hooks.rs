@@ -0,0 +1,129 @@
+//! External commands run on lifecycle events, configured under `[hooks]` in
+//! `config.toml`. A hook gets the event as one JSON object on stdin, e.g.
+//! `{"event": "pr_registered", "pull_request": {...}}`. Hooks run after the
+//! change is stored, so a failing hook is reported but never undoes it.
+
+use serde::Serialize;
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use crate::config::HooksConfig;
+use crate::types::MergedPullRequest;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum HookEvent {
+    /// A newly collected PR was stored
+    PrRegistered { pull_request: MergedPullRequest },
+}
+
+impl HookEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            HookEvent::PrRegistered { .. } => "pr_registered",
+        }
+    }
+}
+
+impl HooksConfig {
+    /// The command line configured for `event`; empty when there is none.
+    pub fn command(&self, event: &HookEvent) -> &[String] {
+        match event {
+            HookEvent::PrRegistered { .. } => &self.pr_registered,
+        }
+    }
+}
+
+/// Run the hook configured for `event`, if any, waiting up to the hook
+/// timeout. Errors describe why the hook failed.
+pub async fn run_hook(
+    config: &HooksConfig,
+    event: &HookEvent,
+) -> std::result::Result<(), String> {
+    let Some((program, args)) = config.command(event).split_first() else {
+        return Ok(());
+    };
+    let fail =
+        |problem: String| format!("{} hook {}: {}", event.name(), program, problem);
+    let payload = serde_json::to_vec(event).map_err(|e| fail(e.to_string()))?;
+
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| fail(e.to_string()))?;
+    let stdin = child.stdin.take();
+    // Writing counts against the timeout too: a hook that never reads a
+    // payload larger than the pipe buffer would otherwise block forever.
+    let finished = async {
+        if let Some(mut stdin) = stdin {
+            // A hook that ignores its input may exit before reading it
+            let _ = stdin.write_all(&payload).await;
+        }
+        child.wait().await
+    };
+
+    match tokio::time::timeout(config.timeout(), finished).await {
+        Ok(Ok(status)) if status.success() => Ok(()),
+        Ok(Ok(status)) => Err(fail(format!("exited with {}", status))),
+        Ok(Err(e)) => Err(fail(e.to_string())),
+        Err(_) => Err(fail(format!(
+            "timed out after {}s",
+            config.timeout().as_secs()
+        ))),
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn event() -> HookEvent {
+        HookEvent::PrRegistered {
+            pull_request: MergedPullRequest {
+                title: "Fix typo".into(),
+                ..MergedPullRequest::for_test(7, "alice")
+            },
+        }
+    }
+
+    fn hooks(command: &[&str]) -> HooksConfig {
+        HooksConfig {
+            pr_registered: command.iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn hooks_read_the_event_from_stdin_and_report_failures() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("event.json");
+        let script = format!("cat > {}", out.display());
+        run_hook(&hooks(&["sh", "-c", &script]), &event())
+            .await
+            .unwrap();
+        let received: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&out).unwrap()).unwrap();
+        assert_eq!(received["event"], "pr_registered");
+        assert_eq!(received["pull_request"]["number"], 7);
+
+        assert!(run_hook(&hooks(&[]), &event()).await.is_ok());
+        let failed = run_hook(&hooks(&["false"]), &event()).await.unwrap_err();
+        assert!(failed.starts_with("pr_registered hook false: exited with"));
+
+        let slow = HooksConfig {
+            timeout_secs: 1,
+            ..hooks(&["sleep", "5"])
+        };
+        let failed = run_hook(&slow, &event()).await.unwrap_err();
+        assert!(failed.ends_with("timed out after 1s"));
+
+        let HookEvent::PrRegistered { mut pull_request } = event();
+        pull_request.body = Some("x".repeat(1 << 20));
+        let large = HookEvent::PrRegistered { pull_request };
+        let failed = run_hook(&slow, &large).await.unwrap_err();
+        assert!(failed.ends_with("timed out after 1s"), "{}", failed);
+    }
+}
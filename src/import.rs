@@ -0,0 +1,612 @@
+//! Backfilling PR history without the GitHub API. `import git-log` reads
+//! the merge commits of a local clone, e.g. for repositories whose history
+//! predates GitHub or was migrated; `import archive` reads GitHub export
+//! archives and GH Archive event files, so large backfills use no API
+//! quota. Either way each PR becomes a [`MergedPullRequest`] marked as
+//! imported.
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::io::BufRead;
+use std::path::Path;
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use crate::github::{login_from_noreply, parse_issue_references};
+use crate::types::{GitCirclesError, MergedPullRequest, Result};
+
+/// Merges that aren't GitHub PRs are numbered from here on, offset by the
+/// first 32 bits of the merge commit, so they never collide with real PR
+/// numbers and a re-import finds them again.
+pub const IMPORTED_NUMBER_BASE: u64 = 1 << 32;
+
+// Field and record separators for `git log --format`
+const FIELD: char = '\u{1f}';
+const RECORD: char = '\u{1e}';
+
+/// A merge commit on the base branch, with the author of the merged side.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeCommit {
+    pub sha: String,
+    pub merged_at: DateTime<Utc>,
+    /// The full commit message
+    pub message: String,
+    pub author_name: String,
+    pub author_email: String,
+}
+
+/// Merge commits reachable from `base_branch` along its first parents,
+/// newest first.
+pub async fn read_merge_commits(
+    clone: &Path,
+    base_branch: &str,
+) -> Result<Vec<MergeCommit>> {
+    let log = git(
+        clone,
+        &[
+            "log",
+            "--merges",
+            "--first-parent",
+            "--format=%H%x1f%P%x1f%cI%x1f%B%x1e",
+            base_branch,
+        ],
+        None,
+    )
+    .await?;
+    let merges: Vec<Vec<&str>> = records(&log)
+        .map(|record| record.splitn(4, FIELD).collect())
+        .filter(|fields: &Vec<&str>| fields.len() == 4)
+        .collect();
+
+    // The merged side's tip carries the contributor, not the merge itself
+    let tips: Vec<&str> = merges
+        .iter()
+        .filter_map(|fields| fields[1].split_whitespace().nth(1))
+        .collect();
+    let authors = git(
+        clone,
+        &[
+            "log",
+            "--no-walk",
+            "--stdin",
+            "--format=%H%x1f%an%x1f%ae%x1e",
+        ],
+        Some(tips.join("\n")),
+    )
+    .await?;
+    let authors: std::collections::HashMap<&str, (&str, &str)> = records(&authors)
+        .filter_map(|record| {
+            let mut fields = record.split(FIELD);
+            Some((fields.next()?, (fields.next()?, fields.next()?)))
+        })
+        .collect();
+
+    merges
+        .iter()
+        .map(|fields| {
+            let merged_at = DateTime::parse_from_rfc3339(fields[2])
+                .map_err(|e| {
+                    GitCirclesError::Io(std::io::Error::other(format!(
+                        "bad commit date '{}': {}",
+                        fields[2], e
+                    )))
+                })?
+                .with_timezone(&Utc);
+            let tip = fields[1].split_whitespace().nth(1).unwrap_or_default();
+            let (name, email) = authors.get(tip).copied().unwrap_or_default();
+            Ok(MergeCommit {
+                sha: fields[0].to_string(),
+                merged_at,
+                message: fields[3].trim().to_string(),
+                author_name: name.to_string(),
+                author_email: email.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// The branch checked out in `clone`.
+pub async fn current_branch(clone: &Path) -> Result<String> {
+    Ok(git(clone, &["rev-parse", "--abbrev-ref", "HEAD"], None)
+        .await?
+        .trim()
+        .to_string())
+}
+
+/// "owner/repo" of the clone's `origin` remote, if it has one.
+pub async fn origin_repository(clone: &Path) -> Option<String> {
+    let url = git(clone, &["remote", "get-url", "origin"], None)
+        .await
+        .ok()?;
+    repository_from_remote(url.trim())
+}
+
+/// "owner/repo" from a remote URL such as `git@github.com:owner/repo.git`
+/// or `https://github.com/owner/repo`.
+pub fn repository_from_remote(url: &str) -> Option<String> {
+    let path = url.trim_end_matches('/').trim_end_matches(".git");
+    let mut parts = path.rsplit(['/', ':']);
+    let repo = parts.next().filter(|s| !s.is_empty())?;
+    let owner = parts.next().filter(|s| !s.is_empty())?;
+    Some(format!("{}/{}", owner, repo))
+}
+
+/// The PR a merge commit stands for. GitHub's "Merge pull request #N from
+/// login/branch" commits keep their PR number and author; other merges get
+/// a number from [`IMPORTED_NUMBER_BASE`] and the author of the merged
+/// commit, as a login when the email is a GitHub noreply address.
+pub fn merge_to_pull_request(
+    merge: &MergeCommit,
+    repository: &str,
+    base_branch: &str,
+) -> MergedPullRequest {
+    let mut lines = merge.message.lines();
+    let subject = lines.next().unwrap_or_default();
+    let description = lines.find(|line| !line.trim().is_empty());
+
+    let github = subject
+        .strip_prefix("Merge pull request #")
+        .and_then(|rest| rest.split_once(" from "))
+        .and_then(|(number, head)| {
+            let (login, branch) = head.split_once('/')?;
+            Some((number.parse::<u64>().ok()?, login, branch))
+        });
+
+    let mut pr = imported_pull_request(
+        repository,
+        base_branch,
+        merge.merged_at,
+        merge.sha.clone(),
+    );
+    pr.title = subject.to_string();
+    match github {
+        Some((number, login, branch)) => {
+            let owner = repository.split('/').next().unwrap_or_default();
+            pr.number = number;
+            pr.author = login.to_string();
+            pr.head_branch = Some(branch.to_string());
+            pr.from_fork = !login.eq_ignore_ascii_case(owner);
+            pr.html_url =
+                Some(format!("https://github.com/{}/pull/{}", repository, number));
+            if let Some(title) = description {
+                pr.title = title.trim().to_string();
+            }
+        }
+        None => {
+            let prefix = merge.sha.get(..8).unwrap_or_default();
+            pr.number =
+                IMPORTED_NUMBER_BASE + u64::from_str_radix(prefix, 16).unwrap_or(0);
+            pr.author = login_from_noreply(&merge.author_email)
+                .unwrap_or(&merge.author_name)
+                .to_string();
+        }
+    }
+    pr
+}
+
+/// Merged PRs of `repository` ("owner/repo") in a GitHub export: the
+/// directory of an extracted migration archive, whose
+/// `pull_requests_*.json` files are read, or a decompressed GH Archive file
+/// of newline-delimited events, whose merged `PullRequestEvent`s are read.
+/// Blocks while reading; GH Archive files can be large.
+pub fn read_archive(
+    path: &Path,
+    repository: &str,
+) -> Result<Vec<MergedPullRequest>> {
+    let mut prs = Vec::new();
+    if path.is_dir() {
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(path)? {
+            let file = entry?.path();
+            let name = file.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            if name.starts_with("pull_requests_") && name.ends_with(".json") {
+                files.push(file);
+            }
+        }
+        files.sort();
+        for file in files {
+            let records: Vec<MigrationPullRequest> =
+                serde_json::from_slice(&std::fs::read(&file)?)?;
+            prs.extend(
+                records
+                    .into_iter()
+                    .filter_map(|record| record.into_merged(repository)),
+            );
+        }
+        return Ok(prs);
+    }
+
+    let reader = std::io::BufReader::new(std::fs::File::open(path)?);
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let event: ArchiveEvent = serde_json::from_str(&line)?;
+        let ours = event
+            .repo
+            .is_some_and(|r| r.name.eq_ignore_ascii_case(repository));
+        if event.kind != "PullRequestEvent" || !ours {
+            continue;
+        }
+        let payload: ArchivePayload = serde_json::from_value(event.payload)?;
+        if payload.action == "closed" {
+            prs.extend(payload.pull_request.into_merged(repository));
+        }
+    }
+    Ok(prs)
+}
+
+/// One entry of a migration archive's `pull_requests_*.json`. Users and
+/// repositories are given as URLs.
+#[derive(Debug, Deserialize)]
+struct MigrationPullRequest {
+    url: String,
+    user: Option<String>,
+    repository: String,
+    title: Option<String>,
+    body: Option<String>,
+    base: MigrationRef,
+    head: MigrationRef,
+    merged_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    merge_commit_sha: Option<String>,
+    #[serde(default)]
+    labels: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MigrationRef {
+    #[serde(rename = "ref")]
+    ref_field: String,
+    repo: Option<String>,
+}
+
+impl MigrationPullRequest {
+    fn into_merged(self, repository: &str) -> Option<MergedPullRequest> {
+        if !url_path(&self.repository).eq_ignore_ascii_case(repository) {
+            return None;
+        }
+        let number = url_path(&self.url).rsplit('/').next()?.parse().ok()?;
+        let mut pr = imported_pull_request(
+            repository,
+            &self.base.ref_field,
+            self.merged_at?,
+            self.merge_commit_sha
+                .unwrap_or_else(|| "unknown".to_string()),
+        );
+        pr.number = number;
+        pr.title = self.title.unwrap_or_else(|| "No title".to_string());
+        pr.author = self.user.as_deref().map_or("unknown", url_path).to_string();
+        pr.head_repository =
+            self.head.repo.as_deref().map(|r| url_path(r).to_string());
+        pr.head_branch = Some(self.head.ref_field);
+        pr.from_fork = !pr
+            .head_repository
+            .as_deref()
+            .is_some_and(|h| h.eq_ignore_ascii_case(repository));
+        pr.html_url = Some(self.url);
+        pr.labels = self
+            .labels
+            .iter()
+            .filter_map(|label| label.rsplit('/').next())
+            .map(str::to_string)
+            .collect();
+        pr.linked_issues = self
+            .body
+            .as_deref()
+            .map(|body| parse_issue_references(body, repository))
+            .unwrap_or_default();
+        pr.body = self.body;
+        Some(pr)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ArchiveEvent {
+    #[serde(rename = "type")]
+    kind: String,
+    repo: Option<ArchiveRepo>,
+    #[serde(default)]
+    payload: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct ArchiveRepo {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ArchivePayload {
+    action: String,
+    pull_request: ApiPullRequest,
+}
+
+/// The REST form of a PR, as embedded in GH Archive events. Only the
+/// fields stored are decoded.
+#[derive(Debug, Deserialize)]
+struct ApiPullRequest {
+    number: u64,
+    title: Option<String>,
+    user: Option<ApiUser>,
+    body: Option<String>,
+    html_url: Option<String>,
+    merged_at: Option<DateTime<Utc>>,
+    merge_commit_sha: Option<String>,
+    base: ApiRef,
+    head: ApiRef,
+    #[serde(default)]
+    labels: Vec<ApiLabel>,
+    author_association: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiUser {
+    login: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiRef {
+    #[serde(rename = "ref")]
+    ref_field: String,
+    repo: Option<ApiRepo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiRepo {
+    full_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiLabel {
+    name: String,
+}
+
+impl ApiPullRequest {
+    fn into_merged(self, repository: &str) -> Option<MergedPullRequest> {
+        let mut pr = imported_pull_request(
+            repository,
+            &self.base.ref_field,
+            self.merged_at?,
+            self.merge_commit_sha
+                .unwrap_or_else(|| "unknown".to_string()),
+        );
+        pr.number = self.number;
+        pr.title = self.title.unwrap_or_else(|| "No title".to_string());
+        pr.author = self.user.map_or_else(|| "unknown".to_string(), |u| u.login);
+        pr.head_repository = self.head.repo.and_then(|r| r.full_name);
+        pr.head_branch = Some(self.head.ref_field);
+        pr.from_fork = !pr
+            .head_repository
+            .as_deref()
+            .is_some_and(|h| h.eq_ignore_ascii_case(repository));
+        pr.html_url = self.html_url;
+        pr.labels = self.labels.into_iter().map(|label| label.name).collect();
+        pr.author_association = self.author_association;
+        pr.linked_issues = self
+            .body
+            .as_deref()
+            .map(|body| parse_issue_references(body, repository))
+            .unwrap_or_default();
+        pr.body = self.body;
+        Some(pr)
+    }
+}
+
+// An imported PR with the fields every source knows; callers fill in the
+// rest.
+fn imported_pull_request(
+    repository: &str,
+    base_branch: &str,
+    merged_at: DateTime<Utc>,
+    merge_commit_sha: String,
+) -> MergedPullRequest {
+    MergedPullRequest {
+        number: 0,
+        title: String::new(),
+        author: String::new(),
+        merged_at,
+        base_branch: base_branch.to_string(),
+        merge_commit_sha,
+        repository: repository.to_string(),
+        head_repository: None,
+        head_branch: None,
+        from_fork: false,
+        resolved_authors: Vec::new(),
+        body: None,
+        linked_issues: Vec::new(),
+        platform: "github".to_string(),
+        html_url: None,
+        labels: Vec::new(),
+        author_association: None,
+        externally_merged: false,
+        changed_files: Vec::new(),
+        imported: true,
+    }
+}
+
+// "owner/repo" (or "login") from a github.com URL
+fn url_path(url: &str) -> &str {
+    url.split_once("://")
+        .map_or(url, |(_, rest)| rest.split_once('/').map_or("", |(_, p)| p))
+        .trim_end_matches('/')
+}
+
+fn records(output: &str) -> impl Iterator<Item = &str> {
+    output
+        .split(RECORD)
+        .map(|record| record.trim_start_matches('\n'))
+        .filter(|record| !record.is_empty())
+}
+
+// Run git in `clone`, returning its stdout; a non-zero exit is an error
+// carrying git's stderr.
+async fn git(clone: &Path, args: &[&str], stdin: Option<String>) -> Result<String> {
+    let mut child = Command::new("git")
+        .arg("-C")
+        .arg(clone)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    if let Some(mut pipe) = child.stdin.take() {
+        pipe.write_all(stdin.unwrap_or_default().as_bytes()).await?;
+    }
+    let output = child.wait_with_output().await?;
+    if !output.status.success() {
+        return Err(GitCirclesError::Io(std::io::Error::other(format!(
+            "git {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn merge(message: &str, email: &str) -> MergeCommit {
+        MergeCommit {
+            sha: "0000002a9f00ba11".into(),
+            merged_at: Utc::now(),
+            message: message.into(),
+            author_name: "Alice Smith".into(),
+            author_email: email.into(),
+        }
+    }
+
+    #[test]
+    fn merges_become_imported_pull_requests() {
+        let pr = merge_to_pull_request(
+            &merge(
+                "Merge pull request #12 from bob/fix-typo\n\nFix typo in README",
+                "bob@example.com",
+            ),
+            "acme/widgets",
+            "main",
+        );
+        assert_eq!((pr.number, pr.author.as_str()), (12, "bob"));
+        assert_eq!(pr.title, "Fix typo in README");
+        assert_eq!(pr.head_branch.as_deref(), Some("fix-typo"));
+        assert!(pr.from_fork && pr.imported);
+
+        let pr = merge_to_pull_request(
+            &merge("Merge branch 'parser'", "7+alice@users.noreply.github.com"),
+            "acme/widgets",
+            "main",
+        );
+        assert_eq!(pr.number, IMPORTED_NUMBER_BASE + 0x2a);
+        assert_eq!(pr.author, "alice");
+        assert_eq!(pr.title, "Merge branch 'parser'");
+        assert_eq!(pr.html_url, None);
+
+        let pr = merge_to_pull_request(
+            &merge("Merge branch 'old'", "alice@example.com"),
+            "acme/widgets",
+            "main",
+        );
+        assert_eq!(pr.author, "Alice Smith");
+    }
+
+    #[test]
+    fn archives_yield_merged_pull_requests() {
+        let dir = tempfile::tempdir().unwrap();
+        let migration = serde_json::json!([
+            {
+                "type": "pull_request",
+                "url": "https://github.com/acme/widgets/pull/3",
+                "user": "https://github.com/bob",
+                "repository": "https://github.com/acme/widgets",
+                "title": "Add gears",
+                "body": "Fixes #1",
+                "base": {"ref": "main", "repo": "https://github.com/acme/widgets"},
+                "head": {"ref": "gears", "repo": "https://github.com/bob/widgets"},
+                "labels": ["https://github.com/acme/widgets/labels/feature"],
+                "merged_at": "2015-03-01T10:00:00Z"
+            },
+            {
+                "type": "pull_request",
+                "url": "https://github.com/acme/widgets/pull/4",
+                "user": "https://github.com/carol",
+                "repository": "https://github.com/acme/widgets",
+                "title": "Closed unmerged",
+                "base": {"ref": "main", "repo": null},
+                "head": {"ref": "nope", "repo": null},
+                "merged_at": null
+            }
+        ]);
+        std::fs::write(
+            dir.path().join("pull_requests_000001.json"),
+            migration.to_string(),
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("users_000001.json"), "[]").unwrap();
+
+        let prs = read_archive(dir.path(), "acme/widgets").unwrap();
+        assert_eq!(prs.len(), 1);
+        let pr = &prs[0];
+        assert_eq!((pr.number, pr.author.as_str()), (3, "bob"));
+        assert_eq!(pr.head_repository.as_deref(), Some("bob/widgets"));
+        assert_eq!(pr.labels, ["feature"]);
+        assert_eq!(pr.linked_issues.len(), 1);
+        assert!(pr.from_fork && pr.imported);
+
+        let event = |repo: &str, action: &str, number: u64, merged: bool| {
+            serde_json::json!({
+                "type": "PullRequestEvent",
+                "repo": {"name": repo},
+                "payload": {
+                    "action": action,
+                    "pull_request": {
+                        "number": number,
+                        "title": "Speed up",
+                        "user": {"login": "dave"},
+                        "merged_at": merged.then_some("2015-03-02T10:00:00Z"),
+                        "merge_commit_sha": "abc123",
+                        "base": {"ref": "main", "repo": {"full_name": repo}},
+                        "head": {"ref": "fast", "repo": {"full_name": repo}},
+                        "author_association": "MEMBER"
+                    }
+                }
+            })
+            .to_string()
+        };
+        let lines = [
+            serde_json::json!({"type": "WatchEvent", "repo": {"name": "acme/widgets"}})
+                .to_string(),
+            event("acme/widgets", "opened", 5, false),
+            event("acme/widgets", "closed", 5, true),
+            event("acme/widgets", "closed", 6, false),
+            event("other/repo", "closed", 7, true),
+            String::new(),
+        ];
+        let file = dir.path().join("2015-03-02-10.json");
+        std::fs::write(&file, lines.join("\n")).unwrap();
+
+        let prs = read_archive(&file, "acme/widgets").unwrap();
+        assert_eq!(prs.len(), 1);
+        assert_eq!((prs[0].number, prs[0].author.as_str()), (5, "dave"));
+        assert_eq!(prs[0].merge_commit_sha, "abc123");
+        assert!(!prs[0].from_fork);
+    }
+
+    #[test]
+    fn remotes_name_their_repository() {
+        for url in [
+            "git@github.com:acme/widgets.git",
+            "https://github.com/acme/widgets",
+            "ssh://git@github.com/acme/widgets.git/",
+        ] {
+            assert_eq!(
+                repository_from_remote(url).as_deref(),
+                Some("acme/widgets")
+            );
+        }
+        assert_eq!(repository_from_remote("widgets"), None);
+    }
+}
@@ -1,11 +1,45 @@
+pub mod announcement;
+pub mod anomalies;
+#[cfg(feature = "cli")]
 pub mod cli;
+pub mod clock;
+pub mod config;
 pub mod database;
+pub mod digest;
+pub mod doctor;
+pub mod ergo;
+pub mod gitcircles;
 pub mod github;
+pub mod hooks;
+pub mod import;
+pub mod output;
+pub mod paths;
+pub mod price;
+pub mod privacy;
+#[cfg(any(test, feature = "http-replay"))]
+pub mod replay;
+pub mod treasury;
 pub mod types;
 pub mod wallet;
 
+pub use announcement::*;
+pub use anomalies::*;
+#[cfg(feature = "cli")]
 pub use cli::*;
+pub use clock::*;
+pub use config::*;
 pub use database::*;
+pub use digest::*;
+pub use doctor::*;
+pub use ergo::*;
+pub use gitcircles::*;
 pub use github::*;
+pub use hooks::*;
+pub use import::*;
+pub use output::*;
+pub use paths::*;
+pub use price::*;
+pub use privacy::*;
+pub use treasury::*;
 pub use types::*;
 pub use wallet::*;
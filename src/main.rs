@@ -1,154 +1,291 @@
-use chrono::Utc;
-use clap::Parser;
+use chrono_tz::Tz;
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser};
+use std::process::ExitCode;
 
 use gitcircles_github::{
     cli::{
-        Cli, Commands, ProjectCommands, WalletCommands, display_project_details,
-        display_projects, display_pull_requests, display_repository_status,
-        display_user_wallet, display_wallet_history, display_wallet_logins,
+        AddressBookCommands, Cli, Commands, ConfigCommands, ContributorCommands,
+        ImportCommands, NodeCommands, PrCommands, PrivacyCommands, ProjectCommands,
+        RepoCommands, ReportCommands, TreasuryCommands, WalletCommands,
+        display_address_book, display_anomalies, display_contributor_preferences,
+        display_contributor_profile, display_digest, display_doctor_checks,
+        display_effective_settings, display_error, display_forget_report,
+        display_import, display_node_health, display_opt_outs,
+        display_pr_search_results, display_project_details, display_project_status,
+        display_project_treasuries, display_projects, display_pull_request_changes,
+        display_pull_request_details, display_pull_requests,
+        display_repository_metadata, display_repository_status, display_sync_log,
+        display_treasury_history, display_user_wallet, display_wallet_history,
+        display_wallet_logins, display_wallet_transitions, display_wallet_warnings,
+        erg, parse_timestamp, print_pull_requests, print_wallet_lookups,
     },
+    config::{Config, get_config_path},
     database::Database,
-    github::GitHubClient,
+    doctor::{CheckStatus, DoctorOptions, run_doctor},
+    ergo::{DEFAULT_NODE_URL, ErgoNodeClient},
+    gitcircles::{CollectOptions, GitCircles, resolve_github_token},
+    github::{MergedRange, PageLimits},
+    output::{
+        Icon, OutputSettings, info, init_output, local_time, output, status, warn,
+    },
+    privacy::Pseudonymizer,
+    treasury::route_payout,
     types::{
-        GitCirclesError, Project, ProjectOwner, Repository, Result, WalletAddress,
-        generate_project_id, get_database_path, parse_repo,
+        AddressKind, ContributorList, GitCirclesError, Result, WalletAddress,
+        get_database_path, parse_repo,
     },
-    wallet::WalletService,
+    wallet::{lookup_wallets, wallet_transitions},
 };
 
+/// Open the default database, acting as the `--as` login if given.
+fn open_app(cli: &Cli) -> Result<GitCircles> {
+    let app = GitCircles::open_default()?
+        .with_lenient_reads(cli.lenient)
+        .with_dry_run(cli.dry_run);
+    Ok(match &cli.actor {
+        Some(login) => app.with_actor(login),
+        None => app,
+    })
+}
+
+/// The export pseudonymizer when `--pseudonymize` is given.
+fn pseudonymizer(enabled: bool) -> Result<Option<Pseudonymizer>> {
+    if !enabled {
+        return Ok(None);
+    }
+    Config::load_default()?.privacy.pseudonymizer().map(Some)
+}
+
+// No amount can exceed the total ERG supply
+const MAX_ERG: f64 = 97_739_924.0;
+
+/// An ERG amount from the command line in nanoERG.
+fn nanoerg(erg: f64) -> Result<u64> {
+    if !erg.is_finite() || erg < 0.0 {
+        return Err(GitCirclesError::InvalidInput(format!(
+            "Invalid ERG amount: {}",
+            erg
+        )));
+    }
+    if erg > MAX_ERG {
+        return Err(GitCirclesError::InvalidInput(format!(
+            "ERG amount {} exceeds the total supply of {} ERG",
+            erg, MAX_ERG
+        )));
+    }
+    Ok((erg * 1_000_000_000.0).round() as u64)
+}
+
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
     let cli = Cli::parse();
+    // A broken config file falls back to UTC here; the commands reading
+    // the config report it.
+    let timezone = cli.timezone.unwrap_or_else(|| {
+        Config::load_default().map_or(Tz::UTC, |config| config.display.timezone)
+    });
+    init_output(
+        OutputSettings::new(cli.no_color, cli.quiet, cli.ascii)
+            .with_timezone(timezone),
+    );
+
+    match run(&cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            display_error(&e, cli.output_format());
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
 
+async fn run(cli: &Cli) -> Result<()> {
+    if cli.dry_run && !cli.supports_dry_run() {
+        return Err(GitCirclesError::InvalidInput(
+            "--dry-run is not supported by this command".to_string(),
+        ));
+    }
     match &cli.command {
         Commands::Collect {
             repo,
             token,
             base_branch,
             days,
+            from,
+            to,
             project_id,
+            skip_commit_authors,
+            max_body_chars,
+            per_page,
+            max_pages,
+            max_prs,
+            refresh,
+            detect_external_merges,
+            paths,
         } => {
-            let db = Database::new(&get_database_path()?)?;
             let (owner, repo_name) = parse_repo(repo)?;
+            let merged_range = from.map(|from| MergedRange {
+                from,
+                to: to.unwrap_or_else(|| chrono::Utc::now().date_naive()),
+            });
+            if let Some(range) = merged_range
+                && range.from > range.to
+            {
+                Cli::command()
+                    .error(
+                        ErrorKind::ValueValidation,
+                        format!("--from {} is after --to {}", range.from, range.to),
+                    )
+                    .exit();
+            }
+            let github_token = resolve_github_token(token.as_deref())?;
+            let app = open_app(cli)?.with_github_token(&github_token)?;
 
-            // Get token from arg or environment
-            let github_token = token.clone()
-                .or_else(|| std::env::var("GITHUB_TOKEN").ok())
-                .ok_or_else(|| GitCirclesError::Auth("GitHub token required. Use --token or set GITHUB_TOKEN environment variable".to_string()))?;
-
-            println!(
+            info(format!(
                 "Collecting merged PRs from {}/{} (base: {})",
                 owner, repo_name, base_branch
-            );
+            ));
             if let Some(days) = days {
-                println!("Looking back {} days", days);
+                info(format!("Looking back {} days", days));
             }
-
-            // Validate project_id if provided
-            if let Some(pid) = project_id
-                && db.get_project(pid)?.is_none()
-            {
-                return Err(GitCirclesError::DatabasePath(format!(
-                    "Project '{}' not found",
-                    pid
-                )));
+            if let Some(range) = merged_range {
+                info(format!(
+                    "Backfilling PRs merged {} to {}",
+                    range.from, range.to
+                ));
+            }
+            if cli.dry_run {
+                info("Dry run: nothing will be stored");
             }
 
-            // Check/update repository tracking
-            let mut repo_record = db
-                .get_repository(&owner, &repo_name)?
-                .unwrap_or_else(|| Repository {
-                    owner: owner.clone(),
-                    name: repo_name.clone(),
-                    current_base_branch: base_branch.clone(),
-                    last_sync: None,
-                    total_prs: 0,
-                    first_sync: Utc::now(),
+            let outcome = app
+                .collect(&CollectOptions {
+                    owner,
+                    repo: repo_name,
+                    base_branch: base_branch.clone(),
+                    days: *days,
                     project_id: project_id.clone(),
-                });
-
-            // Update project_id if provided
-            if project_id.is_some() {
-                repo_record.project_id = project_id.clone();
-            }
+                    resolve_authors: !skip_commit_authors,
+                    max_body_chars: Some(*max_body_chars),
+                    refresh: *refresh,
+                    detect_external_merges: *detect_external_merges,
+                    paths: paths.clone(),
+                    merged_range,
+                    limits: PageLimits {
+                        per_page: *per_page,
+                        max_pages: *max_pages,
+                    },
+                    max_prs: *max_prs,
+                })
+                .await?;
 
-            // Detect base branch changes
-            if repo_record.current_base_branch != *base_branch {
-                db.record_base_branch_change(
-                    &format!("{}/{}", owner, repo_name),
-                    &repo_record.current_base_branch,
-                    base_branch,
-                )?;
-                println!(
-                    "📝 Base branch changed from '{}' to '{}'",
-                    repo_record.current_base_branch, base_branch
+            if let Some(change) = &outcome.base_branch_change {
+                status(
+                    Icon::Note,
+                    format!(
+                        "Base branch changed from '{}' to '{}'",
+                        change.old_branch, change.new_branch
+                    ),
                 );
-                repo_record.current_base_branch = base_branch.clone();
             }
 
-            // Create GitHub client and fetch PRs
-            let github_client = GitHubClient::new(&github_token)?;
-            let fetched_prs = github_client
-                .fetch_merged_pull_requests(&owner, &repo_name, base_branch, *days)
-                .await?;
-
-            // Filter out already-stored PRs (deduplication)
-            let mut new_prs = Vec::new();
-            for pr in fetched_prs {
-                if !db.pull_request_exists(&pr.repository, pr.number)? {
-                    db.upsert_pull_request(&pr)?;
-                    new_prs.push(pr);
+            if !outcome.refreshed.is_empty() {
+                for change in &outcome.refreshed {
+                    info(format!(
+                        "#{} {}: '{}' -> '{}'",
+                        change.number, change.field, change.old, change.new
+                    ));
                 }
+                let mut numbers: Vec<u64> =
+                    outcome.refreshed.iter().map(|c| c.number).collect();
+                numbers.dedup();
+                let verb = if cli.dry_run {
+                    "Would update"
+                } else {
+                    "Updated"
+                };
+                status(Icon::Ok, format!("{} {} stored PRs", verb, numbers.len()));
             }
 
-            // Update repository metadata
-            repo_record.last_sync = Some(Utc::now());
-            repo_record.total_prs += new_prs.len() as u64;
-            db.upsert_repository(&repo_record)?;
-
             // Display results
-            if new_prs.is_empty() {
+            if outcome.new_prs.is_empty() {
                 println!(
                     "No new merged PRs found. {} total PRs tracked.",
-                    repo_record.total_prs
+                    outcome.repository.total_prs
                 );
             } else {
-                display_pull_requests(&new_prs);
-                println!(
-                    "✓ Added {} new PRs. {} total PRs tracked.",
-                    new_prs.len(),
-                    repo_record.total_prs
+                display_pull_requests(&outcome.new_prs);
+                let verb = if cli.dry_run { "Would add" } else { "Added" };
+                status(
+                    Icon::Ok,
+                    format!(
+                        "{} {} new PRs. {} total PRs tracked.",
+                        verb,
+                        outcome.new_prs.len(),
+                        outcome.repository.total_prs
+                    ),
+                );
+            }
+            for failure in &outcome.hook_failures {
+                warn(failure);
+            }
+            for (project_id, exclusion) in &outcome.org_exclusions {
+                status(
+                    Icon::Note,
+                    format!(
+                        "{} is a member of {}; their PRs don't count for project {}",
+                        exclusion.login, exclusion.org, project_id
+                    ),
+                );
+            }
+            if outcome.deferred > 0 {
+                status(
+                    Icon::Note,
+                    format!(
+                        "Stopped at --max-prs; {} more new PRs will be collected by the next run",
+                        outcome.deferred
+                    ),
                 );
             }
         }
-        Commands::Status { project_id } => {
-            let db = Database::new(&get_database_path()?)?;
+        Commands::Status {
+            project_id,
+            detailed,
+        } => {
+            let app = open_app(cli)?;
 
             if let Some(pid) = project_id {
-                // Show status for specific project
-                let project = db.get_project(pid)?.ok_or_else(|| {
-                    GitCirclesError::DatabasePath(format!(
-                        "Project '{}' not found",
-                        pid
-                    ))
-                })?;
-                let owners = db.get_project_owners(pid)?;
-                let repos = db.list_repositories_for_project(pid)?;
-                display_project_details(&project, &owners, &repos);
+                let project_status = app.project_status(pid).await?;
+                display_project_status(&project_status);
+                if *detailed && !project_status.repositories.is_empty() {
+                    println!("\n{} Repository details:", Icon::Repos);
+                    display_repository_metadata(&project_status.repositories);
+                }
             } else {
                 // Show overall status
-                let repos = db.list_repositories()?;
-                let projects = db.list_projects()?;
+                let (repos, projects) = app
+                    .database()
+                    .run(|db| {
+                        Ok((
+                            db.list_repositories()?,
+                            db.list_projects_with_counters()?,
+                        ))
+                    })
+                    .await?;
 
                 if !projects.is_empty() {
-                    println!("📋 Projects:");
+                    println!("{} Projects:", Icon::Project);
                     display_projects(&projects);
                     println!();
                 }
 
                 if !repos.is_empty() {
-                    println!("📦 All Repositories:");
+                    println!("{} All Repositories:", Icon::Repos);
                     display_repository_status(&repos);
+                    if *detailed {
+                        println!("\n{} Repository details:", Icon::Repos);
+                        display_repository_metadata(&repos);
+                    }
                 } else if projects.is_empty() {
                     println!("No repositories or projects being tracked.");
                     println!(
@@ -161,27 +298,113 @@ async fn main() -> Result<()> {
             }
         }
         Commands::Init => {
-            println!("Initializing GitCircles database...");
+            info("Initializing GitCircles database...");
             let db_path = get_database_path()?;
-            let _db = Database::new(&db_path)?;
-            println!("✓ Database initialized at: {}", db_path);
+            let db = Database::new(&db_path)?.with_lenient_reads(cli.lenient);
+            status(Icon::Ok, format!("Database initialized at: {}", db_path));
+
+            let migrated = db.run(|db| db.backfill_pull_request_urls()).await?;
+            if migrated > 0 {
+                status(
+                    Icon::Ok,
+                    format!("Added web URLs to {} stored PRs", migrated),
+                );
+            }
         }
+        Commands::Doctor {
+            token,
+            node_url,
+            node_api_key,
+        } => {
+            let opts = DoctorOptions {
+                config_path: get_config_path()?.into(),
+                database_path: get_database_path()?,
+                token: token.clone(),
+                github_api_base: None,
+                node_url: node_url
+                    .clone()
+                    .or_else(|| std::env::var("ERGO_NODE_URL").ok())
+                    .unwrap_or_else(|| DEFAULT_NODE_URL.to_string()),
+                node_api_key: node_api_key
+                    .clone()
+                    .or_else(|| std::env::var("ERGO_NODE_API_KEY").ok()),
+            };
+            let checks = run_doctor(&opts).await;
+            display_doctor_checks(&checks);
+
+            let failed: Vec<&str> = checks
+                .iter()
+                .filter(|c| c.status == CheckStatus::Fail)
+                .map(|c| c.name)
+                .collect();
+            if !failed.is_empty() {
+                return Err(GitCirclesError::Config(format!(
+                    "doctor checks failed: {}",
+                    failed.join(", ")
+                )));
+            }
+            status(Icon::Ok, "All checks passed");
+        }
+        Commands::Config(config_cmd) => match config_cmd {
+            ConfigCommands::Validate { file } => {
+                let path = match file {
+                    Some(path) => path.clone(),
+                    None => get_config_path()?.into(),
+                };
+                let text = match std::fs::read_to_string(&path) {
+                    Ok(text) => text,
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                        info(format!(
+                            "No config file at {}; defaults apply",
+                            path.display()
+                        ));
+                        return Ok(());
+                    }
+                    Err(e) => return Err(e.into()),
+                };
+                let problems = Config::check(&text);
+                if !problems.is_empty() {
+                    for problem in &problems {
+                        eprintln!("{} {}", Icon::Fail, problem);
+                    }
+                    return Err(GitCirclesError::Config(format!(
+                        "{} has {} problem(s)",
+                        path.display(),
+                        problems.len()
+                    )));
+                }
+                status(Icon::Ok, format!("{} is valid", path.display()));
+            }
+            ConfigCommands::Show { effective } => {
+                let path = get_config_path()?;
+                let config = Config::load(std::path::Path::new(&path))?;
+                if *effective {
+                    let text = std::fs::read_to_string(&path).ok();
+                    display_effective_settings(
+                        &cli.effective_settings(&config, text.as_deref())?,
+                    );
+                } else {
+                    info(format!("# {}", path));
+                    print!(
+                        "{}",
+                        toml::to_string(&config.redacted()).unwrap_or_default()
+                    );
+                }
+            }
+        },
         Commands::TestToken { token } => {
-            let github_token = token.clone()
-                .or_else(|| std::env::var("GITHUB_TOKEN").ok())
-                .ok_or_else(|| GitCirclesError::Auth("GitHub token required. Use --token or set GITHUB_TOKEN environment variable".to_string()))?;
+            let github_token = resolve_github_token(token.as_deref())?;
 
-            println!("Testing GitHub token authentication...");
-            let github_client = GitHubClient::new(&github_token)?;
+            info("Testing GitHub token authentication...");
+            let app = open_app(cli)?.with_github_token(&github_token)?;
 
-            match github_client.test_token().await {
+            match app.test_token().await {
                 Ok(username) => {
-                    println!("✓ Token is valid!");
-                    println!("✓ Authenticated as: {}", username);
+                    status(Icon::Ok, "Token is valid!");
+                    status(Icon::Ok, format!("Authenticated as: {}", username));
                 }
                 Err(e) => {
-                    eprintln!("✗ Token authentication failed!");
-                    eprintln!("Error: {}", e);
+                    eprintln!("{} Token authentication failed!", Icon::Fail);
                     eprintln!("\nTroubleshooting:");
                     eprintln!(
                         "1. Make sure your token starts with 'ghp_' or 'github_pat_'"
@@ -195,53 +418,267 @@ async fn main() -> Result<()> {
             }
         }
         Commands::Wallet(wallet_cmd) => {
-            let db = Database::new(&get_database_path()?)?;
+            let app = open_app(cli)?;
+            let db = app.database();
 
             match wallet_cmd {
-                WalletCommands::Sync { login, token } => {
-                    // Get token from arg or environment
-                    let github_token = token.clone()
-                        .or_else(|| std::env::var("GITHUB_TOKEN").ok())
-                        .ok_or_else(|| GitCirclesError::Auth("GitHub token required. Use --token or set GITHUB_TOKEN environment variable".to_string()))?;
-
-                    let github_client = GitHubClient::new(&github_token)?;
+                WalletCommands::Sync {
+                    login,
+                    token,
+                    force,
+                    notify,
+                    confirmation_hours,
+                    wallet_ref,
+                } => {
+                    let github_token = resolve_github_token(token.as_deref())?;
+                    let app = app
+                        .with_github_token(&github_token)?
+                        .with_wallet_confirmation_window(chrono::Duration::hours(
+                            *confirmation_hours,
+                        ));
 
-                    println!("Syncing wallet for GitHub user: {}", login);
+                    info(format!("Syncing wallet for GitHub user: {}", login));
+                    if cli.dry_run {
+                        info("Dry run: nothing will be stored");
+                    }
 
-                    let wallet_service = WalletService::new(&db, &github_client);
-                    match wallet_service.sync_github_login(login).await? {
+                    match app.sync_wallet(login, *force, wallet_ref.clone()).await?
+                    {
                         Some(result) => {
                             if result.changed {
+                                let (updated, added) = if cli.dry_run {
+                                    ("Wallet would change", "Wallet would be added")
+                                } else {
+                                    ("Wallet updated", "Wallet added")
+                                };
                                 if let Some(prev) = result.previous {
-                                    println!(
-                                        "✓ Wallet updated from {} to {}",
-                                        prev, result.current
+                                    status(
+                                        Icon::Ok,
+                                        format!(
+                                            "{} from {} to {}",
+                                            updated, prev, result.current
+                                        ),
                                     );
                                 } else {
-                                    println!("✓ Wallet added: {}", result.current);
+                                    status(
+                                        Icon::Ok,
+                                        format!("{}: {}", added, result.current),
+                                    );
                                 }
                             } else {
-                                println!("✓ Wallet unchanged: {}", result.current);
+                                status(
+                                    Icon::Ok,
+                                    format!("Wallet unchanged: {}", result.current),
+                                );
+                            }
+                            if !result.shared_with.is_empty() {
+                                warn(format!(
+                                    "Address is also linked to: {}",
+                                    result.shared_with.join(", ")
+                                ));
+                            }
+                            if let Some(rotation) = &result.rotation {
+                                warn(format!(
+                                    "New payouts wait until {} for the change to be confirmed",
+                                    local_time(
+                                        rotation.effective_at,
+                                        "%Y-%m-%d %H:%M %Z"
+                                    )
+                                ));
+                                if *notify && cli.dry_run {
+                                    info(format!(
+                                        "Would open an issue on {}/gitcircles-profile",
+                                        login
+                                    ));
+                                } else if *notify {
+                                    let issue = app
+                                        .notify_wallet_rotation(rotation)
+                                        .await?;
+                                    status(
+                                        Icon::Ok,
+                                        format!(
+                                            "Opened {}/gitcircles-profile#{}",
+                                            login, issue
+                                        ),
+                                    );
+                                }
+                            }
+                        }
+                        None => {
+                            println!("No wallet found for user '{}'", login);
+                            if let Some(miss) = app
+                                .database()
+                                .get_wallet_probe_miss("github", login)?
+                            {
+                                println!(
+                                    "Next check after {} (use --force to check now)",
+                                    local_time(
+                                        miss.retry_after,
+                                        "%Y-%m-%d %H:%M %Z"
+                                    )
+                                );
                             }
                         }
-                        None => println!("No wallet found for user '{}'", login),
                     }
                 }
                 WalletCommands::Show { login } => {
-                    match db.get_user_wallet("github", login)? {
-                        Some(wallet) => display_user_wallet(&wallet),
+                    let who = login.clone();
+                    let (wallet, rotation, prefs) = db
+                        .run(move |db| {
+                            Ok((
+                                db.get_user_wallet("github", &who)?,
+                                db.get_wallet_rotation("github", &who)?,
+                                db.get_contributor_preferences("github", &who)?,
+                            ))
+                        })
+                        .await?;
+                    match wallet {
+                        Some(wallet) => {
+                            display_user_wallet(&wallet);
+                            if let Some(rotation) = rotation
+                                && rotation.is_pending(chrono::Utc::now())
+                            {
+                                warn(format!(
+                                    "Changed from {} on {}; new payouts wait until {}",
+                                    rotation.previous,
+                                    local_time(
+                                        rotation.detected_at,
+                                        "%Y-%m-%d %H:%M %Z"
+                                    ),
+                                    local_time(
+                                        rotation.effective_at,
+                                        "%Y-%m-%d %H:%M %Z"
+                                    )
+                                ));
+                            }
+                            if let Some(prefs) = prefs {
+                                display_contributor_preferences(&prefs);
+                            }
+                        }
                         None => {
                             eprintln!("Error: No wallet found for user '{}'", login)
                         }
                     }
                 }
-                WalletCommands::History { login } => {
-                    let history = db.get_wallet_history("github", login)?;
-                    display_wallet_history(&history);
+                WalletCommands::History { login, diff } => {
+                    let who = login.clone();
+                    let history = db
+                        .run(move |db| db.get_wallet_history("github", &who))
+                        .await?;
+                    if *diff {
+                        display_wallet_transitions(&wallet_transitions(
+                            &history,
+                            chrono::Utc::now(),
+                        ));
+                    } else {
+                        display_wallet_history(&history);
+                    }
+                }
+                WalletCommands::Rollback { login, to } => {
+                    let to = parse_timestamp(to, output().timezone)
+                        .map_err(GitCirclesError::InvalidInput)?;
+                    let result = app.rollback_wallet(login, to).await?;
+                    if result.changed {
+                        status(
+                            Icon::Ok,
+                            format!(
+                                "Wallet for {} restored to {} ({})",
+                                login, result.current, result.source
+                            ),
+                        );
+                    } else {
+                        status(
+                            Icon::Ok,
+                            format!("Wallet unchanged: {}", result.current),
+                        );
+                    }
+                    if !result.shared_with.is_empty() {
+                        warn(format!(
+                            "Address is also linked to: {}",
+                            result.shared_with.join(", ")
+                        ));
+                    }
+                }
+                WalletCommands::Warnings => {
+                    let warnings = db.run(|db| db.list_wallet_warnings()).await?;
+                    display_wallet_warnings(&warnings);
+                }
+                WalletCommands::InitProfile {
+                    address,
+                    preferences,
+                    token,
+                } => {
+                    let preferences = preferences
+                        .as_ref()
+                        .map(std::fs::read_to_string)
+                        .transpose()?;
+                    let github_token = resolve_github_token(token.as_deref())?;
+                    let app = app.with_github_token(&github_token)?;
+                    let outcome =
+                        app.init_profile(address, preferences.as_deref()).await?;
+                    let repo = format!("{}/gitcircles-profile", outcome.login);
+                    if outcome.created_repository {
+                        status(Icon::Ok, format!("Created repository {}", repo));
+                    }
+                    if outcome.committed_files.is_empty() {
+                        status(Icon::Ok, format!("{} is already up to date", repo));
+                    } else {
+                        status(
+                            Icon::Ok,
+                            format!(
+                                "Committed {} to {} ({})",
+                                outcome.committed_files.join(", "),
+                                repo,
+                                outcome.branch
+                            ),
+                        );
+                    }
+                    println!(
+                        "Run 'gitcircles-github wallet sync {}' to record the address",
+                        outcome.login
+                    );
+                }
+                WalletCommands::LookupMany {
+                    file,
+                    format,
+                    pseudonymize,
+                } => {
+                    let pseudonymizer = pseudonymizer(*pseudonymize)?;
+                    let list = match file {
+                        Some(path) if path.as_os_str() != "-" => {
+                            std::fs::read_to_string(path)?
+                        }
+                        _ => std::io::read_to_string(std::io::stdin())?,
+                    };
+                    let (links, book) = db
+                        .run(|db| {
+                            Ok((db.list_wallet_links()?, db.list_address_book()?))
+                        })
+                        .await?;
+                    let mut lookups = lookup_wallets(&list, &links, &book);
+                    if let Some(pseudonymizer) = &pseudonymizer {
+                        lookups
+                            .iter_mut()
+                            .for_each(|l| pseudonymizer.wallet_lookup(l));
+                    }
+                    print_wallet_lookups(&lookups, *format);
                 }
                 WalletCommands::Lookup { wallet } => {
                     let wallet_addr = WalletAddress::try_from(wallet.as_str())?;
-                    let links = db.get_logins_for_wallet(&wallet_addr, "github")?;
+                    let (entry, links) = db
+                        .run(move |db| {
+                            Ok((
+                                db.get_address_book_entry(&wallet_addr)?,
+                                db.get_logins_for_wallet(&wallet_addr, "github")?,
+                            ))
+                        })
+                        .await?;
+                    if let Some(entry) = entry {
+                        info(format!("Known as {}", entry.label()));
+                        if entry.kind == AddressKind::Blacklisted {
+                            warn("This address is blacklisted");
+                        }
+                    }
                     let tuples: Vec<(String, String)> = links
                         .iter()
                         .map(|l| (l.platform.clone(), l.login.clone()))
@@ -250,26 +687,386 @@ async fn main() -> Result<()> {
                 }
             }
         }
+        Commands::Pr(pr_cmd) => {
+            let app = open_app(cli)?;
+
+            match pr_cmd {
+                PrCommands::Show {
+                    repo,
+                    number,
+                    format,
+                    pseudonymize,
+                } => {
+                    let pseudonymizer = pseudonymizer(*pseudonymize)?;
+                    let (owner, name) = parse_repo(repo)?;
+                    let repo_full = format!("{}/{}", owner, name);
+                    let number = *number;
+                    let mut pr = app
+                        .database()
+                        .run(move |db| db.get_pull_request(&repo_full, number))
+                        .await?
+                        .ok_or_else(|| {
+                            GitCirclesError::NotFound(format!(
+                                "Pull request {}#{} not found",
+                                repo, number
+                            ))
+                        })?;
+                    if let Some(pseudonymizer) = &pseudonymizer {
+                        pseudonymizer.pull_request(&mut pr);
+                    }
+                    let (repo_full, number) = (pr.repository.clone(), pr.number);
+                    let changes = app
+                        .database()
+                        .run(move |db| {
+                            db.get_pull_request_changes(&repo_full, number)
+                        })
+                        .await?;
+                    print_pull_requests(&[pr], *format, |prs| {
+                        display_pull_request_details(&prs[0]);
+                        display_pull_request_changes(&changes);
+                    });
+                }
+                PrCommands::Search {
+                    query,
+                    repo,
+                    reindex,
+                    format,
+                    pseudonymize,
+                } => {
+                    let pseudonymizer = pseudonymizer(*pseudonymize)?;
+                    if *reindex {
+                        let count = app
+                            .database()
+                            .run(|db| db.rebuild_pr_search_index())
+                            .await?;
+                        eprintln!("{} Reindexed {} pull requests", Icon::Ok, count);
+                    }
+                    let repo = repo
+                        .as_deref()
+                        .map(parse_repo)
+                        .transpose()?
+                        .map(|(owner, name)| format!("{}/{}", owner, name));
+                    let mut prs =
+                        app.search_pull_requests(query, repo.as_deref()).await?;
+                    if let Some(pseudonymizer) = &pseudonymizer {
+                        prs.iter_mut()
+                            .for_each(|pr| pseudonymizer.pull_request(pr));
+                    }
+                    print_pull_requests(&prs, *format, display_pr_search_results);
+                }
+                PrCommands::Labels { repo, token } => {
+                    let repo = repo
+                        .as_deref()
+                        .map(parse_repo)
+                        .transpose()?
+                        .map(|(owner, name)| format!("{}/{}", owner, name));
+                    let github_token = resolve_github_token(token.as_deref())?;
+                    let app = app.with_github_token(&github_token)?;
+                    let applied = app.sync_labels(repo.as_deref()).await?;
+                    for label in &applied {
+                        info(format!(
+                            "{}#{}: {}",
+                            label.repository, label.number, label.label
+                        ));
+                    }
+                    status(Icon::Ok, format!("Labeled {} PRs", applied.len()));
+                }
+                PrCommands::Statuses { repo, token } => {
+                    let repo = repo
+                        .as_deref()
+                        .map(parse_repo)
+                        .transpose()?
+                        .map(|(owner, name)| format!("{}/{}", owner, name));
+                    let github_token = resolve_github_token(token.as_deref())?;
+                    let app = app.with_github_token(&github_token)?;
+                    let published = app.sync_statuses(repo.as_deref()).await?;
+                    for commit in &published {
+                        info(format!(
+                            "{}#{} ({}): {}",
+                            commit.repository,
+                            commit.number,
+                            &commit.sha[..commit.sha.len().min(7)],
+                            commit.state
+                        ));
+                    }
+                    status(
+                        Icon::Ok,
+                        format!("Published {} commit statuses", published.len()),
+                    );
+                }
+            }
+        }
+        Commands::Contributor(contributor_cmd) => {
+            let app = open_app(cli)?;
+
+            match contributor_cmd {
+                ContributorCommands::OptOut { login } => {
+                    let opt_out = app.opt_out(login).await?;
+                    status(
+                        Icon::Ok,
+                        format!(
+                            "{} is opted out (since {})",
+                            login,
+                            local_time(opt_out.recorded_at, "%Y-%m-%d")
+                        ),
+                    );
+                }
+                ContributorCommands::OptIn { login } => {
+                    if app.opt_in(login).await? {
+                        status(Icon::Ok, format!("{} opted back in", login));
+                    } else {
+                        println!("{} had not opted out", login);
+                    }
+                }
+                ContributorCommands::Show { login } => {
+                    display_contributor_profile(
+                        &app.contributor_profile(login).await?,
+                    );
+                }
+                ContributorCommands::OptOuts => {
+                    let opt_outs = app
+                        .database()
+                        .run(|db| db.list_contributor_opt_outs())
+                        .await?;
+                    display_opt_outs(&opt_outs);
+                }
+                ContributorCommands::ScanComments {
+                    repo,
+                    number,
+                    token,
+                } => {
+                    let (owner, name) = parse_repo(repo)?;
+                    let github_token = resolve_github_token(token.as_deref())?;
+                    let app = app.with_github_token(&github_token)?;
+                    let recorded = app
+                        .scan_opt_out_comments(
+                            app.github()?,
+                            &owner,
+                            &name,
+                            *number,
+                        )
+                        .await?;
+                    if recorded.is_empty() {
+                        println!("No new opt-outs found on {}#{}", repo, number);
+                    } else {
+                        display_opt_outs(&recorded);
+                    }
+                }
+            }
+        }
+        Commands::AddressBook(book_cmd) => {
+            let app = open_app(cli)?;
+
+            match book_cmd {
+                AddressBookCommands::Set {
+                    address,
+                    name,
+                    kind,
+                    note,
+                } => {
+                    let kind: AddressKind = kind.parse()?;
+                    let entry = app
+                        .set_address_label(address, name, kind, note.as_deref())
+                        .await?;
+                    status(
+                        Icon::Ok,
+                        format!("Named {} as {}", entry.address, entry.label()),
+                    );
+                }
+                AddressBookCommands::Remove { address } => {
+                    if app.remove_address_label(address).await? {
+                        status(Icon::Ok, format!("Removed {}", address));
+                    } else {
+                        info(format!("{} is not in the address book", address));
+                    }
+                }
+                AddressBookCommands::List => {
+                    display_address_book(&app.address_book().await?);
+                }
+            }
+        }
+        Commands::Treasury(treasury_cmd) => {
+            let app = open_app(cli)?;
+
+            match treasury_cmd {
+                TreasuryCommands::Sync { project_id } => {
+                    for sync in app.sync_treasury(project_id.as_deref()).await? {
+                        status(
+                            Icon::Ok,
+                            format!(
+                                "Synced {}: {} new transactions",
+                                sync.address,
+                                sync.new.len()
+                            ),
+                        );
+                    }
+                }
+                TreasuryCommands::History {
+                    project_id,
+                    sync,
+                    limit,
+                } => {
+                    let histories =
+                        app.treasury_history(project_id.as_deref(), *sync).await?;
+                    for (i, history) in histories.iter().enumerate() {
+                        if i > 0 {
+                            println!();
+                        }
+                        display_treasury_history(history, *limit);
+                    }
+                }
+            }
+        }
+        Commands::Price { refresh } => {
+            let app = open_app(cli)?;
+            let lookup = app.erg_price(*refresh).await?;
+            let quote = &lookup.quote;
+            println!("1 ERG = {:.4} USD", quote.usd_per_erg);
+            println!(
+                "Source: {} ({}{})",
+                quote.source,
+                local_time(quote.fetched_at, "%Y-%m-%d %H:%M:%S %Z"),
+                if lookup.fresh { "" } else { ", cached" }
+            );
+            if let Some(error) = &lookup.fetch_error {
+                warn(format!(
+                    "Price feed failed, using the cached price: {}",
+                    error
+                ));
+            }
+        }
+        Commands::Node(node_cmd) => match node_cmd {
+            NodeCommands::Status {
+                url,
+                api_key,
+                wallet,
+            } => {
+                let url = url
+                    .clone()
+                    .or_else(|| std::env::var("ERGO_NODE_URL").ok())
+                    .unwrap_or_else(|| DEFAULT_NODE_URL.to_string());
+                let api_key = api_key
+                    .clone()
+                    .or_else(|| std::env::var("ERGO_NODE_API_KEY").ok());
+
+                let health =
+                    ErgoNodeClient::new(&url, api_key).health(*wallet).await?;
+                display_node_health(&url, &health);
+
+                if health.is_healthy() {
+                    status(Icon::Ok, "Node is healthy");
+                } else {
+                    for problem in &health.problems {
+                        eprintln!("{} {}", Icon::Fail, problem);
+                    }
+                    return Err(GitCirclesError::Node(
+                        "node is not ready for payouts".to_string(),
+                    ));
+                }
+            }
+        },
+        Commands::Report(report_cmd) => match report_cmd {
+            ReportCommands::Anomalies {
+                project_id,
+                check_accounts,
+                token,
+            } => {
+                let mut app = open_app(cli)?;
+                if *check_accounts {
+                    let github_token = resolve_github_token(token.as_deref())?;
+                    app = app.with_github_token(&github_token)?;
+                }
+                let anomalies = app
+                    .anomaly_report(project_id.as_deref(), *check_accounts)
+                    .await?;
+                display_anomalies(&anomalies, &app.address_book().await?);
+            }
+            ReportCommands::Digest { project_id, days } => {
+                let app = open_app(cli)?;
+                let digests = app.digest(project_id.as_deref(), *days).await?;
+                if digests.is_empty() {
+                    println!("No projects found.");
+                }
+                for digest in &digests {
+                    display_digest(digest);
+                }
+            }
+        },
+        Commands::Repo(repo_cmd) => match repo_cmd {
+            RepoCommands::SyncLog { repo, limit } => {
+                parse_repo(repo)?;
+                let app = open_app(cli)?;
+                let mut entries = app.sync_log(repo).await?;
+                entries.truncate(*limit);
+                display_sync_log(&entries);
+            }
+            RepoCommands::Recount => {
+                let app = open_app(cli)?;
+                let repaired = app.recount_pull_requests().await?;
+                for (repo, old) in &repaired {
+                    info(format!(
+                        "{}/{}: {} -> {} PRs",
+                        repo.owner, repo.name, old, repo.total_prs
+                    ));
+                }
+                if repaired.is_empty() {
+                    status(Icon::Ok, "All PR counters match the stored PRs");
+                } else {
+                    status(
+                        Icon::Ok,
+                        format!("Repaired {} PR counters", repaired.len()),
+                    );
+                }
+            }
+        },
+        Commands::Import(import_cmd) => match import_cmd {
+            ImportCommands::GitLog {
+                repo,
+                name,
+                base_branch,
+            } => {
+                if let Some(name) = name {
+                    parse_repo(name)?;
+                }
+                let app = open_app(cli)?;
+                let outcome = app
+                    .import_git_log(repo, name.as_deref(), base_branch.as_deref())
+                    .await?;
+                if !outcome.imported.is_empty() {
+                    display_pull_requests(&outcome.imported);
+                }
+                display_import(&outcome, "merges", cli.dry_run);
+            }
+            ImportCommands::Archive { path, repo } => {
+                let app = open_app(cli)?;
+                let outcome = app.import_archive(path, repo).await?;
+                if !outcome.imported.is_empty() {
+                    display_pull_requests(&outcome.imported);
+                }
+                display_import(&outcome, "PRs", cli.dry_run);
+            }
+        },
+        Commands::Privacy(privacy_cmd) => match privacy_cmd {
+            PrivacyCommands::Forget { login } => {
+                let app = open_app(cli)?;
+                let report = app.forget_login(login).await?;
+                display_forget_report(&report, cli.dry_run);
+            }
+        },
         Commands::Project(project_cmd) => {
-            let db = Database::new(&get_database_path()?)?;
+            let app = open_app(cli)?;
+            let db = app.database();
 
             match project_cmd {
                 ProjectCommands::Create { name, description } => {
-                    let project_id = generate_project_id(name);
-                    let now = Utc::now();
-
-                    let project = Project {
-                        id: project_id.clone(),
-                        name: name.clone(),
-                        description: description.clone(),
-                        created_at: now,
-                        updated_at: now,
-                    };
-
-                    db.upsert_project(&project)?;
-                    println!(
-                        "✓ Created project '{}' with ID: {}",
-                        name, project_id
+                    let project =
+                        app.create_project(name, description.clone()).await?;
+                    status(
+                        Icon::Ok,
+                        format!(
+                            "Created project '{}' with ID: {}",
+                            name, project.id
+                        ),
                     );
 
                     if let Some(desc) = description {
@@ -277,53 +1074,32 @@ async fn main() -> Result<()> {
                     }
                 }
                 ProjectCommands::List => {
-                    let projects = db.list_projects()?;
+                    let projects =
+                        db.run(|db| db.list_projects_with_counters()).await?;
                     display_projects(&projects);
                 }
                 ProjectCommands::Show { project_id } => {
-                    let project = db.get_project(project_id)?.ok_or_else(|| {
-                        GitCirclesError::DatabasePath(format!(
-                            "Project '{}' not found",
-                            project_id
-                        ))
-                    })?;
-                    let owners = db.get_project_owners(project_id)?;
-                    let repos = db.list_repositories_for_project(project_id)?;
-                    display_project_details(&project, &owners, &repos);
+                    let project = app.require_project(project_id).await?;
+                    let id = project_id.clone();
+                    let (owners, repos, links) = db
+                        .run(move |db| {
+                            Ok((
+                                db.get_project_owners(&id)?,
+                                db.list_repositories_for_project(&id)?,
+                                db.get_project_links(&id)?,
+                            ))
+                        })
+                        .await?;
+                    display_project_details(&project, &owners, &repos, &links);
                 }
                 ProjectCommands::Delete { project_id } => {
-                    // Check if project exists
-                    let project = db.get_project(project_id)?.ok_or_else(|| {
-                        GitCirclesError::DatabasePath(format!(
-                            "Project '{}' not found",
-                            project_id
-                        ))
-                    })?;
-
-                    // Check for linked repositories
-                    let repos = db.list_repositories_for_project(project_id)?;
-                    if !repos.is_empty() {
-                        return Err(GitCirclesError::DatabasePath(format!(
-                            "Cannot delete project '{}': {} repositories are still linked. Remove repositories first.",
-                            project_id,
-                            repos.len()
-                        )));
-                    }
-
-                    // Remove all project owners first
-                    let owners = db.get_project_owners(project_id)?;
-                    for owner in &owners {
-                        db.remove_project_owner(
-                            project_id,
-                            &owner.github_username,
-                        )?;
-                    }
-
-                    // Delete the project
-                    db.delete_project(project_id)?;
-                    println!(
-                        "✓ Deleted project '{}' ({})",
-                        project.name, project_id
+                    let project = app.delete_project(project_id).await?;
+                    status(
+                        Icon::Ok,
+                        format!(
+                            "Deleted project '{}' ({})",
+                            project.name, project_id
+                        ),
                     );
                 }
                 ProjectCommands::AddOwner {
@@ -331,51 +1107,324 @@ async fn main() -> Result<()> {
                     username,
                     role,
                 } => {
-                    // Validate project exists
-                    let _project =
-                        db.get_project(project_id)?.ok_or_else(|| {
-                            GitCirclesError::DatabasePath(format!(
-                                "Project '{}' not found",
-                                project_id
-                            ))
-                        })?;
-
-                    // Validate role
-                    if !["owner", "admin", "member"].contains(&role.as_str()) {
-                        return Err(GitCirclesError::DatabasePath(
-                            "Invalid role. Must be one of: owner, admin, member"
-                                .to_string(),
+                    app.add_project_owner(project_id, username, role).await?;
+                    status(
+                        Icon::Ok,
+                        format!(
+                            "Added {} as {} to project {}",
+                            username, role, project_id
+                        ),
+                    );
+                }
+                ProjectCommands::RemoveOwner {
+                    project_id,
+                    username,
+                } => {
+                    app.remove_project_owner(project_id, username).await?;
+                    status(
+                        Icon::Ok,
+                        format!("Removed {} from project {}", username, project_id),
+                    );
+                }
+                ProjectCommands::AddTreasury {
+                    project_id,
+                    address,
+                    name,
+                    max_payout,
+                } => {
+                    let max_payout = max_payout.map(nanoerg).transpose()?;
+                    let treasury = app
+                        .add_project_treasury(project_id, address, name, max_payout)
+                        .await?;
+                    status(
+                        Icon::Ok,
+                        format!(
+                            "Added treasury {} ({}) to project {}",
+                            treasury.name, treasury.address, project_id
+                        ),
+                    );
+                }
+                ProjectCommands::RemoveTreasury {
+                    project_id,
+                    address,
+                } => {
+                    if app.remove_project_treasury(project_id, address).await? {
+                        status(
+                            Icon::Ok,
+                            format!(
+                                "Removed treasury {} from project {}",
+                                address, project_id
+                            ),
+                        );
+                    } else {
+                        info(format!(
+                            "{} is not a treasury of project {}",
+                            address, project_id
                         ));
                     }
-
-                    let project_owner = ProjectOwner {
-                        project_id: project_id.clone(),
-                        github_username: username.clone(),
-                        role: role.clone(),
-                        added_at: Utc::now(),
+                }
+                ProjectCommands::Treasuries { project_id, route } => {
+                    let route = route.map(nanoerg).transpose()?;
+                    let treasuries = app.project_treasuries(project_id).await?;
+                    display_project_treasuries(&treasuries);
+                    if let Some(amount) = route {
+                        match route_payout(&treasuries, amount) {
+                            Some(treasury) => info(format!(
+                                "Preview: a payout of {} would be paid from {}",
+                                erg(amount as i64),
+                                treasury.name
+                            )),
+                            None => warn(format!(
+                                "Preview: no treasury would make a payout of {}",
+                                erg(amount as i64)
+                            )),
+                        }
+                    }
+                }
+                ProjectCommands::Link {
+                    project_id,
+                    repo,
+                    paths,
+                } => {
+                    let (owner, name) = parse_repo(repo)?;
+                    let repository = format!("{}/{}", owner, name);
+                    let link = app
+                        .link_repository(project_id, &repository, Some(paths))
+                        .await?;
+                    let scope = if link.paths.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" (only {})", link.paths.join(", "))
                     };
-
-                    db.add_project_owner(&project_owner)?;
-                    println!(
-                        "✓ Added {} as {} to project {}",
-                        username, role, project_id
+                    status(
+                        Icon::Ok,
+                        format!(
+                            "Linked {} to project {}{}",
+                            repository, project_id, scope
+                        ),
                     );
                 }
-                ProjectCommands::RemoveOwner {
+                ProjectCommands::Unlink { project_id, repo } => {
+                    let (owner, name) = parse_repo(repo)?;
+                    let repository = format!("{}/{}", owner, name);
+                    app.unlink_repository(project_id, &repository).await?;
+                    status(
+                        Icon::Ok,
+                        format!(
+                            "Unlinked {} from project {}",
+                            repository, project_id
+                        ),
+                    );
+                }
+                ProjectCommands::Allow {
                     project_id,
-                    username,
+                    logins,
+                    remove,
+                } => {
+                    let project = app
+                        .update_contributor_list(
+                            project_id,
+                            ContributorList::Allow,
+                            logins,
+                            *remove,
+                        )
+                        .await?;
+                    if project.allowed_contributors.is_empty() {
+                        status(
+                            Icon::Ok,
+                            format!("Project {} counts PRs by anyone", project_id),
+                        );
+                    } else {
+                        status(
+                            Icon::Ok,
+                            format!(
+                                "Project {} only counts PRs by: {}",
+                                project_id,
+                                project.allowed_contributors.join(", ")
+                            ),
+                        );
+                    }
+                }
+                ProjectCommands::Block {
+                    project_id,
+                    logins,
+                    remove,
+                } => {
+                    let project = app
+                        .update_contributor_list(
+                            project_id,
+                            ContributorList::Block,
+                            logins,
+                            *remove,
+                        )
+                        .await?;
+                    status(
+                        Icon::Ok,
+                        format!(
+                            "Project {} blocks {} logins",
+                            project_id,
+                            project.blocked_contributors.len()
+                        ),
+                    );
+                }
+                ProjectCommands::ExcludeOrg {
+                    project_id,
+                    orgs,
+                    remove,
+                    token,
                 } => {
-                    // Validate project exists
-                    let _project =
-                        db.get_project(project_id)?.ok_or_else(|| {
-                            GitCirclesError::DatabasePath(format!(
-                                "Project '{}' not found",
+                    let github_token = resolve_github_token(token.as_deref())?;
+                    let app = app.with_github_token(&github_token)?;
+                    let (project, found) =
+                        app.update_excluded_orgs(project_id, orgs, *remove).await?;
+                    for exclusion in &found {
+                        status(
+                            Icon::Note,
+                            format!(
+                                "{} is a member of {}; their PRs no longer count",
+                                exclusion.login, exclusion.org
+                            ),
+                        );
+                    }
+                    if project.excluded_orgs.is_empty() {
+                        status(
+                            Icon::Ok,
+                            format!(
+                                "Project {} excludes no organizations",
                                 project_id
-                            ))
-                        })?;
-
-                    db.remove_project_owner(project_id, username)?;
-                    println!("✓ Removed {} from project {}", username, project_id);
+                            ),
+                        );
+                    } else {
+                        status(
+                            Icon::Ok,
+                            format!(
+                                "Project {} excludes members of: {}",
+                                project_id,
+                                project.excluded_orgs.join(", ")
+                            ),
+                        );
+                    }
+                }
+                ProjectCommands::Pause { project_id, reason } => {
+                    app.pause_project(project_id, reason.clone()).await?;
+                    status(
+                        Icon::Ok,
+                        format!(
+                            "Paused project {}; its PRs are not scheduled until 'project resume'",
+                            project_id
+                        ),
+                    );
+                }
+                ProjectCommands::Resume { project_id } => {
+                    let project = app.resume_project(project_id).await?;
+                    if let Some(pause) = project.pauses.last()
+                        && let Some(resumed_at) = pause.resumed_at
+                    {
+                        let paused = resumed_at - pause.paused_at;
+                        status(
+                            Icon::Ok,
+                            format!(
+                                "Resumed project {} after {}h {}m",
+                                project_id,
+                                paused.num_hours(),
+                                paused.num_minutes() % 60
+                            ),
+                        );
+                    }
+                }
+                ProjectCommands::RequireApproval { project_id, off } => {
+                    app.set_approval_required(project_id, !*off).await?;
+                    status(
+                        Icon::Ok,
+                        if *off {
+                            format!(
+                                "Project {} no longer requires approval",
+                                project_id
+                            )
+                        } else {
+                            format!(
+                                "Project {} now requires approval; comment 'GitCircles APPROVE' or run 'project approve'",
+                                project_id
+                            )
+                        },
+                    );
+                }
+                ProjectCommands::Approve {
+                    project_id,
+                    repo,
+                    number,
+                } => {
+                    let approval =
+                        app.approve_pull_request(project_id, repo, *number).await?;
+                    status(
+                        Icon::Ok,
+                        format!(
+                            "Approved {}#{} for project {} on {}",
+                            repo,
+                            number,
+                            project_id,
+                            local_time(approval.approved_at, "%Y-%m-%d %H:%M %Z")
+                        ),
+                    );
+                }
+                ProjectCommands::Approvals {
+                    project_id,
+                    scan,
+                    token,
+                } => {
+                    let mut app = app;
+                    if *scan {
+                        let github_token = resolve_github_token(token.as_deref())?;
+                        app = app.with_github_token(&github_token)?;
+                        for approval in app
+                            .scan_approval_comments(app.github()?, project_id)
+                            .await?
+                        {
+                            let verb = if cli.dry_run {
+                                "would be approved"
+                            } else {
+                                "approved"
+                            };
+                            info(format!(
+                                "{}#{} {} by {}",
+                                approval.repository,
+                                approval.number,
+                                verb,
+                                approval.approved_by.as_deref().unwrap_or("-")
+                            ));
+                        }
+                    }
+                    let pending = app.pending_approvals(project_id).await?;
+                    if pending.is_empty() {
+                        println!("No PRs waiting for approval.");
+                    } else {
+                        display_pull_requests(&pending);
+                    }
+                }
+                ProjectCommands::Announce {
+                    project_id,
+                    repo,
+                    token,
+                } => {
+                    let (owner, name) = parse_repo(repo)?;
+                    let github_token = resolve_github_token(token.as_deref())?;
+                    let app = app.with_github_token(&github_token)?;
+                    let outcome =
+                        app.announce_project(project_id, &owner, &name).await?;
+                    status(
+                        Icon::Ok,
+                        format!(
+                            "{} announcement issue {}#{}",
+                            if outcome.created { "Opened" } else { "Updated" },
+                            repo,
+                            outcome.announcement.issue_number
+                        ),
+                    );
+                    if !outcome.pinned {
+                        warn(
+                            "Could not pin the issue (repositories allow at most three pinned issues)",
+                        );
+                    }
                 }
             }
         }
@@ -0,0 +1,200 @@
+//! Process-wide terminal output settings, set once from the global
+//! `--no-color`, `--quiet`, `--ascii` and `--timezone` flags.
+
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use once_cell::sync::OnceCell;
+use std::fmt;
+use std::io::IsTerminal;
+use std::time::Duration;
+
+static SETTINGS: OnceCell<OutputSettings> = OnceCell::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputSettings {
+    /// Colored spinners and terminal hyperlinks
+    pub color: bool,
+    /// Skip progress, confirmations and totals; tables, warnings and errors
+    /// still print
+    pub quiet: bool,
+    /// ASCII table borders and text markers instead of emoji
+    pub ascii: bool,
+    /// Zone timestamps are shown in; they are stored in UTC
+    pub timezone: Tz,
+}
+
+impl Default for OutputSettings {
+    fn default() -> Self {
+        Self::new(false, false, false)
+    }
+}
+
+impl OutputSettings {
+    /// Color is also turned off by a non-empty `NO_COLOR` variable.
+    pub fn new(no_color: bool, quiet: bool, ascii: bool) -> Self {
+        let no_color_env =
+            std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        Self {
+            color: !no_color && !no_color_env,
+            quiet,
+            ascii,
+            timezone: Tz::UTC,
+        }
+    }
+
+    pub fn with_timezone(mut self, timezone: Tz) -> Self {
+        self.timezone = timezone;
+        self
+    }
+}
+
+/// Install the settings for this process. Only the first call has an effect.
+pub fn init_output(settings: OutputSettings) {
+    let _ = SETTINGS.set(settings);
+}
+
+pub fn output() -> OutputSettings {
+    *SETTINGS.get_or_init(OutputSettings::default)
+}
+
+/// `at` formatted in the display time zone. Formats with a time of day end
+/// in `%Z` so the zone is visible.
+pub fn local_time(at: DateTime<Utc>, format: &str) -> String {
+    at.with_timezone(&output().timezone)
+        .format(format)
+        .to_string()
+}
+
+/// Marker printed in front of a message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Icon {
+    Ok,
+    Warn,
+    Fail,
+    Note,
+    Project,
+    Owners,
+    Repos,
+    Digest,
+}
+
+impl fmt::Display for Icon {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (emoji, ascii) = match self {
+            Icon::Ok => ("✓", "[ok]"),
+            Icon::Warn => ("⚠", "[warn]"),
+            Icon::Fail => ("✗", "[error]"),
+            Icon::Note => ("📝", "[note]"),
+            Icon::Project => ("📋", "#"),
+            Icon::Owners => ("👥", "#"),
+            Icon::Repos => ("📦", "#"),
+            Icon::Digest => ("📰", "#"),
+        };
+        f.write_str(if output().ascii { ascii } else { emoji })
+    }
+}
+
+/// Print a confirmation or progress line unless `--quiet` is set.
+pub fn status(icon: Icon, message: impl fmt::Display) {
+    if !output().quiet {
+        println!("{} {}", icon, message);
+    }
+}
+
+/// Print a plain informational line (e.g. a table total) unless `--quiet`
+/// is set.
+pub fn info(message: impl fmt::Display) {
+    if !output().quiet {
+        println!("{}", message);
+    }
+}
+
+/// Print a warning. Warnings are shown even with `--quiet`.
+pub fn warn(message: impl fmt::Display) {
+    println!("{} {}", Icon::Warn, message);
+}
+
+// Progress is drawn on stderr, but only when stdout is a terminal too, so
+// piped results (`--format json`, redirects, CI logs) stay free of redraws.
+fn progress_visible() -> bool {
+    !output().quiet && std::io::stdout().is_terminal()
+}
+
+fn spinner_style() -> ProgressStyle {
+    let settings = output();
+    let template = if settings.color {
+        "{spinner:.green} {msg}"
+    } else {
+        "{spinner} {msg}"
+    };
+    let ticks: &[&str] = if settings.ascii {
+        &["|", "/", "-", "\\"]
+    } else {
+        &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"]
+    };
+    ProgressStyle::with_template(template)
+        .unwrap()
+        .tick_strings(ticks)
+}
+
+fn bar_style() -> ProgressStyle {
+    let settings = output();
+    let template = if settings.color {
+        "{prefix} [{bar:30.cyan/blue}] {pos}/{len} {msg}"
+    } else {
+        "{prefix} [{bar:30}] {pos}/{len} {msg}"
+    };
+    let chars = if settings.ascii { "=> " } else { "█▉ " };
+    ProgressStyle::with_template(template)
+        .unwrap()
+        .progress_chars(chars)
+}
+
+/// Shared progress display for batch operations: an overall bar with a
+/// spinner per item in flight underneath. Everything is hidden with
+/// `--quiet` or when stdout is not a terminal.
+pub struct Progress {
+    multi: MultiProgress,
+}
+
+impl Default for Progress {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Progress {
+    pub fn new() -> Self {
+        let target = if progress_visible() {
+            ProgressDrawTarget::stderr()
+        } else {
+            ProgressDrawTarget::hidden()
+        };
+        Self {
+            multi: MultiProgress::with_draw_target(target),
+        }
+    }
+
+    /// Overall bar counting `len` items.
+    pub fn bar(&self, len: u64, prefix: impl Into<String>) -> ProgressBar {
+        let pb = self.multi.add(ProgressBar::new(len));
+        pb.set_style(bar_style());
+        pb.set_prefix(prefix.into());
+        pb
+    }
+
+    /// Spinner for one item, shown below the bars added before it.
+    pub fn spinner(&self, message: impl Into<String>) -> ProgressBar {
+        let pb = self.multi.add(ProgressBar::new_spinner());
+        pb.set_style(spinner_style());
+        pb.set_message(message.into());
+        pb.enable_steady_tick(Duration::from_millis(100));
+        pb
+    }
+}
+
+/// A standalone ticking spinner, hidden like [`Progress`].
+pub fn spinner(message: impl Into<String>) -> ProgressBar {
+    Progress::new().spinner(message)
+}
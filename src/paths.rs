@@ -0,0 +1,77 @@
+//! Path claims for monorepos: a project linked to a repository may claim
+//! only some of its paths, written as globs such as `packages/foo/**`.
+//!
+//! `**` matches any number of whole path segments (including none), `*`
+//! any run of characters within one segment and `?` a single character.
+
+/// Whether `path` (relative to the repository root, `/`-separated) matches
+/// `pattern`.
+pub fn path_matches(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<&str> = pattern.trim_matches('/').split('/').collect();
+    let path: Vec<&str> = path.trim_matches('/').split('/').collect();
+    segments_match(&pattern, &path)
+}
+
+/// Whether any of `files` falls under one of `patterns`. No patterns claim
+/// the whole repository.
+pub fn claims_any<S: AsRef<str>>(patterns: &[String], files: &[S]) -> bool {
+    patterns.is_empty()
+        || files.iter().any(|file| {
+            patterns
+                .iter()
+                .any(|pattern| path_matches(pattern, file.as_ref()))
+        })
+}
+
+fn segments_match(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => {
+            (0..=path.len()).any(|skip| segments_match(rest, &path[skip..]))
+        }
+        Some((first, rest)) => path.split_first().is_some_and(|(segment, tail)| {
+            segment_matches(first.as_bytes(), segment.as_bytes())
+                && segments_match(rest, tail)
+        }),
+    }
+}
+
+fn segment_matches(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) => {
+            (0..=text.len()).any(|skip| segment_matches(rest, &text[skip..]))
+        }
+        Some((b'?', rest)) => !text.is_empty() && segment_matches(rest, &text[1..]),
+        Some((c, rest)) => {
+            text.first() == Some(c) && segment_matches(rest, &text[1..])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn globs_match_segments() {
+        assert!(path_matches("packages/foo/**", "packages/foo/src/lib.rs"));
+        assert!(path_matches("packages/foo/**", "packages/foo"));
+        assert!(!path_matches("packages/foo/**", "packages/foobar/lib.rs"));
+        assert!(path_matches(
+            "packages/*/Cargo.toml",
+            "packages/bar/Cargo.toml"
+        ));
+        assert!(!path_matches(
+            "packages/*/Cargo.toml",
+            "packages/a/b/Cargo.toml"
+        ));
+        assert!(path_matches("**/*.md", "README.md"));
+        assert!(path_matches("docs/v?/**", "docs/v2/index.md"));
+
+        let claim = vec!["packages/foo/**".to_string()];
+        assert!(claims_any(&claim, &["README.md", "packages/foo/a.rs"]));
+        assert!(!claims_any(&claim, &["packages/bar/a.rs"]));
+        assert!(claims_any::<&str>(&[], &[]));
+    }
+}
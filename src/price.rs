@@ -0,0 +1,182 @@
+//! ERG/USD prices for fiat-valued reports: from an Ergo oracle pool via the
+//! explorer API, or from any HTTP endpoint returning JSON. The last price is
+//! cached in the database (see `GitCircles::erg_price`).
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{HttpConfig, PriceConfig};
+use crate::types::{GitCirclesError, Result};
+
+const NANOERG_PER_ERG: f64 = 1_000_000_000.0;
+
+/// Which kind of feed `[price]` points at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceSourceKind {
+    /// A JSON endpoint, read at `json_pointer`
+    Http,
+    /// The latest datapoint of an Ergo oracle pool, read via the explorer
+    Oracle,
+}
+
+/// A price as fetched, in USD per ERG.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PriceQuote {
+    pub usd_per_erg: f64,
+    /// Where the price came from, e.g. the URL
+    pub source: String,
+    pub fetched_at: DateTime<Utc>,
+}
+
+/// A price lookup: the quote, and why a cached one was used instead of a
+/// fresh fetch, if it was.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PriceLookup {
+    pub quote: PriceQuote,
+    /// Fetched just now rather than read from the cache
+    pub fresh: bool,
+    /// The failed fetch a cached quote stands in for
+    pub fetch_error: Option<String>,
+}
+
+/// Anything that can report the current ERG/USD price.
+pub trait PriceSource {
+    /// Shown as the quote's source.
+    fn describe(&self) -> String;
+
+    fn fetch_usd_per_erg(
+        &self,
+    ) -> impl std::future::Future<Output = Result<f64>> + Send;
+}
+
+/// The feed configured under `[price]`.
+pub struct PriceFeed {
+    http: reqwest::Client,
+    kind: PriceSourceKind,
+    url: String,
+    json_pointer: String,
+}
+
+impl PriceConfig {
+    /// Build the configured feed; fails when an oracle pool is selected
+    /// without its NFT id.
+    pub fn feed(&self, http: &HttpConfig) -> Result<PriceFeed> {
+        let url = match self.source {
+            PriceSourceKind::Http => self.url.clone(),
+            PriceSourceKind::Oracle if self.oracle_pool_nft.is_empty() => {
+                return Err(GitCirclesError::Config(
+                    "set price.oracle_pool_nft to read an oracle pool".to_string(),
+                ));
+            }
+            PriceSourceKind::Oracle => format!(
+                "{}/api/v1/boxes/unspent/byTokenId/{}",
+                self.explorer_url.trim_end_matches('/'),
+                self.oracle_pool_nft
+            ),
+        };
+        let client = reqwest::Client::builder()
+            .timeout(http.timeout())
+            .connect_timeout(http.connect_timeout())
+            .build()
+            .map_err(|e| {
+                GitCirclesError::Config(format!("Cannot build HTTP client: {}", e))
+            })?;
+        Ok(PriceFeed {
+            http: client,
+            kind: self.source,
+            url,
+            json_pointer: self.json_pointer.clone(),
+        })
+    }
+}
+
+impl PriceSource for PriceFeed {
+    fn describe(&self) -> String {
+        self.url.clone()
+    }
+
+    async fn fetch_usd_per_erg(&self) -> Result<f64> {
+        let response = self.http.get(&self.url).send().await.map_err(|e| {
+            GitCirclesError::Price(format!("{} is unreachable: {}", self.url, e))
+        })?;
+        let status = response.status();
+        let body = response.text().await.map_err(|e| {
+            GitCirclesError::Price(format!("Failed to read {}: {}", self.url, e))
+        })?;
+        if !status.is_success() {
+            return Err(GitCirclesError::Price(format!(
+                "{} returned HTTP {}",
+                self.url,
+                status.as_u16()
+            )));
+        }
+        match self.kind {
+            PriceSourceKind::Http => parse_json_price(&body, &self.json_pointer),
+            PriceSourceKind::Oracle => parse_oracle_datapoint(&body),
+        }
+    }
+}
+
+/// The price at `pointer` (e.g. "/ergo/usd") in a JSON response. Numbers
+/// given as strings are accepted.
+pub fn parse_json_price(body: &str, pointer: &str) -> Result<f64> {
+    let json: serde_json::Value = serde_json::from_str(body)?;
+    let value = json.pointer(pointer).ok_or_else(|| {
+        GitCirclesError::Price(format!("no value at {} in the response", pointer))
+    })?;
+    let price = match value {
+        serde_json::Value::Number(n) => n.as_f64(),
+        serde_json::Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    };
+    checked_price(price, pointer)
+}
+
+/// USD per ERG from an explorer `boxes/unspent/byTokenId` response for an
+/// oracle pool box, whose R4 datapoint is nanoERG per USD.
+pub fn parse_oracle_datapoint(body: &str) -> Result<f64> {
+    let json: serde_json::Value = serde_json::from_str(body)?;
+    let rendered = json
+        .pointer("/items/0/additionalRegisters/R4/renderedValue")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| {
+            GitCirclesError::Price(
+                "oracle pool box has no R4 datapoint".to_string(),
+            )
+        })?;
+    let nanoerg_per_usd: Option<f64> = rendered.parse().ok();
+    let price = nanoerg_per_usd
+        .filter(|n| *n > 0.0)
+        .map(|n| NANOERG_PER_ERG / n);
+    checked_price(price, "R4")
+}
+
+fn checked_price(price: Option<f64>, at: &str) -> Result<f64> {
+    price.filter(|p| p.is_finite() && *p > 0.0).ok_or_else(|| {
+        GitCirclesError::Price(format!("{} is not a positive price", at))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prices_are_read_from_json_and_oracle_boxes() {
+        let body = r#"{"ergo":{"usd":1.25}}"#;
+        assert_eq!(parse_json_price(body, "/ergo/usd").unwrap(), 1.25);
+        assert_eq!(
+            parse_json_price(r#"{"price":"0.8"}"#, "/price").unwrap(),
+            0.8
+        );
+        assert!(parse_json_price(body, "/ergo/eur").is_err());
+        assert!(parse_json_price(r#"{"price":0}"#, "/price").is_err());
+
+        let oracle = r#"{"items":[{"boxId":"ab","additionalRegisters":{
+            "R4":{"serializedValue":"05","sigmaType":"SLong",
+            "renderedValue":"800000000"}}}],"total":1}"#;
+        assert_eq!(parse_oracle_datapoint(oracle).unwrap(), 1.25);
+        assert!(parse_oracle_datapoint(r#"{"items":[],"total":0}"#).is_err());
+    }
+}
@@ -0,0 +1,185 @@
+//! Pseudonyms for personal data: stable stand-ins for logins and wallet
+//! addresses in shared exports, and the one-off pseudonym that replaces a
+//! login removed by `privacy forget`.
+
+use blake2::Blake2b;
+use blake2::Digest;
+use blake2::digest::{FixedOutput, Update, consts::U32};
+use chrono::{DateTime, Utc};
+
+use crate::config::PrivacyConfig;
+use crate::types::{
+    AddressKind, GitCirclesError, MergedPullRequest, Result, WalletLookup,
+};
+
+/// Replaces logins and addresses with ids derived from a secret key, so the
+/// same login gets the same id in every export made with that key, while
+/// nobody without the key can map ids back by hashing known logins.
+#[derive(Debug, Clone)]
+pub struct Pseudonymizer {
+    key: String,
+}
+
+impl Pseudonymizer {
+    pub fn new(key: &str) -> Self {
+        Self {
+            key: key.to_string(),
+        }
+    }
+
+    /// e.g. "user-5e0c21d9a4b7f318". Logins compare case-insensitively, as
+    /// on GitHub.
+    pub fn login(&self, login: &str) -> String {
+        format!("user-{}", self.id("login", &login.to_lowercase()))
+    }
+
+    /// e.g. "wallet-0b1c9e6f2a7d4483".
+    pub fn address(&self, address: &str) -> String {
+        format!("wallet-{}", self.id("address", address))
+    }
+
+    /// Replace the author, commit authors and fork owner.
+    pub fn pull_request(&self, pr: &mut MergedPullRequest) {
+        pr.author = self.login(&pr.author);
+        for author in &mut pr.resolved_authors {
+            *author = self.login(author);
+        }
+        if let Some((owner, name)) = pr
+            .head_repository
+            .as_deref()
+            .and_then(|h| h.split_once('/'))
+        {
+            pr.head_repository = Some(format!("{}/{}", self.login(owner), name));
+        }
+    }
+
+    /// Replace the address and the logins linked to it, and drop address
+    /// book names other than treasuries' and exchanges'. Invalid lines are
+    /// kept as written, since they identify nobody.
+    pub fn wallet_lookup(&self, lookup: &mut WalletLookup) {
+        if lookup.valid {
+            lookup.address = self.address(&lookup.address);
+        }
+        for login in &mut lookup.logins {
+            *login = self.login(login);
+        }
+        if !matches!(
+            lookup.kind,
+            Some(AddressKind::Treasury | AddressKind::Exchange)
+        ) {
+            lookup.label = None;
+        }
+    }
+
+    fn id(&self, kind: &str, value: &str) -> String {
+        let mut hasher = Blake2b::<U32>::new();
+        // Length-prefixed so no two inputs hash the same bytes
+        for part in [self.key.as_str(), kind, value] {
+            <Blake2b<U32> as Update>::update(
+                &mut hasher,
+                &(part.len() as u64).to_be_bytes(),
+            );
+            <Blake2b<U32> as Update>::update(&mut hasher, part.as_bytes());
+        }
+        hex(&hasher.finalize_fixed()[..8])
+    }
+}
+
+impl PrivacyConfig {
+    /// The pseudonymizer for `--pseudonymize`; fails when no key is set.
+    pub fn pseudonymizer(&self) -> Result<Pseudonymizer> {
+        if self.pseudonym_key.is_empty() {
+            return Err(GitCirclesError::Config(
+                "set privacy.pseudonym_key to pseudonymize exports".to_string(),
+            ));
+        }
+        Ok(Pseudonymizer::new(&self.pseudonym_key))
+    }
+}
+
+/// Stand-in for a forgotten login, e.g. "forgotten-3f2a9c1e". Salted with
+/// the time so it can't be recomputed from the login later.
+pub fn forgotten_pseudonym(login: &str, at: DateTime<Utc>) -> String {
+    let mut hasher = Blake2b::<U32>::new();
+    <Blake2b<U32> as Update>::update(&mut hasher, login.as_bytes());
+    <Blake2b<U32> as Update>::update(
+        &mut hasher,
+        &at.timestamp_nanos_opt().unwrap_or_default().to_be_bytes(),
+    );
+    format!("forgotten-{}", hex(&hasher.finalize_fixed()[..4]))
+}
+
+/// `text` with every mention of `login` replaced by `with`, ignoring case,
+/// e.g. "@Alice" but not "malice".
+pub fn replace_login(text: &str, login: &str, with: &str) -> String {
+    let is_login_char = |c: char| c.is_ascii_alphanumeric() || c == '-';
+    let needle = login.to_ascii_lowercase();
+    if needle.is_empty() {
+        return text.to_string();
+    }
+    // ASCII lowercasing keeps byte offsets, so matches index into `text`
+    let lower = text.to_ascii_lowercase();
+    let (mut out, mut copied, mut from) = (String::new(), 0, 0);
+    while let Some(found) = lower[from..].find(&needle) {
+        let (start, end) = (from + found, from + found + needle.len());
+        if !text[..start].chars().next_back().is_some_and(is_login_char)
+            && !text[end..].chars().next().is_some_and(is_login_char)
+        {
+            out.push_str(&text[copied..start]);
+            out.push_str(with);
+            copied = end;
+        }
+        from = end;
+    }
+    out.push_str(&text[copied..]);
+    out
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pseudonyms_are_stable_per_key() {
+        let one = Pseudonymizer::new("first key");
+        assert_eq!(one.login("alice"), one.login("Alice"));
+        assert_ne!(one.login("alice"), one.login("bob"));
+        assert_ne!(
+            one.login("alice"),
+            Pseudonymizer::new("other").login("alice")
+        );
+        assert!(one.login("alice").starts_with("user-"));
+
+        let mut lookup = WalletLookup {
+            address: "9hUzb5RvSgDqJdtyCN9Ke496Yy63mpcUJKbRq4swzQ5EQKgygKT".into(),
+            valid: true,
+            logins: vec!["alice".into()],
+            label: Some("Alice Liddell".into()),
+            kind: Some(AddressKind::Contributor),
+        };
+        one.wallet_lookup(&mut lookup);
+        assert!(lookup.address.starts_with("wallet-"));
+        assert_eq!(lookup.logins, [one.login("alice")]);
+        assert_eq!(lookup.label, None);
+
+        let missing = PrivacyConfig::default().pseudonymizer().unwrap_err();
+        assert!(matches!(missing, GitCirclesError::Config(_)));
+    }
+
+    #[test]
+    fn replace_login_matches_whole_logins_in_any_case() {
+        assert_eq!(
+            replace_login("Thanks @Alice, ALICE fixed malice", "alice", "x"),
+            "Thanks @x, x fixed malice"
+        );
+        assert_eq!(
+            replace_login("alice-bot ran", "alice", "x"),
+            "alice-bot ran"
+        );
+        assert_eq!(replace_login("no mention", "alice", "x"), "no mention");
+    }
+}
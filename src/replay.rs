@@ -0,0 +1,348 @@
+//! Record/replay harness for GitHub HTTP traffic.
+//!
+//! [`HttpHarness`] is a small local HTTP server fronting both the REST API
+//! (under `/api`) and raw.githubusercontent.com (under `/raw`). In replay mode
+//! it answers from a cassette file; in record mode it forwards every request to
+//! the real hosts and writes the responses to the cassette on
+//! [`HttpHarness::finish`]. Request headers are never recorded, so cassettes
+//! do not leak tokens.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+use crate::github::GitHubClient;
+use crate::types::{GitCirclesError, Result};
+
+const API_PREFIX: &str = "/api";
+const RAW_PREFIX: &str = "/raw";
+const API_UPSTREAM: &str = "https://api.github.com";
+const RAW_UPSTREAM: &str = "https://raw.githubusercontent.com";
+
+/// Set to record fresh cassettes against the real GitHub hosts.
+pub const RECORD_ENV: &str = "GITCIRCLES_RECORD";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    pub method: String,
+    /// Path and query as seen by the harness, including the host prefix.
+    pub path: String,
+    pub status: u16,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub json: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+impl Cassette {
+    pub fn load(path: &Path) -> Result<Self> {
+        let raw = std::fs::read(path)?;
+        Ok(serde_json::from_slice(&raw)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Replay,
+    Record,
+}
+
+struct State {
+    mode: Mode,
+    cassette: Cassette,
+    // Next interaction index per (method, path); repeated requests walk
+    // through the recorded responses and then keep serving the last one.
+    cursors: HashMap<(String, String), usize>,
+    upstream: reqwest::Client,
+}
+
+pub struct HttpHarness {
+    addr: SocketAddr,
+    cassette_path: PathBuf,
+    state: Arc<Mutex<State>>,
+    task: JoinHandle<()>,
+}
+
+impl HttpHarness {
+    /// Serve responses from an existing cassette.
+    pub async fn replay(cassette_path: impl AsRef<Path>) -> Result<Self> {
+        let cassette = Cassette::load(cassette_path.as_ref())?;
+        Self::start(cassette_path.as_ref(), Mode::Replay, cassette).await
+    }
+
+    /// Proxy to the real GitHub hosts and capture every response.
+    pub async fn record(cassette_path: impl AsRef<Path>) -> Result<Self> {
+        Self::start(cassette_path.as_ref(), Mode::Record, Cassette::default()).await
+    }
+
+    /// Record when [`RECORD_ENV`] is set, replay otherwise.
+    pub async fn from_env(cassette_path: impl AsRef<Path>) -> Result<Self> {
+        if std::env::var_os(RECORD_ENV).is_some() {
+            Self::record(cassette_path).await
+        } else {
+            Self::replay(cassette_path).await
+        }
+    }
+
+    async fn start(path: &Path, mode: Mode, cassette: Cassette) -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(State {
+            mode,
+            cassette,
+            cursors: HashMap::new(),
+            upstream: reqwest::Client::new(),
+        }));
+
+        let task_state = state.clone();
+        let task = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let state = task_state.clone();
+                tokio::spawn(async move {
+                    let _ = handle_connection(stream, state).await;
+                });
+            }
+        });
+
+        Ok(Self {
+            addr,
+            cassette_path: path.to_path_buf(),
+            state,
+            task,
+        })
+    }
+
+    pub fn api_base(&self) -> String {
+        format!("http://{}{}", self.addr, API_PREFIX)
+    }
+
+    pub fn raw_base(&self) -> String {
+        format!("http://{}{}", self.addr, RAW_PREFIX)
+    }
+
+    /// A GitHub client wired to this harness. In replay mode any token works.
+    pub fn client(&self, token: &str) -> Result<GitHubClient> {
+        GitHubClient::with_base_urls(token, &self.api_base(), &self.raw_base())
+    }
+
+    /// Stop serving and, when recording, write the captured cassette.
+    pub fn finish(self) -> Result<()> {
+        self.task.abort();
+        let state = self.state.lock().unwrap();
+        if state.mode == Mode::Record {
+            state.cassette.save(&self.cassette_path)?;
+        }
+        Ok(())
+    }
+}
+
+struct Request {
+    method: String,
+    path: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+async fn handle_connection(
+    mut stream: TcpStream,
+    state: Arc<Mutex<State>>,
+) -> Result<()> {
+    let Some(request) = read_request(&mut stream).await? else {
+        return Ok(());
+    };
+
+    let mode = state.lock().unwrap().mode;
+    let interaction = match mode {
+        Mode::Replay => replay(&state, &request),
+        Mode::Record => {
+            let upstream = state.lock().unwrap().upstream.clone();
+            let interaction = forward(&upstream, &request).await?;
+            state
+                .lock()
+                .unwrap()
+                .cassette
+                .interactions
+                .push(interaction.clone());
+            interaction
+        }
+    };
+
+    write_response(&mut stream, &interaction).await
+}
+
+fn replay(state: &Mutex<State>, request: &Request) -> Interaction {
+    let mut state = state.lock().unwrap();
+    let key = (request.method.clone(), request.path.clone());
+    let matches: Vec<usize> = state
+        .cassette
+        .interactions
+        .iter()
+        .enumerate()
+        .filter(|(_, i)| i.method == key.0 && i.path == key.1)
+        .map(|(idx, _)| idx)
+        .collect();
+
+    if matches.is_empty() {
+        return Interaction {
+            method: key.0.clone(),
+            path: key.1.clone(),
+            status: 501,
+            headers: BTreeMap::new(),
+            json: Some(serde_json::json!({
+                "message": format!("No recorded interaction for {} {}", key.0, key.1)
+            })),
+            text: None,
+        };
+    }
+
+    let cursor = state.cursors.entry(key).or_insert(0);
+    let idx = matches[(*cursor).min(matches.len() - 1)];
+    *cursor += 1;
+    state.cassette.interactions[idx].clone()
+}
+
+async fn forward(
+    upstream: &reqwest::Client,
+    request: &Request,
+) -> Result<Interaction> {
+    let url = if let Some(rest) = request.path.strip_prefix(API_PREFIX) {
+        format!("{}{}", API_UPSTREAM, rest)
+    } else if let Some(rest) = request.path.strip_prefix(RAW_PREFIX) {
+        format!("{}{}", RAW_UPSTREAM, rest)
+    } else {
+        return Err(GitCirclesError::Io(std::io::Error::other(format!(
+            "unexpected harness path {}",
+            request.path
+        ))));
+    };
+
+    let method = reqwest::Method::from_bytes(request.method.as_bytes())
+        .map_err(std::io::Error::other)?;
+    let mut builder = upstream.request(method, url).body(request.body.clone());
+    for (name, value) in &request.headers {
+        if matches!(name.as_str(), "authorization" | "accept" | "user-agent") {
+            builder = builder.header(name.as_str(), value.as_str());
+        }
+    }
+
+    let response = builder.send().await.map_err(std::io::Error::other)?;
+    let status = response.status().as_u16();
+    let mut headers = BTreeMap::new();
+    for name in ["content-type", "link"] {
+        if let Some(value) = response.headers().get(name)
+            && let Ok(value) = value.to_str()
+        {
+            headers.insert(name.to_string(), value.to_string());
+        }
+    }
+    let body = response.text().await.map_err(std::io::Error::other)?;
+    let (json, text) = match serde_json::from_str(&body) {
+        Ok(value) => (Some(value), None),
+        Err(_) => (None, Some(body)),
+    };
+
+    Ok(Interaction {
+        method: request.method.clone(),
+        path: request.path.clone(),
+        status,
+        headers,
+        json,
+        text,
+    })
+}
+
+async fn read_request(stream: &mut TcpStream) -> Result<Option<Request>> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let header_end = loop {
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos;
+        }
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Ok(None);
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    };
+
+    let head = String::from_utf8_lossy(&buf[..header_end]).to_string();
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default().to_string();
+    let path = request_line.next().unwrap_or_default().to_string();
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(k, v)| (k.trim().to_ascii_lowercase(), v.trim().to_string()))
+        .collect();
+
+    let content_length = headers
+        .iter()
+        .find(|(k, _)| k == "content-length")
+        .and_then(|(_, v)| v.parse::<usize>().ok())
+        .unwrap_or(0);
+    let mut body = buf[header_end + 4..].to_vec();
+    while body.len() < content_length {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            break;
+        }
+        body.extend_from_slice(&chunk[..n]);
+    }
+
+    Ok(Some(Request {
+        method,
+        path,
+        headers,
+        body,
+    }))
+}
+
+async fn write_response(
+    stream: &mut TcpStream,
+    interaction: &Interaction,
+) -> Result<()> {
+    let body = match (&interaction.json, &interaction.text) {
+        (Some(json), _) => serde_json::to_vec(json)?,
+        (None, Some(text)) => text.clone().into_bytes(),
+        (None, None) => Vec::new(),
+    };
+
+    let mut head = format!("HTTP/1.1 {} Replay\r\n", interaction.status);
+    let mut has_content_type = false;
+    for (name, value) in &interaction.headers {
+        has_content_type |= name == "content-type";
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    if !has_content_type && interaction.json.is_some() {
+        head.push_str("content-type: application/json; charset=utf-8\r\n");
+    }
+    head.push_str(&format!(
+        "content-length: {}\r\nconnection: close\r\n\r\n",
+        body.len()
+    ));
+
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(&body).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
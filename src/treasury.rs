@@ -0,0 +1,331 @@
+//! The treasury's on-chain transactions, read from an Ergo explorer, so
+//! funding and payouts (including ones made outside GitCircles) can be
+//! reconciled in one place. Synced transactions are stored in the database
+//! (see `GitCircles::sync_treasury`).
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::config::{HttpConfig, TreasuryConfig};
+use crate::types::{GitCirclesError, ProjectTreasury, Result};
+
+/// Miner fee contract on mainnet; fee outputs are not counterparties.
+const MINER_FEE_ADDRESS: &str = "2iHkR7CWvD1R4j1yZg5bkeDRQavjAaVPeTDFGGLZduHyfWMuYpmhHocX8GJoaieTx78FntzJbCBVL6rf96ocJoZdmWBL2fci7NqWgAirppPQmZ7fN9V6z13Ay6brPriBKYqLp1bT2Fk4FkFLCfdPpe";
+
+/// Which way value moved in a treasury transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TxDirection {
+    /// Funding: the treasury ended up with more ERG
+    Inflow,
+    /// A payout or other spend
+    Outflow,
+    /// The treasury paid itself, e.g. consolidating boxes
+    Internal,
+}
+
+impl std::fmt::Display for TxDirection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Inflow => "in",
+            Self::Outflow => "out",
+            Self::Internal => "internal",
+        })
+    }
+}
+
+/// A confirmed transaction touching the treasury address.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TreasuryTransaction {
+    /// The treasury address the transaction was synced for
+    pub address: String,
+    pub tx_id: String,
+    pub inclusion_height: u64,
+    pub timestamp: DateTime<Utc>,
+    /// nanoERG paid to the treasury by the outputs
+    pub received: u64,
+    /// nanoERG taken from the treasury by the inputs
+    pub spent: u64,
+    /// The other side: input addresses of an inflow, output addresses
+    /// (without the miner fee) of an outflow
+    pub counterparties: Vec<String>,
+}
+
+impl TreasuryTransaction {
+    /// Change to the treasury balance in nanoERG, including the fee on
+    /// spends.
+    pub fn net(&self) -> i64 {
+        self.received as i64 - self.spent as i64
+    }
+
+    pub fn direction(&self) -> TxDirection {
+        match (self.spent, self.counterparties.is_empty()) {
+            (0, _) => TxDirection::Inflow,
+            (_, true) => TxDirection::Internal,
+            _ if self.net() > 0 => TxDirection::Inflow,
+            _ => TxDirection::Outflow,
+        }
+    }
+}
+
+/// The last completed sync of a treasury address. Until one completes,
+/// syncs page through the whole history.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TreasurySyncState {
+    pub address: String,
+    pub synced_at: DateTime<Utc>,
+    /// Transactions stored for the address after the sync
+    pub transactions: u64,
+}
+
+/// The treasury a payout of `amount_nanoerg` is paid from: the one with
+/// the smallest cap that covers the amount, else one without a cap.
+pub fn route_payout(
+    treasuries: &[ProjectTreasury],
+    amount_nanoerg: u64,
+) -> Option<&ProjectTreasury> {
+    treasuries
+        .iter()
+        .filter(|t| t.max_payout_nanoerg.is_none_or(|max| amount_nanoerg <= max))
+        .min_by_key(|t| t.max_payout_nanoerg.unwrap_or(u64::MAX))
+}
+
+/// What a treasury sync stored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreasurySync {
+    pub address: String,
+    /// Transactions not seen before, newest first
+    pub new: Vec<TreasuryTransaction>,
+    pub synced_at: DateTime<Utc>,
+}
+
+/// Every stored treasury transaction, with names for the counterparties
+/// from the address book and wallet links.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreasuryHistory {
+    pub address: String,
+    /// The treasury's name, e.g. as registered for a project
+    pub name: Option<String>,
+    /// Newest first
+    pub transactions: Vec<TreasuryTransaction>,
+    pub last_sync: Option<TreasurySyncState>,
+    /// Transactions the sync before this report found
+    pub new: usize,
+    /// The failed sync the stored history stands in for
+    pub sync_error: Option<String>,
+    /// Counterparty address to its address book name or linked logins
+    pub names: BTreeMap<String, String>,
+}
+
+impl TreasuryHistory {
+    /// nanoERG added by transactions that grew the balance.
+    pub fn funded(&self) -> u64 {
+        self.transactions
+            .iter()
+            .map(|tx| tx.net().max(0) as u64)
+            .sum()
+    }
+
+    /// nanoERG taken by transactions that shrank the balance, fees
+    /// included.
+    pub fn paid_out(&self) -> u64 {
+        self.transactions
+            .iter()
+            .map(|tx| tx.net().min(0).unsigned_abs())
+            .sum()
+    }
+}
+
+/// One page of an address's transactions, newest first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionPage {
+    pub items: Vec<TreasuryTransaction>,
+    /// Transactions the explorer knows for the address
+    pub total: u64,
+}
+
+/// Anything that can list an address's confirmed transactions.
+pub trait TransactionSource {
+    /// Shown in errors, e.g. the explorer URL.
+    fn describe(&self) -> String;
+
+    /// Transactions newest first, skipping `offset`.
+    fn fetch_transactions(
+        &self,
+        address: &str,
+        offset: u64,
+        limit: u64,
+    ) -> impl std::future::Future<Output = Result<TransactionPage>> + Send;
+}
+
+/// The explorer configured under `[treasury]`.
+pub struct Explorer {
+    http: reqwest::Client,
+    url: String,
+}
+
+impl TreasuryConfig {
+    pub fn explorer(&self, http: &HttpConfig) -> Result<Explorer> {
+        let client = reqwest::Client::builder()
+            .timeout(http.timeout())
+            .connect_timeout(http.connect_timeout())
+            .build()
+            .map_err(|e| {
+                GitCirclesError::Config(format!("Cannot build HTTP client: {}", e))
+            })?;
+        Ok(Explorer {
+            http: client,
+            url: self.explorer_url.trim_end_matches('/').to_string(),
+        })
+    }
+}
+
+impl TransactionSource for Explorer {
+    fn describe(&self) -> String {
+        self.url.clone()
+    }
+
+    async fn fetch_transactions(
+        &self,
+        address: &str,
+        offset: u64,
+        limit: u64,
+    ) -> Result<TransactionPage> {
+        let url = format!(
+            "{}/api/v1/addresses/{}/transactions?offset={}&limit={}",
+            self.url, address, offset, limit
+        );
+        let response = self.http.get(&url).send().await.map_err(|e| {
+            GitCirclesError::Explorer(format!("{} is unreachable: {}", self.url, e))
+        })?;
+        let status = response.status();
+        let body = response.text().await.map_err(|e| {
+            GitCirclesError::Explorer(format!("Failed to read {}: {}", url, e))
+        })?;
+        if !status.is_success() {
+            return Err(GitCirclesError::Explorer(format!(
+                "{} returned HTTP {}",
+                url,
+                status.as_u16()
+            )));
+        }
+        parse_address_transactions(&body, address)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExplorerPage {
+    items: Vec<ExplorerTransaction>,
+    total: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExplorerTransaction {
+    id: String,
+    inclusion_height: u64,
+    /// Milliseconds since the epoch
+    timestamp: i64,
+    inputs: Vec<ExplorerBox>,
+    outputs: Vec<ExplorerBox>,
+}
+
+#[derive(Deserialize)]
+struct ExplorerBox {
+    value: u64,
+    address: String,
+}
+
+/// A `GET /api/v1/addresses/{address}/transactions` response, seen from
+/// `address`.
+pub fn parse_address_transactions(
+    body: &str,
+    address: &str,
+) -> Result<TransactionPage> {
+    let page: ExplorerPage = serde_json::from_str(body)?;
+    let items = page
+        .items
+        .into_iter()
+        .map(|tx| {
+            let ours = |b: &&ExplorerBox| b.address == address;
+            let received = tx.outputs.iter().filter(ours).map(|b| b.value).sum();
+            let spent: u64 = tx.inputs.iter().filter(ours).map(|b| b.value).sum();
+            let others = if spent == 0 { &tx.inputs } else { &tx.outputs };
+            let mut counterparties: Vec<String> = Vec::new();
+            for b in others {
+                if b.address != address
+                    && b.address != MINER_FEE_ADDRESS
+                    && !counterparties.contains(&b.address)
+                {
+                    counterparties.push(b.address.clone());
+                }
+            }
+            let timestamp = DateTime::from_timestamp_millis(tx.timestamp)
+                .ok_or_else(|| {
+                    GitCirclesError::Explorer(format!(
+                        "transaction {} has an invalid timestamp",
+                        tx.id
+                    ))
+                })?;
+            Ok(TreasuryTransaction {
+                address: address.to_string(),
+                tx_id: tx.id,
+                inclusion_height: tx.inclusion_height,
+                timestamp,
+                received,
+                spent,
+                counterparties,
+            })
+        })
+        .collect::<Result<_>>()?;
+    Ok(TransactionPage {
+        items,
+        total: page.total,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn explorer_transactions_are_seen_from_the_treasury() {
+        let body = format!(
+            r#"{{"items":[
+            {{"id":"payout","blockId":"b2","inclusionHeight":1002,
+              "timestamp":1714608000000,"numConfirmations":3,
+              "inputs":[{{"boxId":"i1","value":10000000000,"address":"T"}}],
+              "dataInputs":[],
+              "outputs":[
+                {{"boxId":"o1","value":2000000000,"address":"alice"}},
+                {{"boxId":"o2","value":7998900000,"address":"T"}},
+                {{"boxId":"o3","value":1100000,"address":"{fee}"}}]}},
+            {{"id":"funding","blockId":"b1","inclusionHeight":1001,
+              "timestamp":1714521600000,"numConfirmations":4,
+              "inputs":[{{"boxId":"i0","value":10001100000,"address":"donor"}}],
+              "dataInputs":[],
+              "outputs":[
+                {{"boxId":"i1","value":10000000000,"address":"T"}},
+                {{"boxId":"o4","value":1100000,"address":"{fee}"}}]}}],
+            "total":2}}"#,
+            fee = MINER_FEE_ADDRESS
+        );
+        let page = parse_address_transactions(&body, "T").unwrap();
+        assert_eq!(page.total, 2);
+
+        let [payout, funding] = &page.items[..] else {
+            panic!("expected two transactions");
+        };
+        assert_eq!(payout.direction(), TxDirection::Outflow);
+        assert_eq!(payout.net(), -2_001_100_000);
+        assert_eq!(payout.counterparties, ["alice"]);
+        assert_eq!(funding.direction(), TxDirection::Inflow);
+        assert_eq!(funding.net(), 10_000_000_000);
+        assert_eq!(funding.counterparties, ["donor"]);
+        assert_eq!(funding.timestamp.to_rfc3339(), "2024-05-01T00:00:00+00:00");
+
+        assert!(parse_address_transactions("{}", "T").is_err());
+    }
+}
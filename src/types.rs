@@ -8,6 +8,9 @@ use std::fmt;
 use std::ops::Deref;
 use thiserror::Error;
 
+use crate::github::PROFILE_REPO_NAME;
+use crate::output::local_time;
+
 #[derive(Error, Debug)]
 pub enum GitCirclesError {
     #[error("GitHub API error: {0}")]
@@ -46,10 +49,160 @@ pub enum GitCirclesError {
         "Repository {0} exists but appears to be empty. Please create at least one commit with P2PK.pub file."
     )]
     RepoEmpty(String),
+
+    #[error("Invalid gitcircles.toml in {0}: {1}")]
+    PreferencesInvalid(String, String),
+
+    #[error("Ergo node error: {0}")]
+    Node(String),
+
+    #[error("Not allowed: {0}")]
+    Forbidden(String),
+
+    #[error("Corrupt database record '{key}': {cause}")]
+    CorruptRecord {
+        key: String,
+        #[source]
+        cause: serde_json::Error,
+    },
+
+    #[error("Invalid config: {0}")]
+    Config(String),
+
+    #[error(
+        "{0} is temporarily unavailable ({1}); nothing was changed, try again later"
+    )]
+    TemporarilyUnavailable(String, String),
+
+    #[error("GitHub rate limit exceeded{}", rate_limit_reset(.reset_at))]
+    RateLimited {
+        /// When the quota refills; unknown for secondary (abuse) limits
+        reset_at: Option<DateTime<Utc>>,
+        /// Requests left in the quota, if GitHub reported it
+        remaining: Option<u64>,
+    },
+
+    #[error("Background task failed: {0}")]
+    Task(#[from] tokio::task::JoinError),
+
+    #[error("Price feed error: {0}")]
+    Price(String),
+
+    #[error("Explorer error: {0}")]
+    Explorer(String),
+
+    /// A project, pull request or other record named on the command line
+    /// does not exist
+    #[error("{0}")]
+    NotFound(String),
+
+    /// A value or request the command cannot act on, e.g. an empty name or
+    /// pausing a project that is already paused
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
 }
 
 pub type Result<T> = std::result::Result<T, GitCirclesError>;
 
+fn rate_limit_reset(reset_at: &Option<DateTime<Utc>>) -> String {
+    match reset_at {
+        Some(at) => {
+            format!("; resets at {}", local_time(*at, "%Y-%m-%d %H:%M:%S %Z"))
+        }
+        None => "; wait a minute before retrying".to_string(),
+    }
+}
+
+impl GitCirclesError {
+    /// Whether the same request may succeed later, e.g. after a GitHub
+    /// outage.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            GitCirclesError::TemporarilyUnavailable(_, _)
+                | GitCirclesError::RateLimited { .. }
+        )
+    }
+
+    /// Whether GitHub answered 404 Not Found.
+    pub fn is_not_found(&self) -> bool {
+        matches!(
+            self,
+            GitCirclesError::GitHub(octocrab::Error::GitHub { source, .. })
+                if source.message.contains("Not Found")
+        )
+    }
+
+    /// Stable machine-readable identifier. Codes are never renumbered or
+    /// reused; new variants get new codes.
+    pub fn code(&self) -> &'static str {
+        match self {
+            GitCirclesError::GitHub(_) => "GC-GITHUB-001",
+            GitCirclesError::Database(_) => "GC-DB-001",
+            GitCirclesError::InvalidRepo(_) => "GC-INPUT-001",
+            GitCirclesError::Auth(_) => "GC-AUTH-001",
+            GitCirclesError::Serde(_) => "GC-DATA-001",
+            GitCirclesError::DatabasePath(_) => "GC-DB-002",
+            GitCirclesError::Io(_) => "GC-IO-001",
+            GitCirclesError::WalletNotFound(_) => "GC-WALLET-001",
+            GitCirclesError::WalletInvalidFormat(_, _) => "GC-WALLET-002",
+            GitCirclesError::RepoNotAccessible(_) => "GC-WALLET-003",
+            GitCirclesError::RepoEmpty(_) => "GC-WALLET-004",
+            GitCirclesError::PreferencesInvalid(_, _) => "GC-WALLET-005",
+            GitCirclesError::Node(_) => "GC-NODE-001",
+            GitCirclesError::Forbidden(_) => "GC-AUTH-002",
+            GitCirclesError::CorruptRecord { .. } => "GC-DB-003",
+            GitCirclesError::Config(_) => "GC-CONFIG-001",
+            GitCirclesError::TemporarilyUnavailable(_, _) => "GC-NET-001",
+            GitCirclesError::RateLimited { .. } => "GC-GITHUB-002",
+            GitCirclesError::Task(_) => "GC-INTERNAL-001",
+            GitCirclesError::Price(_) => "GC-PRICE-001",
+            GitCirclesError::Explorer(_) => "GC-EXPLORER-001",
+            GitCirclesError::NotFound(_) => "GC-NOTFOUND-001",
+            GitCirclesError::InvalidInput(_) => "GC-INPUT-002",
+        }
+    }
+
+    /// Process exit status, following the BSD `sysexits.h` classes so
+    /// scripts can tell bad input, missing data, permission problems and
+    /// transient failures apart. Argument errors from the parser exit with 2.
+    pub fn exit_code(&self) -> u8 {
+        const USAGE: u8 = 64;
+        const DATA: u8 = 65;
+        const NO_INPUT: u8 = 66;
+        const UNAVAILABLE: u8 = 69;
+        const SOFTWARE: u8 = 70;
+        const IO: u8 = 74;
+        const TEMP_FAIL: u8 = 75;
+        const NO_PERM: u8 = 77;
+        const CONFIG: u8 = 78;
+
+        match self {
+            GitCirclesError::InvalidRepo(_) => USAGE,
+            GitCirclesError::Serde(_)
+            | GitCirclesError::WalletInvalidFormat(_, _)
+            | GitCirclesError::PreferencesInvalid(_, _)
+            | GitCirclesError::CorruptRecord { .. }
+            | GitCirclesError::InvalidInput(_) => DATA,
+            GitCirclesError::WalletNotFound(_)
+            | GitCirclesError::RepoEmpty(_)
+            | GitCirclesError::NotFound(_) => NO_INPUT,
+            GitCirclesError::GitHub(_)
+            | GitCirclesError::Node(_)
+            | GitCirclesError::Price(_)
+            | GitCirclesError::Explorer(_) => UNAVAILABLE,
+            GitCirclesError::Task(_) => SOFTWARE,
+            GitCirclesError::Database(_) | GitCirclesError::Io(_) => IO,
+            GitCirclesError::TemporarilyUnavailable(_, _)
+            | GitCirclesError::RateLimited { .. } => TEMP_FAIL,
+            GitCirclesError::Auth(_)
+            | GitCirclesError::Forbidden(_)
+            | GitCirclesError::RepoNotAccessible(_) => NO_PERM,
+            GitCirclesError::DatabasePath(_) | GitCirclesError::Config(_) => CONFIG,
+        }
+    }
+}
+
 /// Validates an Ergo mainnet Pay-to-Public-Key (P2PK) address.
 pub fn is_valid_p2pk_mainnet(addr: &str) -> bool {
     if !addr.starts_with('9') {
@@ -146,13 +299,200 @@ impl From<WalletAddress> for String {
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum WalletSource {
-    GitHubProfileRepo { login: String, branch: String },
+    /// `branch` holds the branch or tag name, as told by `ref_kind`
+    GitHubProfileRepo {
+        login: String,
+        branch: String,
+        #[serde(default)]
+        ref_kind: RefKind,
+        /// Last commit touching `P2PK.pub` on the ref when it was read
+        #[serde(default)]
+        commit: Option<String>,
+    },
+    /// Restored by an operator from the address current at `restored_from`
+    Manual {
+        restored_from: DateTime<Utc>,
+        #[serde(default)]
+        actor: Option<String>,
+    },
+}
+
+impl WalletSource {
+    /// Commit the address was read at, if recorded.
+    pub fn commit(&self) -> Option<&str> {
+        match self {
+            WalletSource::GitHubProfileRepo { commit, .. } => commit.as_deref(),
+            WalletSource::Manual { .. } => None,
+        }
+    }
+
+    /// Web URL of that commit.
+    pub fn commit_url(&self) -> Option<String> {
+        match self {
+            WalletSource::GitHubProfileRepo {
+                login,
+                commit: Some(commit),
+                ..
+            } => Some(format!(
+                "https://github.com/{}/{}/commit/{}",
+                login, PROFILE_REPO_NAME, commit
+            )),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for WalletSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WalletSource::GitHubProfileRepo {
+                login,
+                branch,
+                ref_kind,
+                ..
+            } => write!(
+                f,
+                "{}/{} ({} {})",
+                login, PROFILE_REPO_NAME, ref_kind, branch
+            ),
+            WalletSource::Manual {
+                restored_from,
+                actor,
+            } => {
+                write!(
+                    f,
+                    "rollback to {}",
+                    local_time(*restored_from, "%Y-%m-%d %H:%M %Z")
+                )?;
+                match actor {
+                    Some(actor) => write!(f, " by {}", actor),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+/// Whether a profile repository ref names a branch or a tag.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RefKind {
+    #[default]
+    Branch,
+    Tag,
+}
+
+impl fmt::Display for RefKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RefKind::Branch => write!(f, "branch"),
+            RefKind::Tag => write!(f, "tag"),
+        }
+    }
+}
+
+/// A specific branch or tag to read the wallet from, from `wallet sync
+/// --ref` or the `wallet_ref` key of `gitcircles.toml`. Names starting with
+/// `tags/` or `refs/tags/` are tags; anything else is a branch, with an
+/// optional `heads/` or `refs/heads/` prefix.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfileRef {
+    pub kind: RefKind,
+    pub name: String,
+}
+
+impl std::str::FromStr for ProfileRef {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let s = s.trim();
+        let s = s.strip_prefix("refs/").unwrap_or(s);
+        let (kind, name) = match s.strip_prefix("tags/") {
+            Some(tag) => (RefKind::Tag, tag),
+            None => (RefKind::Branch, s.strip_prefix("heads/").unwrap_or(s)),
+        };
+        if name.is_empty() {
+            return Err("Ref must name a branch or tag".to_string());
+        }
+        Ok(Self {
+            kind,
+            name: name.to_string(),
+        })
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct WalletFetchOutcome {
     pub address: WalletAddress,
+    /// Branch or tag name the wallet was read from
     pub branch: String,
+    pub ref_kind: RefKind,
+    /// Last commit touching `P2PK.pub` on that ref, when it could be looked
+    /// up
+    pub commit: Option<String>,
+    /// Parsed `gitcircles.toml` from the same branch, if present
+    pub preferences: Option<ContributorPreferences>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PayoutSchedule {
+    Immediate,
+    Weekly,
+    Monthly,
+}
+
+/// Contributor settings from the optional `gitcircles.toml` in the profile
+/// repository, next to `P2PK.pub`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ContributorPreferences {
+    #[serde(default)]
+    pub display_name: Option<String>,
+    #[serde(default)]
+    pub payout_schedule: Option<PayoutSchedule>,
+    #[serde(default)]
+    pub min_payout_nanoerg: Option<u64>,
+    #[serde(default)]
+    pub opt_out: bool,
+    /// Branch or tag holding `P2PK.pub`, when it isn't on the usual branches
+    #[serde(default)]
+    pub wallet_ref: Option<String>,
+}
+
+/// A wallet probe that found no profile repository. Further probes for the
+/// login are skipped until `retry_after` unless forced.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WalletProbeMiss {
+    pub platform: String,
+    pub login: String,
+    pub checked_at: DateTime<Utc>,
+    pub retry_after: DateTime<Utc>,
+}
+
+/// Where a contributor's opt-out came from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OptOutSource {
+    /// `opt_out = true` in the profile repository's `gitcircles.toml`
+    ProfileRepo,
+    /// An `OPT OUT` comment on a pull request
+    Comment {
+        repository: String,
+        number: u64,
+        comment_id: u64,
+    },
+    /// Recorded by an operator
+    Manual,
+}
+
+/// A contributor who declined rewards. Opted-out logins must never be
+/// registered for appreciation or notified.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContributorOptOut {
+    pub platform: String,
+    pub login: String,
+    pub source: OptOutSource,
+    pub recorded_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Clone)]
@@ -161,6 +501,39 @@ pub struct WalletSyncResult {
     pub previous: Option<WalletAddress>,
     pub changed: bool,
     pub source: WalletSource,
+    pub preferences: Option<ContributorPreferences>,
+    /// Other logins already linked to a newly stored address
+    pub shared_with: Vec<String>,
+    /// Set when a stored address was replaced by a different one
+    pub rotation: Option<WalletRotation>,
+}
+
+/// A replaced wallet address. New payouts wait until `effective_at` so a
+/// hijacked profile repository can be noticed before funds move.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WalletRotation {
+    pub platform: String,
+    pub login: String,
+    pub previous: WalletAddress,
+    pub current: WalletAddress,
+    pub detected_at: DateTime<Utc>,
+    pub effective_at: DateTime<Utc>,
+}
+
+impl WalletRotation {
+    pub fn is_pending(&self, now: DateTime<Utc>) -> bool {
+        now < self.effective_at
+    }
+}
+
+/// Result of `wallet init-profile` for the authenticated user.
+#[derive(Debug, Clone)]
+pub struct ProfileInitOutcome {
+    pub login: String,
+    pub branch: String,
+    pub created_repository: bool,
+    /// Files committed; files whose content already matched are left out
+    pub committed_files: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -181,6 +554,95 @@ pub struct WalletHistoryEntry {
     pub recorded_at: DateTime<Utc>,
 }
 
+/// One change of a login's wallet address, derived from its history.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WalletTransition {
+    /// `None` for the first address recorded
+    pub from: Option<WalletAddress>,
+    pub to: WalletAddress,
+    pub changed_at: DateTime<Utc>,
+    /// How long `to` stayed current, up to now for the latest entry
+    pub held_for: chrono::Duration,
+    pub source: WalletSource,
+}
+
+/// Logins linked to one address of a `wallet lookup-many` list.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WalletLookup {
+    /// The address as written in the list
+    pub address: String,
+    /// `false` when the line is not a valid Ergo address
+    pub valid: bool,
+    pub logins: Vec<String>,
+    /// The address book's name for the address, if it has one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<AddressKind>,
+}
+
+/// What a named address in the address book belongs to.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum AddressKind {
+    /// A project or organisation treasury
+    Treasury,
+    /// An exchange deposit or hot wallet, usually shared by many people
+    Exchange,
+    /// A contributor known by name
+    Contributor,
+    /// An address that should not receive rewards
+    Blacklisted,
+}
+
+impl std::str::FromStr for AddressKind {
+    type Err = GitCirclesError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "treasury" => Ok(Self::Treasury),
+            "exchange" => Ok(Self::Exchange),
+            "contributor" => Ok(Self::Contributor),
+            "blacklisted" => Ok(Self::Blacklisted),
+            _ => Err(GitCirclesError::InvalidInput(
+                "Invalid kind. Must be one of: treasury, exchange, contributor, \
+                 blacklisted"
+                    .to_string(),
+            )),
+        }
+    }
+}
+
+impl fmt::Display for AddressKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Treasury => "treasury",
+            Self::Exchange => "exchange",
+            Self::Contributor => "contributor",
+            Self::Blacklisted => "blacklisted",
+        })
+    }
+}
+
+/// A human-readable name for an address, e.g. "Project treasury".
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AddressBookEntry {
+    pub address: WalletAddress,
+    pub name: String,
+    pub kind: AddressKind,
+    pub note: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl AddressBookEntry {
+    /// e.g. "Project treasury (treasury)".
+    pub fn label(&self) -> String {
+        format!("{} ({})", self.name, self.kind)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WalletLoginLink {
     pub wallet: WalletAddress,
@@ -189,6 +651,23 @@ pub struct WalletLoginLink {
     pub linked_at: DateTime<Utc>,
 }
 
+/// Something suspicious noticed while syncing wallets.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WalletWarningKind {
+    /// The address is also linked to other logins, possibly sybil accounts
+    SharedWallet { other_logins: Vec<String> },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalletWarning {
+    pub platform: String,
+    pub login: String,
+    pub wallet: WalletAddress,
+    pub kind: WalletWarningKind,
+    pub recorded_at: DateTime<Utc>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Project {
     pub id: String,
@@ -196,6 +675,283 @@ pub struct Project {
     pub description: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// Pauses, oldest first; the last one is ongoing if not resumed
+    #[serde(default)]
+    pub pauses: Vec<ProjectPause>,
+    /// When not empty, only these logins' PRs count for the project
+    #[serde(default)]
+    pub allowed_contributors: Vec<String>,
+    /// Logins whose PRs never count, e.g. employees paid separately
+    #[serde(default)]
+    pub blocked_contributors: Vec<String>,
+    /// GitHub organizations whose members' PRs don't count, e.g. the
+    /// company behind the project
+    #[serde(default)]
+    pub excluded_orgs: Vec<String>,
+    /// Authors found to be members of an excluded organization
+    #[serde(default)]
+    pub org_exclusions: Vec<OrgExclusion>,
+    /// PRs only count once an owner or admin approves them
+    #[serde(default)]
+    pub require_approval: bool,
+}
+
+impl Project {
+    /// Whether PRs by `login` count for the project under its allow and
+    /// block lists and organization exclusions. Logins compare
+    /// case-insensitively, as on GitHub.
+    pub fn counts_author(&self, login: &str) -> bool {
+        let listed =
+            |list: &[String]| list.iter().any(|l| l.eq_ignore_ascii_case(login));
+        (self.allowed_contributors.is_empty() || listed(&self.allowed_contributors))
+            && !listed(&self.blocked_contributors)
+            && self.org_exclusion(login).is_none()
+    }
+
+    /// Why `login` is excluded as an organization member, if it is.
+    pub fn org_exclusion(&self, login: &str) -> Option<&OrgExclusion> {
+        self.org_exclusions
+            .iter()
+            .find(|e| e.login.eq_ignore_ascii_case(login))
+    }
+
+    /// The allow or block list.
+    pub fn contributor_list_mut(
+        &mut self,
+        list: ContributorList,
+    ) -> &mut Vec<String> {
+        match list {
+            ContributorList::Allow => &mut self.allowed_contributors,
+            ContributorList::Block => &mut self.blocked_contributors,
+        }
+    }
+
+    /// The ongoing pause, if the project is paused.
+    pub fn current_pause(&self) -> Option<&ProjectPause> {
+        self.pauses.last().filter(|p| p.resumed_at.is_none())
+    }
+}
+
+#[cfg(test)]
+impl Project {
+    /// A project created just now, without pauses, contributor lists or
+    /// required approvals, for tests to adjust.
+    pub(crate) fn for_test(id: &str, name: &str) -> Self {
+        Self {
+            id: id.into(),
+            name: name.into(),
+            description: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            pauses: Vec::new(),
+            allowed_contributors: Vec::new(),
+            blocked_contributors: Vec::new(),
+            excluded_orgs: Vec::new(),
+            org_exclusions: Vec::new(),
+            require_approval: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContributorList {
+    Allow,
+    Block,
+}
+
+/// A PR author left out of a project's counts for belonging to one of its
+/// excluded organizations.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrgExclusion {
+    pub login: String,
+    pub org: String,
+    pub detected_at: DateTime<Utc>,
+}
+
+/// A period in which a project scheduled none of its PRs, e.g. during a
+/// treasury migration.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProjectPause {
+    pub paused_at: DateTime<Utc>,
+    pub resumed_at: Option<DateTime<Utc>>,
+    pub reason: Option<String>,
+    /// Login that paused the project, when acting with `--as`
+    pub paused_by: Option<String>,
+}
+
+/// Where a PR is in the reward pipeline, as shown by its GitHub label.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PipelineLabel {
+    /// Counted by a project and waiting for its reward
+    Scheduled,
+    /// Will not be rewarded, e.g. because the author opted out
+    Stopped,
+}
+
+impl std::fmt::Display for PipelineLabel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Scheduled => "scheduled",
+            Self::Stopped => "stopped",
+        })
+    }
+}
+
+/// The pipeline label last put on a PR.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppliedLabel {
+    pub repository: String,
+    pub number: u64,
+    pub state: PipelineLabel,
+    /// The label's name on GitHub when it was applied
+    pub label: String,
+    pub applied_at: DateTime<Utc>,
+}
+
+/// The state of a commit status, as GitHub names it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommitState {
+    Pending,
+    Success,
+    Failure,
+    Error,
+}
+
+/// A commit status to publish on a merge commit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitStatus {
+    pub state: CommitState,
+    pub context: String,
+    pub description: String,
+    pub target_url: Option<String>,
+}
+
+/// The pipeline state last published as a PR's merge commit status.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PublishedStatus {
+    pub repository: String,
+    pub number: u64,
+    pub sha: String,
+    pub state: PipelineLabel,
+    pub published_at: DateTime<Utc>,
+}
+
+/// A maintainer's go-ahead for a PR in a project that requires approval.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PullRequestApproval {
+    pub project_id: String,
+    pub repository: String,
+    pub number: u64,
+    /// Login that approved, when known
+    pub approved_by: Option<String>,
+    pub source: ApprovalSource,
+    pub approved_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ApprovalSource {
+    /// `project approve` on the command line
+    Manual,
+    /// A "GitCircles APPROVE" comment on the pull request
+    Comment { comment_id: u64 },
+}
+
+/// Running totals for one project, updated in the same batch as the PR
+/// writes they count, so listings can show them without scanning PRs.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProjectCounters {
+    /// Stored PRs the project's links claim
+    pub prs: u64,
+}
+
+/// One contributor's totals over the stored PRs, counting PRs they opened
+/// or authored commits in. Updated in the same batch as the PR writes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContributorStats {
+    pub platform: String,
+    pub login: String,
+    pub first_contribution: DateTime<Utc>,
+    pub last_contribution: DateTime<Utc>,
+    pub prs: u64,
+    /// "owner/repo" of every repository with one of their PRs, sorted
+    pub repositories: Vec<String>,
+}
+
+impl ContributorStats {
+    pub fn new(platform: &str, login: &str, pr: &MergedPullRequest) -> Self {
+        Self {
+            platform: platform.to_string(),
+            login: login.to_string(),
+            first_contribution: pr.merged_at,
+            last_contribution: pr.merged_at,
+            prs: 0,
+            repositories: Vec::new(),
+        }
+    }
+
+    /// Count one more PR.
+    pub fn record(&mut self, pr: &MergedPullRequest) {
+        self.prs += 1;
+        self.first_contribution = self.first_contribution.min(pr.merged_at);
+        self.last_contribution = self.last_contribution.max(pr.merged_at);
+        if let Err(i) = self.repositories.binary_search(&pr.repository) {
+            self.repositories.insert(i, pr.repository.clone());
+        }
+    }
+}
+
+/// What `privacy forget` removed or anonymized for one login.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ForgetReport {
+    pub login: String,
+    /// Takes the login's place in PRs, project lists and contributor stats,
+    /// so aggregate counts stay the same
+    pub pseudonym: String,
+    pub wallet: bool,
+    pub wallet_history: usize,
+    pub wallet_links: usize,
+    /// Warnings about the login removed, or naming it rewritten
+    pub wallet_warnings: usize,
+    /// Preferences, opt-out, pending rotation and wallet probe records, and
+    /// address book entries naming the login's addresses as a contributor
+    pub contributor_records: usize,
+    pub project_roles: usize,
+    /// PRs the login authored, pushed from a fork or was mentioned in
+    pub pull_requests: usize,
+    /// Projects whose allow, block or exclusion lists or pauses named the
+    /// login
+    pub projects: usize,
+    /// PR approvals the login gave
+    pub approvals: usize,
+    pub contributor_stats: bool,
+}
+
+impl ForgetReport {
+    /// Whether nothing was stored for the login.
+    pub fn is_empty(&self) -> bool {
+        !self.wallet
+            && self.wallet_history == 0
+            && self.wallet_links == 0
+            && self.wallet_warnings == 0
+            && self.contributor_records == 0
+            && self.project_roles == 0
+            && self.pull_requests == 0
+            && self.projects == 0
+            && self.approvals == 0
+            && !self.contributor_stats
+    }
+}
+
+/// The onboarding issue opened in a repository by `project announce`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectAnnouncement {
+    pub project_id: String,
+    pub repository: String,
+    pub issue_number: u64,
+    pub updated_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -206,6 +962,52 @@ pub struct ProjectOwner {
     pub added_at: DateTime<Utc>,
 }
 
+/// One of a project's treasury addresses, e.g. a hot wallet for small
+/// payouts next to a multi-sig for large ones.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProjectTreasury {
+    pub project_id: String,
+    pub address: String,
+    pub name: String,
+    /// Largest payout this treasury makes; `None` takes any amount
+    pub max_payout_nanoerg: Option<u64>,
+    pub added_at: DateTime<Utc>,
+}
+
+/// Project roles, ordered by privilege: each role may do everything the
+/// roles below it may.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ProjectRole {
+    Member,
+    Admin,
+    Owner,
+}
+
+impl std::str::FromStr for ProjectRole {
+    type Err = GitCirclesError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "member" => Ok(Self::Member),
+            "admin" => Ok(Self::Admin),
+            "owner" => Ok(Self::Owner),
+            _ => Err(GitCirclesError::InvalidInput(
+                "Invalid role. Must be one of: owner, admin, member".to_string(),
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for ProjectRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Member => "member",
+            Self::Admin => "admin",
+            Self::Owner => "owner",
+        })
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Repository {
     pub owner: String,
@@ -214,7 +1016,37 @@ pub struct Repository {
     pub last_sync: Option<DateTime<Utc>>,
     pub total_prs: u64,
     pub first_sync: DateTime<Utc>,
-    pub project_id: Option<String>, // Link to project
+    /// GitHub's description of the repository as of the last collect;
+    /// `None` until a collect has fetched it
+    #[serde(default)]
+    pub metadata: Option<RepositoryMetadata>,
+}
+
+/// Context about a repository shown next to its PRs, refreshed on collect.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RepositoryMetadata {
+    pub description: Option<String>,
+    pub stars: u32,
+    pub forks: u32,
+    /// Primary language as GitHub detects it
+    pub language: Option<String>,
+    /// SPDX identifier, e.g. "MIT"; "NOASSERTION" for licenses GitHub
+    /// doesn't recognize
+    pub license: Option<String>,
+    pub fetched_at: DateTime<Utc>,
+}
+
+/// Links a repository to a project that rewards its PRs. A repository can
+/// feed several projects, and a project can span several repositories.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProjectLink {
+    pub project_id: String,
+    pub repository: String, // "owner/repo"
+    /// Globs the project claims, e.g. `packages/foo/**`; empty claims the
+    /// whole repository. See [`crate::paths`].
+    #[serde(default)]
+    pub paths: Vec<String>,
+    pub linked_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -226,6 +1058,174 @@ pub struct MergedPullRequest {
     pub base_branch: String,
     pub merge_commit_sha: String,
     pub repository: String, // "owner/repo" format (TODO: separate type)
+    #[serde(default)]
+    pub head_repository: Option<String>, // None when the fork was deleted
+    #[serde(default)]
+    pub head_branch: Option<String>,
+    #[serde(default)]
+    pub from_fork: bool,
+    #[serde(default)]
+    pub resolved_authors: Vec<String>, // Commit author logins, empty if not fetched
+    #[serde(default)]
+    pub body: Option<String>, // May be truncated, see CollectOptions::max_body_chars
+    #[serde(default)]
+    pub linked_issues: Vec<IssueReference>,
+    #[serde(default = "default_platform")]
+    pub platform: String,
+    /// Web page of the PR; `Database::backfill_pull_request_urls` fills it
+    /// in for records stored before it was collected
+    #[serde(default)]
+    pub html_url: Option<String>,
+    #[serde(default)]
+    pub labels: Vec<String>,
+    /// The author's relation to the repository as GitHub reports it, e.g.
+    /// "CONTRIBUTOR" or "FIRST_TIME_CONTRIBUTOR"
+    #[serde(default)]
+    pub author_association: Option<String>,
+    /// Closed without a GitHub merge, but its head commit is on the base
+    /// branch. `merged_at` is then the close time and `merge_commit_sha`
+    /// the head commit.
+    #[serde(default)]
+    pub externally_merged: bool,
+    /// Files the PR touched, only fetched for repositories with path claims
+    #[serde(default)]
+    pub changed_files: Vec<String>,
+    /// Backfilled by an `import` command from a clone or an export rather
+    /// than collected from the GitHub API
+    #[serde(default)]
+    pub imported: bool,
+}
+
+fn default_platform() -> String {
+    "github".to_string()
+}
+
+impl MergedPullRequest {
+    /// Take the title, labels and author association from a fresh copy of
+    /// this PR, returning one change per field that differed.
+    pub fn refresh_from(
+        &mut self,
+        fresh: &MergedPullRequest,
+        at: DateTime<Utc>,
+    ) -> Vec<PullRequestChange> {
+        let mut changes = Vec::new();
+        let mut change = |field: &str, old: String, new: String| {
+            if old != new {
+                changes.push(PullRequestChange {
+                    repository: self.repository.clone(),
+                    number: self.number,
+                    field: field.to_string(),
+                    old,
+                    new,
+                    changed_at: at,
+                });
+            }
+        };
+        change("title", self.title.clone(), fresh.title.clone());
+        change("labels", self.labels.join(", "), fresh.labels.join(", "));
+        change(
+            "author_association",
+            self.author_association.clone().unwrap_or_default(),
+            fresh.author_association.clone().unwrap_or_default(),
+        );
+
+        self.title = fresh.title.clone();
+        self.labels = fresh.labels.clone();
+        self.author_association = fresh.author_association.clone();
+        changes
+    }
+
+    /// The stored web URL, or the GitHub URL derived from the repository
+    /// and number.
+    pub fn url(&self) -> String {
+        self.html_url.clone().unwrap_or_else(|| {
+            format!(
+                "https://github.com/{}/pull/{}",
+                self.repository, self.number
+            )
+        })
+    }
+
+    /// Web URL of the merge commit, next to the PR on the same host.
+    pub fn commit_url(&self) -> String {
+        let url = self.url();
+        let repo_url = url
+            .strip_suffix(&format!("/pull/{}", self.number))
+            .unwrap_or(&url);
+        format!("{}/commit/{}", repo_url, self.merge_commit_sha)
+    }
+}
+
+#[cfg(test)]
+impl MergedPullRequest {
+    /// PR #`number` by `author`, merged into acme/widgets' main branch just
+    /// now, for tests to adjust.
+    pub(crate) fn for_test(number: u64, author: &str) -> Self {
+        Self {
+            number,
+            title: format!("PR {}", number),
+            author: author.into(),
+            merged_at: Utc::now(),
+            base_branch: "main".into(),
+            merge_commit_sha: "0123456789abcdef".into(),
+            repository: "acme/widgets".into(),
+            head_repository: None,
+            head_branch: None,
+            from_fork: false,
+            resolved_authors: Vec::new(),
+            body: None,
+            linked_issues: Vec::new(),
+            platform: "github".into(),
+            html_url: None,
+            labels: Vec::new(),
+            author_association: None,
+            externally_merged: false,
+            changed_files: Vec::new(),
+            imported: false,
+        }
+    }
+}
+
+/// An issue mentioned in a PR description, e.g. `Fixes #12` or
+/// `acme/other#3`. Bare references are qualified with the PR's repository.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IssueReference {
+    pub repository: String,
+    pub number: u64,
+    /// Preceded by a closing keyword such as `fixes` or `closes`
+    pub closing: bool,
+}
+
+impl fmt::Display for IssueReference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}#{}", self.repository, self.number)
+    }
+}
+
+/// One author of a commit in a pull request. `login` is set when GitHub
+/// linked the commit email to an account; co-authors parsed from
+/// `Co-authored-by:` trailers only carry name and email.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PullRequestCommitAuthor {
+    pub login: Option<String>,
+    pub name: String,
+    pub email: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PullRequestComment {
+    pub id: u64,
+    pub author: String,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PullRequestReview {
+    pub id: u64,
+    pub author: String,
+    pub state: String, // "approved", "changes_requested", "commented", ...
+    pub submitted_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -236,6 +1236,38 @@ pub struct BaseBranchChange {
     pub changed_at: DateTime<Utc>,
 }
 
+/// A stored PR field that changed after merge, noticed by `collect --refresh`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PullRequestChange {
+    pub repository: String,
+    pub number: u64,
+    /// "title", "labels" (comma separated) or "author_association"
+    pub field: String,
+    pub old: String,
+    pub new: String,
+    pub changed_at: DateTime<Utc>,
+}
+
+/// One `collect` run against a repository, successful or not.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncLogEntry {
+    pub repository: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    /// Pages of the pull request listing
+    pub pages_fetched: u64,
+    /// All API requests, including per-PR commit lookups
+    pub api_calls: u64,
+    pub new_prs: u64,
+    pub error: Option<String>,
+}
+
+impl SyncLogEntry {
+    pub fn duration(&self) -> chrono::Duration {
+        self.finished_at - self.started_at
+    }
+}
+
 pub fn parse_repo(repo_str: &str) -> Result<(String, String)> {
     let (owner, repo) = repo_str
         .split_once('/')
@@ -289,6 +1321,32 @@ mod tests {
         String::from("9hUzb5RvSgDqJdtyCN9Ke496Yy63mpcUJKbRq4swzQ5EQKgygKT")
     }
 
+    #[test]
+    fn error_codes_are_stable() {
+        let auth = GitCirclesError::Auth("bad token".into());
+        assert_eq!(auth.code(), "GC-AUTH-001");
+        assert_eq!(auth.exit_code(), 77);
+
+        let outage = GitCirclesError::TemporarilyUnavailable(
+            "alice/gitcircles-profile".into(),
+            "HTTP 503".into(),
+        );
+        assert_eq!(outage.code(), "GC-NET-001");
+        assert_eq!(outage.exit_code(), 75);
+        assert_eq!(
+            GitCirclesError::WalletNotFound("alice".into()).exit_code(),
+            66
+        );
+
+        let missing = GitCirclesError::NotFound("Project 'demo' not found".into());
+        assert_eq!(
+            (missing.code(), missing.exit_code()),
+            ("GC-NOTFOUND-001", 66)
+        );
+        let invalid = GitCirclesError::InvalidInput("Invalid ERG amount".into());
+        assert_eq!((invalid.code(), invalid.exit_code()), ("GC-INPUT-002", 65));
+    }
+
     #[test]
     fn trims_whitespace() {
         let inner = mk_valid(60);
@@ -1,42 +1,240 @@
-use chrono::Utc;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{HashMap, HashSet};
 
+use crate::clock::{Clock, SystemClock};
 use crate::database::Database;
 use crate::github::WalletFetcher;
 use crate::types::{
-    Result, UserWallet, WalletHistoryEntry, WalletLoginLink, WalletSource,
-    WalletSyncResult,
+    AddressBookEntry, ContributorOptOut, OptOutSource, ProfileRef, Result,
+    UserWallet, WalletAddress, WalletHistoryEntry, WalletLoginLink, WalletLookup,
+    WalletProbeMiss, WalletRotation, WalletSource, WalletSyncResult,
+    WalletTransition, WalletWarning, WalletWarningKind,
 };
 
-pub struct WalletService<'a, F: WalletFetcher> {
+/// Address changes in `history` (oldest first), each with how long the new
+/// address stayed current.
+pub fn wallet_transitions(
+    history: &[WalletHistoryEntry],
+    now: DateTime<Utc>,
+) -> Vec<WalletTransition> {
+    history
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            let until = history.get(i + 1).map_or(now, |next| next.recorded_at);
+            WalletTransition {
+                from: i.checked_sub(1).map(|prev| history[prev].address.clone()),
+                to: entry.address.clone(),
+                changed_at: entry.recorded_at,
+                held_for: until - entry.recorded_at,
+                source: entry.source.clone(),
+            }
+        })
+        .collect()
+}
+
+/// Resolve `list` (one address per line; blank lines, `#` comments and
+/// repeats skipped) against the GitHub logins in `links`, naming addresses
+/// found in `book`.
+pub fn lookup_wallets(
+    list: &str,
+    links: &[WalletLoginLink],
+    book: &[AddressBookEntry],
+) -> Vec<WalletLookup> {
+    let mut by_wallet: HashMap<&str, Vec<String>> = HashMap::new();
+    for link in links.iter().filter(|link| link.platform == "github") {
+        let logins = by_wallet.entry(link.wallet.as_str()).or_default();
+        if !logins.contains(&link.login) {
+            logins.push(link.login.clone());
+        }
+    }
+
+    let mut seen = HashSet::new();
+    list.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter(|line| seen.insert(*line))
+        .map(|line| {
+            let valid = WalletAddress::try_from(line).is_ok();
+            let entry = book.iter().find(|entry| entry.address.as_str() == line);
+            WalletLookup {
+                address: line.to_string(),
+                valid,
+                logins: by_wallet.get(line).cloned().unwrap_or_default(),
+                label: entry.map(|entry| entry.name.clone()),
+                kind: entry.map(|entry| entry.kind),
+            }
+        })
+        .collect()
+}
+
+static SYSTEM_CLOCK: SystemClock = SystemClock;
+
+/// How long a "no profile repository" result is trusted before probing again.
+pub const DEFAULT_NEGATIVE_TTL_HOURS: i64 = 24;
+
+/// How long a replaced address waits before new payouts may use it.
+pub const DEFAULT_CONFIRMATION_WINDOW_HOURS: i64 = 72;
+
+pub struct WalletService<'a, F: WalletFetcher, C: Clock + ?Sized = SystemClock> {
     db: &'a Database,
     fetcher: &'a F,
+    clock: &'a C,
+    negative_ttl: Duration,
+    confirmation_window: Duration,
+    wallet_ref: Option<ProfileRef>,
+    dry_run: bool,
 }
 
 impl<'a, F: WalletFetcher> WalletService<'a, F> {
     pub fn new(db: &'a Database, fetcher: &'a F) -> Self {
-        Self { db, fetcher }
+        Self::with_clock(db, fetcher, &SYSTEM_CLOCK)
     }
+}
 
+impl<'a, F: WalletFetcher, C: Clock + ?Sized> WalletService<'a, F, C> {
+    pub fn with_clock(db: &'a Database, fetcher: &'a F, clock: &'a C) -> Self {
+        Self {
+            db,
+            fetcher,
+            clock,
+            negative_ttl: Duration::hours(DEFAULT_NEGATIVE_TTL_HOURS),
+            confirmation_window: Duration::hours(DEFAULT_CONFIRMATION_WINDOW_HOURS),
+            wallet_ref: None,
+            dry_run: false,
+        }
+    }
+
+    pub fn with_negative_ttl(mut self, ttl: Duration) -> Self {
+        self.negative_ttl = ttl;
+        self
+    }
+
+    pub fn with_confirmation_window(mut self, window: Duration) -> Self {
+        self.confirmation_window = window;
+        self
+    }
+
+    /// Read the wallet from this branch or tag instead of searching the
+    /// usual branches.
+    pub fn with_wallet_ref(mut self, wallet_ref: Option<ProfileRef>) -> Self {
+        self.wallet_ref = wallet_ref;
+        self
+    }
+
+    /// Fetch and compare as usual but write nothing, so the result only
+    /// reports what a sync would change.
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Sync the wallet for `login`. Logins recently found without a profile
+    /// repository are not probed again until the negative TTL expires,
+    /// unless `force` is set.
     pub async fn sync_github_login(
         &self,
         login: &str,
+        force: bool,
     ) -> Result<Option<WalletSyncResult>> {
-        // Step 1: Fetch from GitHub
-        let outcome = match self.fetcher.fetch_wallet_address(login).await? {
+        // Step 1: Skip logins with a fresh negative result
+        if !force {
+            let owned_login = login.to_string();
+            let miss = self
+                .db
+                .run(move |db| db.get_wallet_probe_miss("github", &owned_login))
+                .await?;
+            if miss.is_some_and(|m| self.clock.now() < m.retry_after) {
+                return Ok(None);
+            }
+        }
+
+        // Step 2: Fetch from GitHub
+        let outcome = match self
+            .fetcher
+            .fetch_wallet_address(login, self.wallet_ref.as_ref())
+            .await?
+        {
             Some(o) => o,
-            None => return Ok(None),
+            None => {
+                let now = self.clock.now();
+                let miss = WalletProbeMiss {
+                    platform: "github".to_string(),
+                    login: login.to_string(),
+                    checked_at: now,
+                    retry_after: now + self.negative_ttl,
+                };
+                if !self.dry_run {
+                    self.db
+                        .run(move |db| db.record_wallet_probe_miss(&miss))
+                        .await?;
+                }
+                return Ok(None);
+            }
         };
 
-        // Step 2: Get existing wallet
-        let previous_wallet = self.db.get_user_wallet("github", login)?;
+        // Step 3: Forget any earlier miss, store the latest preferences
+        // (including a profile opt-out) and get the existing wallet
+        let owned_login = login.to_string();
+        let preferences = outcome.preferences.clone();
+        let (now, dry_run) = (self.clock.now(), self.dry_run);
+        let previous_wallet = self
+            .db
+            .run(move |db| {
+                let mut batch = db.keyspace.batch();
+                db.clear_wallet_probe_miss_batch(
+                    &mut batch,
+                    "github",
+                    &owned_login,
+                );
+                db.set_contributor_preferences_batch(
+                    &mut batch,
+                    "github",
+                    &owned_login,
+                    preferences.as_ref(),
+                )?;
+
+                let existing =
+                    db.get_contributor_opt_out("github", &owned_login)?;
+                let wants_out = preferences.as_ref().is_some_and(|p| p.opt_out);
+                match existing {
+                    None if wants_out => {
+                        let opt_out = ContributorOptOut {
+                            platform: "github".to_string(),
+                            login: owned_login.clone(),
+                            source: OptOutSource::ProfileRepo,
+                            recorded_at: now,
+                        };
+                        db.set_contributor_opt_out_batch(&mut batch, &opt_out)?;
+                    }
+                    // Only a profile opt-out can be withdrawn from the profile
+                    Some(o)
+                        if !wants_out && o.source == OptOutSource::ProfileRepo =>
+                    {
+                        db.remove_contributor_opt_out_batch(
+                            &mut batch,
+                            "github",
+                            &owned_login,
+                        );
+                    }
+                    _ => {}
+                }
+                if !dry_run {
+                    batch.commit()?;
+                }
+                db.get_user_wallet("github", &owned_login)
+            })
+            .await?;
         let previous_address = previous_wallet.as_ref().map(|w| w.address.clone());
 
-        // Step 3: Detect changes
+        // Step 4: Detect changes
         let changed = previous_address.as_ref() != Some(&outcome.address);
 
-        // Step 4: Persist only if changed
+        // Step 5: Persist only if changed
+        let mut shared_with = Vec::new();
+        let mut rotation = None;
         if changed {
-            let now = Utc::now();
+            let now = self.clock.now();
 
             let user_wallet = UserWallet {
                 login: login.to_string(),
@@ -45,6 +243,8 @@ impl<'a, F: WalletFetcher> WalletService<'a, F> {
                 source: WalletSource::GitHubProfileRepo {
                     login: login.to_string(),
                     branch: outcome.branch.clone(),
+                    ref_kind: outcome.ref_kind,
+                    commit: outcome.commit.clone(),
                 },
                 synced_at: now,
             };
@@ -64,17 +264,55 @@ impl<'a, F: WalletFetcher> WalletService<'a, F> {
                 linked_at: now,
             };
 
-            // Atomic batch write
-            let mut batch = self.db.keyspace.batch();
-            self.db.upsert_user_wallet_batch(&mut batch, &user_wallet)?;
-            self.db
-                .append_wallet_history_batch(&mut batch, &history_entry)?;
-            self.db
-                .replace_wallet_link_batch(&mut batch, &wallet_link)?;
-            batch.commit()?;
+            rotation = previous_address.clone().map(|previous| WalletRotation {
+                platform: "github".to_string(),
+                login: login.to_string(),
+                previous,
+                current: outcome.address.clone(),
+                detected_at: now,
+                effective_at: now + self.confirmation_window,
+            });
+            let stored_rotation = rotation.clone();
+
+            // Atomic batch write, flagging addresses other logins already use
+            shared_with = self
+                .db
+                .run(move |db| {
+                    let shared_with: Vec<String> = db
+                        .get_logins_for_wallet(&wallet_link.wallet, "github")?
+                        .into_iter()
+                        .map(|link| link.login)
+                        .filter(|other| *other != wallet_link.login)
+                        .collect();
+
+                    let mut batch = db.keyspace.batch();
+                    db.upsert_user_wallet_batch(&mut batch, &user_wallet)?;
+                    db.append_wallet_history_batch(&mut batch, &history_entry)?;
+                    db.replace_wallet_link_batch(&mut batch, &wallet_link)?;
+                    if let Some(rotation) = &stored_rotation {
+                        db.set_wallet_rotation_batch(&mut batch, rotation)?;
+                    }
+                    if !shared_with.is_empty() {
+                        let warning = WalletWarning {
+                            platform: "github".to_string(),
+                            login: wallet_link.login.clone(),
+                            wallet: wallet_link.wallet.clone(),
+                            kind: WalletWarningKind::SharedWallet {
+                                other_logins: shared_with.clone(),
+                            },
+                            recorded_at: now,
+                        };
+                        db.append_wallet_warning_batch(&mut batch, &warning)?;
+                    }
+                    if !dry_run {
+                        batch.commit()?;
+                    }
+                    Ok(shared_with)
+                })
+                .await?;
         }
 
-        // Step 5: Return result
+        // Step 6: Return result
         Ok(Some(WalletSyncResult {
             current: outcome.address,
             previous: previous_address,
@@ -82,7 +320,12 @@ impl<'a, F: WalletFetcher> WalletService<'a, F> {
             source: WalletSource::GitHubProfileRepo {
                 login: login.to_string(),
                 branch: outcome.branch,
+                ref_kind: outcome.ref_kind,
+                commit: outcome.commit,
             },
+            preferences: outcome.preferences,
+            shared_with,
+            rotation,
         }))
     }
 }
@@ -90,8 +333,13 @@ impl<'a, F: WalletFetcher> WalletService<'a, F> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::clock::ManualClock;
     use crate::github::WalletFetcher;
-    use crate::types::{WalletAddress, WalletFetchOutcome};
+    use crate::types::{
+        AddressKind, ContributorPreferences, RefKind, WalletAddress,
+        WalletFetchOutcome,
+    };
+    use chrono::Utc;
     use std::collections::VecDeque;
     use std::sync::Mutex;
     use tempfile::tempdir;
@@ -104,6 +352,7 @@ mod tests {
         async fn fetch_wallet_address(
             &self,
             _login: &str,
+            _wallet_ref: Option<&ProfileRef>,
         ) -> Result<Option<WalletFetchOutcome>> {
             let mut lock = self.outcomes.lock().unwrap();
             Ok(lock.pop_front().unwrap_or(None))
@@ -122,6 +371,59 @@ mod tests {
         WalletAddress::try_from(base.as_str()).unwrap()
     }
 
+    #[test]
+    fn address_lists_resolve_in_one_pass() {
+        let link = |login: &str, platform: &str| WalletLoginLink {
+            wallet: addr(),
+            platform: platform.into(),
+            login: login.into(),
+            linked_at: Utc::now(),
+        };
+        let links = [link("alice", "github"), link("bob", "gitlab")];
+        let unused = "9fZZEJVg7z29LARcVTffLKaxBW19dL1wiX34zSnE2rrWfMd2qcz";
+        let list = format!(
+            "# treasury audit\n{a}\n\n  {u}\nnot-an-address\n{a}\n",
+            a = addr().as_str(),
+            u = unused
+        );
+
+        let treasury = AddressBookEntry {
+            address: WalletAddress::try_from(unused).unwrap(),
+            name: "Project treasury".into(),
+            kind: AddressKind::Treasury,
+            note: None,
+            updated_at: Utc::now(),
+        };
+
+        let lookups = lookup_wallets(&list, &links, &[treasury]);
+        assert_eq!(
+            lookups,
+            [
+                WalletLookup {
+                    address: addr().as_str().into(),
+                    valid: true,
+                    logins: vec!["alice".into()],
+                    label: None,
+                    kind: None,
+                },
+                WalletLookup {
+                    address: unused.into(),
+                    valid: true,
+                    logins: Vec::new(),
+                    label: Some("Project treasury".into()),
+                    kind: Some(AddressKind::Treasury),
+                },
+                WalletLookup {
+                    address: "not-an-address".into(),
+                    valid: false,
+                    logins: Vec::new(),
+                    label: None,
+                    kind: None,
+                },
+            ]
+        );
+    }
+
     #[tokio::test]
     async fn first_sync_changes_and_persists() {
         let dir = tempdir().unwrap();
@@ -130,20 +432,69 @@ mod tests {
         let outcome = WalletFetchOutcome {
             address: addr(),
             branch: "main".to_string(),
+            ref_kind: RefKind::Branch,
+            commit: Some("3f2a9c1e8b7d6a5f4e3d2c1b0a9f8e7d6c5b4a39".to_string()),
+            preferences: None,
         };
         let fetcher = MockFetcher {
             outcomes: Mutex::new(VecDeque::from([Some(outcome)])),
         };
 
         let service = WalletService::new(&db, &fetcher);
-        let res = service.sync_github_login("alice").await.unwrap().unwrap();
+        let res = service
+            .sync_github_login("alice", false)
+            .await
+            .unwrap()
+            .unwrap();
 
         assert!(res.changed);
+        assert!(res.rotation.is_none());
         let stored = db.get_user_wallet("github", "alice").unwrap().unwrap();
         assert_eq!(stored.address, res.current);
 
         let history = db.get_wallet_history("github", "alice").unwrap();
         assert_eq!(history.len(), 1);
+        assert_eq!(
+            history[0].source.commit(),
+            Some("3f2a9c1e8b7d6a5f4e3d2c1b0a9f8e7d6c5b4a39")
+        );
+    }
+
+    #[tokio::test]
+    async fn dry_run_sync_reports_the_change_without_storing_it() {
+        let dir = tempdir().unwrap();
+        let db = Database::new(dir.path().to_str().unwrap()).unwrap();
+
+        let outcome = WalletFetchOutcome {
+            address: addr(),
+            branch: "main".to_string(),
+            ref_kind: RefKind::Branch,
+            commit: None,
+            preferences: None,
+        };
+        let fetcher = MockFetcher {
+            outcomes: Mutex::new(VecDeque::from([Some(outcome), None])),
+        };
+
+        let service = WalletService::new(&db, &fetcher).with_dry_run(true);
+        let res = service
+            .sync_github_login("alice", false)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(res.changed);
+        assert!(db.get_user_wallet("github", "alice").unwrap().is_none());
+        assert!(db.get_wallet_history("github", "alice").unwrap().is_empty());
+
+        // A missing profile is not remembered either
+        assert!(
+            service
+                .sync_github_login("bob", false)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(db.get_wallet_probe_miss("github", "bob").unwrap().is_none());
     }
 
     #[tokio::test]
@@ -154,26 +505,213 @@ mod tests {
         let a1 = WalletFetchOutcome {
             address: addr(),
             branch: "main".to_string(),
+            ref_kind: RefKind::Branch,
+            commit: None,
+            preferences: None,
         };
         let a2 = WalletFetchOutcome {
             address: addr(),
             branch: "main".to_string(),
+            ref_kind: RefKind::Branch,
+            commit: None,
+            preferences: None,
         };
         let fetcher = MockFetcher {
             outcomes: Mutex::new(VecDeque::from([Some(a1), Some(a2)])),
         };
 
         let service = WalletService::new(&db, &fetcher);
-        let first = service.sync_github_login("bob").await.unwrap().unwrap();
+        let first = service
+            .sync_github_login("bob", false)
+            .await
+            .unwrap()
+            .unwrap();
         assert!(first.changed);
 
         let before_history = db.get_wallet_history("github", "bob").unwrap().len();
-        let second = service.sync_github_login("bob").await.unwrap().unwrap();
+        let second = service
+            .sync_github_login("bob", false)
+            .await
+            .unwrap()
+            .unwrap();
         assert!(!second.changed);
         let after_history = db.get_wallet_history("github", "bob").unwrap().len();
         assert_eq!(before_history, after_history);
     }
 
+    #[tokio::test]
+    async fn preferences_follow_the_profile_repo() {
+        let dir = tempdir().unwrap();
+        let db = Database::new(dir.path().to_str().unwrap()).unwrap();
+
+        let with_prefs = WalletFetchOutcome {
+            address: addr(),
+            branch: "main".to_string(),
+            ref_kind: RefKind::Branch,
+            commit: None,
+            preferences: Some(ContributorPreferences {
+                opt_out: true,
+                ..Default::default()
+            }),
+        };
+        let without_prefs = WalletFetchOutcome {
+            preferences: None,
+            ..with_prefs.clone()
+        };
+        let fetcher = MockFetcher {
+            outcomes: Mutex::new(VecDeque::from([
+                Some(with_prefs),
+                Some(without_prefs),
+            ])),
+        };
+        let service = WalletService::new(&db, &fetcher);
+
+        let res = service
+            .sync_github_login("fay", false)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(res.preferences.unwrap().opt_out);
+        let stored = db.get_contributor_preferences("github", "fay").unwrap();
+        assert!(stored.unwrap().opt_out);
+
+        let opt_out = db.get_contributor_opt_out("github", "fay").unwrap();
+        assert_eq!(opt_out.unwrap().source, OptOutSource::ProfileRepo);
+
+        // Removing gitcircles.toml clears stored preferences and the opt-out
+        service.sync_github_login("fay", false).await.unwrap();
+        assert!(
+            db.get_contributor_preferences("github", "fay")
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            db.get_contributor_opt_out("github", "fay")
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn missing_profile_is_not_reprobed_until_ttl_or_force() {
+        let dir = tempdir().unwrap();
+        let db = Database::new(dir.path().to_str().unwrap()).unwrap();
+
+        let found = WalletFetchOutcome {
+            address: addr(),
+            branch: "main".to_string(),
+            ref_kind: RefKind::Branch,
+            commit: None,
+            preferences: None,
+        };
+        let fetcher = MockFetcher {
+            outcomes: Mutex::new(VecDeque::from([
+                None,
+                Some(found.clone()),
+                None,
+                Some(found.clone()),
+            ])),
+        };
+        let remaining = || fetcher.outcomes.lock().unwrap().len();
+
+        let clock = ManualClock::new(Utc::now());
+        let service = WalletService::with_clock(&db, &fetcher, &clock);
+
+        assert!(
+            service
+                .sync_github_login("dave", false)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        let miss = db.get_wallet_probe_miss("github", "dave").unwrap().unwrap();
+        assert_eq!(
+            miss.retry_after,
+            clock.now() + Duration::hours(DEFAULT_NEGATIVE_TTL_HOURS)
+        );
+
+        // Cached miss: GitHub is not asked again
+        assert!(
+            service
+                .sync_github_login("dave", false)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert_eq!(remaining(), 3);
+
+        // Forcing bypasses the cache, and a hit clears the miss
+        assert!(
+            service
+                .sync_github_login("dave", true)
+                .await
+                .unwrap()
+                .is_some()
+        );
+        assert!(
+            db.get_wallet_probe_miss("github", "dave")
+                .unwrap()
+                .is_none()
+        );
+
+        // Once the TTL expires the login is probed again
+        assert!(
+            service
+                .sync_github_login("erin", false)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        clock.advance(Duration::hours(DEFAULT_NEGATIVE_TTL_HOURS));
+        assert!(
+            service
+                .sync_github_login("erin", false)
+                .await
+                .unwrap()
+                .is_some()
+        );
+        assert_eq!(remaining(), 0);
+    }
+
+    #[tokio::test]
+    async fn shared_address_is_flagged() {
+        let dir = tempdir().unwrap();
+        let db = Database::new(dir.path().to_str().unwrap()).unwrap();
+
+        let outcome = WalletFetchOutcome {
+            address: addr(),
+            branch: "main".to_string(),
+            ref_kind: RefKind::Branch,
+            commit: None,
+            preferences: None,
+        };
+        let fetcher = MockFetcher {
+            outcomes: Mutex::new(VecDeque::from([
+                Some(outcome.clone()),
+                Some(outcome.clone()),
+                Some(outcome),
+            ])),
+        };
+        let service = WalletService::new(&db, &fetcher);
+
+        let first = service.sync_github_login("gus", false).await.unwrap();
+        assert!(first.unwrap().shared_with.is_empty());
+        let second = service.sync_github_login("hal", false).await.unwrap();
+        assert_eq!(second.unwrap().shared_with, ["gus"]);
+        // Unchanged syncs don't raise the warning again
+        service.sync_github_login("hal", false).await.unwrap();
+
+        let warnings = db.list_wallet_warnings().unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].login, "hal");
+        assert_eq!(
+            warnings[0].kind,
+            WalletWarningKind::SharedWallet {
+                other_logins: vec!["gus".to_string()]
+            }
+        );
+    }
+
     #[tokio::test]
     async fn address_change_appends_history_and_updates_index() {
         let dir = tempdir().unwrap();
@@ -182,6 +720,9 @@ mod tests {
         let a1 = WalletFetchOutcome {
             address: addr(),
             branch: "main".to_string(),
+            ref_kind: RefKind::Branch,
+            commit: None,
+            preferences: None,
         };
         let a2 = WalletFetchOutcome {
             address: WalletAddress::try_from(
@@ -189,6 +730,9 @@ mod tests {
             )
             .unwrap(),
             branch: "main".to_string(),
+            ref_kind: RefKind::Branch,
+            commit: None,
+            preferences: None,
         };
         let fetcher = MockFetcher {
             outcomes: Mutex::new(VecDeque::from([
@@ -197,18 +741,37 @@ mod tests {
             ])),
         };
 
-        let service = WalletService::new(&db, &fetcher);
-        let _ = service.sync_github_login("carol").await.unwrap();
+        let clock = ManualClock::new(Utc::now());
+        let service = WalletService::with_clock(&db, &fetcher, &clock);
+        let _ = service.sync_github_login("carol", false).await.unwrap();
 
-        // Sleep for 1 second to ensure timestamps differ
-        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+        // History is keyed by timestamp, so the second sync must land later
+        clock.advance(Duration::days(1));
 
-        let res2 = service.sync_github_login("carol").await.unwrap().unwrap();
+        let res2 = service
+            .sync_github_login("carol", false)
+            .await
+            .unwrap()
+            .unwrap();
 
         assert!(res2.changed);
 
+        // The replaced address is held back for the confirmation window
+        let rotation = res2.rotation.unwrap();
+        assert_eq!(rotation.previous, a1.address);
+        assert_eq!(
+            rotation.effective_at,
+            clock.now() + Duration::hours(DEFAULT_CONFIRMATION_WINDOW_HOURS)
+        );
+        assert!(rotation.is_pending(clock.now()));
+        assert_eq!(
+            db.get_wallet_rotation("github", "carol").unwrap(),
+            Some(rotation.clone())
+        );
+
         let history = db.get_wallet_history("github", "carol").unwrap();
         assert_eq!(history.len(), 2);
+        assert_eq!(history[1].recorded_at, clock.now());
 
         // Index should resolve login for both old and new addresses
         let old_links = db.get_logins_for_wallet(&a1.address, "github").unwrap();
@@ -216,5 +779,8 @@ mod tests {
 
         let new_links = db.get_logins_for_wallet(&a2.address, "github").unwrap();
         assert!(new_links.iter().any(|l| l.login == "carol"));
+
+        clock.advance(Duration::hours(DEFAULT_CONFIRMATION_WINDOW_HOURS));
+        assert!(!rotation.is_pending(clock.now()));
     }
 }